@@ -0,0 +1,90 @@
+use bytes::BytesMut;
+use encoding_rs::Encoding;
+use std::io;
+use tokio_codec::Decoder;
+
+/// Transcodes its input from a character encoding to UTF-8 before handing it
+/// to the wrapped decoder. As the wrapped decoder only ever sees UTF-8, it
+/// can frame on single byte delimiters even when the input encodes them as
+/// several bytes, as UTF-16 does with newlines.
+///
+/// A byte order mark at the start of the input is removed, and overrides the
+/// encoding when it marks the input as UTF-8 or UTF-16. Malformed sequences
+/// are replaced with the replacement character.
+///
+/// Without an encoding, the input is passed on as is.
+pub struct CharsetDecoder<D> {
+    inner: D,
+    charset: Option<Charset>,
+}
+
+struct Charset {
+    encoding: &'static Encoding,
+    decoder: encoding_rs::Decoder,
+    buffer: BytesMut,
+}
+
+impl<D> CharsetDecoder<D> {
+    pub fn new(inner: D, encoding: Option<&'static Encoding>) -> Self {
+        let charset = encoding.map(|encoding| Charset {
+            encoding,
+            decoder: encoding.new_decoder(),
+            buffer: BytesMut::new(),
+        });
+        CharsetDecoder { inner, charset }
+    }
+
+    /// Returns the encoding the input is transcoded from, if any.
+    pub fn encoding(&self) -> Option<&'static Encoding> {
+        self.charset.as_ref().map(|charset| charset.encoding)
+    }
+}
+
+impl Charset {
+    /// Transcodes all of `src` into the buffer. Once the `last` of the input
+    /// has been transcoded, the decoder starts over, so that a new stream,
+    /// like the next datagram, gets its byte order mark detected as well.
+    fn transcode(&mut self, src: &mut BytesMut, last: bool) -> io::Result<()> {
+        let max_len = self
+            .decoder
+            .max_utf8_buffer_length(src.len())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "input too large to transcode"))?;
+
+        let start = self.buffer.len();
+        self.buffer.resize(start + max_len, 0);
+        let output = &mut self.buffer[start..];
+        let (_, read, written, _) = self.decoder.decode_to_utf8(&src[..], output, last);
+        self.buffer.truncate(start + written);
+        src.advance(read);
+
+        if last {
+            self.decoder = self.encoding.new_decoder();
+        }
+        Ok(())
+    }
+}
+
+impl<D: Decoder> Decoder for CharsetDecoder<D> {
+    type Item = D::Item;
+    type Error = D::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<D::Item>, D::Error> {
+        match &mut self.charset {
+            Some(charset) => {
+                charset.transcode(src, false)?;
+                self.inner.decode(&mut charset.buffer)
+            }
+            None => self.inner.decode(src),
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<D::Item>, D::Error> {
+        match &mut self.charset {
+            Some(charset) => {
+                charset.transcode(src, true)?;
+                self.inner.decode_eof(&mut charset.buffer)
+            }
+            None => self.inner.decode_eof(src),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use bytes::{BufMut, BytesMut};
+use codec::{BytesDelimitedCodec, CharsetDecoder};
+use encoding_rs::{SHIFT_JIS, UTF_16LE, WINDOWS_1252};
+use tokio_codec::Decoder;
+
+fn utf16le(s: &str) -> Vec<u8> {
+    s.encode_utf16()
+        .flat_map(|unit| unit.to_le_bytes().to_vec())
+        .collect()
+}
+
+#[test]
+fn charset_decode_utf16le() {
+    let mut codec = CharsetDecoder::new(BytesDelimitedCodec::new(b'\n'), Some(UTF_16LE));
+    let buf = &mut BytesMut::new();
+
+    // U+0A0A is encoded as two 0x0a bytes, which must not be mistaken for
+    // delimiters.
+    let input = utf16le("h\u{e9}llo\n\u{0a0a}\n");
+    // Split inside of a character.
+    buf.put_slice(&input[..3]);
+    assert_eq!(None, codec.decode(buf).unwrap());
+    buf.put_slice(&input[3..]);
+    assert_eq!(Some("h\u{e9}llo".into()), codec.decode(buf).unwrap());
+    assert_eq!(Some("\u{0a0a}".into()), codec.decode(buf).unwrap());
+    assert_eq!(None, codec.decode(buf).unwrap());
+}
+
+#[test]
+fn charset_decode_bom() {
+    // The byte order mark overrides the configured encoding.
+    let mut codec = CharsetDecoder::new(BytesDelimitedCodec::new(b'\n'), Some(WINDOWS_1252));
+    let buf = &mut BytesMut::new();
+    buf.put_slice(&[0xff, 0xfe]);
+    buf.put_slice(&utf16le("one\ntwo"));
+
+    assert_eq!(Some("one".into()), codec.decode(buf).unwrap());
+    assert_eq!(Some("two".into()), codec.decode_eof(buf).unwrap());
+    assert_eq!(None, codec.decode_eof(buf).unwrap());
+
+    // Detection starts over after the end of the input.
+    buf.put_slice(b"caf\xe9\n");
+    assert_eq!(Some("caf\u{e9}".into()), codec.decode(buf).unwrap());
+}
+
+#[test]
+fn charset_decode_shift_jis() {
+    let mut codec = CharsetDecoder::new(BytesDelimitedCodec::new(b'\n'), Some(SHIFT_JIS));
+    let buf = &mut BytesMut::new();
+    buf.put_slice(b"\x83\x65\x83\x58\x83\x67\n");
+
+    assert_eq!(
+        Some("\u{30c6}\u{30b9}\u{30c8}".into()),
+        codec.decode(buf).unwrap()
+    );
+}
+
+#[test]
+fn charset_decode_passthrough() {
+    let mut codec = CharsetDecoder::new(BytesDelimitedCodec::new(b'\n'), None);
+    let buf = &mut BytesMut::new();
+    buf.put_slice(b"caf\xe9\n");
+
+    assert_eq!(Some(b"caf\xe9"[..].into()), codec.decode(buf).unwrap());
+}
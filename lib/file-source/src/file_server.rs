@@ -1,5 +1,9 @@
-use crate::{file_watcher::FileWatcher, FileFingerprint, FilePosition};
+use crate::{
+    file_watcher::{ByteOrderMark, FileWatcher},
+    Checkpoint, FileFingerprint, FilePosition,
+};
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use futures::{
     executor::block_on,
     future::{select, Either},
@@ -7,11 +11,14 @@ use futures::{
 };
 use glob::glob;
 use indexmap::IndexMap;
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::fs::{self, remove_file, File};
 use std::io::{self, Read, Seek, Write};
 use std::path::{Path, PathBuf};
-use std::time::{self, Duration};
+use std::sync::{Arc, Mutex};
+use std::time::{self, Duration, Instant};
 use tokio::time::delay_for;
 use tracing::field;
 
@@ -36,11 +43,25 @@ where
     pub start_at_beginning: bool,
     pub ignore_before: Option<time::SystemTime>,
     pub max_line_bytes: usize,
+    /// The bytes lines end with, which are longer than a byte in encodings
+    /// such as UTF-16.
+    pub line_delimiter: Bytes,
+    /// Whether files that start with a byte order mark are split on the
+    /// delimiter of the encoding it marks instead of `line_delimiter`. The
+    /// mark is sent along with each line of such a file.
+    pub detect_byte_order_mark: bool,
     pub data_dir: PathBuf,
     pub glob_minimum_cooldown: Duration,
     pub fingerprinter: Fingerprinter,
     pub oldest_first: bool,
+    pub read_scheduling: ReadScheduling,
+    /// A limit on the bytes read per second, across all files.
+    pub max_bytes_per_sec: Option<u64>,
     pub remove_after: Option<Duration>,
+    /// When set, checkpoints are only advanced through this view, once the
+    /// lines read up to them have been acknowledged, instead of as soon as the
+    /// lines are read.
+    pub checkpoints: Option<CheckpointsView>,
 }
 
 /// `FileServer` as Source
@@ -64,10 +85,10 @@ where
         self,
         mut chans: C,
         mut shutdown: impl Future + Unpin,
-    ) -> Result<Shutdown, <C as Sink<(Bytes, String)>>::Error>
+    ) -> Result<Shutdown, <C as Sink<(Bytes, String, Checkpoint, Option<ByteOrderMark>)>>::Error>
     where
-        C: Sink<(Bytes, String)> + Unpin,
-        <C as Sink<(Bytes, String)>>::Error: std::error::Error,
+        C: Sink<(Bytes, String, Checkpoint, Option<ByteOrderMark>)> + Unpin,
+        <C as Sink<(Bytes, String, Checkpoint, Option<ByteOrderMark>)>>::Error: std::error::Error,
     {
         let mut line_buffer = Vec::new();
         let mut fingerprint_buffer = Vec::new();
@@ -77,11 +98,17 @@ where
         let mut backoff_cap: usize = 1;
         let mut lines = Vec::new();
 
-        let mut checkpointer = Checkpointer::new(&self.data_dir);
+        let strategy = self.fingerprinter.strategy();
+        let mut checkpointer = match &self.checkpoints {
+            Some(view) => Checkpointer::with_view(&self.data_dir, strategy, view.clone()),
+            None => Checkpointer::new(&self.data_dir, strategy),
+        };
         checkpointer.read_checkpoints(self.ignore_before);
 
         let mut known_small_files = HashSet::new();
 
+        let mut read_limiter = self.max_bytes_per_sec.map(ReadLimiter::new);
+
         let mut existing_files = Vec::new();
         for path in self.paths_provider.paths().into_iter() {
             if let Some(file_id) = self.fingerprinter.get_fingerprint_or_log_error(
@@ -104,7 +131,7 @@ where
                 path,
                 file_id,
                 &mut fp_map,
-                &checkpointer,
+                &mut checkpointer,
                 self.start_at_beginning,
             );
         }
@@ -126,7 +153,7 @@ where
                 // Schedule the next glob time.
                 next_glob_time = now_time.checked_add(self.glob_minimum_cooldown).unwrap();
 
-                // Write any stored checkpoints (uses glob to find old checkpoints).
+                // Write any stored checkpoints.
                 checkpointer
                     .write_checkpoints()
                     .map_err(|e| warn!("Problem writing checkpoints: {:?}", e))
@@ -159,6 +186,7 @@ where
                                         path = field::debug(&path),
                                         old_path = field::debug(&watcher.path)
                                     );
+                                    checkpointer.set_path(file_id, &path);
                                     watcher.update_path(path).ok(); // ok if this fails: might fix next cycle
                                 } else {
                                     info!(
@@ -177,6 +205,7 @@ where
                                                         new_modified_time = field::debug(&new_modified_time),
                                                         old_modified_time = field::debug(&old_modified_time),
                                                         );
+                                            checkpointer.set_path(file_id, &path);
                                             watcher.update_path(path).ok(); // ok if this fails: might fix next cycle
                                         }
                                     }
@@ -184,76 +213,47 @@ where
                             }
                         } else {
                             // untracked file fingerprint
-                            self.watch_new_file(path, file_id, &mut fp_map, &checkpointer, false);
+                            self.watch_new_file(
+                                path,
+                                file_id,
+                                &mut fp_map,
+                                &mut checkpointer,
+                                false,
+                            );
                         }
                     }
                 }
             }
 
             // Collect lines by polling files.
-            let mut global_bytes_read: usize = 0;
-            let mut maxed_out_reading_single_file = false;
-            for (&file_id, watcher) in &mut fp_map {
-                if !watcher.should_read() {
-                    continue;
-                }
-
-                let mut bytes_read: usize = 0;
-                while let Ok(sz) = watcher.read_line(&mut line_buffer, self.max_line_bytes) {
-                    if sz > 0 {
-                        trace!(
-                            message = "Read bytes.",
-                            path = field::debug(&watcher.path),
-                            bytes = field::debug(sz)
-                        );
-
-                        bytes_read += sz;
-
-                        if !line_buffer.is_empty() {
-                            lines.push((
-                                line_buffer.clone().into(),
-                                watcher.path.to_str().expect("not a valid path").to_owned(),
-                            ));
-                            line_buffer.clear();
-                        }
-                    } else {
-                        // Should the file be removed
-                        if let Some(grace_period) = self.remove_after {
-                            if watcher.last_read_success().elapsed() >= grace_period {
-                                // Try to remove
-                                match remove_file(&watcher.path) {
-                                    Ok(()) => {
-                                        info!(message = "Log file deleted.", path = ?watcher.path);
-                                        watcher.set_dead();
-                                    }
-                                    Err(error) => {
-                                        // We will try again after some time.
-                                        warn!(message = "Failed deleting log file.",path = ?watcher.path, ?error, rate_limit_secs = 1);
-                                    }
-                                }
-                            }
-                        }
-
-                        break;
-                    }
-                    if bytes_read > self.max_read_bytes {
-                        maxed_out_reading_single_file = true;
-                        break;
-                    }
-                }
-                if bytes_read > 0 {
-                    global_bytes_read = global_bytes_read.saturating_add(bytes_read);
-                    checkpointer.set_checkpoint(file_id, watcher.get_file_position());
-                }
-                // Do not move on to newer files if we are behind on an older file
-                if self.oldest_first && maxed_out_reading_single_file {
-                    break;
-                }
+            if let Some(limiter) = &mut read_limiter {
+                limiter.refill();
             }
+            let global_bytes_read = match self.read_scheduling {
+                ReadScheduling::Sequential => self.read_sequentially(
+                    &mut fp_map,
+                    &mut checkpointer,
+                    &mut read_limiter,
+                    &mut line_buffer,
+                    &mut lines,
+                ),
+                ReadScheduling::RoundRobin => self.read_round_robin(
+                    &mut fp_map,
+                    &mut checkpointer,
+                    &mut read_limiter,
+                    &mut line_buffer,
+                    &mut lines,
+                ),
+            };
 
             // A FileWatcher is dead when the underlying file has disappeared.
             // If the FileWatcher is dead we don't retain it; it will be deallocated.
-            fp_map.retain(|_file_id, watcher| !watcher.dead());
+            fp_map.retain(|file_id, watcher| {
+                if watcher.dead() {
+                    checkpointer.unwatch(*file_id);
+                }
+                !watcher.dead()
+            });
 
             let mut stream = stream::iter(lines.drain(..).map(Ok));
             let result = block_on(chans.send_all(&mut stream));
@@ -279,37 +279,209 @@ where
             } else {
                 backoff_cap = 1;
             }
-            let backoff = backoff_cap.saturating_sub(global_bytes_read);
+            let mut backoff = backoff_cap.saturating_sub(global_bytes_read) as u64;
+            // Don't come back before the read limiter has allowance again.
+            if let Some(limiter) = &read_limiter {
+                backoff = backoff.max(limiter.wait_time().as_millis() as u64);
+            }
 
             // This works only if run inside tokio context since we are using
             // tokio's Timer. Outside of such context, this will panic on the first
             // call. Also since we are using block_on here and in the above code,
             // this should be run in it's own thread. `spawn_blocking` fulfills
             // all of these requirements.
-            match block_on(select(
-                shutdown,
-                delay_for(Duration::from_millis(backoff as u64)),
-            )) {
+            match block_on(select(shutdown, delay_for(Duration::from_millis(backoff)))) {
                 Either::Left((_, _)) => return Ok(Shutdown),
                 Either::Right((_, future)) => shutdown = future,
             }
         }
     }
 
+    /// Reads up to `max_read_bytes` from each file in turn. Once a file is
+    /// behind, no newer files are read from when `oldest_first` is set.
+    fn read_sequentially(
+        &self,
+        fp_map: &mut IndexMap<FileFingerprint, FileWatcher>,
+        checkpointer: &mut Checkpointer,
+        read_limiter: &mut Option<ReadLimiter>,
+        line_buffer: &mut Vec<u8>,
+        lines: &mut Vec<(Bytes, String, Checkpoint, Option<ByteOrderMark>)>,
+    ) -> usize {
+        let mut global_bytes_read: usize = 0;
+        for (&file_id, watcher) in fp_map {
+            if !watcher.should_read() {
+                continue;
+            }
+
+            let (bytes_read, caught_up) = self.read_lines(
+                file_id,
+                watcher,
+                self.max_read_bytes,
+                read_limiter,
+                line_buffer,
+                lines,
+            );
+            if bytes_read > 0 {
+                global_bytes_read = global_bytes_read.saturating_add(bytes_read);
+                if self.checkpoints.is_none() {
+                    checkpointer.set_checkpoint(file_id, watcher.get_file_position());
+                }
+            }
+            if caught_up && watcher.compressed() {
+                checkpointer.set_end(file_id, watcher.get_file_position());
+            }
+            // Do not move on to newer files if we are behind on an older file
+            if self.oldest_first && !caught_up {
+                break;
+            }
+            if !has_allowance(read_limiter) {
+                break;
+            }
+        }
+        global_bytes_read
+    }
+
+    /// Reads one line at a time from whichever file has had the fewest bytes
+    /// read from it so far, until every file has either caught up or had
+    /// `max_read_bytes` read from it. A file with long lines thereby can't
+    /// crowd out files with short ones.
+    fn read_round_robin(
+        &self,
+        fp_map: &mut IndexMap<FileFingerprint, FileWatcher>,
+        checkpointer: &mut Checkpointer,
+        read_limiter: &mut Option<ReadLimiter>,
+        line_buffer: &mut Vec<u8>,
+        lines: &mut Vec<(Bytes, String, Checkpoint, Option<ByteOrderMark>)>,
+    ) -> usize {
+        // Ties go to the file that was found first, which is the oldest one.
+        let mut queue = fp_map
+            .values()
+            .enumerate()
+            .filter(|(_, watcher)| watcher.should_read())
+            .map(|(index, _)| Reverse((0, index)))
+            .collect::<BinaryHeap<_>>();
+
+        let mut global_bytes_read: usize = 0;
+        while has_allowance(read_limiter) {
+            let Reverse((file_bytes_read, index)) = match queue.pop() {
+                Some(next) => next,
+                None => break,
+            };
+            let (file_id, watcher) = fp_map.get_index_mut(index).expect("index out of bounds");
+            let file_id = *file_id;
+
+            let (bytes_read, caught_up) =
+                self.read_lines(file_id, watcher, 0, read_limiter, line_buffer, lines);
+            if bytes_read > 0 {
+                global_bytes_read = global_bytes_read.saturating_add(bytes_read);
+                if self.checkpoints.is_none() {
+                    checkpointer.set_checkpoint(file_id, watcher.get_file_position());
+                }
+            }
+            if caught_up && watcher.compressed() {
+                checkpointer.set_end(file_id, watcher.get_file_position());
+            }
+
+            let file_bytes_read = file_bytes_read + bytes_read;
+            if !caught_up && file_bytes_read <= self.max_read_bytes {
+                queue.push(Reverse((file_bytes_read, index)));
+            }
+        }
+        global_bytes_read
+    }
+
+    /// Reads lines from a file until more than `max_bytes` have been read from
+    /// it, it has no more lines or the read limiter runs out of allowance.
+    /// Returns how many bytes were read and whether the file has been caught
+    /// up with.
+    fn read_lines(
+        &self,
+        file_id: FileFingerprint,
+        watcher: &mut FileWatcher,
+        max_bytes: usize,
+        read_limiter: &mut Option<ReadLimiter>,
+        line_buffer: &mut Vec<u8>,
+        lines: &mut Vec<(Bytes, String, Checkpoint, Option<ByteOrderMark>)>,
+    ) -> (usize, bool) {
+        let mut bytes_read: usize = 0;
+        while has_allowance(read_limiter) {
+            let sz = match watcher.read_line(line_buffer, self.max_line_bytes) {
+                Ok(sz) => sz,
+                Err(_) => return (bytes_read, true),
+            };
+            if sz > 0 {
+                trace!(
+                    message = "Read bytes.",
+                    path = field::debug(&watcher.path),
+                    bytes = field::debug(sz)
+                );
+
+                bytes_read += sz;
+                if let Some(limiter) = read_limiter {
+                    limiter.consume(sz);
+                }
+
+                if !line_buffer.is_empty() {
+                    lines.push((
+                        line_buffer.clone().into(),
+                        watcher.path.to_str().expect("not a valid path").to_owned(),
+                        (file_id, watcher.get_file_position()),
+                        watcher.byte_order_mark(),
+                    ));
+                    line_buffer.clear();
+                }
+            } else {
+                // Should the file be removed
+                if let Some(grace_period) = self.remove_after {
+                    if watcher.last_read_success().elapsed() >= grace_period {
+                        // Try to remove
+                        match remove_file(&watcher.path) {
+                            Ok(()) => {
+                                info!(message = "Log file deleted.", path = ?watcher.path);
+                                watcher.set_dead();
+                            }
+                            Err(error) => {
+                                // We will try again after some time.
+                                warn!(message = "Failed deleting log file.",path = ?watcher.path, ?error, rate_limit_secs = 1);
+                            }
+                        }
+                    }
+                }
+
+                return (bytes_read, true);
+            }
+            if bytes_read > max_bytes {
+                break;
+            }
+        }
+        (bytes_read, false)
+    }
+
     fn watch_new_file(
         &self,
         path: PathBuf,
         file_id: FileFingerprint,
         fp_map: &mut IndexMap<FileFingerprint, FileWatcher>,
-        checkpointer: &Checkpointer,
+        checkpointer: &mut Checkpointer,
         read_from_beginning: bool,
     ) {
-        let file_position = if read_from_beginning {
-            0
+        checkpointer.set_path(file_id, &path);
+        let (file_position, compressed_end) = if read_from_beginning {
+            (0, None)
         } else {
-            checkpointer.get_checkpoint(file_id).unwrap_or(0)
+            (
+                checkpointer.get_checkpoint(file_id).unwrap_or(0),
+                checkpointer.get_end(file_id),
+            )
         };
-        match FileWatcher::new(path.clone(), file_position, self.ignore_before) {
+        match FileWatcher::new(
+            path.clone(),
+            file_position,
+            self.ignore_before,
+            self.line_delimiter.clone(),
+            self.detect_byte_order_mark,
+            compressed_end,
+        ) {
             Ok(mut watcher) => {
                 info!(
                     message = "Found file to watch.",
@@ -324,6 +496,78 @@ where
     }
 }
 
+/// How `FileServer` schedules reads over the files it watches.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadScheduling {
+    /// Read up to `max_read_bytes` from each file in turn.
+    Sequential,
+    /// Read one line at a time from the file that has had the fewest bytes
+    /// read from it, up to `max_read_bytes` per file.
+    RoundRobin,
+}
+
+impl Default for ReadScheduling {
+    fn default() -> Self {
+        ReadScheduling::Sequential
+    }
+}
+
+fn has_allowance(read_limiter: &Option<ReadLimiter>) -> bool {
+    read_limiter
+        .as_ref()
+        .map_or(true, |limiter| limiter.has_allowance())
+}
+
+/// A token bucket limiting the bytes read per second, which allows bursts of
+/// up to a second's worth of bytes. A line is always read as a whole, so the
+/// allowance may go negative, to be paid back before reading again.
+struct ReadLimiter {
+    bytes_per_sec: u64,
+    allowance: i64,
+    last_refill: Instant,
+}
+
+impl ReadLimiter {
+    fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            allowance: bytes_per_sec as i64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        let refill = (elapsed * self.bytes_per_sec as f64) as i64;
+        // Wait for at least a whole byte, so that frequent calls don't round
+        // the refill away.
+        if refill > 0 {
+            self.allowance = (self.allowance + refill).min(self.bytes_per_sec as i64);
+            self.last_refill = now;
+        }
+    }
+
+    fn has_allowance(&self) -> bool {
+        self.allowance > 0
+    }
+
+    fn consume(&mut self, bytes: usize) {
+        self.allowance -= bytes as i64;
+    }
+
+    /// How long until there is allowance again.
+    fn wait_time(&self) -> Duration {
+        if self.has_allowance() {
+            Duration::from_secs(0)
+        } else {
+            let missing = (1 - self.allowance) as f64;
+            Duration::from_secs_f64(missing / self.bytes_per_sec as f64)
+        }
+    }
+}
+
 /// A sentinel type to signal that file server was gracefully shut down.
 ///
 /// The purpose of this type is to clarify the semantics of the result values
@@ -332,25 +576,118 @@ where
 #[derive(Debug)]
 pub struct Shutdown;
 
+/// A handle to the checkpoints of a `Checkpointer` that can be updated from
+/// outside of the file server, e.g. once the lines read up to a checkpoint
+/// have been acknowledged.
+#[derive(Clone, Debug, Default)]
+pub struct CheckpointsView {
+    checkpoints: Arc<Mutex<HashMap<FileFingerprint, FilePosition>>>,
+}
+
+impl CheckpointsView {
+    pub fn update(&self, fng: FileFingerprint, pos: FilePosition) {
+        self.checkpoints.lock().unwrap().insert(fng, pos);
+    }
+
+    fn get(&self, fng: FileFingerprint) -> Option<FilePosition> {
+        self.checkpoints.lock().unwrap().get(&fng).cloned()
+    }
+}
+
+const TMP_FILE_NAME: &str = "checkpoints.new.json";
+const STABLE_FILE_NAME: &str = "checkpoints.json";
+/// How long the checkpoint of a file that is no longer watched is kept after
+/// the file was last modified.
+const CHECKPOINT_RETENTION_DAYS: i64 = 7;
+
+/// The on-disk checkpoint state. New versions are added as variants, so older
+/// files can still be read after an upgrade.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "version")]
+enum State {
+    #[serde(rename = "1")]
+    V1 { checkpoints: Vec<CheckpointEntry> },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CheckpointEntry {
+    fingerprint: FileFingerprint,
+    position: FilePosition,
+    strategy: FingerprintStrategy,
+    path: Option<PathBuf>,
+    modified: Option<DateTime<Utc>>,
+    end: Option<FilePosition>,
+}
+
+/// Where a checkpointed file was last seen, when it was last modified, and
+/// where it ended if it's a compressed file that was read to the end.
+#[derive(Debug, Default)]
+struct FileMetadata {
+    path: Option<PathBuf>,
+    modified: Option<DateTime<Utc>>,
+    end: Option<FilePosition>,
+    watched: bool,
+}
+
+impl FileMetadata {
+    /// Whether the checkpoint can be dropped: the file isn't watched anymore,
+    /// and either it's gone from where it was last seen or it was last
+    /// modified before `cutoff`.
+    fn expired(&self, cutoff: DateTime<Utc>) -> bool {
+        !self.watched
+            && (self.modified.map_or(false, |modified| modified < cutoff)
+                || self.path.as_ref().map_or(false, |path| !path.exists()))
+    }
+}
+
+/// Persists checkpoints to a single file in the data directory, which is
+/// replaced as a whole whenever a checkpoint changed so a crash never leaves
+/// it half written. Checkpoints of files that are no longer watched are
+/// dropped once the file is gone or hasn't been modified for
+/// `CHECKPOINT_RETENTION_DAYS`.
+///
+/// Checkpoints stored by older versions, as one empty file per checkpoint in a
+/// `checkpoints` directory, are read when no checkpoint file exists yet, and
+/// the directory is removed once they have been written to the new file.
 pub struct Checkpointer {
     directory: PathBuf,
+    tmp_file_path: PathBuf,
+    stable_file_path: PathBuf,
+    legacy_directory: PathBuf,
     glob_string: String,
-    checkpoints: HashMap<FileFingerprint, FilePosition>,
+    strategy: FingerprintStrategy,
+    checkpoints: CheckpointsView,
+    files: HashMap<FileFingerprint, FileMetadata>,
+    written: HashMap<FileFingerprint, (FilePosition, Option<FilePosition>)>,
 }
 
 impl Checkpointer {
-    pub fn new(data_dir: &Path) -> Checkpointer {
-        let directory = data_dir.join("checkpoints");
-        let glob_string = directory.join("*").to_string_lossy().into_owned();
+    pub fn new(data_dir: &Path, strategy: FingerprintStrategy) -> Checkpointer {
+        Self::with_view(data_dir, strategy, CheckpointsView::default())
+    }
+
+    pub fn with_view(
+        data_dir: &Path,
+        strategy: FingerprintStrategy,
+        checkpoints: CheckpointsView,
+    ) -> Checkpointer {
+        let legacy_directory = data_dir.join("checkpoints");
+        let glob_string = legacy_directory.join("*").to_string_lossy().into_owned();
         Checkpointer {
-            directory,
+            directory: data_dir.to_path_buf(),
+            tmp_file_path: data_dir.join(TMP_FILE_NAME),
+            stable_file_path: data_dir.join(STABLE_FILE_NAME),
+            legacy_directory,
             glob_string,
-            checkpoints: HashMap::new(),
+            strategy,
+            checkpoints,
+            files: HashMap::new(),
+            written: HashMap::new(),
         }
     }
 
     fn encode(&self, fng: FileFingerprint, pos: FilePosition) -> PathBuf {
-        self.directory.join(format!("{:x}.{}", fng, pos))
+        self.legacy_directory.join(format!("{:x}.{}", fng, pos))
     }
     fn decode(&self, path: &Path) -> (FileFingerprint, FilePosition) {
         let file_name = &path.file_name().unwrap().to_string_lossy();
@@ -358,23 +695,179 @@ impl Checkpointer {
     }
 
     pub fn set_checkpoint(&mut self, fng: FileFingerprint, pos: FilePosition) {
-        self.checkpoints.insert(fng, pos);
+        self.checkpoints.update(fng, pos);
     }
 
     pub fn get_checkpoint(&self, fng: FileFingerprint) -> Option<FilePosition> {
-        self.checkpoints.get(&fng).cloned()
+        self.checkpoints.get(fng)
+    }
+
+    /// Records the path the file with the given fingerprint is read from, which
+    /// is stored along with its checkpoint. The file is watched until
+    /// `unwatch` is called for it.
+    pub fn set_path(&mut self, fng: FileFingerprint, path: &Path) {
+        let metadata = self.files.entry(fng).or_default();
+        metadata.path = Some(path.to_path_buf());
+        metadata.watched = true;
+    }
+
+    /// Records that the file with the given fingerprint is no longer watched,
+    /// so its checkpoint may expire.
+    pub fn unwatch(&mut self, fng: FileFingerprint) {
+        if let Some(metadata) = self.files.get_mut(&fng) {
+            metadata.watched = false;
+        }
     }
 
+    /// Records that the compressed file with the given fingerprint was read to
+    /// the end at `pos`, so it isn't decompressed again after a restart.
+    pub fn set_end(&mut self, fng: FileFingerprint, pos: FilePosition) {
+        self.files.entry(fng).or_default().end = Some(pos);
+    }
+
+    pub fn get_end(&self, fng: FileFingerprint) -> Option<FilePosition> {
+        self.files.get(&fng).and_then(|metadata| metadata.end)
+    }
+
+    /// Writes the checkpoints if any of them changed since they were last
+    /// written, dropping the expired ones.
     pub fn write_checkpoints(&mut self) -> Result<(), io::Error> {
-        fs::remove_dir_all(&self.directory).ok();
+        let mut positions = self.checkpoints.checkpoints.lock().unwrap().clone();
+        let mut changed = positions.len() != self.written.len();
+        for (&fingerprint, &position) in &positions {
+            let metadata = self.files.entry(fingerprint).or_default();
+            if self.written.get(&fingerprint) == Some(&(position, metadata.end)) {
+                continue;
+            }
+            // Only files that were read from since the last write are looked
+            // at again.
+            changed = true;
+            let modified = metadata
+                .path
+                .as_ref()
+                .and_then(|path| fs::metadata(path).and_then(|m| m.modified()).ok());
+            if let Some(modified) = modified {
+                metadata.modified = Some(modified.into());
+            }
+        }
+        if !changed {
+            return Ok(());
+        }
+
+        let cutoff = Utc::now() - chrono::Duration::days(CHECKPOINT_RETENTION_DAYS);
+        let files = &mut self.files;
+        let expired = positions
+            .keys()
+            .copied()
+            .filter(|fingerprint| files[fingerprint].expired(cutoff))
+            .collect::<Vec<_>>();
+        if !expired.is_empty() {
+            let mut view = self.checkpoints.checkpoints.lock().unwrap();
+            for fingerprint in expired {
+                view.remove(&fingerprint);
+                positions.remove(&fingerprint);
+                files.remove(&fingerprint);
+            }
+        }
+
+        let strategy = self.strategy;
+        let mut checkpoints = positions
+            .into_iter()
+            .map(|(fingerprint, position)| {
+                let metadata = &files[&fingerprint];
+                CheckpointEntry {
+                    fingerprint,
+                    position,
+                    strategy,
+                    path: metadata.path.clone(),
+                    modified: metadata.modified,
+                    end: metadata.end,
+                }
+            })
+            .collect::<Vec<_>>();
+        checkpoints.sort_by_key(|entry| entry.fingerprint);
+
         fs::create_dir_all(&self.directory)?;
-        for (&fng, &pos) in self.checkpoints.iter() {
-            fs::File::create(self.encode(fng, pos))?;
+        let mut writer = io::BufWriter::new(File::create(&self.tmp_file_path)?);
+        let state = State::V1 { checkpoints };
+        serde_json::to_writer(&mut writer, &state)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        fs::rename(&self.tmp_file_path, &self.stable_file_path)?;
+
+        let State::V1 { checkpoints } = state;
+        self.written = checkpoints
+            .into_iter()
+            .map(|entry| (entry.fingerprint, (entry.position, entry.end)))
+            .collect();
+
+        // The legacy checkpoints have been migrated into the file just written.
+        if self.legacy_directory.exists() {
+            fs::remove_dir_all(&self.legacy_directory)?;
         }
         Ok(())
     }
 
+    /// Reads the stored checkpoints, dropping those recorded with another
+    /// fingerprinting strategy or for files last modified before
+    /// `ignore_before`. Dropped checkpoints are not written back.
     pub fn read_checkpoints(&mut self, ignore_before: Option<time::SystemTime>) {
+        match self.read_checkpoints_file(ignore_before) {
+            Ok(()) => return,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => {
+                warn!(
+                    message = "Unable to read checkpoints.",
+                    path = ?self.stable_file_path,
+                    %error,
+                );
+            }
+        }
+        self.read_legacy_checkpoints(ignore_before);
+    }
+
+    fn read_checkpoints_file(
+        &mut self,
+        ignore_before: Option<time::SystemTime>,
+    ) -> Result<(), io::Error> {
+        let reader = io::BufReader::new(File::open(&self.stable_file_path)?);
+        let State::V1 { checkpoints } = serde_json::from_reader(reader)?;
+
+        let ignore_before = ignore_before.map(DateTime::<Utc>::from);
+        let mut dropped = false;
+        for entry in checkpoints {
+            if entry.strategy != self.strategy {
+                dropped = true;
+                continue;
+            }
+            if let (Some(ignore_before), Some(modified)) = (ignore_before, entry.modified) {
+                if modified < ignore_before {
+                    dropped = true;
+                    continue;
+                }
+            }
+            // Unless some were dropped, the file already holds what would be
+            // written next.
+            self.written
+                .insert(entry.fingerprint, (entry.position, entry.end));
+            self.checkpoints.update(entry.fingerprint, entry.position);
+            self.files.insert(
+                entry.fingerprint,
+                FileMetadata {
+                    path: entry.path,
+                    modified: entry.modified,
+                    end: entry.end,
+                    watched: false,
+                },
+            );
+        }
+        if dropped {
+            self.written.clear();
+        }
+        Ok(())
+    }
+
+    fn read_legacy_checkpoints(&mut self, ignore_before: Option<time::SystemTime>) {
         for path in glob(&self.glob_string).unwrap().flatten() {
             if let Some(ignore_before) = ignore_before {
                 if let Ok(Ok(modified)) = fs::metadata(&path).map(|metadata| metadata.modified()) {
@@ -385,11 +878,21 @@ impl Checkpointer {
                 }
             }
             let (fng, pos) = self.decode(&path);
-            self.checkpoints.insert(fng, pos);
+            self.checkpoints.update(fng, pos);
         }
     }
 }
 
+/// How a file's fingerprint was computed. Fingerprints computed with different
+/// strategies never identify the same file.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FingerprintStrategy {
+    Checksum,
+    FirstLineChecksum,
+    DevInode,
+}
+
 #[derive(Clone)]
 pub enum Fingerprinter {
     Checksum {
@@ -403,6 +906,14 @@ pub enum Fingerprinter {
 }
 
 impl Fingerprinter {
+    pub fn strategy(&self) -> FingerprintStrategy {
+        match self {
+            Fingerprinter::Checksum { .. } => FingerprintStrategy::Checksum,
+            Fingerprinter::FirstLineChecksum { .. } => FingerprintStrategy::FirstLineChecksum,
+            Fingerprinter::DevInode => FingerprintStrategy::DevInode,
+        }
+    }
+
     fn get_fingerprint_of_file(
         &self,
         path: &PathBuf,
@@ -482,8 +993,12 @@ fn fingerprinter_read_until(mut r: impl Read, delim: u8, mut buf: &mut [u8]) ->
 
 #[cfg(test)]
 mod test {
-    use super::{Checkpointer, FileFingerprint, FilePosition, Fingerprinter};
+    use super::{
+        Checkpointer, CheckpointsView, FileFingerprint, FilePosition, FingerprintStrategy,
+        Fingerprinter, ReadLimiter, STABLE_FILE_NAME,
+    };
     use std::fs;
+    use std::time::{Duration, SystemTime};
     use tempfile::tempdir;
 
     #[test]
@@ -630,7 +1145,7 @@ mod test {
         let fingerprint: FileFingerprint = 0x1234567890abcdef;
         let position: FilePosition = 1234;
         let data_dir = tempdir().unwrap();
-        let mut chkptr = Checkpointer::new(&data_dir.path());
+        let mut chkptr = Checkpointer::new(&data_dir.path(), FingerprintStrategy::Checksum);
         assert_eq!(
             chkptr.decode(&chkptr.encode(fingerprint, position)),
             (fingerprint, position)
@@ -645,16 +1160,169 @@ mod test {
         let position: FilePosition = 1234;
         let data_dir = tempdir().unwrap();
         {
-            let mut chkptr = Checkpointer::new(&data_dir.path());
+            let mut chkptr = Checkpointer::new(&data_dir.path(), FingerprintStrategy::Checksum);
             chkptr.set_checkpoint(fingerprint, position);
             assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
             chkptr.write_checkpoints().ok();
         }
         {
-            let mut chkptr = Checkpointer::new(&data_dir.path());
+            let mut chkptr = Checkpointer::new(&data_dir.path(), FingerprintStrategy::Checksum);
             assert_eq!(chkptr.get_checkpoint(fingerprint), None);
             chkptr.read_checkpoints(None);
             assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
         }
     }
+
+    #[test]
+    fn test_checkpointer_restart_compressed_end() {
+        let fingerprint: FileFingerprint = 0x1234567890abcdef;
+        let position: FilePosition = 1234;
+        let data_dir = tempdir().unwrap();
+        {
+            let mut chkptr = Checkpointer::new(&data_dir.path(), FingerprintStrategy::Checksum);
+            chkptr.set_checkpoint(fingerprint, position);
+            chkptr.set_end(fingerprint, position);
+            chkptr.write_checkpoints().unwrap();
+        }
+        {
+            let mut chkptr = Checkpointer::new(&data_dir.path(), FingerprintStrategy::Checksum);
+            chkptr.read_checkpoints(None);
+            assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
+            assert_eq!(chkptr.get_end(fingerprint), Some(position));
+        }
+    }
+
+    #[test]
+    fn test_checkpointer_view() {
+        let fingerprint: FileFingerprint = 0x1234567890abcdef;
+        let position: FilePosition = 1234;
+        let data_dir = tempdir().unwrap();
+        let view = CheckpointsView::default();
+        {
+            let mut chkptr = Checkpointer::with_view(
+                &data_dir.path(),
+                FingerprintStrategy::Checksum,
+                view.clone(),
+            );
+            view.update(fingerprint, position);
+            assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
+            chkptr.write_checkpoints().ok();
+        }
+        {
+            let mut chkptr = Checkpointer::new(&data_dir.path(), FingerprintStrategy::Checksum);
+            chkptr.read_checkpoints(None);
+            assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
+        }
+    }
+
+    #[test]
+    fn test_checkpointer_migrates_legacy_directory() {
+        let fingerprint: FileFingerprint = 0x1234567890abcdef;
+        let position: FilePosition = 1234;
+        let data_dir = tempdir().unwrap();
+        let legacy_directory = data_dir.path().join("checkpoints");
+        fs::create_dir_all(&legacy_directory).unwrap();
+        fs::File::create(legacy_directory.join(format!("{:x}.{}", fingerprint, position))).unwrap();
+        {
+            let mut chkptr = Checkpointer::new(&data_dir.path(), FingerprintStrategy::Checksum);
+            chkptr.read_checkpoints(None);
+            assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
+            chkptr.write_checkpoints().unwrap();
+            assert!(!legacy_directory.exists());
+            assert!(data_dir.path().join(STABLE_FILE_NAME).exists());
+        }
+        {
+            let mut chkptr = Checkpointer::new(&data_dir.path(), FingerprintStrategy::Checksum);
+            chkptr.read_checkpoints(None);
+            assert_eq!(chkptr.get_checkpoint(fingerprint), Some(position));
+        }
+    }
+
+    #[test]
+    fn test_checkpointer_drops_stale_checkpoints() {
+        let data_dir = tempdir().unwrap();
+        let log_path = data_dir.path().join("file.log");
+        fs::write(&log_path, b"hello world\n").unwrap();
+        {
+            let mut chkptr = Checkpointer::new(&data_dir.path(), FingerprintStrategy::Checksum);
+            chkptr.set_checkpoint(1, 12);
+            chkptr.set_path(1, &log_path);
+            chkptr.write_checkpoints().unwrap();
+        }
+
+        let stored = fs::read_to_string(data_dir.path().join(STABLE_FILE_NAME)).unwrap();
+        assert!(stored.contains(r#""version":"1""#));
+        assert!(stored.contains(r#""strategy":"checksum""#));
+        assert!(stored.contains("file.log"));
+
+        // Checkpoints of another fingerprinting strategy never match.
+        let mut chkptr = Checkpointer::new(&data_dir.path(), FingerprintStrategy::DevInode);
+        chkptr.read_checkpoints(None);
+        assert_eq!(chkptr.get_checkpoint(1), None);
+
+        // The file was last modified before `ignore_before`.
+        let ignore_before = SystemTime::now() + Duration::from_secs(60);
+        let mut chkptr = Checkpointer::new(&data_dir.path(), FingerprintStrategy::Checksum);
+        chkptr.read_checkpoints(Some(ignore_before));
+        assert_eq!(chkptr.get_checkpoint(1), None);
+
+        let mut chkptr = Checkpointer::new(&data_dir.path(), FingerprintStrategy::Checksum);
+        chkptr.read_checkpoints(None);
+        assert_eq!(chkptr.get_checkpoint(1), Some(12));
+    }
+
+    #[test]
+    fn test_checkpointer_expires_checkpoints() {
+        let data_dir = tempdir().unwrap();
+        let stable_file_path = data_dir.path().join(STABLE_FILE_NAME);
+        let log_path = data_dir.path().join("file.log");
+        fs::write(&log_path, b"hello world\n").unwrap();
+
+        let mut chkptr = Checkpointer::new(&data_dir.path(), FingerprintStrategy::Checksum);
+        chkptr.set_checkpoint(1, 12);
+        chkptr.set_path(1, &log_path);
+        chkptr.write_checkpoints().unwrap();
+
+        // Nothing is written while no checkpoint changed.
+        fs::remove_file(&stable_file_path).unwrap();
+        chkptr.write_checkpoints().unwrap();
+        assert!(!stable_file_path.exists());
+
+        // A watched file keeps its checkpoint even once it's gone.
+        fs::remove_file(&log_path).unwrap();
+        chkptr.set_checkpoint(2, 5);
+        chkptr.write_checkpoints().unwrap();
+        assert_eq!(chkptr.get_checkpoint(1), Some(12));
+
+        chkptr.unwatch(1);
+        chkptr.set_checkpoint(2, 10);
+        chkptr.write_checkpoints().unwrap();
+        assert_eq!(chkptr.get_checkpoint(1), None);
+        assert_eq!(chkptr.get_checkpoint(2), Some(10));
+
+        let mut chkptr = Checkpointer::new(&data_dir.path(), FingerprintStrategy::Checksum);
+        chkptr.read_checkpoints(None);
+        assert_eq!(chkptr.get_checkpoint(1), None);
+        assert_eq!(chkptr.get_checkpoint(2), Some(10));
+    }
+
+    #[test]
+    fn test_read_limiter() {
+        let mut limiter = ReadLimiter::new(100);
+        assert!(limiter.has_allowance());
+        assert_eq!(limiter.wait_time(), Duration::from_secs(0));
+
+        // Lines are read as a whole, running the allowance into debt.
+        limiter.consume(60);
+        assert!(limiter.has_allowance());
+        limiter.consume(89);
+        assert!(!limiter.has_allowance());
+        assert_eq!(limiter.wait_time(), Duration::from_millis(500));
+
+        // The allowance never grows beyond a second's worth.
+        limiter.allowance = 0;
+        limiter.last_refill -= Duration::from_secs(10);
+        limiter.refill();
+        assert_eq!(limiter.allowance, 100);
+    }
 }
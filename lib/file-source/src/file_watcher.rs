@@ -1,12 +1,14 @@
 use crate::FilePosition;
+use bytes::Bytes;
 use flate2::bufread::MultiGzDecoder;
 use std::{
     fs::{self, File},
-    io::{self, BufRead, Seek},
+    io::{self, BufRead, Read, Seek},
     path::PathBuf,
     thread,
     time::{Duration, Instant, SystemTime},
 };
+use zstd::stream::read::Decoder;
 
 use crate::metadata_ext::PortableFileExt;
 
@@ -21,7 +23,14 @@ pub struct FileWatcher {
     pub path: PathBuf,
     findable: bool,
     reader: Box<dyn BufRead>,
+    compressed: bool,
     file_position: FilePosition,
+    line_delimiter: Bytes,
+    detect_byte_order_mark: bool,
+    byte_order_mark: Option<ByteOrderMark>,
+    /// The bytes read of a character that isn't complete yet, with a
+    /// delimiter longer than a byte. They're not part of `file_position`.
+    partial: Vec<u8>,
     devno: u64,
     inode: u64,
     is_dead: bool,
@@ -35,10 +44,22 @@ impl FileWatcher {
     /// The input path will be used by `FileWatcher` to prime its state
     /// machine. A `FileWatcher` tracks _only one_ file. This function returns
     /// None if the path does not exist or is not readable by the current process.
+    /// Lines are split on `line_delimiter`, which is only matched at multiples
+    /// of its length into a line when it is longer than a byte. With
+    /// `detect_byte_order_mark`, a file that starts with one is split on the
+    /// delimiter of the encoding it marks instead, and the mark itself is
+    /// skipped.
+    ///
+    /// `compressed_end` is where a compressed file ended when it was last read
+    /// to the end. A compressed file that was already read that far isn't
+    /// decompressed again.
     pub fn new(
         path: PathBuf,
         file_position: FilePosition,
         ignore_before: Option<SystemTime>,
+        line_delimiter: Bytes,
+        detect_byte_order_mark: bool,
+        compressed_end: Option<FilePosition>,
     ) -> Result<FileWatcher, io::Error> {
         let f = fs::File::open(&path)?;
         let (devno, ino) = (f.portable_dev()?, f.portable_ino()?);
@@ -53,27 +74,40 @@ impl FileWatcher {
             false
         };
 
-        let (reader, file_position): (Box<dyn BufRead>, FilePosition) = if is_gzipped(&mut reader)?
-        {
-            if file_position != 0 || too_old {
-                // We can't accurately seek into gzipped files without manually scanning through
-                // the entire thing, so for now we simply refuse to read gzipped files for which we
-                // already have a stored file position from a previous run.
+        let compression = detect_compression(&mut reader)?;
+        let byte_order_mark = match compression {
+            None if detect_byte_order_mark => ByteOrderMark::detect(reader.fill_buf()?),
+            _ => None,
+        };
+        let complete = compressed_end.map_or(false, |end| file_position >= end);
+
+        let (reader, file_position): (Box<dyn BufRead>, FilePosition) = match compression {
+            Some(compression) if too_old => {
                 debug!(
-                    message = "Not re-reading gzipped file with existing stored offset",
+                    message = "Not reading compressed file older than ignore_older.",
                     ?path,
-                    %file_position
+                    ?compression,
                 );
                 (Box::new(null_reader()), file_position)
-            } else {
-                (Box::new(io::BufReader::new(MultiGzDecoder::new(reader))), 0)
             }
-        } else if too_old {
-            let pos = reader.seek(io::SeekFrom::End(0)).unwrap();
-            (Box::new(reader), pos)
-        } else {
-            let pos = reader.seek(io::SeekFrom::Start(file_position)).unwrap();
-            (Box::new(reader), pos)
+            Some(compression) if complete => {
+                debug!(
+                    message = "Not reading compressed file that was already read.",
+                    ?path,
+                    ?compression,
+                );
+                (Box::new(null_reader()), file_position)
+            }
+            Some(compression) => decompress(reader, compression, file_position)?,
+            None if too_old => {
+                let pos = reader.seek(io::SeekFrom::End(0)).unwrap();
+                (Box::new(reader), pos)
+            }
+            None => {
+                let start = ByteOrderMark::skip(byte_order_mark, file_position);
+                let pos = reader.seek(io::SeekFrom::Start(start)).unwrap();
+                (Box::new(reader), pos)
+            }
         };
 
         let ts = metadata
@@ -87,7 +121,12 @@ impl FileWatcher {
             path,
             findable: true,
             reader,
+            compressed: compression.is_some(),
             file_position,
+            line_delimiter,
+            detect_byte_order_mark,
+            byte_order_mark,
+            partial: Vec::new(),
             devno,
             inode: ino,
             is_dead: false,
@@ -100,18 +139,24 @@ impl FileWatcher {
         let file_handle = File::open(&path)?;
         if (file_handle.portable_dev()?, file_handle.portable_ino()?) != (self.devno, self.inode) {
             let mut reader = io::BufReader::new(fs::File::open(&path)?);
-            let gzipped = is_gzipped(&mut reader)?;
-            let new_reader: Box<dyn BufRead> = if gzipped {
-                if self.file_position != 0 {
-                    Box::new(null_reader())
-                } else {
-                    Box::new(io::BufReader::new(MultiGzDecoder::new(reader)))
+            let compression = detect_compression(&mut reader)?;
+            let byte_order_mark = match compression {
+                None if self.detect_byte_order_mark => ByteOrderMark::detect(reader.fill_buf()?),
+                _ => None,
+            };
+            let (new_reader, file_position): (Box<dyn BufRead>, FilePosition) = match compression {
+                Some(compression) => decompress(reader, compression, self.file_position)?,
+                None => {
+                    let start = ByteOrderMark::skip(byte_order_mark, self.file_position);
+                    reader.seek(io::SeekFrom::Start(start))?;
+                    (Box::new(reader), start)
                 }
-            } else {
-                reader.seek(io::SeekFrom::Start(self.file_position))?;
-                Box::new(reader)
             };
             self.reader = new_reader;
+            self.compressed = compression.is_some();
+            self.byte_order_mark = byte_order_mark;
+            self.file_position = file_position;
+            self.partial.clear();
             self.devno = file_handle.portable_dev()?;
             self.inode = file_handle.portable_ino()?;
         }
@@ -139,6 +184,17 @@ impl FileWatcher {
         self.file_position
     }
 
+    /// Whether the file is decompressed as it's read. Compressed files are
+    /// never appended to, so they're done once they've been read to the end.
+    pub fn compressed(&self) -> bool {
+        self.compressed
+    }
+
+    /// The byte order mark the file started with, if it was looked for.
+    pub fn byte_order_mark(&self) -> Option<ByteOrderMark> {
+        self.byte_order_mark
+    }
+
     /// Read a single line from the underlying file
     ///
     /// This function will attempt to read a new line from its file, blocking,
@@ -151,7 +207,13 @@ impl FileWatcher {
         buffer.clear();
         let reader = &mut self.reader;
         let file_position = &mut self.file_position;
-        match read_until_with_max_size(reader, file_position, b'\n', &mut buffer, max_size) {
+        let delim = match self.byte_order_mark {
+            Some(byte_order_mark) => byte_order_mark.line_delimiter(),
+            None => &self.line_delimiter[..],
+        };
+        let partial = &mut self.partial;
+        match read_until_with_max_size(reader, file_position, delim, partial, &mut buffer, max_size)
+        {
             Ok(sz) => {
                 if sz > 0 {
                     self.track_read_success()
@@ -190,9 +252,84 @@ impl FileWatcher {
     }
 }
 
-fn is_gzipped(r: &mut io::BufReader<fs::File>) -> io::Result<bool> {
+/// A byte order mark at the start of a file, which tells the encoding of the
+/// whole file regardless of the configured one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ByteOrderMark {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl ByteOrderMark {
+    fn detect(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&[0xef, 0xbb, 0xbf]) {
+            Some(ByteOrderMark::Utf8)
+        } else if header.starts_with(&[0xff, 0xfe]) {
+            Some(ByteOrderMark::Utf16Le)
+        } else if header.starts_with(&[0xfe, 0xff]) {
+            Some(ByteOrderMark::Utf16Be)
+        } else {
+            None
+        }
+    }
+
+    /// Moves a position at the start of the file past the mark, so that it
+    /// never ends up in the first line.
+    fn skip(byte_order_mark: Option<Self>, file_position: FilePosition) -> FilePosition {
+        let len = match byte_order_mark {
+            Some(ByteOrderMark::Utf8) => 3,
+            Some(ByteOrderMark::Utf16Le) | Some(ByteOrderMark::Utf16Be) => 2,
+            None => 0,
+        };
+        file_position.max(len)
+    }
+
+    /// The bytes a newline is encoded as.
+    fn line_delimiter(self) -> &'static [u8] {
+        match self {
+            ByteOrderMark::Utf8 => b"\n",
+            ByteOrderMark::Utf16Le => b"\n\0",
+            ByteOrderMark::Utf16Be => b"\0\n",
+        }
+    }
+}
+
+/// A compression format detected by the magic bytes at the start of a file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Compression {
+    Gzip,
+    Zstd,
+}
+
+fn detect_compression(r: &mut io::BufReader<fs::File>) -> io::Result<Option<Compression>> {
     let header_bytes = r.fill_buf()?;
-    Ok(header_bytes.starts_with(&[0x1f, 0x8b]))
+    Ok(if header_bytes.starts_with(&[0x1f, 0x8b]) {
+        Some(Compression::Gzip)
+    } else if header_bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Some(Compression::Zstd)
+    } else {
+        None
+    })
+}
+
+/// Stream-decompresses `reader`, skipping the first `file_position`
+/// decompressed bytes.
+///
+/// Compressed files can't be seeked into, so the lines read before a restart
+/// are decompressed again and discarded. Files that were already read to
+/// their end aren't passed here at all, see `FileWatcher::new`.
+fn decompress(
+    reader: io::BufReader<fs::File>,
+    compression: Compression,
+    file_position: FilePosition,
+) -> io::Result<(Box<dyn BufRead>, FilePosition)> {
+    let mut reader: Box<dyn BufRead> = match compression {
+        Compression::Gzip => Box::new(io::BufReader::new(MultiGzDecoder::new(reader))),
+        Compression::Zstd => Box::new(io::BufReader::new(Decoder::with_buffer(reader)?)),
+    };
+    let skipped = io::copy(&mut reader.by_ref().take(file_position), &mut io::sink())?;
+    Ok((reader, skipped))
 }
 
 fn null_reader() -> impl BufRead {
@@ -202,10 +339,15 @@ fn null_reader() -> impl BufRead {
 // Tweak of https://github.com/rust-lang/rust/blob/bf843eb9c2d48a80a5992a5d60858e27269f9575/src/libstd/io/mod.rs#L1471
 // After more than max_size bytes are read as part of a single line, this discard the remaining bytes
 // in that line, and then starts again on the next line.
+//
+// With a delimiter longer than a byte, `partial` holds the bytes of a character that was cut off at
+// EOF. They're kept out of the line and the position until the rest of the character is read by a
+// later call, so that a half-written character is neither returned nor skipped after a restart.
 fn read_until_with_max_size<R: BufRead + ?Sized>(
     r: &mut R,
     p: &mut FilePosition,
-    delim: u8,
+    delim: &[u8],
+    partial: &mut Vec<u8>,
     buf: &mut Vec<u8>,
     max_size: usize,
 ) -> io::Result<usize> {
@@ -219,7 +361,8 @@ fn read_until_with_max_size<R: BufRead + ?Sized>(
             Err(e) => return Err(e),
         };
 
-        let (done, used) = {
+        let partial_before = partial.len();
+        let (done, used) = if let [delim] = *delim {
             // TODO: use memchr to make this faster
             match available.iter().position(|&b| b == delim) {
                 Some(i) => {
@@ -235,10 +378,14 @@ fn read_until_with_max_size<R: BufRead + ?Sized>(
                     (false, available.len())
                 }
             }
+        } else {
+            find_aligned(available, delim, partial, buf, discarding)
         };
         r.consume(used);
-        *p += used as u64; // do this at exactly same time
-        total_read += used;
+        // Bytes only count once the character they belong to is complete.
+        let read = partial_before + used - partial.len();
+        *p += read as u64; // do this at exactly same time
+        total_read += read;
 
         if !discarding && buf.len() > max_size {
             warn!(
@@ -263,55 +410,252 @@ fn read_until_with_max_size<R: BufRead + ?Sized>(
     }
 }
 
+/// Finds a delimiter that is longer than a byte, such as a newline in UTF-16,
+/// only at multiples of its length into the line, so that it's never matched
+/// across two characters. Bytes before the delimiter are added to `buf`,
+/// unless `discarding`. Returns whether the delimiter was found, and how many
+/// bytes of `available` were used.
+fn find_aligned(
+    available: &[u8],
+    delim: &[u8],
+    partial: &mut Vec<u8>,
+    buf: &mut Vec<u8>,
+    discarding: bool,
+) -> (bool, usize) {
+    for (i, &b) in available.iter().enumerate() {
+        partial.push(b);
+        if partial.len() == delim.len() {
+            let found = partial[..] == *delim;
+            if !found && !discarding {
+                buf.extend_from_slice(partial);
+            }
+            partial.clear();
+            if found {
+                return (true, i + 1);
+            }
+        }
+    }
+    (false, available.len())
+}
+
 #[cfg(test)]
 mod test {
-    use super::read_until_with_max_size;
-    use std::io::Cursor;
+    use super::{read_until_with_max_size, ByteOrderMark, FileWatcher};
+    use bytes::Bytes;
+    use flate2::{write::GzEncoder, Compression};
+    use std::{fs, io::Cursor, io::Write};
+    use tempfile::tempdir;
 
     #[test]
     fn test_read_until_with_max_size() {
         let mut buf = Cursor::new(&b"12"[..]);
         let mut pos = 0;
+        let mut partial = Vec::new();
         let mut v = Vec::new();
-        let p = read_until_with_max_size(&mut buf, &mut pos, b'3', &mut v, 1000).unwrap();
+        let p =
+            read_until_with_max_size(&mut buf, &mut pos, b"3", &mut partial, &mut v, 1000).unwrap();
         assert_eq!(pos, 2);
         assert_eq!(p, 2);
         assert_eq!(v, b"12");
 
         let mut buf = Cursor::new(&b"1233"[..]);
         let mut pos = 0;
+        let mut partial = Vec::new();
         let mut v = Vec::new();
-        let p = read_until_with_max_size(&mut buf, &mut pos, b'3', &mut v, 1000).unwrap();
+        let p =
+            read_until_with_max_size(&mut buf, &mut pos, b"3", &mut partial, &mut v, 1000).unwrap();
         assert_eq!(pos, 3);
         assert_eq!(p, 3);
         assert_eq!(v, b"12");
         v.truncate(0);
-        let p = read_until_with_max_size(&mut buf, &mut pos, b'3', &mut v, 1000).unwrap();
+        let p =
+            read_until_with_max_size(&mut buf, &mut pos, b"3", &mut partial, &mut v, 1000).unwrap();
         assert_eq!(pos, 4);
         assert_eq!(p, 1);
         assert_eq!(v, b"");
         v.truncate(0);
-        let p = read_until_with_max_size(&mut buf, &mut pos, b'3', &mut v, 1000).unwrap();
+        let p =
+            read_until_with_max_size(&mut buf, &mut pos, b"3", &mut partial, &mut v, 1000).unwrap();
         assert_eq!(pos, 4);
         assert_eq!(p, 0);
         assert_eq!(v, []);
 
         let mut buf = Cursor::new(&b"short\nthis is too long\nexact size\n11 eleven11\n"[..]);
         let mut pos = 0;
+        let mut partial = Vec::new();
         let mut v = Vec::new();
-        let p = read_until_with_max_size(&mut buf, &mut pos, b'\n', &mut v, 10).unwrap();
+        let p =
+            read_until_with_max_size(&mut buf, &mut pos, b"\n", &mut partial, &mut v, 10).unwrap();
         assert_eq!(pos, 6);
         assert_eq!(p, 6);
         assert_eq!(v, b"short");
         v.truncate(0);
-        let p = read_until_with_max_size(&mut buf, &mut pos, b'\n', &mut v, 10).unwrap();
+        let p =
+            read_until_with_max_size(&mut buf, &mut pos, b"\n", &mut partial, &mut v, 10).unwrap();
         assert_eq!(pos, 34);
         assert_eq!(p, 28);
         assert_eq!(v, b"exact size");
         v.truncate(0);
-        let p = read_until_with_max_size(&mut buf, &mut pos, b'\n', &mut v, 10).unwrap();
+        let p =
+            read_until_with_max_size(&mut buf, &mut pos, b"\n", &mut partial, &mut v, 10).unwrap();
         assert_eq!(pos, 46);
         assert_eq!(p, 12);
         assert_eq!(v, []);
     }
+
+    #[test]
+    fn test_read_until_with_multi_byte_delimiter() {
+        // "a\n\u{0a0a}\n" in UTF-16LE, split into lines on "\n" in UTF-16LE.
+        let mut buf = Cursor::new(&b"a\0\n\0\n\n\n\0"[..]);
+        let mut pos = 0;
+        let mut partial = Vec::new();
+        let mut v = Vec::new();
+        let p = read_until_with_max_size(&mut buf, &mut pos, b"\n\0", &mut partial, &mut v, 1000)
+            .unwrap();
+        assert_eq!(pos, 4);
+        assert_eq!(p, 4);
+        assert_eq!(v, b"a\0");
+        v.truncate(0);
+        let p = read_until_with_max_size(&mut buf, &mut pos, b"\n\0", &mut partial, &mut v, 1000)
+            .unwrap();
+        assert_eq!(pos, 8);
+        assert_eq!(p, 4);
+        assert_eq!(v, b"\n\n");
+
+        // An incomplete character at the end is held back until it's complete.
+        let mut buf = Cursor::new(&b"a\0b"[..]);
+        let mut pos = 0;
+        let mut partial = Vec::new();
+        let mut v = Vec::new();
+        let p = read_until_with_max_size(&mut buf, &mut pos, b"\n\0", &mut partial, &mut v, 1000)
+            .unwrap();
+        assert_eq!(pos, 2);
+        assert_eq!(p, 2);
+        assert_eq!(v, b"a\0");
+        assert_eq!(partial, b"b");
+    }
+
+    #[test]
+    fn test_read_half_written_utf16_line() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        // "ab" in UTF-16LE with the second character cut in half.
+        fs::write(&path, b"a\0b").unwrap();
+
+        let mut buf = Vec::new();
+        let mut watcher = FileWatcher::new(
+            path.clone(),
+            0,
+            None,
+            Bytes::from_static(b"\n\0"),
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(watcher.read_line(&mut buf, 1000).unwrap(), 2);
+        assert_eq!(buf, b"a\0");
+        assert_eq!(watcher.get_file_position(), 2);
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"\0\n\0c\0\n\0").unwrap();
+        assert_eq!(watcher.read_line(&mut buf, 1000).unwrap(), 4);
+        assert_eq!(buf, b"b\0");
+        assert_eq!(watcher.read_line(&mut buf, 1000).unwrap(), 4);
+        assert_eq!(buf, b"c\0");
+        assert_eq!(watcher.get_file_position(), 10);
+    }
+
+    #[test]
+    fn test_byte_order_mark_picks_delimiter() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        // "a\nb\n" in UTF-16BE, while UTF-16LE is configured.
+        fs::write(&path, b"\xfe\xff\0a\0\n\0b\0\n").unwrap();
+
+        let mut buf = Vec::new();
+        let mut watcher = FileWatcher::new(
+            path.clone(),
+            0,
+            None,
+            Bytes::from_static(b"\n\0"),
+            true,
+            None,
+        )
+        .unwrap();
+        assert_eq!(watcher.byte_order_mark(), Some(ByteOrderMark::Utf16Be));
+        assert_eq!(watcher.get_file_position(), 2);
+        assert_eq!(watcher.read_line(&mut buf, 1000).unwrap(), 4);
+        assert_eq!(buf, b"\0a");
+
+        // The mark is found again when resuming past it.
+        let position = watcher.get_file_position();
+        let mut watcher = FileWatcher::new(
+            path,
+            position,
+            None,
+            Bytes::from_static(b"\n\0"),
+            true,
+            None,
+        )
+        .unwrap();
+        assert_eq!(watcher.read_line(&mut buf, 1000).unwrap(), 4);
+        assert_eq!(buf, b"\0b");
+    }
+
+    #[test]
+    fn test_skip_read_compressed_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("app.log.1.gz");
+        // Only the magic bytes are valid, so decompressing it would fail.
+        fs::write(&path, b"\x1f\x8b not actually gzip").unwrap();
+        assert!(FileWatcher::new(
+            path.clone(),
+            20,
+            None,
+            Bytes::from_static(b"\n"),
+            false,
+            None
+        )
+        .is_err());
+
+        let mut buf = Vec::new();
+        let mut watcher =
+            FileWatcher::new(path, 20, None, Bytes::from_static(b"\n"), false, Some(20)).unwrap();
+        assert!(watcher.compressed());
+        assert_eq!(watcher.get_file_position(), 20);
+        assert_eq!(watcher.read_line(&mut buf, 1000).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_resume_compressed_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("app.log.1.gz");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"first\nsecond\nthird\n").unwrap();
+        fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+        let mut buf = Vec::new();
+        let mut watcher = FileWatcher::new(
+            path.clone(),
+            6,
+            None,
+            Bytes::from_static(b"\n"),
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(watcher.get_file_position(), 6);
+        assert_eq!(watcher.read_line(&mut buf, 1000).unwrap(), 7);
+        assert_eq!(buf, b"second");
+        assert_eq!(watcher.read_line(&mut buf, 1000).unwrap(), 6);
+        assert_eq!(buf, b"third");
+        assert_eq!(watcher.read_line(&mut buf, 1000).unwrap(), 0);
+
+        // A fully read file has nothing more to read after a restart.
+        let position = watcher.get_file_position();
+        let mut watcher =
+            FileWatcher::new(path, position, None, Bytes::from_static(b"\n"), false, None).unwrap();
+        assert_eq!(watcher.get_file_position(), position);
+        assert_eq!(watcher.read_line(&mut buf, 1000).unwrap(), 0);
+    }
 }
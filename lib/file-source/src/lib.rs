@@ -8,15 +8,22 @@ mod file_watcher;
 mod metadata_ext;
 pub mod paths_provider;
 
-pub use self::file_server::{FileServer, Fingerprinter, Shutdown as FileServerShutdown};
+pub use self::file_server::{
+    CheckpointsView, FileServer, FingerprintStrategy, Fingerprinter, ReadScheduling,
+    Shutdown as FileServerShutdown,
+};
+pub use self::file_watcher::ByteOrderMark;
 
-type FileFingerprint = u64;
-type FilePosition = u64;
+pub type FileFingerprint = u64;
+pub type FilePosition = u64;
+/// The position in a file after a line read from it.
+pub type Checkpoint = (FileFingerprint, FilePosition);
 
 #[cfg(test)]
 mod test {
     use self::file_watcher::FileWatcher;
     use super::*;
+    use bytes::Bytes;
     use quickcheck::{Arbitrary, Gen, QuickCheck, TestResult};
     use std::fs;
     use std::io::Write;
@@ -201,7 +208,15 @@ mod test {
         let path = dir.path().join("a_file.log");
         let mut fp = fs::File::create(&path).expect("could not create");
         let mut rotation_count = 0;
-        let mut fw = FileWatcher::new(path.clone(), 0, None).expect("must be able to create");
+        let mut fw = FileWatcher::new(
+            path.clone(),
+            0,
+            None,
+            Bytes::from_static(b"\n"),
+            false,
+            None,
+        )
+        .expect("must be able to create");
 
         let mut writes = 0;
         let mut sut_reads = 0;
@@ -293,7 +308,15 @@ mod test {
         let path = dir.path().join("a_file.log");
         let mut fp = fs::File::create(&path).expect("could not create");
         let mut rotation_count = 0;
-        let mut fw = FileWatcher::new(path.clone(), 0, None).expect("must be able to create");
+        let mut fw = FileWatcher::new(
+            path.clone(),
+            0,
+            None,
+            Bytes::from_static(b"\n"),
+            false,
+            None,
+        )
+        .expect("must be able to create");
 
         let mut fwfiles: Vec<FWFile> = vec![];
         fwfiles.push(FWFile::new());
@@ -0,0 +1,378 @@
+//! A local HTTP API for inspecting and controlling a running topology.
+//!
+//! The API is opt-in through the `[api]` section of the global options. It
+//! exposes the components of the running topology along with their
+//! per-component counters, and allows triggering a configuration reload in
+//! the same way `SIGHUP` does.
+
+use crate::topology::{HealthRegistry, RunningTopology};
+use futures::{compat::Future01CompatExt, FutureExt};
+use futures01::sync::mpsc;
+use hyper::{
+    header::HeaderValue,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, StatusCode,
+};
+use metrics_runtime::Measurement;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+};
+use stream_cancel::{Trigger, Tripwire};
+use tracing::field;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Options {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_address")]
+    pub address: SocketAddr,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: default_address(),
+        }
+    }
+}
+
+pub fn default_address() -> SocketAddr {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8686)
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ComponentKind {
+    Source,
+    Transform,
+    Sink,
+}
+
+impl ComponentKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ComponentKind::Source => "source",
+            ComponentKind::Transform => "transform",
+            ComponentKind::Sink => "sink",
+        }
+    }
+}
+
+/// A single component of the running topology.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Component {
+    pub name: String,
+    pub kind: ComponentKind,
+    #[serde(rename = "type")]
+    pub component_type: String,
+    pub inputs: Vec<String>,
+    pub healthy: bool,
+}
+
+/// Counters collected for a single component from the internal metrics
+/// registry.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ComponentMetrics {
+    /// Events that entered the component, only counted for transforms and
+    /// sinks.
+    pub events_received: u64,
+    pub events_processed: u64,
+    pub bytes_processed: u64,
+    pub processing_errors: u64,
+    /// Events currently held in the component's input buffer, if it has one.
+    pub buffer_events: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ComponentStatus {
+    #[serde(flatten)]
+    pub component: Component,
+    pub metrics: ComponentMetrics,
+}
+
+/// Snapshot of the running topology shared with the API server. The list of
+/// components is refreshed by the owner of the `RunningTopology` whenever the
+/// topology changes, while their health is read live.
+#[derive(Debug, Clone, Default)]
+pub struct TopologyState {
+    components: Arc<RwLock<Vec<Component>>>,
+    health: Arc<RwLock<HealthRegistry>>,
+}
+
+impl TopologyState {
+    pub fn update(&self, topology: &RunningTopology) {
+        *self.components.write().unwrap() = topology.components();
+        *self.health.write().unwrap() = topology.health();
+    }
+
+    fn components(&self) -> Vec<Component> {
+        self.components.read().unwrap().clone()
+    }
+
+    fn statuses(&self) -> Vec<ComponentStatus> {
+        let mut metrics = capture_component_metrics();
+        let health = self.health.read().unwrap();
+        self.components()
+            .into_iter()
+            .map(|mut component| {
+                component.healthy = health.is_healthy(&component.name);
+                let metrics = metrics.remove(&component.name).unwrap_or_default();
+                ComponentStatus { component, metrics }
+            })
+            .collect()
+    }
+}
+
+/// Sums the per-component counters and gauges currently held by the metrics
+/// controller, keyed by the `component_name` label.
+fn capture_component_metrics() -> HashMap<String, ComponentMetrics> {
+    let mut metrics = HashMap::<String, ComponentMetrics>::new();
+
+    let controller = match crate::metrics::CONTROLLER.get() {
+        Some(controller) => controller,
+        None => return metrics,
+    };
+
+    crate::buffers::report_usage();
+    for (key, measurement) in controller.snapshot().into_measurements() {
+        let name = match key.labels().find(|label| label.key() == "component_name") {
+            Some(label) => label.value().to_string(),
+            None => continue,
+        };
+
+        let entry = metrics.entry(name).or_default();
+        match (&*key.name(), measurement) {
+            ("events_received", Measurement::Counter(value)) => entry.events_received += value,
+            ("events_processed", Measurement::Counter(value)) => entry.events_processed += value,
+            ("bytes_processed", Measurement::Counter(value)) => entry.bytes_processed += value,
+            ("processing_errors", Measurement::Counter(value)) => entry.processing_errors += value,
+            ("buffer_events", Measurement::Gauge(value)) => entry.buffer_events = Some(value),
+            _ => (),
+        }
+    }
+
+    metrics
+}
+
+/// A running API server. The server shuts down when this is dropped.
+pub struct Server {
+    _shutdown_trigger: Trigger,
+}
+
+impl Server {
+    /// Binds the API server to the configured address and spawns it onto the
+    /// current runtime. Reload requests are forwarded to `reload`.
+    pub fn start(
+        options: &Options,
+        state: TopologyState,
+        reload: mpsc::UnboundedSender<()>,
+    ) -> crate::Result<Self> {
+        let builder = hyper::Server::try_bind(&options.address)?;
+
+        let new_service = make_service_fn(move |_| {
+            let state = state.clone();
+            let reload = reload.clone();
+
+            async move {
+                Ok::<_, crate::Error>(service_fn(move |req: Request<Body>| {
+                    let span = info_span!(
+                        "api_server",
+                        method = field::debug(req.method()),
+                        path = field::debug(req.uri().path()),
+                    );
+                    let response = span.in_scope(|| handle(req, &state, &reload));
+                    futures::future::ok::<_, crate::Error>(response)
+                }))
+            }
+        });
+
+        let (trigger, tripwire) = Tripwire::new();
+
+        let server = builder
+            .serve(new_service)
+            .with_graceful_shutdown(tripwire.compat().map(|_| ()))
+            .map(|result| {
+                if let Err(error) = result {
+                    error!(message = "API server error.", %error);
+                }
+            });
+
+        tokio::spawn(server);
+
+        Ok(Self {
+            _shutdown_trigger: trigger,
+        })
+    }
+}
+
+fn handle(
+    req: Request<Body>,
+    state: &TopologyState,
+    reload: &mpsc::UnboundedSender<()>,
+) -> Response<Body> {
+    let path = req.uri().path().trim_end_matches('/');
+
+    let response = match (req.method(), path) {
+        (&Method::GET, "/health") => {
+            json_response(StatusCode::OK, &serde_json::json!({ "ok": true }))
+        }
+        (&Method::GET, "/components") => json_response(StatusCode::OK, &state.statuses()),
+        (&Method::GET, path) if path.starts_with("/components/") => {
+            let name = &path["/components/".len()..];
+            match state
+                .statuses()
+                .into_iter()
+                .find(|status| status.component.name == name)
+            {
+                Some(status) => json_response(StatusCode::OK, &status),
+                None => empty_response(StatusCode::NOT_FOUND),
+            }
+        }
+        (&Method::POST, "/reload") => match reload.unbounded_send(()) {
+            Ok(()) => empty_response(StatusCode::ACCEPTED),
+            Err(_) => empty_response(StatusCode::SERVICE_UNAVAILABLE),
+        },
+        _ => empty_response(StatusCode::NOT_FOUND),
+    };
+
+    debug!(
+        message = "request complete",
+        response_code = field::debug(response.status())
+    );
+    response
+}
+
+fn json_response(status: StatusCode, body: &impl Serialize) -> Response<Body> {
+    let mut response = Response::new(Body::from(serde_json::to_vec(body).unwrap()));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert("Content-Type", HeaderValue::from_static("application/json"));
+    response
+}
+
+fn empty_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures01::Stream;
+
+    fn state() -> TopologyState {
+        let state = TopologyState::default();
+        state.health.read().unwrap().register("in");
+        state.health.read().unwrap().register("out");
+        *state.components.write().unwrap() = vec![
+            Component {
+                name: "in".into(),
+                kind: ComponentKind::Source,
+                component_type: "stdin".into(),
+                inputs: vec![],
+                healthy: true,
+            },
+            Component {
+                name: "out".into(),
+                kind: ComponentKind::Sink,
+                component_type: "console".into(),
+                inputs: vec!["in".into()],
+                healthy: true,
+            },
+        ];
+        state
+    }
+
+    fn request(method: Method, path: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    async fn body_json(response: Response<Body>) -> serde_json::Value {
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn options_default() {
+        let options: Options = toml::from_str("").unwrap();
+        assert_eq!(options, Options::default());
+        assert!(!options.enabled);
+        assert_eq!(options.address, "127.0.0.1:8686".parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn lists_components() {
+        let (tx, _rx) = mpsc::unbounded();
+        let response = handle(request(Method::GET, "/components"), &state(), &tx);
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_json(response).await;
+        let components = body.as_array().unwrap();
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0]["name"], "in");
+        assert_eq!(components[0]["kind"], "source");
+        assert_eq!(components[0]["type"], "stdin");
+        assert_eq!(components[1]["inputs"], serde_json::json!(["in"]));
+        assert_eq!(components[1]["metrics"]["events_processed"], 0);
+    }
+
+    #[test]
+    fn reads_health_live() {
+        let state = state();
+        let healthy = |state: &TopologyState| {
+            state
+                .statuses()
+                .into_iter()
+                .map(|status| status.component.healthy)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(healthy(&state), vec![true, true]);
+
+        // Reported without the state being updated.
+        let health = state.health.read().unwrap().clone();
+        health.register("out").healthcheck_failed();
+        assert_eq!(healthy(&state), vec![true, false]);
+    }
+
+    #[tokio::test]
+    async fn gets_single_component() {
+        let (tx, _rx) = mpsc::unbounded();
+
+        let response = handle(request(Method::GET, "/components/out"), &state(), &tx);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_json(response).await["type"], "console");
+
+        let response = handle(request(Method::GET, "/components/nope"), &state(), &tx);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn reload_is_forwarded() {
+        let (tx, rx) = mpsc::unbounded();
+        let mut rx = rx.wait();
+
+        let response = handle(request(Method::GET, "/reload"), &state(), &tx);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = handle(request(Method::POST, "/reload"), &state(), &tx);
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert_eq!(rx.next(), Some(Ok(())));
+
+        drop(rx);
+        let response = handle(request(Method::POST, "/reload"), &state(), &tx);
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
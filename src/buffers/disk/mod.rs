@@ -1,12 +1,12 @@
-#![cfg(feature = "leveldb")]
-
 use crate::event::Event;
 use futures01::{Async, AsyncSink, Sink, Stream};
 use snafu::Snafu;
 use std::io;
 use std::path::{Path, PathBuf};
 
+#[cfg(feature = "leveldb")]
 pub mod leveldb_buffer;
+pub mod segmented_buffer;
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -19,11 +19,14 @@ pub enum Error {
         data_dir: PathBuf,
         source: std::io::Error,
     },
+    #[cfg(feature = "leveldb")]
     #[snafu(display("Unable to open data_dir {:?}", data_dir))]
     DataDirOpenError {
         data_dir: PathBuf,
         source: leveldb::database::error::Error,
     },
+    #[snafu(display("Unable to open disk buffer {:?}: {}", path, source))]
+    BufferOpenError { path: PathBuf, source: io::Error },
 }
 
 pub trait DiskBuffer {
@@ -38,7 +41,14 @@ pub trait DiskBuffer {
 
 #[derive(Clone)]
 pub struct Writer {
-    inner: leveldb_buffer::Writer,
+    inner: WriterInner,
+}
+
+#[derive(Clone)]
+enum WriterInner {
+    #[cfg(feature = "leveldb")]
+    LevelDb(leveldb_buffer::Writer),
+    Segmented(segmented_buffer::Writer),
 }
 
 impl Sink for Writer {
@@ -49,29 +59,52 @@ impl Sink for Writer {
         &mut self,
         event: Self::SinkItem,
     ) -> Result<AsyncSink<Self::SinkItem>, Self::SinkError> {
-        self.inner.start_send(event)
+        match &mut self.inner {
+            #[cfg(feature = "leveldb")]
+            WriterInner::LevelDb(writer) => writer.start_send(event),
+            WriterInner::Segmented(writer) => writer.start_send(event),
+        }
     }
 
     fn poll_complete(&mut self) -> Result<Async<()>, Self::SinkError> {
-        self.inner.poll_complete()
+        match &mut self.inner {
+            #[cfg(feature = "leveldb")]
+            WriterInner::LevelDb(writer) => writer.poll_complete(),
+            WriterInner::Segmented(writer) => writer.poll_complete(),
+        }
     }
 }
 
-pub fn open(
-    data_dir: &Path,
-    name: &str,
-    max_size: usize,
-) -> Result<
-    (
-        Writer,
-        Box<dyn Stream<Item = Event, Error = ()> + Send>,
-        super::Acker,
-    ),
-    Error,
-> {
-    let path = data_dir.join(name);
+type Opened = (
+    Writer,
+    Box<dyn Stream<Item = Event, Error = ()> + Send>,
+    super::Acker,
+);
+
+/// Opens the LevelDB backed buffer `name` inside `data_dir`.
+#[cfg(feature = "leveldb")]
+pub fn open(data_dir: &Path, name: &str, max_size: usize) -> Result<Opened, Error> {
+    check_data_dir(data_dir)?;
+
+    let (writer, reader, acker) = leveldb_buffer::Buffer::build(data_dir.join(name), max_size)?;
+    let writer = Writer {
+        inner: WriterInner::LevelDb(writer),
+    };
+    Ok((writer, Box::new(reader), acker))
+}
+
+/// Opens the segmented file buffer `name` inside `data_dir`.
+pub fn open_segmented(data_dir: &Path, name: &str, max_size: usize) -> Result<Opened, Error> {
+    check_data_dir(data_dir)?;
+
+    let (writer, reader, acker) = segmented_buffer::Buffer::build(data_dir.join(name), max_size)?;
+    let writer = Writer {
+        inner: WriterInner::Segmented(writer),
+    };
+    Ok((writer, Box::new(reader), acker))
+}
 
-    // Check data dir
+fn check_data_dir(data_dir: &Path) -> Result<(), Error> {
     std::fs::metadata(&data_dir)
         .map_err(|e| match e.kind() {
             io::ErrorKind::PermissionDenied => Error::DataDirNotWritable {
@@ -93,8 +126,5 @@ pub fn open(
             } else {
                 Ok(())
             }
-        })?;
-
-    let (writer, reader, acker) = leveldb_buffer::Buffer::build(path, max_size)?;
-    Ok((Writer { inner: writer }, Box::new(reader), acker))
+        })
 }
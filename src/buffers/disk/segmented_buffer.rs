@@ -0,0 +1,689 @@
+//! A disk buffer built on plain files, without any native dependencies.
+//!
+//! Events are appended to a sequence of segment files in the buffer
+//! directory. Each record is framed as a little endian `u32` payload length
+//! and a CRC32 checksum of the payload, followed by the protobuf encoded
+//! event. The position just past the last acknowledged record is kept in the
+//! `ack` file, which is replaced atomically, and segments that lie entirely
+//! before it are deleted.
+//!
+//! On startup everything after the ack cursor is replayed. A record that was
+//! only partially written when the process died is truncated away.
+
+use super::{BufferOpenError, Error};
+use crate::{
+    buffers::Acker,
+    event::{proto, Event},
+};
+use futures01::{
+    task::{self, AtomicTask, Task},
+    Async, AsyncSink, Poll, Sink, Stream,
+};
+use prost::Message;
+use snafu::ResultExt;
+use std::{
+    collections::VecDeque,
+    convert::TryInto,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+};
+
+const HEADER_LEN: usize = 8;
+const MAX_RECORD_SIZE: usize = 128 * 1024 * 1024;
+const MAX_SEGMENT_SIZE: usize = 4 * 1024 * 1024;
+const SEGMENT_EXTENSION: &str = "segment";
+const ACK_FILE: &str = "ack";
+const ACK_TMP_FILE: &str = "ack.tmp";
+
+/// Position just past the last acknowledged record.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Cursor {
+    segment: usize,
+    offset: u64,
+}
+
+enum ReadResult {
+    Record(Vec<u8>),
+    /// There is no data left at the read position.
+    End,
+    /// Only part of a record is available, it may still be being written.
+    Incomplete,
+    /// The record failed its length or checksum validation.
+    Corrupt,
+}
+
+struct Shared {
+    dir: PathBuf,
+    write_segment: AtomicUsize,
+    current_size: AtomicUsize,
+    blocked_write_tasks: Mutex<Vec<Task>>,
+}
+
+struct WriterState {
+    file: BufWriter<File>,
+    segment_size: usize,
+    segment_max_size: usize,
+}
+
+#[derive(Clone)]
+pub struct Writer {
+    state: Arc<Mutex<WriterState>>,
+    shared: Arc<Shared>,
+    write_notifier: Arc<AtomicTask>,
+    max_size: usize,
+}
+
+impl Sink for Writer {
+    type SinkItem = Event;
+    type SinkError = ();
+
+    fn start_send(
+        &mut self,
+        event: Self::SinkItem,
+    ) -> Result<AsyncSink<Self::SinkItem>, Self::SinkError> {
+        let mut value = vec![];
+        proto::EventWrapper::from(event).encode(&mut value).unwrap(); // This will not error when writing to a Vec
+        let event_size = value.len();
+
+        if event_size > MAX_RECORD_SIZE {
+            error!(
+                message = "Event is too large for the disk buffer; dropping event.",
+                %event_size,
+                rate_limit_secs = 10
+            );
+            return Ok(AsyncSink::Ready);
+        }
+
+        if self
+            .shared
+            .current_size
+            .fetch_add(event_size, Ordering::Relaxed)
+            + (event_size / 2)
+            > self.max_size
+        {
+            self.shared
+                .blocked_write_tasks
+                .lock()
+                .unwrap()
+                .push(task::current());
+
+            self.shared
+                .current_size
+                .fetch_sub(event_size, Ordering::Relaxed);
+
+            self.poll_complete()?;
+
+            let event = proto::EventWrapper::decode(value).unwrap().into();
+            return Ok(AsyncSink::NotReady(event));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state
+            .append(&self.shared, &self.write_notifier, &value)
+            .map_err(|error| error!(message = "Error writing to disk buffer.", %error))?;
+
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Result<Async<()>, Self::SinkError> {
+        // Records are handed to the OS here, which is enough for them to
+        // survive a crash of the process.
+        self.state
+            .lock()
+            .unwrap()
+            .file
+            .flush()
+            .map_err(|error| error!(message = "Error flushing disk buffer.", %error))?;
+        self.write_notifier.notify();
+
+        Ok(Async::Ready(()))
+    }
+}
+
+impl Drop for Writer {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Err(error) = state.file.flush() {
+                error!(message = "Error flushing disk buffer.", %error);
+            }
+        }
+
+        // We need to wake up the reader so it can return None if there are no more writers
+        self.write_notifier.notify();
+    }
+}
+
+impl WriterState {
+    fn append(
+        &mut self,
+        shared: &Shared,
+        write_notifier: &AtomicTask,
+        value: &[u8],
+    ) -> io::Result<()> {
+        if self.segment_size >= self.segment_max_size {
+            self.rotate(shared, write_notifier)?;
+        }
+
+        write_record(&mut self.file, value)?;
+        self.segment_size += HEADER_LEN + value.len();
+        Ok(())
+    }
+
+    /// Starts a new segment. The current one is flushed first, so once the
+    /// reader sees the new segment number it knows the previous segment is
+    /// complete.
+    fn rotate(&mut self, shared: &Shared, write_notifier: &AtomicTask) -> io::Result<()> {
+        self.file.flush()?;
+
+        let next = shared.write_segment.load(Ordering::SeqCst) + 1;
+        self.file = BufWriter::new(open_for_append(&segment_path(&shared.dir, next))?);
+        self.segment_size = 0;
+        shared.write_segment.store(next, Ordering::SeqCst);
+
+        write_notifier.notify();
+        Ok(())
+    }
+}
+
+struct Unacked {
+    segment: usize,
+    end: u64,
+    size: usize,
+}
+
+pub struct Reader {
+    shared: Arc<Shared>,
+    writers: Weak<Mutex<WriterState>>,
+    write_notifier: Arc<AtomicTask>,
+    file: BufReader<File>,
+    segment: usize,
+    offset: u64,
+    acked: Cursor,
+    ack_counter: Arc<AtomicUsize>,
+    unacked: VecDeque<Unacked>,
+}
+
+impl Stream for Reader {
+    type Item = Event;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        self.delete_acked();
+
+        // If there's no record at the read position, we return NotReady and rely on Writer
+        // using write_notifier to wake this task up after the next write.
+        self.write_notifier.register();
+
+        loop {
+            // This will usually be served from the read buffer, but in the case of a large
+            // queue (or a fresh launch of the app), this will have to go to disk.
+            let result = tokio::task::block_in_place(|| read_record(&mut self.file))
+                .map_err(|error| error!(message = "Error reading disk buffer.", %error))?;
+
+            match result {
+                ReadResult::Record(value) => {
+                    let event_size = value.len();
+                    self.offset += (HEADER_LEN + event_size) as u64;
+
+                    match proto::EventWrapper::decode(value) {
+                        Ok(event) => {
+                            self.unacked.push_back(Unacked {
+                                segment: self.segment,
+                                end: self.offset,
+                                size: event_size,
+                            });
+                            return Ok(Async::Ready(Some(Event::from(event))));
+                        }
+                        Err(err) => {
+                            error!("Error deserializing proto: {:?}", err);
+                            self.shared
+                                .current_size
+                                .fetch_sub(event_size, Ordering::Relaxed);
+                        }
+                    }
+                }
+                partial => {
+                    // Rewind so the record is read from its start once more of it is available.
+                    self.file
+                        .seek(SeekFrom::Start(self.offset))
+                        .map_err(|error| error!(message = "Error reading disk buffer.", %error))?;
+
+                    if self.segment < self.shared.write_segment.load(Ordering::SeqCst) {
+                        if let ReadResult::Corrupt | ReadResult::Incomplete = partial {
+                            error!(
+                                message =
+                                    "Skipping corrupted records at the end of disk buffer segment.",
+                                segment = self.segment,
+                                offset = self.offset,
+                            );
+                        }
+                        self.next_segment().map_err(
+                            |error| error!(message = "Error reading disk buffer.", %error),
+                        )?;
+                    } else if self.writers.upgrade().is_none() {
+                        // There are no writers left
+                        return Ok(Async::Ready(None));
+                    } else {
+                        if let ReadResult::Corrupt = partial {
+                            error!(
+                                message = "Found corrupted record in disk buffer, waiting for the next segment.",
+                                segment = self.segment,
+                                offset = self.offset,
+                                rate_limit_secs = 10,
+                            );
+                        }
+                        return Ok(Async::NotReady);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        self.delete_acked();
+    }
+}
+
+impl Reader {
+    fn next_segment(&mut self) -> io::Result<()> {
+        let next = self.segment + 1;
+        self.file = BufReader::new(File::open(segment_path(&self.shared.dir, next))?);
+        self.segment = next;
+        self.offset = 0;
+        Ok(())
+    }
+
+    fn delete_acked(&mut self) {
+        let num_to_delete = self.ack_counter.swap(0, Ordering::Relaxed);
+
+        if num_to_delete > 0 {
+            assert!(
+                num_to_delete <= self.unacked.len(),
+                "Tried to ack beyond read offset"
+            );
+
+            let mut size_deleted = 0;
+            let mut cursor = self.acked;
+            for record in self.unacked.drain(..num_to_delete) {
+                size_deleted += record.size;
+                cursor = Cursor {
+                    segment: record.segment,
+                    offset: record.end,
+                };
+            }
+            self.shared
+                .current_size
+                .fetch_sub(size_deleted, Ordering::Relaxed);
+
+            if let Err(error) = write_cursor(&self.shared.dir, cursor) {
+                error!(message = "Error persisting disk buffer position.", %error);
+            }
+
+            // Segments before the acknowledged one will never be read again.
+            for segment in self.acked.segment..cursor.segment {
+                remove_segment(&self.shared.dir, segment);
+            }
+            self.acked = cursor;
+        }
+
+        for task in self.shared.blocked_write_tasks.lock().unwrap().drain(..) {
+            task.notify();
+        }
+    }
+}
+
+pub struct Buffer;
+
+impl super::DiskBuffer for Buffer {
+    type Writer = Writer;
+    type Reader = Reader;
+
+    fn build(path: PathBuf, max_size: usize) -> Result<(Self::Writer, Self::Reader, Acker), Error> {
+        Self::open(&path, max_size).with_context(|| BufferOpenError { path: path.clone() })
+    }
+}
+
+impl Buffer {
+    fn open(dir: &Path, max_size: usize) -> io::Result<(Writer, Reader, Acker)> {
+        fs::create_dir_all(dir)?;
+
+        let mut segments = list_segments(dir)?;
+        let acked = read_cursor(dir)?.unwrap_or_else(|| Cursor {
+            segment: segments.first().copied().unwrap_or(0),
+            offset: 0,
+        });
+
+        // Segments before the ack cursor only hold acknowledged records.
+        for &segment in segments.iter().filter(|&&segment| segment < acked.segment) {
+            remove_segment(dir, segment);
+        }
+        segments.retain(|&segment| segment >= acked.segment);
+        if segments.is_empty() {
+            segments.push(acked.segment);
+        }
+
+        let read_start = if segments[0] == acked.segment {
+            acked
+        } else {
+            Cursor {
+                segment: segments[0],
+                offset: 0,
+            }
+        };
+
+        let mut initial_size = 0;
+        let mut write_end = 0;
+        for &segment in &segments {
+            let start = if segment == read_start.segment {
+                read_start.offset
+            } else {
+                0
+            };
+            let (end, size) = scan_segment(&segment_path(dir, segment), start)?;
+            initial_size += size;
+            write_end = end;
+        }
+
+        // Drop whatever follows the last valid record, which is a record that
+        // was torn by a crash in the middle of a write.
+        let write_segment = *segments.last().expect("always at least one segment");
+        let file = open_for_append(&segment_path(dir, write_segment))?;
+        file.set_len(write_end)?;
+
+        let shared = Arc::new(Shared {
+            dir: dir.into(),
+            write_segment: AtomicUsize::new(write_segment),
+            current_size: AtomicUsize::new(initial_size),
+            blocked_write_tasks: Mutex::new(Vec::new()),
+        });
+
+        let state = Arc::new(Mutex::new(WriterState {
+            file: BufWriter::new(file),
+            segment_size: write_end as usize,
+            segment_max_size: (max_size / 8).min(MAX_SEGMENT_SIZE).max(1),
+        }));
+
+        let mut file = BufReader::new(File::open(segment_path(dir, read_start.segment))?);
+        file.seek(SeekFrom::Start(read_start.offset))?;
+
+        let write_notifier = Arc::new(AtomicTask::new());
+
+        let ack_counter = Arc::new(AtomicUsize::new(0));
+        let acker = Acker::Disk(Arc::clone(&ack_counter), Arc::clone(&write_notifier));
+
+        let reader = Reader {
+            shared: Arc::clone(&shared),
+            writers: Arc::downgrade(&state),
+            write_notifier: Arc::clone(&write_notifier),
+            file,
+            segment: read_start.segment,
+            offset: read_start.offset,
+            acked: read_start,
+            ack_counter,
+            unacked: VecDeque::new(),
+        };
+
+        let writer = Writer {
+            state,
+            shared,
+            write_notifier,
+            max_size,
+        };
+
+        Ok((writer, reader, acker))
+    }
+}
+
+fn segment_path(dir: &Path, segment: usize) -> PathBuf {
+    dir.join(format!("{:020}.{}", segment, SEGMENT_EXTENSION))
+}
+
+fn list_segments(dir: &Path) -> io::Result<Vec<usize>> {
+    let mut segments = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(SEGMENT_EXTENSION) {
+            continue;
+        }
+        if let Some(segment) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+        {
+            segments.push(segment);
+        }
+    }
+    segments.sort_unstable();
+    Ok(segments)
+}
+
+fn remove_segment(dir: &Path, segment: usize) {
+    match fs::remove_file(segment_path(dir, segment)) {
+        Ok(()) => {}
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => error!(message = "Error removing disk buffer segment.", segment, %error),
+    }
+}
+
+fn open_for_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn read_cursor(dir: &Path) -> io::Result<Option<Cursor>> {
+    let bytes = match fs::read(dir.join(ACK_FILE)) {
+        Ok(bytes) => bytes,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+
+    if bytes.len() != 16 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "disk buffer ack file has an invalid length",
+        ));
+    }
+
+    let segment = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+    let offset = u64::from_le_bytes(bytes[8..].try_into().unwrap());
+    Ok(Some(Cursor {
+        segment: segment as usize,
+        offset,
+    }))
+}
+
+/// Replaces the ack file by renaming a freshly written one over it, so a
+/// crash never leaves a partially written cursor behind.
+fn write_cursor(dir: &Path, cursor: Cursor) -> io::Result<()> {
+    let tmp_path = dir.join(ACK_TMP_FILE);
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(&(cursor.segment as u64).to_le_bytes())?;
+    file.write_all(&cursor.offset.to_le_bytes())?;
+    file.sync_data()?;
+    fs::rename(tmp_path, dir.join(ACK_FILE))
+}
+
+fn write_record<W: Write>(writer: &mut W, value: &[u8]) -> io::Result<()> {
+    writer.write_all(&(value.len() as u32).to_le_bytes())?;
+    writer.write_all(&crc32fast::hash(value).to_le_bytes())?;
+    writer.write_all(value)
+}
+
+fn read_record<R: Read>(reader: &mut R) -> io::Result<ReadResult> {
+    let mut header = [0; HEADER_LEN];
+    match read_full(reader, &mut header)? {
+        0 => return Ok(ReadResult::End),
+        HEADER_LEN => {}
+        _ => return Ok(ReadResult::Incomplete),
+    }
+
+    let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+    let checksum = u32::from_le_bytes(header[4..].try_into().unwrap());
+    if len > MAX_RECORD_SIZE {
+        return Ok(ReadResult::Corrupt);
+    }
+
+    let mut value = vec![0; len];
+    if read_full(reader, &mut value)? < len {
+        return Ok(ReadResult::Incomplete);
+    }
+
+    if crc32fast::hash(&value) != checksum {
+        return Ok(ReadResult::Corrupt);
+    }
+
+    Ok(ReadResult::Record(value))
+}
+
+/// Like `read_exact`, but returns how much was read when hitting the end of
+/// the file instead of failing.
+fn read_full<R: Read>(reader: &mut R, mut buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while !buf.is_empty() {
+        match reader.read(buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                read += n;
+                buf = &mut buf[n..];
+            }
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(read)
+}
+
+/// Validates the records of a segment from `offset` on, returning the end of
+/// the last valid record and the total size of the valid records.
+fn scan_segment(path: &Path, offset: u64) -> io::Result<(u64, usize)> {
+    let mut file = match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok((0, 0)),
+        Err(error) => return Err(error),
+    };
+    file.seek(SeekFrom::Start(offset))?;
+
+    let mut end = offset;
+    let mut size = 0;
+    while let ReadResult::Record(value) = read_record(&mut file)? {
+        end += (HEADER_LEN + value.len()) as u64;
+        size += value.len();
+    }
+    Ok((end, size))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        buffers::disk::DiskBuffer,
+        runtime::Runtime,
+        test_util::{random_events_with_stream, runtime},
+    };
+    use futures01::stream;
+    use tempfile::tempdir;
+
+    fn build(dir: &Path, max_size: usize) -> (Writer, Reader, Acker) {
+        Buffer::build(dir.join("buffer"), max_size).unwrap()
+    }
+
+    fn write(rt: &mut Runtime, writer: Writer, events: &[Event]) -> Writer {
+        let (writer, _) = rt
+            .block_on(writer.send_all(stream::iter_ok(events.to_vec())))
+            .unwrap();
+        writer
+    }
+
+    fn read(rt: &mut Runtime, mut reader: Reader, count: usize) -> (Reader, Vec<Event>) {
+        let mut events = Vec::new();
+        for _ in 0..count {
+            let (event, rest) = rt.block_on(reader.into_future()).map_err(|_| ()).unwrap();
+            reader = rest;
+            events.extend(event);
+        }
+        (reader, events)
+    }
+
+    #[test]
+    fn recovers_unacked_events() {
+        let dir = tempdir().unwrap();
+        let mut rt = runtime();
+        let (events, _) = random_events_with_stream(100, 10);
+
+        let (writer, reader, acker) = build(dir.path(), 10_000);
+        let writer = write(&mut rt, writer, &events);
+        let (reader, output) = read(&mut rt, reader, 10);
+        assert_eq!(events, output);
+
+        acker.ack(4);
+        drop(reader);
+        drop(writer);
+
+        let (_writer, reader, _acker) = build(dir.path(), 10_000);
+        let (_reader, output) = read(&mut rt, reader, 6);
+        assert_eq!(&events[4..], &output[..]);
+    }
+
+    #[test]
+    fn truncates_torn_writes() {
+        let dir = tempdir().unwrap();
+        let mut rt = runtime();
+        let (events, _) = random_events_with_stream(100, 4);
+
+        let (writer, reader, _acker) = build(dir.path(), 10_000);
+        let writer = write(&mut rt, writer, &events[..3]);
+        drop(reader);
+        drop(writer);
+
+        // Simulate a crash in the middle of writing a record.
+        let path = dir.path().join("buffer");
+        let last = *list_segments(&path).unwrap().last().unwrap();
+        let mut file = open_for_append(&segment_path(&path, last)).unwrap();
+        file.write_all(&100u32.to_le_bytes()).unwrap();
+        file.write_all(&[0; 10]).unwrap();
+        drop(file);
+
+        let (writer, reader, _acker) = build(dir.path(), 10_000);
+        let _writer = write(&mut rt, writer, &events[3..]);
+        let (_reader, output) = read(&mut rt, reader, 4);
+        assert_eq!(events, output);
+    }
+
+    #[test]
+    fn deletes_acked_segments() {
+        let dir = tempdir().unwrap();
+        let mut rt = runtime();
+        let (events, _) = random_events_with_stream(300, 10);
+
+        let (writer, reader, acker) = build(dir.path(), 8_000);
+        let _writer = write(&mut rt, writer, &events);
+
+        let path = dir.path().join("buffer");
+        assert!(list_segments(&path).unwrap().len() > 1);
+
+        let (reader, output) = read(&mut rt, reader, 10);
+        assert_eq!(events, output);
+
+        acker.ack(10);
+        drop(reader);
+
+        let segments = list_segments(&path).unwrap();
+        assert_eq!(segments.len(), 1);
+        let end = fs::metadata(segment_path(&path, segments[0]))
+            .unwrap()
+            .len();
+        assert_eq!(
+            read_cursor(&path).unwrap(),
+            Some(Cursor {
+                segment: segments[0],
+                offset: end,
+            })
+        );
+    }
+}
@@ -1,13 +1,19 @@
-use crate::Event;
-use futures01::{sync::mpsc, task::AtomicTask, AsyncSink, Poll, Sink, StartSend, Stream};
+use crate::{
+    event::EventFinalizers,
+    internal_events::BufferEventsSampled,
+    topology::{builder::filter_event_type, config::DataType},
+    Event,
+};
+use futures01::{sync::mpsc, task::AtomicTask, Async, AsyncSink, Poll, Sink, StartSend, Stream};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::path::PathBuf;
 use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc,
+    atomic::{AtomicI64, AtomicUsize, Ordering},
+    Arc, Mutex, Weak,
 };
 
-#[cfg(feature = "leveldb")]
 pub mod disk;
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -19,6 +25,10 @@ pub enum BufferConfig {
         max_events: usize,
         #[serde(default)]
         when_full: WhenFull,
+        /// The buffer that takes events once this one is full, used with
+        /// `when_full = "overflow"`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        overflow: Option<Box<BufferConfig>>,
     },
     #[cfg(feature = "leveldb")]
     Disk {
@@ -26,6 +36,11 @@ pub enum BufferConfig {
         #[serde(default)]
         when_full: WhenFull,
     },
+    DiskV2 {
+        max_size: usize,
+        #[serde(default)]
+        when_full: WhenFull,
+    },
 }
 
 impl Default for BufferConfig {
@@ -33,6 +48,7 @@ impl Default for BufferConfig {
         BufferConfig::Memory {
             max_events: BufferConfig::memory_max_events(),
             when_full: Default::default(),
+            overflow: None,
         }
     }
 }
@@ -42,6 +58,7 @@ impl Default for BufferConfig {
 pub enum WhenFull {
     Block,
     DropNewest,
+    Overflow,
 }
 
 impl Default for WhenFull {
@@ -50,17 +67,24 @@ impl Default for WhenFull {
     }
 }
 
+#[derive(Clone)]
 pub enum BufferInputCloner {
-    Memory(mpsc::Sender<Event>, WhenFull),
-    #[cfg(feature = "leveldb")]
-    Disk(disk::Writer, WhenFull),
+    Memory(mpsc::Sender<Event>, WhenFull, BufferUsage),
+    Disk(disk::Writer, WhenFull, BufferUsage),
+    Overflow(
+        mpsc::Sender<Event>,
+        Box<BufferInputCloner>,
+        Arc<AtomicUsize>,
+        BufferUsage,
+    ),
 }
 
 impl BufferInputCloner {
     pub fn get(&self) -> Box<dyn Sink<SinkItem = Event, SinkError = ()> + Send> {
         match self {
-            BufferInputCloner::Memory(tx, when_full) => {
+            BufferInputCloner::Memory(tx, when_full, usage) => {
                 let inner = tx.clone().sink_map_err(|e| error!("sender error: {:?}", e));
+                let inner = usage.meter_input(inner);
                 if when_full == &WhenFull::DropNewest {
                     Box::new(DropWhenFull { inner })
                 } else {
@@ -68,17 +92,148 @@ impl BufferInputCloner {
                 }
             }
 
-            #[cfg(feature = "leveldb")]
-            BufferInputCloner::Disk(writer, when_full) => {
+            BufferInputCloner::Disk(writer, when_full, usage) => {
+                let inner = usage.meter_input(writer.clone());
                 if when_full == &WhenFull::DropNewest {
-                    Box::new(DropWhenFull {
-                        inner: writer.clone(),
-                    })
+                    Box::new(DropWhenFull { inner })
                 } else {
-                    Box::new(writer.clone())
+                    Box::new(inner)
                 }
             }
+
+            BufferInputCloner::Overflow(tx, overflow, spilled, usage) => {
+                let memory = tx.clone().sink_map_err(|e| error!("sender error: {:?}", e));
+                Box::new(OverflowWriter {
+                    memory: Box::new(usage.meter_input(memory)),
+                    overflow: overflow.get(),
+                    spilled: Arc::clone(spilled),
+                })
+            }
+        }
+    }
+}
+
+/// Tracks the number of events currently held in a component's buffer and
+/// reports it as the `buffer_events` gauge.
+///
+/// The count starts at zero when the buffer is built, so events left over in
+/// an on-disk buffer from a previous run are not included. The gauge is only
+/// recorded when metrics are read, see `report_usage`, rather than every time
+/// an event enters or leaves the buffer.
+#[derive(Debug, Clone)]
+pub struct BufferUsage {
+    inner: Arc<Usage>,
+}
+
+#[derive(Debug)]
+struct Usage {
+    component_name: String,
+    events: AtomicI64,
+}
+
+/// Every buffer that has been built, until the last of its handles is dropped.
+static USAGES: Lazy<Mutex<Vec<Weak<Usage>>>> = Lazy::new(Default::default);
+
+impl BufferUsage {
+    pub fn new(component_name: &str) -> Self {
+        let inner = Arc::new(Usage {
+            component_name: component_name.into(),
+            events: AtomicI64::new(0),
+        });
+        USAGES.lock().unwrap().push(Arc::downgrade(&inner));
+        Self { inner }
+    }
+
+    pub fn events(&self) -> i64 {
+        self.inner.events()
+    }
+
+    /// Counts every event accepted by `sink` as entering the buffer.
+    pub fn meter_input<S: Sink>(&self, sink: S) -> MeteredInput<S> {
+        MeteredInput {
+            inner: sink,
+            usage: self.clone(),
+        }
+    }
+
+    /// Counts every event yielded by `stream` as leaving the buffer.
+    pub fn meter_output<S: Stream>(&self, stream: S) -> MeteredOutput<S> {
+        MeteredOutput {
+            inner: stream,
+            usage: self.clone(),
+        }
+    }
+
+    fn update(&self, delta: i64) {
+        self.inner.events.fetch_add(delta, Ordering::Relaxed);
+    }
+}
+
+impl Usage {
+    fn events(&self) -> i64 {
+        self.events.load(Ordering::Relaxed).max(0)
+    }
+}
+
+/// Records the `buffer_events` gauge of every buffer that is still in use.
+/// Called by the readers of the metrics right before they take a snapshot.
+pub fn report_usage() {
+    USAGES
+        .lock()
+        .unwrap()
+        .retain(|usage| match usage.upgrade() {
+            Some(usage) => {
+                emit!(BufferEventsSampled {
+                    component_name: &usage.component_name,
+                    events: usage.events(),
+                });
+                true
+            }
+            None => false,
+        });
+}
+
+pub struct MeteredInput<S> {
+    inner: S,
+    usage: BufferUsage,
+}
+
+impl<S: Sink> Sink for MeteredInput<S> {
+    type SinkItem = S::SinkItem;
+    type SinkError = S::SinkError;
+
+    fn start_send(&mut self, item: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
+        let result = self.inner.start_send(item)?;
+        if result.is_ready() {
+            self.usage.update(1);
         }
+        Ok(result)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        self.inner.poll_complete()
+    }
+
+    fn close(&mut self) -> Poll<(), Self::SinkError> {
+        self.inner.close()
+    }
+}
+
+pub struct MeteredOutput<S> {
+    inner: S,
+    usage: BufferUsage,
+}
+
+impl<S: Stream> Stream for MeteredOutput<S> {
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let result = self.inner.poll()?;
+        if let Async::Ready(Some(_)) = result {
+            self.usage.update(-1);
+        }
+        Ok(result)
     }
 }
 
@@ -88,11 +243,34 @@ impl BufferConfig {
         500
     }
 
-    #[cfg_attr(not(feature = "leveldb"), allow(unused))]
+    /// Builds the buffer of a sink that takes events of `data_type`. Events
+    /// of any other type are dropped as they are read, before the sink can
+    /// see them, so that they are finalized right away and don't take part in
+    /// the sink's acknowledgements.
     pub fn build(
         &self,
         data_dir: &Option<PathBuf>,
         sink_name: &str,
+        data_type: DataType,
+    ) -> Result<
+        (
+            BufferInputCloner,
+            Box<dyn Stream<Item = Event, Error = ()> + Send>,
+            Acker,
+        ),
+        String,
+    > {
+        self.build_stage(data_dir, sink_name, data_type, BufferUsage::new(sink_name))
+    }
+
+    /// Builds a single stage of the buffer chain. All stages share `usage`,
+    /// so the chain as a whole is reported as the sink's buffer.
+    fn build_stage(
+        &self,
+        data_dir: &Option<PathBuf>,
+        sink_name: &str,
+        data_type: DataType,
+        usage: BufferUsage,
     ) -> Result<
         (
             BufferInputCloner,
@@ -105,11 +283,47 @@ impl BufferConfig {
             BufferConfig::Memory {
                 max_events,
                 when_full,
+                overflow,
             } => {
                 let (tx, rx) = mpsc::channel(*max_events);
-                let tx = BufferInputCloner::Memory(tx, *when_full);
-                let rx = Box::new(rx);
-                Ok((tx, rx, Acker::Null))
+                let rx = filter_event_type(usage.meter_output(rx), data_type);
+
+                match (when_full, overflow) {
+                    (WhenFull::Overflow, Some(overflow)) => {
+                        let (overflow_tx, overflow_rx, overflow_acker) =
+                            overflow.build_stage(data_dir, sink_name, data_type, usage.clone())?;
+
+                        let spilled = Arc::new(AtomicUsize::new(0));
+                        let acks = Arc::new(OverflowAcks::new(overflow_acker));
+
+                        let rx = Box::new(OverflowReader {
+                            memory: rx,
+                            overflow: overflow_rx,
+                            spilled: Arc::clone(&spilled),
+                            acks: Arc::clone(&acks),
+                        });
+                        let tx =
+                            BufferInputCloner::Overflow(tx, Box::new(overflow_tx), spilled, usage);
+                        Ok((tx, rx, Acker::Overflow(acks)))
+                    }
+                    (WhenFull::Overflow, None) => Err(
+                        "Must configure an overflow buffer to use `when_full = \"overflow\"`."
+                            .to_string(),
+                    ),
+                    (_, Some(_)) => Err(
+                        "An overflow buffer is only used with `when_full = \"overflow\"`."
+                            .to_string(),
+                    ),
+                    (when_full, None) => {
+                        let tx = BufferInputCloner::Memory(tx, *when_full, usage);
+                        let pending = Arc::new(PendingFinalizers::default());
+                        let rx = FinalizingReader {
+                            inner: rx,
+                            pending: Arc::clone(&pending),
+                        };
+                        Ok((tx, Box::new(rx), Acker::Finalizers(pending)))
+                    }
+                }
             }
 
             #[cfg(feature = "leveldb")]
@@ -117,6 +331,7 @@ impl BufferConfig {
                 max_size,
                 when_full,
             } => {
+                check_when_full(*when_full)?;
                 let data_dir = data_dir
                     .as_ref()
                     .ok_or_else(|| "Must set data_dir to use on-disk buffering.".to_string())?;
@@ -124,17 +339,270 @@ impl BufferConfig {
 
                 let (tx, rx, acker) = disk::open(&data_dir, buffer_dir.as_ref(), *max_size)
                     .map_err(|err| err.to_string())?;
-                let tx = BufferInputCloner::Disk(tx, *when_full);
-                let rx = Box::new(rx);
+                let rx = filter_event_type(usage.meter_output(rx), data_type);
+                let tx = BufferInputCloner::Disk(tx, *when_full, usage);
                 Ok((tx, rx, acker))
             }
+
+            BufferConfig::DiskV2 {
+                max_size,
+                when_full,
+            } => {
+                check_when_full(*when_full)?;
+                let data_dir = data_dir
+                    .as_ref()
+                    .ok_or_else(|| "Must set data_dir to use on-disk buffering.".to_string())?;
+                let buffer_dir = format!("{}_buffer_v2", sink_name);
+
+                let (tx, rx, acker) =
+                    disk::open_segmented(&data_dir, buffer_dir.as_ref(), *max_size)
+                        .map_err(|err| err.to_string())?;
+                let rx = filter_event_type(usage.meter_output(rx), data_type);
+                let tx = BufferInputCloner::Disk(tx, *when_full, usage);
+                Ok((tx, rx, acker))
+            }
+        }
+    }
+}
+
+fn check_when_full(when_full: WhenFull) -> Result<(), String> {
+    if when_full == WhenFull::Overflow {
+        Err("Only memory buffers can overflow into another buffer.".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// Input side of a memory buffer with an overflow stage. Events go to the
+/// memory channel until it is full, and to the overflow stage from then on
+/// until the reader has drained everything that was spilled, which keeps
+/// events from a single input in order.
+pub struct OverflowWriter {
+    memory: Box<dyn Sink<SinkItem = Event, SinkError = ()> + Send>,
+    overflow: Box<dyn Sink<SinkItem = Event, SinkError = ()> + Send>,
+    spilled: Arc<AtomicUsize>,
+}
+
+impl Sink for OverflowWriter {
+    type SinkItem = Event;
+    type SinkError = ();
+
+    fn start_send(&mut self, item: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
+        let item = if self.spilled.load(Ordering::SeqCst) == 0 {
+            match self.memory.start_send(item)? {
+                AsyncSink::Ready => return Ok(AsyncSink::Ready),
+                AsyncSink::NotReady(item) => item,
+            }
+        } else {
+            item
+        };
+
+        // Counted before sending so the reader can never see the event
+        // before it is accounted for.
+        self.spilled.fetch_add(1, Ordering::SeqCst);
+        let result = self.overflow.start_send(item)?;
+        if !result.is_ready() {
+            self.spilled.fetch_sub(1, Ordering::SeqCst);
+        }
+        Ok(result)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        let memory = self.memory.poll_complete()?;
+        let overflow = self.overflow.poll_complete()?;
+        if memory.is_ready() && overflow.is_ready() {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+/// Output side of a memory buffer with an overflow stage. The memory channel
+/// is always drained first, as everything in it was written before anything
+/// that was spilled.
+///
+/// Events left in a persistent overflow stage by a previous run are read
+/// whenever the memory channel is empty.
+///
+/// Like `FinalizingReader`, it holds on to the finalizers of the events it
+/// hands out until the sink acknowledges them.
+pub struct OverflowReader<S> {
+    memory: S,
+    overflow: Box<dyn Stream<Item = Event, Error = ()> + Send>,
+    spilled: Arc<AtomicUsize>,
+    acks: Arc<OverflowAcks>,
+}
+
+impl<S> Stream for OverflowReader<S>
+where
+    S: Stream<Item = Event, Error = ()>,
+{
+    type Item = Event;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let memory = match self.memory.poll()? {
+            Async::Ready(Some(mut event)) => {
+                self.acks.record(false, event.take_finalizers());
+                return Ok(Async::Ready(Some(event)));
+            }
+            memory => memory,
+        };
+
+        match self.overflow.poll()? {
+            Async::Ready(Some(mut event)) => {
+                let mut spilled = self.spilled.load(Ordering::SeqCst);
+                while spilled > 0 {
+                    match self.spilled.compare_exchange(
+                        spilled,
+                        spilled - 1,
+                        Ordering::SeqCst,
+                        Ordering::SeqCst,
+                    ) {
+                        Ok(_) => break,
+                        Err(actual) => spilled = actual,
+                    }
+                }
+                // Events from a memory overflow stage come without finalizers,
+                // as that stage holds on to them itself.
+                self.acks.record(true, event.take_finalizers());
+                Ok(Async::Ready(Some(event)))
+            }
+            // The memory channel can only be ready here once it is closed.
+            Async::Ready(None) if memory.is_ready() => Ok(Async::Ready(None)),
+            _ => Ok(Async::NotReady),
         }
     }
 }
 
+/// Finalizers of the events a sink has read from its memory buffer but not
+/// acknowledged yet, in stream order.
+#[derive(Debug, Default)]
+pub struct PendingFinalizers {
+    state: Mutex<PendingState>,
+}
+
+#[derive(Debug, Default)]
+struct PendingState {
+    /// The number of events read and acknowledged so far.
+    read: usize,
+    acked: usize,
+    /// Only events that carry finalizers are queued, along with their
+    /// position in the stream, so events without them cost nothing.
+    queue: VecDeque<(usize, EventFinalizers)>,
+}
+
+impl PendingFinalizers {
+    fn record(&self, finalizers: EventFinalizers) {
+        let mut state = self.state.lock().unwrap();
+        if !finalizers.is_empty() {
+            let position = state.read;
+            state.queue.push_back((position, finalizers));
+        }
+        state.read += 1;
+    }
+
+    fn ack(&self, num: usize) {
+        let mut state = self.state.lock().unwrap();
+        let acked = state.read.min(state.acked + num);
+        state.acked = acked;
+        // Dropping the finalizers is what finalizes the events.
+        while let Some((position, _)) = state.queue.front() {
+            if *position >= acked {
+                break;
+            }
+            state.queue.pop_front();
+        }
+    }
+}
+
+/// Holds on to the finalizers of the events read from a memory buffer until
+/// the sink acknowledges them, so that sources only learn about an event
+/// being done once it was actually delivered.
+struct FinalizingReader<S> {
+    inner: S,
+    pending: Arc<PendingFinalizers>,
+}
+
+impl<S> Stream for FinalizingReader<S>
+where
+    S: Stream<Item = Event, Error = ()>,
+{
+    type Item = Event;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let mut event = match self.inner.poll()? {
+            Async::Ready(Some(event)) => event,
+            other => return Ok(other),
+        };
+
+        self.pending.record(event.take_finalizers());
+        Ok(Async::Ready(Some(event)))
+    }
+}
+
+/// Remembers which stage of an overflow buffer each event was read from, in
+/// stream order, so that acknowledgements only reach the overflow stage for
+/// events that it handed out.
+#[derive(Debug)]
+pub struct OverflowAcks {
+    // Runs of consecutive events read from the same stage, as
+    // `(from_overflow, count)`.
+    origins: Mutex<VecDeque<(bool, usize)>>,
+    overflow: Acker,
+    pending: PendingFinalizers,
+}
+
+impl OverflowAcks {
+    fn new(overflow: Acker) -> Self {
+        Self {
+            origins: Mutex::new(VecDeque::new()),
+            overflow,
+            pending: PendingFinalizers::default(),
+        }
+    }
+
+    fn record(&self, from_overflow: bool, finalizers: EventFinalizers) {
+        self.pending.record(finalizers);
+        let mut origins = self.origins.lock().unwrap();
+        match origins.back_mut() {
+            Some((last, count)) if *last == from_overflow => *count += 1,
+            _ => origins.push_back((from_overflow, 1)),
+        }
+    }
+
+    fn ack(&self, mut num: usize) {
+        let num_acked = num;
+        let mut overflow_acks = 0;
+        {
+            let mut origins = self.origins.lock().unwrap();
+            while num > 0 {
+                let (from_overflow, count) = origins
+                    .front_mut()
+                    .expect("Tried to ack beyond read offset");
+                let acked = num.min(*count);
+                if *from_overflow {
+                    overflow_acks += acked;
+                }
+                *count -= acked;
+                num -= acked;
+                if *count == 0 {
+                    origins.pop_front();
+                }
+            }
+        }
+        self.pending.ack(num_acked);
+        self.overflow.ack(overflow_acks);
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Acker {
     Disk(Arc<AtomicUsize>, Arc<AtomicTask>),
+    Overflow(Arc<OverflowAcks>),
+    Finalizers(Arc<PendingFinalizers>),
     Null,
 }
 
@@ -154,6 +622,8 @@ impl Acker {
                     counter.fetch_add(num, Ordering::Relaxed);
                     notifier.notify();
                 }
+                Acker::Overflow(acks) => acks.ack(num),
+                Acker::Finalizers(pending) => pending.ack(num),
             }
         }
     }
@@ -195,10 +665,21 @@ impl<S: Sink> Sink for DropWhenFull<S> {
 
 #[cfg(test)]
 mod test {
-    use super::{Acker, BufferConfig, DropWhenFull, WhenFull};
-    use crate::test_util::block_on;
+    use super::{Acker, BufferConfig, BufferUsage, DropWhenFull, OverflowAcks, WhenFull};
+    use crate::{
+        event::{
+            metric::{Metric, MetricKind, MetricValue},
+            BatchNotifier, EventFinalizers,
+        },
+        test_util::block_on,
+        topology::config::DataType,
+        Event,
+    };
     use futures01::{future, sync::mpsc, task::AtomicTask, Async, AsyncSink, Sink, Stream};
-    use std::sync::{atomic::AtomicUsize, Arc};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
     use tokio01_test::task::MockTask;
 
     #[test]
@@ -223,6 +704,226 @@ mod test {
         .unwrap();
     }
 
+    #[test]
+    fn buffer_usage() {
+        block_on::<_, _, ()>(future::lazy(|| {
+            let usage = BufferUsage::new("out");
+            let (tx, rx) = mpsc::channel(2);
+
+            let mut tx = DropWhenFull {
+                inner: usage.meter_input(tx),
+            };
+            let mut rx = usage.meter_output(rx);
+
+            assert_eq!(tx.start_send(1), Ok(AsyncSink::Ready));
+            assert_eq!(tx.start_send(2), Ok(AsyncSink::Ready));
+            assert_eq!(usage.events(), 2);
+
+            assert_eq!(rx.poll(), Ok(Async::Ready(Some(1))));
+            assert_eq!(usage.events(), 1);
+
+            future::ok(())
+        }))
+        .unwrap();
+    }
+
+    #[test]
+    fn memory_buffer_finalizes_on_ack() {
+        block_on::<_, _, ()>(future::lazy(|| {
+            let config = BufferConfig::default();
+            let (tx, mut rx, acker) = config.build(&None, "out", DataType::Any).unwrap();
+            let mut tx = tx.get();
+
+            let (notifier, mut receiver) = BatchNotifier::new_with_receiver();
+            let mut first = Event::from("first");
+            first.as_mut_log().add_finalizer(notifier);
+            assert_eq!(tx.start_send(first), Ok(AsyncSink::Ready));
+            assert_eq!(tx.start_send(Event::from("second")), Ok(AsyncSink::Ready));
+            assert_eq!(tx.poll_complete(), Ok(Async::Ready(())));
+
+            // Reading the event doesn't finalize it, even once it is dropped.
+            match rx.poll() {
+                Ok(Async::Ready(Some(_event))) => {}
+                other => panic!("unexpected poll result: {:?}", other),
+            }
+            assert_eq!(receiver.try_recv(), Ok(None));
+
+            acker.ack(1);
+            assert_eq!(receiver.try_recv(), Ok(Some(())));
+
+            future::ok(())
+        }))
+        .unwrap();
+    }
+
+    #[test]
+    fn memory_buffer_finalizes_events_of_other_types() {
+        block_on::<_, _, ()>(future::lazy(|| {
+            let config = BufferConfig::default();
+            let (tx, mut rx, acker) = config.build(&None, "out", DataType::Log).unwrap();
+            let mut tx = tx.get();
+
+            let metric = Event::Metric(Metric {
+                name: "requests".into(),
+                timestamp: None,
+                tags: None,
+                kind: MetricKind::Incremental,
+                value: MetricValue::Counter { value: 1.0 },
+            });
+            let (notifier, mut receiver) = BatchNotifier::new_with_receiver();
+            let mut log = Event::from("log");
+            log.as_mut_log().add_finalizer(notifier);
+            assert_eq!(tx.start_send(metric), Ok(AsyncSink::Ready));
+            assert_eq!(tx.start_send(log), Ok(AsyncSink::Ready));
+            assert_eq!(tx.poll_complete(), Ok(Async::Ready(())));
+
+            // The sink never sees the metric, so it only acknowledges the log.
+            match rx.poll() {
+                Ok(Async::Ready(Some(Event::Log(_)))) => {}
+                other => panic!("unexpected poll result: {:?}", other),
+            }
+            assert_eq!(receiver.try_recv(), Ok(None));
+
+            acker.ack(1);
+            assert_eq!(receiver.try_recv(), Ok(Some(())));
+
+            future::ok(())
+        }))
+        .unwrap();
+    }
+
+    #[test]
+    fn overflow_preserves_order() {
+        let config: BufferConfig = toml::from_str(
+            r#"
+          type = "memory"
+          max_events = 2
+          when_full = "overflow"
+
+          [overflow]
+          type = "memory"
+          max_events = 10
+          "#,
+        )
+        .unwrap();
+
+        block_on::<_, _, ()>(future::lazy(move || {
+            let (tx, mut rx, _acker) = config.build(&None, "out", DataType::Any).unwrap();
+            let mut tx = tx.get();
+
+            let events = (0..6)
+                .map(|i| Event::from(i.to_string()))
+                .collect::<Vec<_>>();
+            for event in events.clone() {
+                assert_eq!(tx.start_send(event), Ok(AsyncSink::Ready));
+            }
+
+            // Memory has room again, but everything spilled must be read first.
+            assert_eq!(rx.poll(), Ok(Async::Ready(Some(events[0].clone()))));
+            let late = Event::from("late");
+            assert_eq!(tx.start_send(late.clone()), Ok(AsyncSink::Ready));
+
+            for event in events.into_iter().skip(1).chain(Some(late)) {
+                assert_eq!(rx.poll(), Ok(Async::Ready(Some(event))));
+            }
+            assert_eq!(rx.poll(), Ok(Async::NotReady));
+
+            future::ok(())
+        }))
+        .unwrap();
+    }
+
+    #[test]
+    fn overflow_finalizes_on_ack() {
+        let config: BufferConfig = toml::from_str(
+            r#"
+          type = "memory"
+          max_events = 1
+          when_full = "overflow"
+
+          [overflow]
+          type = "memory"
+          max_events = 10
+          "#,
+        )
+        .unwrap();
+
+        block_on::<_, _, ()>(future::lazy(move || {
+            let (tx, mut rx, acker) = config.build(&None, "out", DataType::Any).unwrap();
+            let mut tx = tx.get();
+
+            // More events than fit in memory, so that the later ones spill.
+            let mut receivers = Vec::new();
+            for i in 0..5 {
+                let (notifier, receiver) = BatchNotifier::new_with_receiver();
+                let mut event = Event::from(i.to_string());
+                event.as_mut_log().add_finalizer(notifier);
+                assert_eq!(tx.start_send(event), Ok(AsyncSink::Ready));
+                receivers.push(receiver);
+            }
+            assert_eq!(tx.poll_complete(), Ok(Async::Ready(())));
+
+            // Reading the events doesn't finalize them, even once dropped.
+            for _ in 0..5 {
+                match rx.poll() {
+                    Ok(Async::Ready(Some(_event))) => {}
+                    other => panic!("unexpected poll result: {:?}", other),
+                }
+            }
+            for receiver in &mut receivers {
+                assert_eq!(receiver.try_recv(), Ok(None));
+            }
+
+            // Every acknowledgement finalizes exactly the next event.
+            let mut receivers = receivers.into_iter();
+            let mut next = receivers.next();
+            while let Some(mut receiver) = next {
+                acker.ack(1);
+                assert_eq!(receiver.try_recv(), Ok(Some(())));
+                next = receivers.next();
+                if let Some(receiver) = &mut next {
+                    assert_eq!(receiver.try_recv(), Ok(None));
+                }
+            }
+
+            future::ok(())
+        }))
+        .unwrap();
+    }
+
+    #[test]
+    fn overflow_forwards_acks_for_spilled_events() {
+        let (inner, counter) = Acker::new_for_testing();
+        let acks = Arc::new(OverflowAcks::new(inner));
+        let acker = Acker::Overflow(Arc::clone(&acks));
+
+        for &from_overflow in &[false, false, true, true, false, true] {
+            acks.record(from_overflow, EventFinalizers::default());
+        }
+
+        acker.ack(3);
+        assert_eq!(counter.load(Ordering::Relaxed), 1);
+        acker.ack(3);
+        assert_eq!(counter.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn overflow_requires_matching_when_full() {
+        let missing = BufferConfig::Memory {
+            max_events: 10,
+            when_full: WhenFull::Overflow,
+            overflow: None,
+        };
+        assert!(missing.build(&None, "out", DataType::Any).is_err());
+
+        let unused = BufferConfig::Memory {
+            max_events: 10,
+            when_full: WhenFull::Block,
+            overflow: Some(Box::new(BufferConfig::default())),
+        };
+        assert!(unused.build(&None, "out", DataType::Any).is_err());
+    }
+
     #[test]
     fn ack_with_none() {
         let counter = Arc::new(AtomicUsize::new(0));
@@ -254,6 +955,7 @@ mod test {
             BufferConfig::Memory {
                 max_events: 500,
                 when_full: WhenFull::Block,
+                overflow: None,
             },
         );
 
@@ -265,6 +967,7 @@ mod test {
             BufferConfig::Memory {
                 max_events: 100,
                 when_full: WhenFull::Block,
+                overflow: None,
             },
         );
 
@@ -276,6 +979,7 @@ mod test {
             BufferConfig::Memory {
                 max_events: 500,
                 when_full: WhenFull::DropNewest,
+                overflow: None,
             },
         );
 
@@ -290,5 +994,36 @@ mod test {
                 when_full: WhenFull::Block,
             },
         );
+
+        check(
+            r#"
+          type = "memory"
+          when_full = "overflow"
+
+          [overflow]
+          type = "disk_v2"
+          max_size = 1024
+          "#,
+            BufferConfig::Memory {
+                max_events: 500,
+                when_full: WhenFull::Overflow,
+                overflow: Some(Box::new(BufferConfig::DiskV2 {
+                    max_size: 1024,
+                    when_full: WhenFull::Block,
+                })),
+            },
+        );
+
+        check(
+            r#"
+          type = "disk_v2"
+          max_size = 1024
+          when_full = "drop_newest"
+          "#,
+            BufferConfig::DiskV2 {
+                max_size: 1024,
+                when_full: WhenFull::DropNewest,
+            },
+        );
     }
 }
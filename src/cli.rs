@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 use structopt::{clap::AppSettings, StructOpt};
-use vector::{generate, list, unit_test, validate};
+use vector::{
+    config_paths, generate, list, top, topology::config::FormatHint, unit_test, validate,
+};
 
 #[derive(StructOpt, Debug)]
 #[structopt(rename_all = "kebab-case")]
@@ -48,11 +50,34 @@ impl Opts {
 #[structopt(rename_all = "kebab-case")]
 pub struct RootOpts {
     /// Read configuration from one or more files. Wildcard paths are supported.
-    /// If zero files are specified the default config path
+    /// The format of each file is detected from its extension, defaulting to
+    /// TOML. If zero files are specified the default config path
     /// `/etc/vector/vector.toml` will be targeted.
     #[structopt(name = "config", short, long)]
     pub config_paths: Vec<PathBuf>,
 
+    /// Read configuration from one or more TOML files, regardless of their
+    /// extension. Wildcard paths are supported.
+    #[structopt(name = "config-toml", long)]
+    pub config_paths_toml: Vec<PathBuf>,
+
+    /// Read configuration from one or more JSON files, regardless of their
+    /// extension. Wildcard paths are supported.
+    #[structopt(name = "config-json", long)]
+    pub config_paths_json: Vec<PathBuf>,
+
+    /// Read configuration from one or more YAML files, regardless of their
+    /// extension. Wildcard paths are supported.
+    #[structopt(name = "config-yaml", long)]
+    pub config_paths_yaml: Vec<PathBuf>,
+
+    /// Read configuration from one or more directories. Config files directly
+    /// in a directory are loaded as usual, while each file in its `sources`,
+    /// `transforms` and `sinks` subdirectories holds a single component named
+    /// after the file.
+    #[structopt(name = "config-dir", long)]
+    pub config_dirs: Vec<PathBuf>,
+
     /// Exit on startup if any sinks fail healthchecks
     #[structopt(short, long)]
     pub require_healthy: bool,
@@ -88,6 +113,18 @@ pub struct RootOpts {
     pub watch_config: bool,
 }
 
+impl RootOpts {
+    /// The config paths along with their format.
+    pub fn config_paths_with_formats(&self) -> Vec<(PathBuf, FormatHint)> {
+        config_paths::with_formats(
+            &self.config_paths,
+            &self.config_paths_toml,
+            &self.config_paths_json,
+            &self.config_paths_yaml,
+        )
+    }
+}
+
 #[derive(StructOpt, Debug)]
 #[structopt(rename_all = "kebab-case")]
 pub enum SubCommand {
@@ -103,6 +140,10 @@ pub enum SubCommand {
     /// Run Vector config unit tests, then exit. This command is experimental and therefore subject to change.
     /// For guidance on how to write unit tests check out: https://vector.dev/docs/setup/guides/unit-testing/
     Test(unit_test::Opts),
+
+    /// Display a live, continuously refreshing table of the throughput of each component of a
+    /// running Vector instance. The instance must have the `api` option enabled.
+    Top(top::Opts),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -1,3 +1,4 @@
+use crate::topology::config::{Format, FormatHint};
 use glob::glob;
 use lazy_static::lazy_static;
 use once_cell::sync::OnceCell;
@@ -9,25 +10,51 @@ lazy_static! {
 
 pub static CONFIG_PATHS: OnceCell<Vec<PathBuf>> = OnceCell::new();
 
+/// Pairs config paths with their format: `auto` paths have their format
+/// detected from the extension, while the others are given explicitly.
+pub fn with_formats(
+    auto: &[PathBuf],
+    toml: &[PathBuf],
+    json: &[PathBuf],
+    yaml: &[PathBuf],
+) -> Vec<(PathBuf, FormatHint)> {
+    let hinted = |paths: &[PathBuf], format: FormatHint| {
+        paths
+            .iter()
+            .map(|path| (path.clone(), format))
+            .collect::<Vec<_>>()
+    };
+
+    let mut paths = hinted(auto, None);
+    paths.extend(hinted(toml, Some(Format::Toml)));
+    paths.extend(hinted(json, Some(Format::Json)));
+    paths.extend(hinted(yaml, Some(Format::Yaml)));
+    paths
+}
+
 /// Expands, dedups, and sets global values.
-pub fn prepare(paths: Vec<PathBuf>) -> Option<Vec<PathBuf>> {
+pub fn prepare(paths: Vec<(PathBuf, FormatHint)>) -> Option<Vec<(PathBuf, FormatHint)>> {
     let mut config_paths = expand(paths)?;
-    config_paths.sort();
-    config_paths.dedup();
+    config_paths.sort_by(|(a, _), (b, _)| a.cmp(b));
+    config_paths.dedup_by(|(a, _), (b, _)| a == b);
     CONFIG_PATHS
-        .set(config_paths.clone())
+        .set(config_paths.iter().map(|(path, _)| path.clone()).collect())
         .expect("Cannot set global config paths");
     Some(config_paths)
 }
 
 /// Expand a list of paths (potentially containing glob patterns) into real
-/// config paths, replacing it with the default paths when empty.
-pub fn expand(config_paths: Vec<PathBuf>) -> Option<Vec<PathBuf>> {
+/// config paths, replacing it with the default paths when empty. Each path
+/// keeps the format it was given with.
+pub fn expand(config_paths: Vec<(PathBuf, FormatHint)>) -> Option<Vec<(PathBuf, FormatHint)>> {
     let mut paths = Vec::new();
-    for config_pattern in if !config_paths.is_empty() {
+    for (config_pattern, format) in if !config_paths.is_empty() {
         config_paths
     } else {
-        DEFAULT_CONFIG_PATHS.to_vec()
+        DEFAULT_CONFIG_PATHS
+            .iter()
+            .map(|path| (path.clone(), None))
+            .collect()
     } {
         let matches: Vec<PathBuf> = match glob(config_pattern.to_str().expect("No ability to glob"))
         {
@@ -44,7 +71,7 @@ pub fn expand(config_paths: Vec<PathBuf>) -> Option<Vec<PathBuf>> {
         }
 
         for path in matches {
-            paths.push(path);
+            paths.push((path, format));
         }
     }
     Some(paths)
@@ -0,0 +1,313 @@
+//! Routing of events that a sink permanently failed to deliver.
+//!
+//! Sinks hand the events of a request that was rejected as not retriable, or
+//! that ran out of retries, to their `DeadLetters` handle. The events are
+//! annotated with the failing sink and the reason, and delivered either to
+//! another component of the topology or appended to a file as JSON lines, so
+//! they can be inspected and replayed.
+
+use crate::{
+    buffers::BufferInputCloner,
+    event::Event,
+    internal_events::{DeadLetterEventDropped, DeadLetterEventsSent},
+};
+use futures01::{stream, sync::mpsc, Async, AsyncSink, Future, Poll, Sink, StartSend, Stream};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+pub const SINK_FIELD: &str = "dead_letter.sink";
+pub const REASON_FIELD: &str = "dead_letter.reason";
+
+/// Where a sink sends the events it failed to deliver.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum DeadLetterConfig {
+    /// Name of a sink or transform that receives the failed events.
+    Component(String),
+    /// A file the failed events are appended to, one JSON object per line.
+    File { path: PathBuf },
+}
+
+/// Inputs of the components of the running topology, kept up to date by the
+/// topology so that dead letters reach a component even after it has been
+/// rebuilt by a reload.
+pub type DeadLetterInputs = Arc<Mutex<HashMap<String, BufferInputCloner>>>;
+
+/// The number of dead letters that can wait for their destination before the
+/// sink sending them has to wait as well.
+const CHANNEL_SIZE: usize = 100;
+
+/// Handle through which a sink sends the events it failed to deliver.
+#[derive(Debug, Clone)]
+pub struct DeadLetters {
+    sink_name: Arc<str>,
+    tx: mpsc::Sender<Event>,
+}
+
+impl DeadLetters {
+    pub fn new(sink_name: &str) -> (Self, mpsc::Receiver<Event>) {
+        let (tx, rx) = mpsc::channel(CHANNEL_SIZE);
+        let dead_letters = Self {
+            sink_name: sink_name.into(),
+            tx,
+        };
+        (dead_letters, rx)
+    }
+
+    /// Sends the events to the destination. The returned future waits for
+    /// room in the channel to the destination, so that a slow destination
+    /// holds back the sink instead of piling up failed events.
+    pub fn send(
+        &self,
+        mut events: Vec<Event>,
+        reason: &str,
+    ) -> impl Future<Item = (), Error = ()> + Send + 'static {
+        emit!(DeadLetterEventsSent {
+            component_name: &self.sink_name,
+            count: events.len(),
+            reason,
+        });
+
+        for event in &mut events {
+            match event {
+                Event::Log(log) => {
+                    log.insert(SINK_FIELD, self.sink_name.to_string());
+                    log.insert(REASON_FIELD, reason.to_string());
+                }
+                Event::Metric(metric) => {
+                    let tags = metric.tags.get_or_insert_with(Default::default);
+                    tags.insert("dead_letter_sink".into(), self.sink_name.to_string());
+                    tags.insert("dead_letter_reason".into(), reason.to_string());
+                }
+            }
+        }
+
+        let sink_name = Arc::clone(&self.sink_name);
+        self.tx
+            .clone()
+            .send_all(stream::iter_ok::<_, mpsc::SendError<Event>>(events))
+            .map(|_| ())
+            .map_err(move |_| {
+                emit!(DeadLetterEventDropped {
+                    component_name: &sink_name,
+                    reason: "dead letter destination is shut down",
+                });
+            })
+    }
+}
+
+/// Delivers the events sent through a sink's `DeadLetters` handles to their
+/// destination. Finishes once all of the handles have been dropped.
+pub fn pump(
+    sink_name: &str,
+    config: &DeadLetterConfig,
+    rx: impl Stream<Item = Event, Error = ()> + Send + 'static,
+    inputs: DeadLetterInputs,
+) -> Box<dyn Future<Item = (), Error = ()> + Send> {
+    match config {
+        DeadLetterConfig::Component(target) => {
+            let sink = ComponentSink {
+                sink_name: sink_name.to_string(),
+                target: target.clone(),
+                inputs,
+                inner: None,
+            };
+            Box::new(rx.forward(sink).map(|_| ()))
+        }
+        DeadLetterConfig::File { path } => {
+            let sink_name = sink_name.to_string();
+            let path = path.clone();
+            let mut file = None;
+            Box::new(rx.for_each(move |event| {
+                let result = tokio::task::block_in_place(|| append_json(&mut file, &path, &event));
+                if let Err(error) = result {
+                    error!(message = "Unable to write dead letter file.", path = ?path, %error);
+                    emit!(DeadLetterEventDropped {
+                        component_name: &sink_name,
+                        reason: "unable to write dead letter file",
+                    });
+                    // Reopen the file for the next event.
+                    file = None;
+                }
+                Ok(())
+            }))
+        }
+    }
+}
+
+fn append_json(file: &mut Option<File>, path: &Path, event: &Event) -> io::Result<()> {
+    if file.is_none() {
+        *file = Some(OpenOptions::new().create(true).append(true).open(path)?);
+    }
+
+    let mut line = match event {
+        Event::Log(log) => serde_json::to_vec(log),
+        Event::Metric(metric) => serde_json::to_vec(metric),
+    }
+    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    line.push(b'\n');
+
+    file.as_mut()
+        .expect("file was just opened")
+        .write_all(&line)
+}
+
+/// Forwards dead letters into the input of another component, looking the
+/// input up again for every batch of dead letters.
+struct ComponentSink {
+    sink_name: String,
+    target: String,
+    inputs: DeadLetterInputs,
+    inner: Option<Box<dyn Sink<SinkItem = Event, SinkError = ()> + Send>>,
+}
+
+impl ComponentSink {
+    fn dropped(&self, reason: &'static str) {
+        emit!(DeadLetterEventDropped {
+            component_name: &self.sink_name,
+            reason,
+        });
+    }
+}
+
+impl Sink for ComponentSink {
+    type SinkItem = Event;
+    type SinkError = ();
+
+    fn start_send(&mut self, event: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
+        if self.inner.is_none() {
+            self.inner = self
+                .inputs
+                .lock()
+                .unwrap()
+                .get(&self.target)
+                .map(BufferInputCloner::get);
+        }
+
+        let result = match &mut self.inner {
+            Some(inner) => inner.start_send(event),
+            None => {
+                self.dropped("dead letter target is not running");
+                return Ok(AsyncSink::Ready);
+            }
+        };
+
+        match result {
+            Ok(result) => Ok(result),
+            Err(()) => {
+                // The target was shut down; it is looked up again for the next event.
+                self.inner = None;
+                self.dropped("dead letter target is shut down");
+                Ok(AsyncSink::Ready)
+            }
+        }
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        let result = match &mut self.inner {
+            Some(inner) => inner.poll_complete(),
+            None => return Ok(Async::Ready(())),
+        };
+
+        match result {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            // Don't hold on to the input between batches of dead letters, so
+            // that the target can shut down when it is removed by a reload.
+            Ok(Async::Ready(())) | Err(()) => {
+                self.inner = None;
+                Ok(Async::Ready(()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffers::{BufferUsage, WhenFull};
+    use crate::test_util::{block_on, runtime};
+
+    #[test]
+    fn annotates_failed_events() {
+        let (dead_letters, rx) = DeadLetters::new("out");
+        block_on(dead_letters.send(vec![Event::from("boom")], "response status: 400")).unwrap();
+        drop(dead_letters);
+
+        let events = block_on(rx.collect()).unwrap();
+        assert_eq!(events.len(), 1);
+        let log = events[0].as_log();
+        assert_eq!(log[&SINK_FIELD.into()], "out".into());
+        assert_eq!(log[&REASON_FIELD.into()], "response status: 400".into());
+    }
+
+    #[test]
+    fn delivers_to_component() {
+        let (tx, rx) = mpsc::channel(10);
+        let inputs = DeadLetterInputs::default();
+        inputs.lock().unwrap().insert(
+            "errors".into(),
+            BufferInputCloner::Memory(tx, WhenFull::Block, BufferUsage::new("errors")),
+        );
+
+        let (dead_letters, dead_rx) = DeadLetters::new("out");
+        let config = DeadLetterConfig::Component("errors".into());
+        let pump = pump("out", &config, dead_rx, inputs.clone());
+
+        block_on(dead_letters.send(vec![Event::from("a"), Event::from("b")], "nope")).unwrap();
+        drop(dead_letters);
+        block_on(pump).unwrap();
+        drop(inputs);
+
+        let events = block_on(rx.collect()).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].as_log()[&REASON_FIELD.into()], "nope".into());
+    }
+
+    #[test]
+    fn appends_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dead.log");
+
+        let (dead_letters, rx) = DeadLetters::new("out");
+        let config = DeadLetterConfig::File { path: path.clone() };
+        let pump = pump("out", &config, rx, DeadLetterInputs::default());
+
+        block_on(dead_letters.send(vec![Event::from("a"), Event::from("b")], "nope")).unwrap();
+        drop(dead_letters);
+        runtime().block_on(pump).unwrap();
+
+        let lines = crate::test_util::lines_from_file(&path);
+        assert_eq!(lines.len(), 2);
+        let line: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(line["message"], "a");
+        assert_eq!(line["dead_letter"]["reason"], "nope");
+    }
+
+    #[test]
+    fn parses_config() {
+        #[derive(Deserialize)]
+        struct Outer {
+            dead_letter: DeadLetterConfig,
+        }
+
+        let outer: Outer = toml::from_str(r#"dead_letter = "errors""#).unwrap();
+        assert_eq!(
+            outer.dead_letter,
+            DeadLetterConfig::Component("errors".into())
+        );
+
+        let outer: Outer = toml::from_str(r#"dead_letter = { path = "/tmp/dead.log" }"#).unwrap();
+        assert_eq!(
+            outer.dead_letter,
+            DeadLetterConfig::File {
+                path: "/tmp/dead.log".into()
+            }
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! End-to-end acknowledgement of events.
+//!
+//! A source that wants to know when its events have been fully processed
+//! attaches a `BatchNotifier` to them. Every copy of an event shares the
+//! notifier, and the notifier fires once the last copy is finalized: when a
+//! sink acknowledged delivering it, it was written to a disk buffer, or it was
+//! dropped along the way, e.g. by a filter.
+
+use futures01::{
+    future,
+    sync::{mpsc, oneshot},
+    Future, Stream,
+};
+use std::sync::Arc;
+
+pub type BatchReceiver = oneshot::Receiver<()>;
+
+/// Fires its receiver once every event it is attached to has been finalized.
+#[derive(Debug)]
+pub struct BatchNotifier {
+    tx: Option<oneshot::Sender<()>>,
+}
+
+impl BatchNotifier {
+    pub fn new_with_receiver() -> (Arc<Self>, BatchReceiver) {
+        let (tx, rx) = oneshot::channel();
+        (Arc::new(Self { tx: Some(tx) }), rx)
+    }
+}
+
+impl Drop for BatchNotifier {
+    fn drop(&mut self) {
+        if let Some(tx) = self.tx.take() {
+            // The source may have stopped waiting, which is fine.
+            let _ = tx.send(());
+        }
+    }
+}
+
+/// The notifiers attached to a single event. Dropping them finalizes the
+/// event.
+#[derive(Clone, Debug, Default)]
+pub struct EventFinalizers(Vec<Arc<BatchNotifier>>);
+
+impl EventFinalizers {
+    pub fn new(notifier: Arc<BatchNotifier>) -> Self {
+        Self(vec![notifier])
+    }
+
+    pub fn add(&mut self, notifier: Arc<BatchNotifier>) {
+        self.0.push(notifier);
+    }
+
+    /// Used when events are merged into one, which is then only finalized
+    /// once all of the notifiers of the merged events are.
+    pub fn merge(&mut self, other: Self) {
+        self.0.extend(other.0);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Finalizers carry no data, so they don't take part in comparing events.
+impl PartialEq for EventFinalizers {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// Calls a function with the checkpoints of a source in the order they were
+/// pushed, each only once its batch of events has been finalized and all of
+/// the checkpoints before it have been handled. This keeps a source from
+/// advancing past events that are still in flight when batches finish out of
+/// order.
+pub struct OrderedAcker<T> {
+    tx: mpsc::UnboundedSender<(BatchReceiver, T)>,
+}
+
+impl<T: Send + 'static> OrderedAcker<T> {
+    /// Returns the acker along with the task driving it, which finishes once
+    /// the acker is dropped and all pending batches were finalized.
+    pub fn new<F>(mut f: F) -> (Self, Box<dyn Future<Item = (), Error = ()> + Send>)
+    where
+        F: FnMut(T) + Send + 'static,
+    {
+        let (tx, rx) = mpsc::unbounded();
+        let task = rx
+            .map(|(receiver, checkpoint): (BatchReceiver, T)| {
+                // A cancelled receiver means the notifier went away, which
+                // is a finalization as well.
+                receiver.then(move |_| future::ok::<_, ()>(checkpoint))
+            })
+            .buffered(usize::max_value())
+            .for_each(move |checkpoint| {
+                f(checkpoint);
+                Ok(())
+            });
+
+        (Self { tx }, Box::new(task))
+    }
+
+    pub fn push(&self, receiver: BatchReceiver, checkpoint: T) {
+        if self.tx.unbounded_send((receiver, checkpoint)).is_err() {
+            debug!(message = "Acknowledgement task is gone; checkpoint is discarded.");
+        }
+    }
+
+    /// Creates a notifier for a batch that ends at `checkpoint`.
+    pub fn add(&self, checkpoint: T) -> Arc<BatchNotifier> {
+        let (notifier, receiver) = BatchNotifier::new_with_receiver();
+        self.push(receiver, checkpoint);
+        notifier
+    }
+}
+
+impl<T> Clone for OrderedAcker<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::block_on;
+    use std::sync::Mutex;
+
+    #[test]
+    fn notifies_after_last_copy() {
+        let (notifier, mut receiver) = BatchNotifier::new_with_receiver();
+        let first = EventFinalizers::new(notifier);
+        let second = first.clone();
+
+        drop(first);
+        assert_eq!(receiver.try_recv(), Ok(None));
+        drop(second);
+        assert_eq!(receiver.try_recv(), Ok(Some(())));
+    }
+
+    #[test]
+    fn acks_in_order() {
+        let acked = Arc::new(Mutex::new(Vec::new()));
+        let acked2 = Arc::clone(&acked);
+        let (acker, task) = OrderedAcker::new(move |n| acked2.lock().unwrap().push(n));
+
+        let first = acker.add(1);
+        let second = acker.add(2);
+        drop(acker);
+
+        drop(second);
+        drop(first);
+        block_on(task).unwrap();
+
+        assert_eq!(*acked.lock().unwrap(), vec![1, 2]);
+    }
+}
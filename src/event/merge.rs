@@ -3,6 +3,7 @@ use string_cache::DefaultAtom as Atom;
 
 /// Merges all fields specified at `merge_fields` from `incoming` to `current`.
 pub fn merge_log_event(current: &mut LogEvent, mut incoming: LogEvent, merge_fields: &[Atom]) {
+    current.finalizers.merge(incoming.take_finalizers());
     for merge_field in merge_fields {
         let incoming_val = match incoming.remove(merge_field) {
             None => continue,
@@ -1,3 +1,4 @@
+use super::sketch::DDSketch;
 use chrono::{DateTime, Utc};
 use derive_is_enum_variant::is_enum_variant;
 use serde::{Deserialize, Serialize};
@@ -48,9 +49,121 @@ pub enum MetricValue {
         count: u32,
         sum: f64,
     },
+    Sketch {
+        sketch: DDSketch,
+    },
+}
+
+impl MetricValue {
+    /// Counts the samples of a distribution, weighted by their sample rates,
+    /// or of a sketch into a histogram with the given bucket bounds. Returns
+    /// `None` for any other value.
+    pub fn distribution_to_histogram(&self, buckets: &[f64]) -> Option<Self> {
+        match self {
+            MetricValue::Distribution {
+                values,
+                sample_rates,
+            } => {
+                let mut counts = vec![0; buckets.len()];
+                let mut count = 0;
+                let mut sum = 0.0;
+                for (value, rate) in values.iter().zip(sample_rates.iter()) {
+                    // Bucket counts are cumulative, as in Prometheus.
+                    for (bucket, bucket_count) in buckets.iter().zip(counts.iter_mut()) {
+                        if value <= bucket {
+                            *bucket_count += rate;
+                        }
+                    }
+                    count += rate;
+                    sum += value * f64::from(*rate);
+                }
+
+                Some(MetricValue::AggregatedHistogram {
+                    buckets: buckets.to_vec(),
+                    counts,
+                    count,
+                    sum,
+                })
+            }
+            MetricValue::Sketch { sketch } => Some(MetricValue::AggregatedHistogram {
+                buckets: buckets.to_vec(),
+                counts: sketch.bucket_counts(buckets),
+                count: sketch.count(),
+                sum: sketch.sum(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Estimates the given quantiles of a distribution, weighted by their
+    /// sample rates, or of a sketch as a summary. Returns `None` for any other
+    /// value.
+    pub fn distribution_to_summary(&self, quantiles: &[f64]) -> Option<Self> {
+        match self {
+            MetricValue::Distribution {
+                values,
+                sample_rates,
+            } => Some(summarize(&sketch_samples(values, sample_rates), quantiles)),
+            MetricValue::Sketch { sketch } => Some(summarize(sketch, quantiles)),
+            _ => None,
+        }
+    }
+
+    /// Inserts the samples of a distribution, weighted by their sample rates,
+    /// into a sketch. Returns `None` for any other value.
+    pub fn distribution_to_sketch(&self) -> Option<Self> {
+        match self {
+            MetricValue::Distribution {
+                values,
+                sample_rates,
+            } => Some(MetricValue::Sketch {
+                sketch: sketch_samples(values, sample_rates),
+            }),
+            _ => None,
+        }
+    }
+}
+
+fn sketch_samples(values: &[f64], sample_rates: &[u32]) -> DDSketch {
+    let mut sketch = DDSketch::new();
+    for (value, rate) in values.iter().zip(sample_rates.iter()) {
+        sketch.insert_n(*value, *rate);
+    }
+    sketch
+}
+
+fn summarize(sketch: &DDSketch, quantiles: &[f64]) -> MetricValue {
+    MetricValue::AggregatedSummary {
+        quantiles: quantiles.to_vec(),
+        values: quantiles
+            .iter()
+            .map(|quantile| sketch.quantile(*quantile).unwrap_or(0.0))
+            .collect(),
+        count: sketch.count(),
+        sum: sketch.sum(),
+    }
 }
 
 impl Metric {
+    /// An estimate of the size of the metric in bytes, see `Event::size_of`.
+    pub fn size_of(&self) -> usize {
+        let tags = self
+            .tags
+            .iter()
+            .flatten()
+            .map(|(key, value)| key.len() + value.len())
+            .sum::<usize>();
+        let value = match &self.value {
+            MetricValue::Counter { .. } | MetricValue::Gauge { .. } => 8,
+            MetricValue::Set { values } => values.iter().map(String::len).sum(),
+            MetricValue::Distribution { values, .. } => values.len() * 12,
+            MetricValue::AggregatedHistogram { buckets, .. } => buckets.len() * 12 + 12,
+            MetricValue::AggregatedSummary { quantiles, .. } => quantiles.len() * 16 + 12,
+            MetricValue::Sketch { sketch } => sketch.size_of(),
+        };
+        self.name.len() + tags + value
+    }
+
     pub fn to_absolute(&self) -> Self {
         Self {
             name: self.name.clone(),
@@ -113,6 +226,9 @@ impl Metric {
                     *sum += sum2;
                 }
             }
+            (MetricValue::Sketch { ref mut sketch }, MetricValue::Sketch { sketch: sketch2 }) => {
+                sketch.merge(sketch2);
+            }
             _ => {}
         }
     }
@@ -159,6 +275,9 @@ impl Metric {
                 *count = 0;
                 *sum = 0.0;
             }
+            MetricValue::Sketch { ref mut sketch } => {
+                *sketch = DDSketch::new();
+            }
         }
     }
 }
@@ -320,4 +439,103 @@ mod test {
             }
         )
     }
+
+    #[test]
+    fn distribution_to_histogram() {
+        let value = MetricValue::Distribution {
+            values: vec![1.0, 2.0, 3.0],
+            sample_rates: vec![3, 3, 2],
+        };
+
+        assert_eq!(
+            value.distribution_to_histogram(&[0.0, 2.5, 5.0]),
+            Some(MetricValue::AggregatedHistogram {
+                buckets: vec![0.0, 2.5, 5.0],
+                counts: vec![0, 6, 8],
+                count: 8,
+                sum: 15.0,
+            })
+        );
+        assert_eq!(
+            MetricValue::Counter { value: 1.0 }.distribution_to_histogram(&[1.0]),
+            None
+        );
+    }
+
+    #[test]
+    fn distribution_to_summary() {
+        let value = MetricValue::Distribution {
+            values: (1..=100).map(f64::from).collect(),
+            sample_rates: vec![1; 100],
+        };
+
+        match value.distribution_to_summary(&[0.5, 0.99]) {
+            Some(MetricValue::AggregatedSummary {
+                quantiles,
+                values,
+                count,
+                sum,
+            }) => {
+                assert_eq!(quantiles, vec![0.5, 0.99]);
+                assert!((values[0] - 50.0).abs() <= 0.5);
+                assert!((values[1] - 99.0).abs() <= 1.0);
+                assert_eq!(count, 100);
+                assert_eq!(sum, 5050.0);
+            }
+            other => panic!("unexpected value: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn distribution_to_sketch() {
+        let value = MetricValue::Distribution {
+            values: vec![1.0, 2.0],
+            sample_rates: vec![3, 1],
+        };
+
+        match value.distribution_to_sketch() {
+            Some(MetricValue::Sketch { sketch }) => {
+                assert_eq!(sketch.count(), 4);
+                assert_eq!(sketch.sum(), 5.0);
+            }
+            other => panic!("unexpected value: {:?}", other),
+        }
+        assert_eq!(
+            MetricValue::Counter { value: 1.0 }.distribution_to_sketch(),
+            None
+        );
+    }
+
+    #[test]
+    fn merge_sketches() {
+        let sketch = |values: &[f64]| {
+            let mut sketch = DDSketch::new();
+            for value in values {
+                sketch.insert(*value);
+            }
+            MetricValue::Sketch { sketch }
+        };
+
+        let mut metric = Metric {
+            name: "sketch".into(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: sketch(&[1.0, 2.0]),
+        };
+
+        let delta = Metric {
+            name: "sketch".into(),
+            timestamp: Some(ts()),
+            tags: Some(tags()),
+            kind: MetricKind::Incremental,
+            value: sketch(&[3.0]),
+        };
+
+        metric.add(&delta);
+        assert_eq!(metric.value, sketch(&[1.0, 2.0, 3.0]));
+
+        metric.reset();
+        assert_eq!(metric.value, sketch(&[]));
+    }
 }
@@ -11,11 +11,14 @@ use std::{collections::BTreeMap, iter::FromIterator};
 use string_cache::DefaultAtom as Atom;
 
 pub mod discriminant;
+pub mod finalization;
 pub mod merge;
 pub mod merge_state;
 pub mod metric;
+pub mod sketch;
 mod util;
 
+pub use finalization::{BatchNotifier, EventFinalizers};
 pub use metric::Metric;
 pub(crate) use util::log::PathComponent;
 pub(crate) use util::log::PathIter;
@@ -45,6 +48,7 @@ pub enum Event {
 #[derive(PartialEq, Debug, Clone, Default)]
 pub struct LogEvent {
     fields: BTreeMap<String, Value>,
+    finalizers: EventFinalizers,
 }
 
 impl Event {
@@ -93,6 +97,30 @@ impl Event {
             _ => panic!("failed type coercion, {:?} is not a metric", self),
         }
     }
+
+    /// Takes the finalizers of the event, which are then finalized when the
+    /// returned value is dropped rather than the event. Metrics don't carry
+    /// finalizers.
+    pub fn take_finalizers(&mut self) -> EventFinalizers {
+        match self {
+            Event::Log(log) => log.take_finalizers(),
+            Event::Metric(_) => EventFinalizers::default(),
+        }
+    }
+
+    /// An estimate of the payload size of the event in bytes, used for
+    /// internal throughput metrics. This is not the size of any particular
+    /// encoding of the event.
+    pub fn size_of(&self) -> usize {
+        match self {
+            Event::Log(log) => log
+                .fields
+                .iter()
+                .map(|(key, value)| key.len() + value.size_of())
+                .sum(),
+            Event::Metric(metric) => metric.size_of(),
+        }
+    }
 }
 
 impl LogEvent {
@@ -163,6 +191,14 @@ impl LogEvent {
     pub fn is_empty(&self) -> bool {
         self.fields.is_empty()
     }
+
+    pub fn add_finalizer(&mut self, notifier: std::sync::Arc<BatchNotifier>) {
+        self.finalizers.add(notifier);
+    }
+
+    pub fn take_finalizers(&mut self) -> EventFinalizers {
+        std::mem::take(&mut self.finalizers)
+    }
 }
 
 impl std::ops::Index<&Atom> for LogEvent {
@@ -440,6 +476,20 @@ impl Value {
         self.as_bytes()
     }
 
+    /// An estimate of the size of the value in bytes, see `Event::size_of`.
+    pub fn size_of(&self) -> usize {
+        match self {
+            Value::Bytes(bytes) => bytes.len(),
+            Value::Integer(_) | Value::Float(_) | Value::Timestamp(_) => 8,
+            Value::Boolean(_) | Value::Null => 1,
+            Value::Map(map) => map
+                .iter()
+                .map(|(key, value)| key.len() + value.size_of())
+                .sum(),
+            Value::Array(array) => array.iter().map(Value::size_of).sum(),
+        }
+    }
+
     pub fn as_timestamp(&self) -> Option<&DateTime<Utc>> {
         match &self {
             Value::Timestamp(ts) => Some(ts),
@@ -507,7 +557,10 @@ impl From<proto::EventWrapper> for Event {
                     .filter_map(|(k, v)| decode_value(v).map(|value| (k, value)))
                     .collect::<BTreeMap<_, _>>();
 
-                Event::Log(LogEvent { fields })
+                Event::Log(LogEvent {
+                    fields,
+                    finalizers: Default::default(),
+                })
             }
             EventProto::Metric(proto) => {
                 let kind = match proto.kind() {
@@ -551,6 +604,9 @@ impl From<proto::EventWrapper> for Event {
                         count: summary.count,
                         sum: summary.sum,
                     },
+                    MetricProto::Sketch(sketch) => MetricValue::Sketch {
+                        sketch: sketch.into(),
+                    },
                 };
 
                 Event::Metric(Metric {
@@ -601,7 +657,8 @@ fn encode_array(items: Vec<Value>) -> proto::ValueArray {
 impl From<Event> for proto::EventWrapper {
     fn from(event: Event) -> Self {
         match event {
-            Event::Log(LogEvent { fields }) => {
+            // Serializing an event, e.g. into a disk buffer, finalizes it.
+            Event::Log(LogEvent { fields, .. }) => {
                 let fields = fields
                     .into_iter()
                     .map(|(k, v)| (k, encode_value(v)))
@@ -668,6 +725,7 @@ impl From<Event> for proto::EventWrapper {
                         count,
                         sum,
                     }),
+                    MetricValue::Sketch { sketch } => MetricProto::Sketch(sketch.into()),
                 };
 
                 let event = EventProto::Metric(proto::Metric {
@@ -698,9 +756,7 @@ impl From<Event> for Vec<u8> {
 
 impl From<Bytes> for Event {
     fn from(message: Bytes) -> Self {
-        let mut event = Event::Log(LogEvent {
-            fields: BTreeMap::new(),
-        });
+        let mut event = Event::new_empty_log();
 
         event
             .as_mut_log()
@@ -715,9 +771,7 @@ impl From<Bytes> for Event {
 
 impl From<bytes05::Bytes> for Event {
     fn from(message: bytes05::Bytes) -> Self {
-        let mut event = Event::Log(LogEvent {
-            fields: BTreeMap::new(),
-        });
+        let mut event = Event::new_empty_log();
 
         event
             .as_mut_log()
@@ -756,9 +810,31 @@ impl From<Metric> for Event {
 
 #[cfg(test)]
 mod test {
-    use super::{Atom, Event, LogSchema, Value};
+    use super::{
+        metric::{MetricKind, MetricValue},
+        proto,
+        sketch::DDSketch,
+        Atom, Event, LogSchema, Metric, Value,
+    };
     use regex::Regex;
-    use std::collections::HashSet;
+    use std::collections::{BTreeMap, HashSet};
+
+    #[test]
+    fn size_of() {
+        let mut event = Event::new_empty_log();
+        event.as_mut_log().insert("message", "hello");
+        assert_eq!(event.size_of(), "message".len() + "hello".len());
+
+        let mut map = BTreeMap::new();
+        map.insert("a".to_string(), Value::Integer(1));
+        event
+            .as_mut_log()
+            .insert("nested", Value::Array(vec![Value::Map(map), Value::Null]));
+        assert_eq!(
+            event.size_of(),
+            "message".len() + "hello".len() + "nested".len() + 1 + 8 + 1
+        );
+    }
 
     #[test]
     fn serialization() {
@@ -857,4 +933,22 @@ timestamp_key = "timestamp"
 "#;
         let _ = toml::from_str::<LogSchema>(toml).unwrap();
     }
+
+    #[test]
+    fn sketch_proto_roundtrip() {
+        let mut sketch = DDSketch::new();
+        sketch.insert_n(-2.5, 2);
+        sketch.insert_n(0.0, 1);
+        sketch.insert_n(150.0, 3);
+        let event = Event::Metric(Metric {
+            name: "latency".into(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Sketch { sketch },
+        });
+
+        let decoded = Event::from(proto::EventWrapper::from(event.clone()));
+        assert_eq!(decoded, event);
+    }
 }
@@ -0,0 +1,335 @@
+use super::proto;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Relative accuracy of quantile estimates. Sketches can only be merged when
+/// they share it, so it isn't configurable.
+const RELATIVE_ACCURACY: f64 = 0.01;
+
+/// Values closer to zero than this are counted as zero.
+const MIN_VALUE: f64 = 1.0e-9;
+
+/// Upper bound on the number of bins kept for each sign. Once exceeded, the
+/// bins nearest to zero are collapsed, trading accuracy of the lowest
+/// quantiles for bounded memory.
+const MAX_BINS: usize = 2048;
+
+/// A [DDSketch](https://arxiv.org/abs/1908.10693) quantile sketch.
+///
+/// Samples are counted in logarithmically sized bins, so quantiles are
+/// estimated within `RELATIVE_ACCURACY` of their true value, and two sketches
+/// are merged by adding their bins.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DDSketch {
+    positive: BTreeMap<i32, u32>,
+    negative: BTreeMap<i32, u32>,
+    zero_count: u32,
+    count: u32,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for DDSketch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DDSketch {
+    pub fn new() -> Self {
+        Self {
+            positive: BTreeMap::new(),
+            negative: BTreeMap::new(),
+            zero_count: 0,
+            count: 0,
+            sum: 0.0,
+            min: 0.0,
+            max: 0.0,
+        }
+    }
+
+    pub fn insert(&mut self, value: f64) {
+        self.insert_n(value, 1);
+    }
+
+    /// Adds `value` as if it had been seen `n` times.
+    pub fn insert_n(&mut self, value: f64, n: u32) {
+        if n == 0 || !value.is_finite() {
+            return;
+        }
+
+        if value > MIN_VALUE {
+            *self.positive.entry(index(value)).or_insert(0) += n;
+            collapse(&mut self.positive);
+        } else if value < -MIN_VALUE {
+            *self.negative.entry(index(-value)).or_insert(0) += n;
+            collapse(&mut self.negative);
+        } else {
+            self.zero_count += n;
+        }
+
+        if self.is_empty() {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.count += n;
+        self.sum += value * f64::from(n);
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        if other.is_empty() {
+            return;
+        }
+        if self.is_empty() {
+            *self = other.clone();
+            return;
+        }
+
+        for (index, count) in &other.positive {
+            *self.positive.entry(*index).or_insert(0) += count;
+        }
+        collapse(&mut self.positive);
+        for (index, count) in &other.negative {
+            *self.negative.entry(*index).or_insert(0) += count;
+        }
+        collapse(&mut self.negative);
+
+        self.zero_count += other.zero_count;
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        self.value_if_not_empty(self.min)
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.value_if_not_empty(self.max)
+    }
+
+    /// An estimate of the size of the sketch in bytes, see `Metric::size_of`.
+    pub fn size_of(&self) -> usize {
+        (self.positive.len() + self.negative.len()) * 8 + 36
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Estimates the value at quantile `q`, which must be between 0 and 1.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.is_empty() || !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+
+        let rank = q * f64::from(self.count - 1);
+        let mut seen = 0.0;
+        let estimate = self
+            .negative
+            .iter()
+            .rev()
+            .map(|(index, count)| (-value(*index), *count))
+            .chain(Some((0.0, self.zero_count)))
+            .chain(
+                self.positive
+                    .iter()
+                    .map(|(index, count)| (value(*index), *count)),
+            )
+            .find(|(_, count)| {
+                seen += f64::from(*count);
+                seen > rank
+            })
+            .map_or(self.max, |(value, _)| value);
+
+        // The extremes are tracked exactly, and no estimate lies outside them.
+        Some(estimate.max(self.min).min(self.max))
+    }
+
+    /// Counts the samples at or below each of `buckets`, which must be
+    /// sorted. Samples are placed by the estimated value of their bin, so
+    /// counts are only as accurate as quantiles are.
+    pub fn bucket_counts(&self, buckets: &[f64]) -> Vec<u32> {
+        let bins = self
+            .negative
+            .iter()
+            .map(|(index, count)| (-value(*index), *count))
+            .chain(Some((0.0, self.zero_count)))
+            .chain(
+                self.positive
+                    .iter()
+                    .map(|(index, count)| (value(*index), *count)),
+            )
+            .collect::<Vec<_>>();
+
+        buckets
+            .iter()
+            .map(|bucket| {
+                bins.iter()
+                    .filter(|(value, _)| value <= bucket)
+                    .map(|(_, count)| count)
+                    .sum()
+            })
+            .collect()
+    }
+
+    fn value_if_not_empty(&self, value: f64) -> Option<f64> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(value)
+        }
+    }
+}
+
+impl From<proto::Sketch> for DDSketch {
+    fn from(sketch: proto::Sketch) -> Self {
+        Self {
+            positive: sketch.positive,
+            negative: sketch.negative,
+            zero_count: sketch.zero_count,
+            count: sketch.count,
+            sum: sketch.sum,
+            min: sketch.min,
+            max: sketch.max,
+        }
+    }
+}
+
+impl From<DDSketch> for proto::Sketch {
+    fn from(sketch: DDSketch) -> Self {
+        Self {
+            positive: sketch.positive,
+            negative: sketch.negative,
+            zero_count: sketch.zero_count,
+            count: sketch.count,
+            sum: sketch.sum,
+            min: sketch.min,
+            max: sketch.max,
+        }
+    }
+}
+
+fn gamma() -> f64 {
+    (1.0 + RELATIVE_ACCURACY) / (1.0 - RELATIVE_ACCURACY)
+}
+
+/// The bin covering `(gamma^(index - 1), gamma^index]`.
+fn index(value: f64) -> i32 {
+    (value.ln() / gamma().ln()).ceil() as i32
+}
+
+/// The value within `RELATIVE_ACCURACY` of every value in bin `index`.
+fn value(index: i32) -> f64 {
+    let gamma = gamma();
+    2.0 * gamma.powi(index) / (gamma + 1.0)
+}
+
+fn collapse(bins: &mut BTreeMap<i32, u32>) {
+    while bins.len() > MAX_BINS {
+        let (lowest, count) = bins
+            .iter()
+            .next()
+            .map(|(index, count)| (*index, *count))
+            .expect("more than MAX_BINS bins");
+        bins.remove(&lowest);
+        if let Some(next) = bins.values_mut().next() {
+            *next += count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.unwrap();
+        assert!(
+            (actual - expected).abs() <= expected.abs() * RELATIVE_ACCURACY,
+            "{} is not within {} of {}",
+            actual,
+            RELATIVE_ACCURACY,
+            expected
+        );
+    }
+
+    #[test]
+    fn estimates_quantiles() {
+        let mut sketch = DDSketch::new();
+        for value in 1..=1000 {
+            sketch.insert(f64::from(value));
+        }
+
+        assert_eq!(sketch.count(), 1000);
+        assert_eq!(sketch.min(), Some(1.0));
+        assert_eq!(sketch.max(), Some(1000.0));
+        assert_close(sketch.quantile(0.5), 500.0);
+        assert_close(sketch.quantile(0.9), 900.0);
+        assert_close(sketch.quantile(0.99), 990.0);
+        assert_close(sketch.quantile(1.0), 1000.0);
+        assert_eq!(sketch.quantile(1.5), None);
+    }
+
+    #[test]
+    fn handles_negative_and_zero_values() {
+        let mut sketch = DDSketch::new();
+        sketch.insert_n(-10.0, 2);
+        sketch.insert_n(0.0, 2);
+        sketch.insert_n(10.0, 1);
+
+        assert_close(sketch.quantile(0.0), -10.0);
+        assert_close(sketch.quantile(0.25), -10.0);
+        assert_eq!(sketch.quantile(0.5), Some(0.0));
+        assert_close(sketch.quantile(1.0), 10.0);
+        assert_eq!(sketch.sum(), -10.0);
+    }
+
+    #[test]
+    fn merges_sketches() {
+        let mut low = DDSketch::new();
+        let mut high = DDSketch::new();
+        for value in 1..=500 {
+            low.insert(f64::from(value));
+            high.insert(f64::from(value + 500));
+        }
+
+        low.merge(&high);
+        assert_eq!(low.count(), 1000);
+        assert_eq!(low.min(), Some(1.0));
+        assert_eq!(low.max(), Some(1000.0));
+        assert_close(low.quantile(0.5), 500.0);
+        assert_close(low.quantile(0.75), 750.0);
+    }
+
+    #[test]
+    fn counts_buckets() {
+        let mut sketch = DDSketch::new();
+        sketch.insert_n(-1.0, 1);
+        sketch.insert_n(0.5, 2);
+        sketch.insert_n(2.0, 3);
+
+        assert_eq!(sketch.bucket_counts(&[0.0, 1.0, 10.0]), vec![1, 3, 6]);
+    }
+
+    #[test]
+    fn empty_sketch() {
+        let sketch = DDSketch::new();
+        assert!(sketch.is_empty());
+        assert_eq!(sketch.min(), None);
+        assert_eq!(sketch.quantile(0.5), None);
+    }
+}
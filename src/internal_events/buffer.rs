@@ -0,0 +1,16 @@
+use super::InternalEvent;
+use metrics::gauge;
+
+#[derive(Debug)]
+pub struct BufferEventsSampled<'a> {
+    pub component_name: &'a str,
+    pub events: i64,
+}
+
+impl InternalEvent for BufferEventsSampled<'_> {
+    fn emit_metrics(&self) {
+        gauge!("buffer_events", self.events,
+            "component_name" => self.component_name.to_string(),
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use super::InternalEvent;
+use metrics::counter;
+
+#[derive(Debug)]
+pub struct DeadLetterEventsSent<'a> {
+    pub component_name: &'a str,
+    pub count: usize,
+    pub reason: &'a str,
+}
+
+impl InternalEvent for DeadLetterEventsSent<'_> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "Sending failed events to dead letter destination.",
+            count = self.count,
+            reason = self.reason,
+            rate_limit_secs = 10,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("dead_letter_events", self.count as u64,
+            "component_kind" => "sink",
+            "component_name" => self.component_name.to_string(),
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct DeadLetterEventDropped<'a> {
+    pub component_name: &'a str,
+    pub reason: &'static str,
+}
+
+impl InternalEvent for DeadLetterEventDropped<'_> {
+    fn emit_logs(&self) {
+        error!(
+            message = "Unable to deliver dead letter; dropping event.",
+            reason = self.reason,
+            rate_limit_secs = 10,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("dead_letter_events_dropped", 1,
+            "component_kind" => "sink",
+            "component_name" => self.component_name.to_string(),
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use super::InternalEvent;
+use metrics::counter;
+
+#[derive(Debug)]
+pub struct KubernetesLogsEventReceived<'a> {
+    pub file: &'a str,
+    pub byte_size: usize,
+}
+
+impl InternalEvent for KubernetesLogsEventReceived<'_> {
+    fn emit_logs(&self) {
+        trace!(
+            message = "received one event.",
+            %self.file,
+            rate_limit_secs = 10
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("events_processed", 1,
+            "component_kind" => "source",
+            "component_type" => "kubernetes_logs",
+        );
+        counter!("bytes_processed", self.byte_size as u64,
+            "component_kind" => "source",
+            "component_type" => "kubernetes_logs",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct KubernetesLogsParseFailed<'a> {
+    pub file: &'a str,
+    pub error: crate::Error,
+}
+
+impl InternalEvent for KubernetesLogsParseFailed<'_> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "failed to parse log line; sending it as is.",
+            %self.file,
+            error = %self.error,
+            rate_limit_secs = 30
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("parse_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "kubernetes_logs",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct KubernetesLogsMetadataRefreshFailed {
+    pub error: crate::Error,
+}
+
+impl InternalEvent for KubernetesLogsMetadataRefreshFailed {
+    fn emit_logs(&self) {
+        error!(
+            message = "failed to refresh pod metadata; keeping the previous metadata.",
+            error = %self.error,
+            rate_limit_secs = 30
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("metadata_refresh_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "kubernetes_logs",
+        );
+    }
+}
@@ -1,17 +1,28 @@
 mod add_fields;
 mod aws_kinesis_streams;
 mod blackhole;
+mod buffer;
+mod dead_letter;
 mod elasticsearch;
 mod file;
 mod json;
+#[cfg(feature = "sources-kubernetes_logs")]
+mod kubernetes_logs;
 #[cfg(feature = "transforms-lua")]
 mod lua;
+#[cfg(feature = "sources-otlp")]
+mod otlp;
 #[cfg(feature = "sources-prometheus")]
 mod prometheus;
+#[cfg(feature = "sources-prometheus_remote_write")]
+mod prometheus_remote_write;
 mod regex;
 mod splunk_hec;
+#[cfg(feature = "sources-statsd")]
+mod statsd;
 mod syslog;
 mod tcp;
+mod topology;
 mod udp;
 mod unix;
 mod vector;
@@ -21,17 +32,28 @@ mod wasm;
 pub use self::add_fields::*;
 pub use self::aws_kinesis_streams::*;
 pub use self::blackhole::*;
+pub use self::buffer::*;
+pub use self::dead_letter::*;
 pub use self::elasticsearch::*;
 pub use self::file::*;
 pub use self::json::*;
+#[cfg(feature = "sources-kubernetes_logs")]
+pub use self::kubernetes_logs::*;
 #[cfg(feature = "transforms-lua")]
 pub use self::lua::*;
+#[cfg(feature = "sources-otlp")]
+pub use self::otlp::*;
 #[cfg(feature = "sources-prometheus")]
 pub use self::prometheus::*;
+#[cfg(feature = "sources-prometheus_remote_write")]
+pub use self::prometheus_remote_write::*;
 pub use self::regex::*;
 pub use self::splunk_hec::*;
+#[cfg(feature = "sources-statsd")]
+pub use self::statsd::*;
 pub use self::syslog::*;
 pub use self::tcp::*;
+pub use self::topology::*;
 pub use self::udp::*;
 pub use self::unix::*;
 pub use self::vector::*;
@@ -0,0 +1,51 @@
+use super::InternalEvent;
+use crate::sources::otlp::RequestError;
+use metrics::counter;
+
+#[derive(Debug)]
+pub struct OtlpEventsReceived {
+    pub count: usize,
+    pub byte_size: usize,
+}
+
+impl InternalEvent for OtlpEventsReceived {
+    fn emit_logs(&self) {
+        trace!(message = "received export request.", count = %self.count);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", self.count as u64,
+            "component_kind" => "source",
+            "component_type" => "otlp",
+        );
+        counter!(
+            "bytes_processed", self.byte_size as u64,
+            "component_kind" => "source",
+            "component_type" => "otlp",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct OtlpRequestError<'a> {
+    pub error: &'a RequestError,
+}
+
+impl<'a> InternalEvent for OtlpRequestError<'a> {
+    fn emit_logs(&self) {
+        error!(
+            message = "could not handle export request.",
+            error = %self.error,
+            rate_limit_secs = 10,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "request_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "otlp",
+        );
+    }
+}
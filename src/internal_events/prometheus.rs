@@ -1,6 +1,8 @@
 use super::InternalEvent;
 use crate::sources::prometheus::parser::ParserError;
+use http::Uri;
 use metrics::counter;
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub struct PrometheusRequestCompleted;
@@ -35,12 +37,13 @@ impl InternalEvent for PrometheusParseError {
 
 #[derive(Debug)]
 pub struct PrometheusHttpError {
-    pub error: hyper::Error,
+    pub error: crate::Error,
+    pub url: Uri,
 }
 
 impl InternalEvent for PrometheusHttpError {
     fn emit_logs(&self) {
-        error!(message = "http request processing error", error = %self.error);
+        error!(message = "http request processing error", url = %self.url, error = %self.error);
     }
 
     fn emit_metrics(&self) {
@@ -50,3 +53,22 @@ impl InternalEvent for PrometheusHttpError {
         );
     }
 }
+
+#[derive(Debug)]
+pub struct PrometheusFileSdError {
+    pub path: PathBuf,
+    pub error: crate::Error,
+}
+
+impl InternalEvent for PrometheusFileSdError {
+    fn emit_logs(&self) {
+        error!(message = "failed to read targets", path = ?self.path, error = %self.error);
+    }
+
+    fn emit_metrics(&self) {
+        counter!("file_sd_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "prometheus",
+        );
+    }
+}
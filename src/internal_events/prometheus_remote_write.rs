@@ -0,0 +1,51 @@
+use super::InternalEvent;
+use crate::prometheus::DecodeError;
+use metrics::counter;
+
+#[derive(Debug)]
+pub struct PrometheusRemoteWriteReceived {
+    pub count: usize,
+    pub byte_size: usize,
+}
+
+impl InternalEvent for PrometheusRemoteWriteReceived {
+    fn emit_logs(&self) {
+        trace!(message = "received remote write request.", count = %self.count);
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "events_processed", self.count as u64,
+            "component_kind" => "source",
+            "component_type" => "prometheus_remote_write",
+        );
+        counter!(
+            "bytes_processed", self.byte_size as u64,
+            "component_kind" => "source",
+            "component_type" => "prometheus_remote_write",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct PrometheusRemoteWriteParseError {
+    pub error: DecodeError,
+}
+
+impl InternalEvent for PrometheusRemoteWriteParseError {
+    fn emit_logs(&self) {
+        error!(
+            message = "could not decode remote write request.",
+            error = %self.error,
+            rate_limit_secs = 10,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "parse_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "prometheus_remote_write",
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use super::InternalEvent;
+use crate::sources::statsd::parser::ParseError;
+use metrics::counter;
+
+#[derive(Debug)]
+pub struct StatsdEventReceived {
+    pub byte_size: usize,
+}
+
+impl InternalEvent for StatsdEventReceived {
+    fn emit_logs(&self) {
+        trace!(message = "received line.", byte_size = %self.byte_size);
+    }
+
+    fn emit_metrics(&self) {
+        counter!("events_processed", 1,
+            "component_kind" => "source",
+            "component_type" => "statsd",
+        );
+        counter!("bytes_processed", self.byte_size as u64,
+            "component_kind" => "source",
+            "component_type" => "statsd",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct StatsdInvalidRecord<'a> {
+    pub error: &'a ParseError,
+    pub text: &'a str,
+}
+
+impl InternalEvent for StatsdInvalidRecord<'_> {
+    fn emit_logs(&self) {
+        error!(message = "invalid packet from statsd, discarding.", error = %self.error, text = %self.text, rate_limit_secs = 10);
+    }
+
+    fn emit_metrics(&self) {
+        counter!("parse_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "statsd",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct StatsdSocketError {
+    pub error: std::io::Error,
+    pub mode: &'static str,
+}
+
+impl InternalEvent for StatsdSocketError {
+    fn emit_logs(&self) {
+        error!(message = "error reading from socket.", error = %self.error, mode = %self.mode);
+    }
+
+    fn emit_metrics(&self) {
+        counter!("socket_errors", 1,
+            "component_kind" => "source",
+            "component_type" => "statsd",
+            "mode" => self.mode,
+        );
+    }
+}
@@ -0,0 +1,22 @@
+use super::InternalEvent;
+
+/// A component's task failed. The `processing_errors` counter itself is
+/// recorded by the topology through a handle registered when the component
+/// is built, as it is also incremented for every failed event.
+#[derive(Debug)]
+pub struct ComponentProcessingError<'a> {
+    pub component_kind: &'static str,
+    pub component_type: &'static str,
+    pub component_name: &'a str,
+}
+
+impl InternalEvent for ComponentProcessingError<'_> {
+    fn emit_logs(&self) {
+        error!(
+            message = "component failed.",
+            component_kind = self.component_kind,
+            component_type = self.component_type,
+            component_name = self.component_name,
+        );
+    }
+}
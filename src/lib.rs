@@ -21,9 +21,11 @@ extern crate derivative;
 #[global_allocator]
 static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
 
+pub mod api;
 pub mod buffers;
 pub mod conditions;
 pub mod config_paths;
+pub mod dead_letter;
 pub mod dns;
 pub mod event;
 pub mod expiring_hash_map;
@@ -38,6 +40,13 @@ pub mod hyper;
 pub mod kafka;
 pub mod list;
 pub mod metrics;
+#[cfg(any(feature = "sinks-otlp", feature = "sources-otlp"))]
+pub mod otlp;
+#[cfg(any(
+    feature = "sinks-prometheus_remote_write",
+    feature = "sources-prometheus_remote_write"
+))]
+pub mod prometheus;
 pub mod region;
 pub mod runtime;
 pub mod serde;
@@ -48,6 +57,7 @@ pub mod stream;
 pub mod template;
 pub mod test_util;
 pub mod tls;
+pub mod top;
 pub mod topology;
 pub mod trace;
 pub mod transforms;
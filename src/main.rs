@@ -6,16 +6,16 @@ mod cli;
 use cli::{Color, LogFormat, Opts, SubCommand};
 use futures::compat::Future01CompatExt;
 use futures01::{future, Future, Stream};
-use std::{
-    cmp::max,
-    fs::File,
-    path::{Path, PathBuf},
-};
+use std::{cmp::max, path::PathBuf};
 #[cfg(unix)]
 use tokio_signal::unix::{Signal, SIGHUP, SIGINT, SIGQUIT, SIGTERM};
-use topology::Config;
+use topology::{
+    config::{loading, FormatHint},
+    Config,
+};
 use vector::{
-    config_paths, event, generate, list, metrics, runtime, topology, trace, unit_test, validate,
+    api, config_paths, event, generate, list, metrics, runtime, top, topology, trace, unit_test,
+    validate,
 };
 
 fn main() {
@@ -81,17 +81,31 @@ fn main() {
                 SubCommand::List(l) => list::cmd(&l),
                 SubCommand::Test(t) => unit_test::cmd(&t),
                 SubCommand::Generate(g) => generate::cmd(&g),
+                SubCommand::Top(t) => top::cmd(&t).await,
             })
         };
 
-        let config_paths = config_paths::prepare(opts.config_paths.clone()).unwrap_or_else(|| {
-            std::process::exit(exitcode::CONFIG);
-        });
+        // Config directories replace the default config path rather than
+        // being loaded alongside it.
+        let config_paths = opts.config_paths_with_formats();
+        let config_paths = if config_paths.is_empty() && !opts.config_dirs.is_empty() {
+            Vec::new()
+        } else {
+            config_paths::prepare(config_paths).unwrap_or_else(|| {
+                std::process::exit(exitcode::CONFIG);
+            })
+        };
+        let config_dirs = opts.config_dirs.clone();
 
         if opts.watch_config {
             // Start listening for config changes immediately.
             vector::topology::config::watcher::config_watcher(
-                config_paths.clone(),
+                config_paths
+                    .iter()
+                    .map(|(path, _)| path)
+                    .chain(&config_dirs)
+                    .cloned()
+                    .collect(),
                 vector::topology::config::watcher::CONFIG_WATCH_DELAY,
             )
             .unwrap_or_else(|error| {
@@ -102,10 +116,11 @@ fn main() {
 
         info!(
             message = "Loading configs.",
-            path = ?config_paths
+            path = ?config_paths,
+            dirs = ?config_dirs
         );
 
-        let read_config = read_configs(&config_paths);
+        let read_config = read_configs(&config_paths, &config_dirs);
         let maybe_config = handle_config_errors(read_config);
         let config = maybe_config.unwrap_or_else(|| {
             std::process::exit(exitcode::CONFIG);
@@ -122,6 +137,8 @@ fn main() {
             arch = built_info::CFG_TARGET_ARCH
         );
 
+        let api_options = config.global.api.clone();
+
         let diff = topology::ConfigDiff::initial(&config);
         let pieces = topology::validate(&config, &diff).unwrap_or_else(|| {
             std::process::exit(exitcode::CONFIG);
@@ -132,6 +149,26 @@ fn main() {
             std::process::exit(exitcode::CONFIG);
         });
 
+        let api_state = api::TopologyState::default();
+        api_state.update(&topology);
+
+        // Reloads requested through the API are handled the same way as `SIGHUP`.
+        let (api_reload_tx, api_reload_rx) = futures01::sync::mpsc::unbounded();
+        let _api_server = if api_options.enabled {
+            match api::Server::start(&api_options, api_state.clone(), api_reload_tx) {
+                Ok(server) => {
+                    info!(message = "API server running.", address = %api_options.address);
+                    Some(server)
+                }
+                Err(error) => {
+                    error!(message = "Unable to start API server.", %error);
+                    std::process::exit(exitcode::CONFIG);
+                }
+            }
+        } else {
+            None
+        };
+
         #[cfg(unix)]
         {
             let mut topology = topology;
@@ -139,8 +176,12 @@ fn main() {
             let sigterm = Signal::new(SIGTERM).flatten_stream();
             let sigquit = Signal::new(SIGQUIT).flatten_stream();
             let sighup = Signal::new(SIGHUP).flatten_stream();
+            let api_reload = api_reload_rx
+                .map(|()| SIGHUP)
+                .map_err(|()| std::io::Error::from(std::io::ErrorKind::Other));
 
-            let mut signals = sigint.select(sigterm.select(sigquit.select(sighup)));
+            let mut signals =
+                sigint.select(sigterm.select(sigquit.select(sighup.select(api_reload))));
 
             let signal = loop {
                 let signal = future::poll_fn(|| signals.poll());
@@ -168,13 +209,18 @@ fn main() {
                 // Reload config
                 info!(
                     message = "Reloading configs.",
-                    path = ?config_paths
+                    path = ?config_paths,
+                    dirs = ?config_dirs
                 );
-                let new_config = read_configs(&config_paths);
+                let new_config = read_configs(&config_paths, &config_dirs);
 
                 trace!("Parsing config");
                 let new_config = handle_config_errors(new_config);
                 if let Some(new_config) = new_config {
+                    if new_config.global.api != api_options {
+                        warn!("Changes to the `api` options require a restart; keeping the current API settings.");
+                    }
+
                     match topology
                         .reload_config_and_respawn(new_config, opts.require_healthy)
                         .await
@@ -184,6 +230,7 @@ fn main() {
                         // Trigger graceful shutdown for what remains of the topology
                         Err(()) => break SIGINT,
                     }
+                    api_state.update(&topology);
                 } else {
                     error!("Reload aborted.");
                 }
@@ -212,6 +259,9 @@ fn main() {
         }
         #[cfg(windows)]
         {
+            // Reloads are only supported through signals on unix.
+            drop(api_reload_rx);
+
             let ctrl_c = tokio_signal::ctrl_c().flatten_stream().into_future();
             let to_shutdown = future::poll_fn(move || graceful_crash.poll())
                 .map(|_| ())
@@ -260,52 +310,13 @@ fn handle_config_errors(config: Result<Config, Vec<String>>) -> Option<Config> {
     }
 }
 
-fn read_configs(config_paths: &[PathBuf]) -> Result<Config, Vec<String>> {
-    let mut config = vector::topology::Config::empty();
-    let mut errors = Vec::new();
-
-    config_paths.iter().for_each(|p| {
-        let file = if let Some(file) = open_config(&p) {
-            file
-        } else {
-            errors.push(format!("Config file not found in path: {:?}.", p));
-            return;
-        };
-
-        trace!(
-            message = "Parsing config.",
-            path = ?p
-        );
-
-        if let Err(errs) = Config::load(file).and_then(|n| config.append(n)) {
-            errors.extend(errs.iter().map(|e| format!("{:?}: {}", p, e)));
-        }
-    });
-
-    if let Err(mut errs) = config.expand_macros() {
-        errors.append(&mut errs);
-    }
-
-    if !errors.is_empty() {
-        Err(errors)
-    } else {
-        Ok(config)
-    }
-}
-
-fn open_config(path: &Path) -> Option<File> {
-    match File::open(path) {
-        Ok(f) => Some(f),
-        Err(error) => {
-            if let std::io::ErrorKind::NotFound = error.kind() {
-                error!(message = "Config file not found in path.", ?path);
-                None
-            } else {
-                error!(message = "Error opening config file.", %error);
-                None
-            }
-        }
-    }
+fn read_configs(
+    config_paths: &[(PathBuf, FormatHint)],
+    config_dirs: &[PathBuf],
+) -> Result<Config, Vec<String>> {
+    let mut config = loading::load_from_paths(config_paths, config_dirs)?;
+    config.expand_macros()?;
+    Ok(config)
 }
 
 #[allow(unused)]
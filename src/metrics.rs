@@ -1,8 +1,13 @@
-use metrics_runtime::{Controller, Receiver};
+use metrics_runtime::{Controller, Receiver, Sink};
 use once_cell::sync::OnceCell;
 
 pub static CONTROLLER: OnceCell<Controller> = OnceCell::new();
 
+/// Used to register metric handles up front for metrics that are recorded
+/// too often to look up their key every time, like the per-event topology
+/// counters.
+pub static SINK: OnceCell<Sink> = OnceCell::new();
+
 pub fn init() -> crate::Result<()> {
     let receiver = Receiver::builder()
         .build()
@@ -11,6 +16,8 @@ pub fn init() -> crate::Result<()> {
     CONTROLLER
         .set(receiver.controller())
         .map_err(|_| "failed to set receiver. metrics system already initialized.")?;
+    SINK.set(receiver.sink())
+        .map_err(|_| "failed to set sink. metrics system already initialized.")?;
 
     receiver.install();
 
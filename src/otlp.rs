@@ -0,0 +1,61 @@
+//! The OpenTelemetry protocol (OTLP), shared by the `otlp` source and sink.
+
+use chrono::{DateTime, TimeZone, Utc};
+use prost::Message;
+
+pub mod proto {
+    include!(concat!(env!("OUT_DIR"), "/opentelemetry.rs"));
+}
+
+/// The OTLP/HTTP path of each signal.
+pub const LOGS_PATH: &str = "/v1/logs";
+pub const METRICS_PATH: &str = "/v1/metrics";
+
+/// The content type of OTLP/HTTP requests and responses.
+pub const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+
+/// The log event fields holding the parts of a log record other than its body
+/// and timestamp.
+pub const ATTRIBUTES_KEY: &str = "attributes";
+pub const RESOURCES_KEY: &str = "resources";
+pub const SEVERITY_TEXT_KEY: &str = "severity_text";
+pub const SEVERITY_NUMBER_KEY: &str = "severity_number";
+pub const TRACE_ID_KEY: &str = "trace_id";
+pub const SPAN_ID_KEY: &str = "span_id";
+
+pub fn encode_message(message: &impl Message) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(message.encoded_len());
+    message
+        .encode(&mut buf)
+        .expect("Vec<u8> has unlimited capacity");
+    buf
+}
+
+/// Converts an OTLP timestamp, where zero means it is not set.
+pub fn timestamp_from_nanos(nanos: u64) -> Option<DateTime<Utc>> {
+    if nanos == 0 {
+        None
+    } else {
+        Some(Utc.timestamp_nanos(nanos as i64))
+    }
+}
+
+pub fn timestamp_to_nanos(timestamp: Option<DateTime<Utc>>) -> u64 {
+    timestamp.map_or(0, |timestamp| timestamp.timestamp_nanos().max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_timestamps() {
+        let timestamp = Utc.ymd(2020, 7, 1).and_hms_nano(12, 30, 15, 123_456_789);
+        let nanos = timestamp_to_nanos(Some(timestamp));
+
+        assert_eq!(nanos, 1_593_606_615_123_456_789);
+        assert_eq!(timestamp_from_nanos(nanos), Some(timestamp));
+        assert_eq!(timestamp_from_nanos(0), None);
+        assert_eq!(timestamp_to_nanos(None), 0);
+    }
+}
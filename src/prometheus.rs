@@ -0,0 +1,68 @@
+//! The Prometheus remote storage protocol, shared by the remote-write source
+//! and sink.
+
+use prost::Message;
+use snafu::{ResultExt, Snafu};
+
+pub mod proto {
+    include!(concat!(env!("OUT_DIR"), "/prometheus.proto.rs"));
+}
+
+/// The label holding the name of a series.
+pub const METRIC_NAME_LABEL: &str = "__name__";
+
+#[derive(Debug, Snafu)]
+pub enum DecodeError {
+    #[snafu(display("Could not decompress request body: {}", source))]
+    Decompress { source: snap::Error },
+    #[snafu(display("Could not decode write request: {}", source))]
+    Decode { source: prost::DecodeError },
+    #[snafu(display("Sample timestamp out of range: {}", timestamp))]
+    InvalidTimestamp { timestamp: i64 },
+}
+
+/// Encodes a write request the way remote-write receivers expect it, as a
+/// snappy compressed protobuf message.
+pub fn encode_write_request(request: &proto::WriteRequest) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(request.encoded_len());
+    request
+        .encode(&mut buf)
+        .expect("Vec<u8> has unlimited capacity");
+    snap::raw::Encoder::new()
+        .compress_vec(&buf)
+        .expect("Out of memory")
+}
+
+/// Decodes a snappy compressed protobuf write request.
+pub fn decode_write_request(body: &[u8]) -> Result<proto::WriteRequest, DecodeError> {
+    let buf = snap::raw::Decoder::new()
+        .decompress_vec(body)
+        .context(Decompress)?;
+    proto::WriteRequest::decode(&buf[..]).context(Decode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_and_decodes_write_requests() {
+        let request = proto::WriteRequest {
+            timeseries: vec![proto::TimeSeries {
+                labels: vec![proto::Label {
+                    name: METRIC_NAME_LABEL.into(),
+                    value: "hits".into(),
+                }],
+                samples: vec![proto::Sample {
+                    value: 42.0,
+                    timestamp: 1_500_000_000_000,
+                }],
+            }],
+            metadata: vec![],
+        };
+
+        let body = encode_write_request(&request);
+        assert_eq!(decode_write_request(&body).unwrap(), request);
+        assert!(decode_write_request(b"not snappy").is_err());
+    }
+}
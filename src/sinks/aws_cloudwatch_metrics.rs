@@ -3,8 +3,14 @@ use crate::{
     event::metric::{Metric, MetricKind, MetricValue},
     region::RegionOrEndpoint,
     sinks::util::{
-        retries2::RetryLogic, rusoto, service2::TowerRequestConfig, BatchConfig, BatchSettings,
-        Compression, MetricBuffer,
+        distribution::{
+            default_histogram_buckets, default_summary_quantiles, histogram_samples, quantile_name,
+        },
+        retries2::RetryLogic,
+        rusoto,
+        service2::TowerRequestConfig,
+        BatchConfig, BatchSettings, Compression, DistributionConversion, DistributionsAs,
+        MetricBuffer,
     },
     topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
 };
@@ -26,6 +32,7 @@ use tower03::Service;
 pub struct CloudWatchMetricsSvc {
     client: CloudWatchClient,
     config: CloudWatchMetricsSinkConfig,
+    distributions: DistributionConversion,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
@@ -41,6 +48,11 @@ pub struct CloudWatchMetricsSinkConfig {
     #[serde(default)]
     pub request: TowerRequestConfig,
     pub assume_role: Option<String>,
+    pub distributions_as: Option<DistributionsAs>,
+    #[serde(default = "default_histogram_buckets")]
+    pub buckets: Vec<f64>,
+    #[serde(default = "default_summary_quantiles")]
+    pub quantiles: Vec<f64>,
 }
 
 lazy_static! {
@@ -110,6 +122,14 @@ impl CloudWatchMetricsSinkConfig {
         let client = rusoto_core::Client::new_with_encoding(creds, client, self.compression.into());
         Ok(CloudWatchClient::new_with_client(client, region))
     }
+
+    fn distributions(&self) -> DistributionConversion {
+        DistributionConversion {
+            distributions_as: self.distributions_as.unwrap_or_default(),
+            buckets: self.buckets.clone(),
+            quantiles: self.quantiles.clone(),
+        }
+    }
 }
 
 impl CloudWatchMetricsSvc {
@@ -126,7 +146,12 @@ impl CloudWatchMetricsSvc {
             .get_settings_or_default(BatchSettings::default().events(20).timeout(1));
         let request = config.request.unwrap_with(&REQUEST_DEFAULTS);
 
-        let cloudwatch_metrics = CloudWatchMetricsSvc { client, config };
+        let distributions = config.distributions();
+        let cloudwatch_metrics = CloudWatchMetricsSvc {
+            client,
+            config,
+            distributions,
+        };
 
         let sink = request
             .batch_sink(
@@ -141,51 +166,92 @@ impl CloudWatchMetricsSvc {
         Ok(Box::new(sink))
     }
 
+    /// Encodes metrics into datums. An incremental `AggregatedHistogram` is
+    /// sent as the number of samples at each bucket's upper bound, and an
+    /// incremental `AggregatedSummary` as a datum per quantile, along with
+    /// `.count` and `.sum` datums. Sketches are always converted, as they
+    /// can't be sent as they are.
     fn encode_events(&mut self, events: Vec<Metric>) -> PutMetricDataInput {
+        let distributions = &self.distributions;
+        let convert_distributions = self.config.distributions_as.is_some();
         let metric_data: Vec<_> = events
             .into_iter()
-            .filter_map(|event| {
+            .map(|event| {
+                if convert_distributions {
+                    distributions.convert(event)
+                } else {
+                    distributions.convert_sketch(event)
+                }
+            })
+            .flat_map(|event| {
                 let metric_name = event.name.to_string();
                 let timestamp = event.timestamp.map(timestamp_to_string);
                 let dimensions = event.tags.clone().map(tags_to_dimensions);
+                let datum = |metric_name, value| MetricDatum {
+                    metric_name,
+                    value: Some(value),
+                    timestamp: timestamp.clone(),
+                    dimensions: dimensions.clone(),
+                    ..Default::default()
+                };
                 match event.kind {
                     MetricKind::Incremental => match event.value {
-                        MetricValue::Counter { value } => Some(MetricDatum {
-                            metric_name,
-                            value: Some(value),
-                            timestamp,
-                            dimensions,
-                            ..Default::default()
-                        }),
+                        MetricValue::Counter { value } => vec![datum(metric_name, value)],
                         MetricValue::Distribution {
                             values,
                             sample_rates,
-                        } => Some(MetricDatum {
+                        } => vec![MetricDatum {
                             metric_name,
                             values: Some(values.to_vec()),
                             counts: Some(sample_rates.iter().cloned().map(f64::from).collect()),
                             timestamp,
                             dimensions,
                             ..Default::default()
-                        }),
-                        MetricValue::Set { values } => Some(MetricDatum {
-                            metric_name,
-                            value: Some(values.len() as f64),
-                            timestamp,
-                            dimensions,
-                            ..Default::default()
-                        }),
-                        _ => None,
+                        }],
+                        MetricValue::Set { values } => {
+                            vec![datum(metric_name, values.len() as f64)]
+                        }
+                        MetricValue::AggregatedHistogram {
+                            buckets,
+                            counts,
+                            count,
+                            ..
+                        } => {
+                            let (values, counts) = histogram_samples(&buckets, &counts, count)
+                                .into_iter()
+                                .map(|(bucket, count)| (bucket, f64::from(count)))
+                                .unzip();
+                            vec![MetricDatum {
+                                metric_name,
+                                values: Some(values),
+                                counts: Some(counts),
+                                timestamp,
+                                dimensions,
+                                ..Default::default()
+                            }]
+                        }
+                        MetricValue::AggregatedSummary {
+                            quantiles,
+                            values,
+                            count,
+                            sum,
+                        } => {
+                            let mut data = quantiles
+                                .iter()
+                                .zip(values.iter())
+                                .map(|(quantile, value)| {
+                                    datum(quantile_name(&metric_name, *quantile), *value)
+                                })
+                                .collect::<Vec<_>>();
+                            data.push(datum(format!("{}.count", metric_name), f64::from(count)));
+                            data.push(datum(format!("{}.sum", metric_name), sum));
+                            data
+                        }
+                        _ => Vec::new(),
                     },
                     MetricKind::Absolute => match event.value {
-                        MetricValue::Gauge { value } => Some(MetricDatum {
-                            metric_name,
-                            value: Some(value),
-                            timestamp,
-                            dimensions,
-                            ..Default::default()
-                        }),
-                        _ => None,
+                        MetricValue::Gauge { value } => vec![datum(metric_name, value)],
+                        _ => Vec::new(),
                     },
                 }
             })
@@ -266,6 +332,7 @@ mod tests {
     use super::*;
     use crate::dns::Resolver;
     use crate::event::metric::{Metric, MetricKind, MetricValue};
+    use crate::event::sketch::DDSketch;
     use chrono::offset::TimeZone;
     use pretty_assertions::assert_eq;
     use rusoto_cloudwatch::PutMetricDataInput;
@@ -282,7 +349,12 @@ mod tests {
         let resolver = Resolver;
         let config = config();
         let client = config.create_client(resolver).unwrap();
-        CloudWatchMetricsSvc { client, config }
+        let distributions = config.distributions();
+        CloudWatchMetricsSvc {
+            client,
+            config,
+            distributions,
+        }
     }
 
     #[test]
@@ -396,6 +468,100 @@ mod tests {
         );
     }
 
+    #[test]
+    fn encode_events_distribution_as_histogram() {
+        let events = vec![Metric {
+            name: "latency".into(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Distribution {
+                values: vec![1.5, 3.0, 9.0],
+                sample_rates: vec![2, 1, 1],
+            },
+        }];
+
+        let mut svc = svc();
+        svc.config.distributions_as = Some(DistributionsAs::Histogram);
+        svc.distributions.buckets = vec![1.0, 2.0, 4.0];
+
+        assert_eq!(
+            svc.encode_events(events),
+            PutMetricDataInput {
+                namespace: "vector".into(),
+                metric_data: vec![MetricDatum {
+                    metric_name: "latency".into(),
+                    values: Some(vec![2.0, 4.0, 4.0]),
+                    counts: Some(vec![2.0, 1.0, 1.0]),
+                    ..Default::default()
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn encode_events_aggregated_summary() {
+        let events = vec![Metric {
+            name: "latency".into(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::AggregatedSummary {
+                quantiles: vec![0.5, 0.99],
+                values: vec![1.5, 3.0],
+                count: 7,
+                sum: 20.0,
+            },
+        }];
+
+        let datum = |metric_name: &str, value| MetricDatum {
+            metric_name: metric_name.into(),
+            value: Some(value),
+            ..Default::default()
+        };
+        assert_eq!(
+            svc().encode_events(events),
+            PutMetricDataInput {
+                namespace: "vector".into(),
+                metric_data: vec![
+                    datum("latency.50percentile", 1.5),
+                    datum("latency.99percentile", 3.0),
+                    datum("latency.count", 7.0),
+                    datum("latency.sum", 20.0),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn encode_events_sketch() {
+        let mut sketch = DDSketch::new();
+        sketch.insert_n(1.5, 3);
+        let events = vec![Metric {
+            name: "latency".into(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Sketch { sketch },
+        }];
+
+        let mut svc = svc();
+        svc.distributions.buckets = vec![1.0, 2.0];
+
+        assert_eq!(
+            svc.encode_events(events),
+            PutMetricDataInput {
+                namespace: "vector".into(),
+                metric_data: vec![MetricDatum {
+                    metric_name: "latency".into(),
+                    values: Some(vec![2.0]),
+                    counts: Some(vec![3.0]),
+                    ..Default::default()
+                }],
+            }
+        );
+    }
+
     #[test]
     fn encode_events_set() {
         let events = vec![Metric {
@@ -91,6 +91,10 @@ impl SinkConfig for ClickhouseConfig {
     fn sink_type(&self) -> &'static str {
         "clickhouse"
     }
+
+    fn supports_dead_letter(&self) -> bool {
+        true
+    }
 }
 
 #[async_trait::async_trait]
@@ -2,6 +2,7 @@ use crate::{
     dns::Resolver,
     event::{
         metric::{Metric, MetricKind, MetricValue},
+        sketch::DDSketch,
         Event,
     },
     sinks::util::{
@@ -145,6 +146,10 @@ impl SinkConfig for DatadogConfig {
     fn sink_type(&self) -> &'static str {
         "datadog_metrics"
     }
+
+    fn supports_dead_letter(&self) -> bool {
+        true
+    }
 }
 
 #[async_trait::async_trait]
@@ -276,6 +281,77 @@ fn stats(values: &[f64], counts: &[u32]) -> Option<DatadogStats> {
     })
 }
 
+/// Estimates the same statistics as `stats` from a sketch.
+fn sketch_stats(sketch: &DDSketch) -> Option<DatadogStats> {
+    let count = f64::from(sketch.count());
+    Some(DatadogStats {
+        min: sketch.min()?,
+        max: sketch.max()?,
+        median: sketch.quantile(0.5)?,
+        avg: sketch.sum() / count,
+        sum: sketch.sum(),
+        count,
+        quantiles: vec![(0.95, sketch.quantile(0.95)?)],
+    })
+}
+
+// https://docs.datadoghq.com/developers/metrics/metrics_type/?tab=histogram#metric-type-definition
+fn encode_stats(
+    fullname: &str,
+    tags: Option<Vec<String>>,
+    ts: i64,
+    interval: i64,
+    s: DatadogStats,
+) -> Vec<DatadogMetric> {
+    let mut result = vec![
+        DatadogMetric {
+            metric: format!("{}.min", fullname),
+            r#type: DatadogMetricType::Gauge,
+            interval: Some(interval),
+            points: vec![DatadogPoint(ts, s.min)],
+            tags: tags.clone(),
+        },
+        DatadogMetric {
+            metric: format!("{}.avg", fullname),
+            r#type: DatadogMetricType::Gauge,
+            interval: Some(interval),
+            points: vec![DatadogPoint(ts, s.avg)],
+            tags: tags.clone(),
+        },
+        DatadogMetric {
+            metric: format!("{}.count", fullname),
+            r#type: DatadogMetricType::Rate,
+            interval: Some(interval),
+            points: vec![DatadogPoint(ts, s.count)],
+            tags: tags.clone(),
+        },
+        DatadogMetric {
+            metric: format!("{}.median", fullname),
+            r#type: DatadogMetricType::Gauge,
+            interval: Some(interval),
+            points: vec![DatadogPoint(ts, s.median)],
+            tags: tags.clone(),
+        },
+        DatadogMetric {
+            metric: format!("{}.max", fullname),
+            r#type: DatadogMetricType::Gauge,
+            interval: Some(interval),
+            points: vec![DatadogPoint(ts, s.max)],
+            tags: tags.clone(),
+        },
+    ];
+    for (q, v) in s.quantiles {
+        result.push(DatadogMetric {
+            metric: format!("{}.{}percentile", fullname, (q * 100.0) as u32),
+            r#type: DatadogMetricType::Gauge,
+            interval: Some(interval),
+            points: vec![DatadogPoint(ts, v)],
+            tags: tags.clone(),
+        })
+    }
+    result
+}
+
 fn encode_events(events: Vec<Metric>, interval: i64, namespace: &str) -> DatadogRequest {
     let series = events
         .into_iter()
@@ -295,64 +371,10 @@ fn encode_events(events: Vec<Metric>, interval: i64, namespace: &str) -> Datadog
                     MetricValue::Distribution {
                         values,
                         sample_rates,
-                    } => {
-                        // https://docs.datadoghq.com/developers/metrics/metrics_type/?tab=histogram#metric-type-definition
-                        if let Some(s) = stats(&values, &sample_rates) {
-                            let mut result = vec![
-                                DatadogMetric {
-                                    metric: format!("{}.min", &fullname),
-                                    r#type: DatadogMetricType::Gauge,
-                                    interval: Some(interval),
-                                    points: vec![DatadogPoint(ts, s.min)],
-                                    tags: tags.clone(),
-                                },
-                                DatadogMetric {
-                                    metric: format!("{}.avg", &fullname),
-                                    r#type: DatadogMetricType::Gauge,
-                                    interval: Some(interval),
-                                    points: vec![DatadogPoint(ts, s.avg)],
-                                    tags: tags.clone(),
-                                },
-                                DatadogMetric {
-                                    metric: format!("{}.count", &fullname),
-                                    r#type: DatadogMetricType::Rate,
-                                    interval: Some(interval),
-                                    points: vec![DatadogPoint(ts, s.count)],
-                                    tags: tags.clone(),
-                                },
-                                DatadogMetric {
-                                    metric: format!("{}.median", &fullname),
-                                    r#type: DatadogMetricType::Gauge,
-                                    interval: Some(interval),
-                                    points: vec![DatadogPoint(ts, s.median)],
-                                    tags: tags.clone(),
-                                },
-                                DatadogMetric {
-                                    metric: format!("{}.max", &fullname),
-                                    r#type: DatadogMetricType::Gauge,
-                                    interval: Some(interval),
-                                    points: vec![DatadogPoint(ts, s.max)],
-                                    tags: tags.clone(),
-                                },
-                            ];
-                            for (q, v) in s.quantiles {
-                                result.push(DatadogMetric {
-                                    metric: format!(
-                                        "{}.{}percentile",
-                                        &fullname,
-                                        (q * 100.0) as u32
-                                    ),
-                                    r#type: DatadogMetricType::Gauge,
-                                    interval: Some(interval),
-                                    points: vec![DatadogPoint(ts, v)],
-                                    tags: tags.clone(),
-                                })
-                            }
-                            Some(result)
-                        } else {
-                            None
-                        }
-                    }
+                    } => stats(&values, &sample_rates)
+                        .map(|s| encode_stats(&fullname, tags, ts, interval, s)),
+                    MetricValue::Sketch { sketch } => sketch_stats(&sketch)
+                        .map(|s| encode_stats(&fullname, tags, ts, interval, s)),
                     MetricValue::Set { values } => Some(vec![DatadogMetric {
                         metric: fullname,
                         r#type: DatadogMetricType::Gauge,
@@ -662,4 +684,37 @@ mod tests {
             r#"{"series":[{"metric":"requests.min","type":"gauge","interval":60,"points":[[1542182950,1.0]],"tags":null},{"metric":"requests.avg","type":"gauge","interval":60,"points":[[1542182950,1.875]],"tags":null},{"metric":"requests.count","type":"rate","interval":60,"points":[[1542182950,8.0]],"tags":null},{"metric":"requests.median","type":"gauge","interval":60,"points":[[1542182950,2.0]],"tags":null},{"metric":"requests.max","type":"gauge","interval":60,"points":[[1542182950,3.0]],"tags":null},{"metric":"requests.95percentile","type":"gauge","interval":60,"points":[[1542182950,3.0]],"tags":null}]}"#
         );
     }
+
+    #[test]
+    fn encode_sketch() {
+        let mut sketch = DDSketch::new();
+        sketch.insert_n(1.0, 3);
+        sketch.insert_n(3.0, 1);
+        let events = vec![Metric {
+            name: "requests".into(),
+            timestamp: Some(ts()),
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Sketch { sketch },
+        }];
+        let series = encode_events(events, 60, "").series;
+
+        let names = series
+            .iter()
+            .map(|metric| metric.metric.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "requests.min",
+                "requests.avg",
+                "requests.count",
+                "requests.median",
+                "requests.max",
+                "requests.95percentile"
+            ]
+        );
+        assert_eq!(series[1].points, vec![DatadogPoint(1542182950, 1.5)]);
+        assert_eq!(series[2].points, vec![DatadogPoint(1542182950, 4.0)]);
+    }
 }
@@ -140,6 +140,10 @@ impl SinkConfig for ElasticSearchConfig {
     fn sink_type(&self) -> &'static str {
         "elasticsearch"
     }
+
+    fn supports_dead_letter(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug)]
@@ -353,6 +357,29 @@ impl RetryLogic for ElasticSearchRetryLogic {
             _ => RetryAction::DontRetry(format!("response status: {}", status)),
         }
     }
+
+    // Bulk requests can partially succeed, in which case only the documents
+    // that errored are returned, to not index the others twice.
+    fn failed_items(&self, response: &Self::Response) -> Option<Vec<usize>> {
+        if !response.status().is_success() {
+            return None;
+        }
+
+        let esrr = serde_json::from_slice::<ESResultResponse>(response.body()).ok()?;
+        let failed = esrr
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.index.error.is_some())
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        if failed.is_empty() {
+            None
+        } else {
+            Some(failed)
+        }
+    }
 }
 
 impl ElasticSearchCommon {
@@ -564,6 +591,17 @@ mod tests {
             RetryAction::DontRetry(_)
         ));
     }
+
+    #[test]
+    fn finds_failed_items() {
+        let json = r#"{"took":3,"errors":true,"items":[{"index":{"_index":"test","_id":"1","status":201}},{"index":{"_index":"test","_id":"2","status":400,"error":{"type":"mapper_parsing_exception","reason":"failed to parse"}}},{"index":{"_index":"test","_id":"3","status":201}}]}"#;
+        let response = Response::builder()
+            .status(StatusCode::OK)
+            .body(Bytes::from(json))
+            .unwrap();
+        let logic = ElasticSearchRetryLogic;
+        assert_eq!(logic.failed_items(&response), Some(vec![1]));
+    }
 }
 
 #[cfg(test)]
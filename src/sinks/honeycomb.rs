@@ -70,6 +70,10 @@ impl SinkConfig for HoneycombConfig {
     fn sink_type(&self) -> &'static str {
         "honeycomb"
     }
+
+    fn supports_dead_letter(&self) -> bool {
+        true
+    }
 }
 
 #[async_trait::async_trait]
@@ -63,6 +63,10 @@ impl SinkConfig for HumioLogsConfig {
     fn sink_type(&self) -> &'static str {
         "humio_logs"
     }
+
+    fn supports_dead_letter(&self) -> bool {
+        true
+    }
 }
 
 impl HumioLogsConfig {
@@ -128,6 +128,10 @@ impl SinkConfig for InfluxDBLogsConfig {
     fn sink_type(&self) -> &'static str {
         "influxdb_logs"
     }
+
+    fn supports_dead_letter(&self) -> bool {
+        true
+    }
 }
 
 #[async_trait::async_trait]
@@ -1,13 +1,17 @@
 use crate::{
-    event::metric::{Metric, MetricValue},
+    event::{
+        metric::{Metric, MetricValue},
+        sketch::DDSketch,
+    },
     sinks::influxdb::{
         encode_namespace, encode_timestamp, healthcheck, influx_line_protocol, influxdb_settings,
         Field, InfluxDB1Settings, InfluxDB2Settings, ProtocolVersion,
     },
     sinks::util::{
+        distribution::{default_histogram_buckets, default_summary_quantiles},
         http::{HttpBatchService, HttpRetryLogic},
         service2::TowerRequestConfig,
-        BatchConfig, BatchSettings, MetricBuffer,
+        BatchConfig, BatchSettings, DistributionConversion, DistributionsAs, MetricBuffer,
     },
     topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
 };
@@ -24,6 +28,7 @@ use tower03::Service;
 struct InfluxDBSvc {
     config: InfluxDBConfig,
     protocol_version: ProtocolVersion,
+    distributions: Option<DistributionConversion>,
     inner: HttpBatchService<BoxFuture<'static, crate::Result<hyper::Request<Vec<u8>>>>>,
 }
 
@@ -36,6 +41,11 @@ pub struct InfluxDBConfig {
     pub influxdb1_settings: Option<InfluxDB1Settings>,
     #[serde(flatten)]
     pub influxdb2_settings: Option<InfluxDB2Settings>,
+    pub distributions_as: Option<DistributionsAs>,
+    #[serde(default = "default_histogram_buckets")]
+    pub buckets: Vec<f64>,
+    #[serde(default = "default_summary_quantiles")]
+    pub quantiles: Vec<f64>,
     #[serde(default)]
     pub batch: BatchConfig,
     #[serde(default)]
@@ -104,9 +114,19 @@ impl InfluxDBSvc {
         let http_service =
             HttpBatchService::new(cx.resolver(), None, create_build_request(uri, token));
 
+        let distributions =
+            config
+                .distributions_as
+                .map(|distributions_as| DistributionConversion {
+                    distributions_as,
+                    buckets: config.buckets.clone(),
+                    quantiles: config.quantiles.clone(),
+                });
+
         let influxdb_http_service = InfluxDBSvc {
             config,
             protocol_version,
+            distributions,
             inner: http_service,
         };
 
@@ -134,6 +154,13 @@ impl Service<Vec<Metric>> for InfluxDBSvc {
     }
 
     fn call(&mut self, items: Vec<Metric>) -> Self::Future {
+        let items = match &self.distributions {
+            Some(distributions) => items
+                .into_iter()
+                .map(|metric| distributions.convert(metric))
+                .collect(),
+            None => items,
+        };
         let input = encode_events(self.protocol_version, items, &self.config.namespace);
         let body: Vec<u8> = input.into_bytes();
 
@@ -262,6 +289,19 @@ fn encode_events(
             } => {
                 let fields = encode_distribution(&values, &sample_rates);
 
+                influx_line_protocol(
+                    protocol_version,
+                    fullname,
+                    "distribution",
+                    tags,
+                    fields,
+                    ts,
+                    &mut output,
+                );
+            }
+            MetricValue::Sketch { sketch } => {
+                let fields = encode_sketch(&sketch);
+
                 influx_line_protocol(
                     protocol_version,
                     fullname,
@@ -280,6 +320,28 @@ fn encode_events(
     output
 }
 
+/// Encodes the same statistics as `encode_distribution`, estimated by the
+/// sketch.
+fn encode_sketch(sketch: &DDSketch) -> Option<HashMap<String, Field>> {
+    let count = f64::from(sketch.count());
+    Some(
+        vec![
+            ("min".to_owned(), Field::Float(sketch.min()?)),
+            ("max".to_owned(), Field::Float(sketch.max()?)),
+            ("median".to_owned(), Field::Float(sketch.quantile(0.5)?)),
+            ("avg".to_owned(), Field::Float(sketch.sum() / count)),
+            ("sum".to_owned(), Field::Float(sketch.sum())),
+            ("count".to_owned(), Field::Float(count)),
+            (
+                "quantile_0.95".to_owned(),
+                Field::Float(sketch.quantile(0.95)?),
+            ),
+        ]
+        .into_iter()
+        .collect(),
+    )
+}
+
 fn encode_distribution(values: &[f64], counts: &[u32]) -> Option<HashMap<String, Field>> {
     if values.len() != counts.len() {
         return None;
@@ -573,6 +635,68 @@ mod tests {
         assert_eq!("1542182950000000011", line_protocol1.3);
     }
 
+    #[test]
+    fn test_encode_sketch() {
+        let mut sketch = DDSketch::new();
+        sketch.insert_n(2.0, 4);
+        let events = vec![Metric {
+            name: "requests".into(),
+            timestamp: Some(ts()),
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Sketch { sketch },
+        }];
+
+        let line_protocols = encode_events(ProtocolVersion::V2, events, "ns");
+        let line_protocol1 = split_line_protocol(&line_protocols);
+        assert_eq!("ns.requests", line_protocol1.0);
+        assert_eq!("metric_type=distribution", line_protocol1.1);
+        assert_fields(
+            line_protocol1.2.to_string(),
+            [
+                "avg=2",
+                "count=4",
+                "max=2",
+                "median=2",
+                "min=2",
+                "quantile_0.95=2",
+                "sum=8",
+            ]
+            .to_vec(),
+        );
+    }
+
+    #[test]
+    fn test_encode_distribution_as_histogram() {
+        let distributions = DistributionConversion {
+            distributions_as: DistributionsAs::Histogram,
+            buckets: vec![1.0, 2.5],
+            quantiles: default_summary_quantiles(),
+        };
+        let events = vec![distributions.convert(Metric {
+            name: "requests".into(),
+            timestamp: Some(ts()),
+            tags: Some(tags()),
+            kind: MetricKind::Incremental,
+            value: MetricValue::Distribution {
+                values: vec![1.0, 2.0, 3.0],
+                sample_rates: vec![3, 3, 2],
+            },
+        })];
+
+        let line_protocols = encode_events(ProtocolVersion::V2, events, "ns");
+        let line_protocol1 = split_line_protocol(&line_protocols);
+        assert_eq!("ns.requests", line_protocol1.0);
+        assert_eq!(
+            "metric_type=histogram,normal_tag=value,true_tag=true",
+            line_protocol1.1
+        );
+        assert_fields(
+            line_protocol1.2.to_string(),
+            ["bucket_1=3u", "bucket_2.5=6u", "count=8u", "sum=15"].to_vec(),
+        );
+    }
+
     #[test]
     fn test_encode_distribution() {
         let events = vec![
@@ -773,6 +897,9 @@ mod integration_tests {
                 bucket: BUCKET.to_string(),
                 token: TOKEN.to_string(),
             }),
+            distributions_as: None,
+            buckets: default_histogram_buckets(),
+            quantiles: default_summary_quantiles(),
             batch: Default::default(),
             request: Default::default(),
         };
@@ -92,6 +92,10 @@ impl SinkConfig for LogdnaConfig {
     fn sink_type(&self) -> &'static str {
         "logdna"
     }
+
+    fn supports_dead_letter(&self) -> bool {
+        true
+    }
 }
 
 #[async_trait::async_trait]
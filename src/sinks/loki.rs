@@ -110,6 +110,10 @@ impl SinkConfig for LokiConfig {
     fn sink_type(&self) -> &'static str {
         "loki"
     }
+
+    fn supports_dead_letter(&self) -> bool {
+        true
+    }
 }
 
 #[async_trait::async_trait]
@@ -43,10 +43,14 @@ pub mod logdna;
 pub mod loki;
 #[cfg(feature = "sinks-new_relic_logs")]
 pub mod new_relic_logs;
+#[cfg(feature = "sinks-otlp")]
+pub mod otlp;
 #[cfg(feature = "sinks-papertrail")]
 pub mod papertrail;
 #[cfg(feature = "sinks-prometheus")]
 pub mod prometheus;
+#[cfg(feature = "sinks-prometheus_remote_write")]
+pub mod prometheus_remote_write;
 #[cfg(feature = "sinks-pulsar")]
 pub mod pulsar;
 #[cfg(feature = "sinks-sematext_logs")]
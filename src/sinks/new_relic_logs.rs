@@ -87,6 +87,10 @@ impl SinkConfig for NewRelicLogsConfig {
     fn sink_type(&self) -> &'static str {
         "new_relic_logs"
     }
+
+    fn supports_dead_letter(&self) -> bool {
+        true
+    }
 }
 
 impl NewRelicLogsConfig {
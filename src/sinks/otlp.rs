@@ -0,0 +1,700 @@
+use crate::{
+    event::{
+        self,
+        metric::{Metric, MetricKind, MetricValue},
+        LogEvent, Value,
+    },
+    otlp::{
+        encode_message,
+        proto::{
+            self, any_value, metric::Data, number_data_point, AggregationTemporality,
+            ExportLogsServiceRequest, ExportMetricsServiceRequest,
+        },
+        timestamp_to_nanos, ATTRIBUTES_KEY, LOGS_PATH, METRICS_PATH, PROTOBUF_CONTENT_TYPE,
+        RESOURCES_KEY, SEVERITY_NUMBER_KEY, SEVERITY_TEXT_KEY, SPAN_ID_KEY, TRACE_ID_KEY,
+    },
+    sinks::util::{
+        distribution::{default_histogram_buckets, default_summary_quantiles},
+        http::{Auth, HttpBatchService, HttpRetryLogic},
+        service2::TowerRequestConfig,
+        BatchConfig, BatchSettings, DistributionConversion, DistributionsAs, Length,
+        PartitionBatchSink, PartitionBuffer, PartitionInnerBuffer, UriSerde, VecBuffer2,
+    },
+    tls::{TlsOptions, TlsSettings},
+    topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
+    Event,
+};
+use futures::future::{ready, Ready};
+use futures01::{future, stream::iter_ok, Sink};
+use http::{uri::PathAndQuery, Request, Uri};
+use lazy_static::lazy_static;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct OtlpSinkConfig {
+    pub endpoint: UriSerde,
+    #[serde(default)]
+    pub distributions_as: DistributionsAs,
+    #[serde(default = "default_histogram_buckets")]
+    pub buckets: Vec<f64>,
+    #[serde(default = "default_summary_quantiles")]
+    pub quantiles: Vec<f64>,
+    pub auth: Option<Auth>,
+    #[serde(default)]
+    pub batch: BatchConfig,
+    #[serde(default)]
+    pub request: TowerRequestConfig,
+    pub tls: Option<TlsOptions>,
+}
+
+lazy_static! {
+    static ref REQUEST_DEFAULTS: TowerRequestConfig = TowerRequestConfig {
+        in_flight_limit: Some(5),
+        retry_attempts: Some(5),
+        ..Default::default()
+    };
+}
+
+inventory::submit! {
+    SinkDescription::new_without_default::<OtlpSinkConfig>("otlp")
+}
+
+#[typetag::serde(name = "otlp")]
+impl SinkConfig for OtlpSinkConfig {
+    fn build(&self, cx: SinkContext) -> crate::Result<(super::RouterSink, super::Healthcheck)> {
+        let tls = TlsSettings::from_options(&self.tls)?;
+        let batch = self
+            .batch
+            .use_size_as_events()?
+            .get_settings_or_default(BatchSettings::default().events(1_000).timeout(1));
+        let request = self.request.unwrap_with(&REQUEST_DEFAULTS);
+
+        let logs_uri = signal_uri(&self.endpoint, LOGS_PATH)?;
+        let metrics_uri = signal_uri(&self.endpoint, METRICS_PATH)?;
+        let auth = self.auth.clone();
+        let service = HttpBatchService::new(
+            cx.resolver(),
+            tls,
+            move |batch: PartitionInnerBuffer<Vec<Record>, Signal>| {
+                let (records, signal) = batch.into_parts();
+                let uri = match signal {
+                    Signal::Logs => &logs_uri,
+                    Signal::Metrics => &metrics_uri,
+                };
+                build_request(uri.clone(), auth.as_ref(), signal, records)
+            },
+        );
+
+        let distributions = DistributionConversion {
+            distributions_as: self.distributions_as,
+            buckets: self.buckets.clone(),
+            quantiles: self.quantiles.clone(),
+        };
+        let buffer = PartitionBuffer::new(VecBuffer2::new(batch.size));
+        let sink = PartitionBatchSink::new(
+            request.service(HttpRetryLogic, service),
+            buffer,
+            batch.timeout,
+            cx.acker(),
+        )
+        .sink_map_err(|e| error!("Fatal otlp sink error: {}", e))
+        .with_flat_map(move |event| iter_ok(Some(encode_event(event, &distributions))));
+
+        // OTLP receivers have no common healthcheck endpoint.
+        let healthcheck = Box::new(future::ok(()));
+
+        Ok((Box::new(sink), healthcheck))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Any
+    }
+
+    fn sink_type(&self) -> &'static str {
+        "otlp"
+    }
+}
+
+/// Logs and metrics are exported to different paths, so they are batched
+/// separately.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+enum Signal {
+    Logs,
+    Metrics,
+}
+
+#[derive(Clone, Debug)]
+enum Record {
+    Log {
+        resource: Vec<proto::KeyValue>,
+        record: proto::LogRecord,
+    },
+    Metric(proto::Metric),
+}
+
+impl Length for Record {
+    fn len(&self) -> usize {
+        match self {
+            Record::Log { resource, record } => {
+                resource.iter().map(Message::encoded_len).sum::<usize>() + record.encoded_len()
+            }
+            Record::Metric(metric) => metric.encoded_len(),
+        }
+    }
+}
+
+/// Appends the path of a signal to any path the endpoint already has.
+fn signal_uri(endpoint: &Uri, path: &str) -> crate::Result<Uri> {
+    let path = format!("{}{}", endpoint.path().trim_end_matches('/'), path);
+    let mut parts = endpoint.clone().into_parts();
+    parts.path_and_query = Some(path.parse::<PathAndQuery>()?);
+    Ok(Uri::from_parts(parts)?)
+}
+
+fn build_request(
+    uri: Uri,
+    auth: Option<&Auth>,
+    signal: Signal,
+    records: Vec<Record>,
+) -> Ready<crate::Result<Request<Vec<u8>>>> {
+    let body = match signal {
+        Signal::Logs => encode_message(&logs_request(records)),
+        Signal::Metrics => encode_message(&metrics_request(records)),
+    };
+
+    let request = Request::post(uri)
+        .header("Content-Type", PROTOBUF_CONTENT_TYPE)
+        .body(body)
+        .map(|mut request| {
+            if let Some(auth) = auth {
+                auth.apply(&mut request);
+            }
+            request
+        })
+        .map_err(Into::into);
+    ready(request)
+}
+
+/// Groups the log records by their resource.
+fn logs_request(records: Vec<Record>) -> ExportLogsServiceRequest {
+    let mut resource_logs = Vec::<proto::ResourceLogs>::new();
+    for record in records {
+        let (resource, record) = match record {
+            Record::Log { resource, record } => (resource, record),
+            Record::Metric(_) => continue,
+        };
+
+        let position = resource_logs.iter().position(|logs| {
+            logs.resource
+                .as_ref()
+                .map_or(false, |logs| logs.attributes == resource)
+        });
+        let logs = match position {
+            Some(position) => &mut resource_logs[position],
+            None => {
+                resource_logs.push(proto::ResourceLogs {
+                    resource: Some(proto::Resource {
+                        attributes: resource,
+                        dropped_attributes_count: 0,
+                    }),
+                    scope_logs: vec![proto::ScopeLogs::default()],
+                    schema_url: String::new(),
+                });
+                resource_logs.last_mut().unwrap()
+            }
+        };
+        logs.scope_logs[0].log_records.push(record);
+    }
+
+    ExportLogsServiceRequest { resource_logs }
+}
+
+fn metrics_request(records: Vec<Record>) -> ExportMetricsServiceRequest {
+    let metrics = records
+        .into_iter()
+        .filter_map(|record| match record {
+            Record::Metric(metric) => Some(metric),
+            Record::Log { .. } => None,
+        })
+        .collect();
+
+    ExportMetricsServiceRequest {
+        resource_metrics: vec![proto::ResourceMetrics {
+            resource: None,
+            scope_metrics: vec![proto::ScopeMetrics {
+                scope: None,
+                metrics,
+                schema_url: String::new(),
+            }],
+            schema_url: String::new(),
+        }],
+    }
+}
+
+/// Every event becomes exactly one record, as the sink acknowledges events by
+/// the number of records it sent. A metric that can't be encoded is sent
+/// without any data.
+fn encode_event(
+    event: Event,
+    distributions: &DistributionConversion,
+) -> PartitionInnerBuffer<Record, Signal> {
+    match event {
+        Event::Log(log) => PartitionInnerBuffer::new(encode_log(log), Signal::Logs),
+        Event::Metric(metric) => {
+            let name = metric.name.clone();
+            let metric =
+                encode_metric(distributions.convert(metric)).unwrap_or_else(|| proto::Metric {
+                    name,
+                    ..Default::default()
+                });
+            PartitionInnerBuffer::new(Record::Metric(metric), Signal::Metrics)
+        }
+    }
+}
+
+/// The reverse of the `otlp` source: the message becomes the body of the log
+/// record, the `resources` map its resource and any other field an attribute.
+fn encode_log(log: LogEvent) -> Record {
+    let message_key: &str = event::log_schema().message_key();
+    let timestamp_key: &str = event::log_schema().timestamp_key();
+
+    let mut resource = Vec::new();
+    let mut record = proto::LogRecord::default();
+    for (key, value) in log {
+        match (key.as_str(), value) {
+            (key, value) if key == message_key => record.body = Some(encode_value(value)),
+            (key, Value::Timestamp(timestamp)) if key == timestamp_key => {
+                record.time_unix_nano = timestamp_to_nanos(Some(timestamp))
+            }
+            (SEVERITY_TEXT_KEY, value) => record.severity_text = value.to_string_lossy(),
+            (SEVERITY_NUMBER_KEY, Value::Integer(number)) => record.severity_number = number as i32,
+            (TRACE_ID_KEY, value) | (SPAN_ID_KEY, value) => {
+                match from_hex(&value.to_string_lossy()) {
+                    Some(id) if key == TRACE_ID_KEY => record.trace_id = id,
+                    Some(id) => record.span_id = id,
+                    None => record.attributes.push(key_value(&key, value)),
+                }
+            }
+            (ATTRIBUTES_KEY, Value::Map(attributes)) => {
+                record.attributes.extend(encode_attributes(attributes))
+            }
+            (RESOURCES_KEY, Value::Map(attributes)) => {
+                resource.extend(encode_attributes(attributes))
+            }
+            (_, value) => record.attributes.push(key_value(&key, value)),
+        }
+    }
+
+    Record::Log { resource, record }
+}
+
+/// Sums and histograms keep the kind of the metric as their aggregation
+/// temporality, so incremental metrics are sent as deltas.
+fn encode_metric(metric: Metric) -> Option<proto::Metric> {
+    let attributes = metric
+        .tags
+        .into_iter()
+        .flatten()
+        .map(|(key, value)| key_value(key, Value::from(value)))
+        .collect::<Vec<_>>();
+    let time_unix_nano = timestamp_to_nanos(metric.timestamp);
+    let temporality = match metric.kind {
+        MetricKind::Incremental => AggregationTemporality::Delta,
+        MetricKind::Absolute => AggregationTemporality::Cumulative,
+    } as i32;
+
+    let data = match metric.value {
+        MetricValue::Counter { value } => Data::Sum(proto::Sum {
+            data_points: vec![number_point(attributes, time_unix_nano, value)],
+            aggregation_temporality: temporality,
+            is_monotonic: true,
+        }),
+        MetricValue::Gauge { value } if metric.kind.is_absolute() => Data::Gauge(proto::Gauge {
+            data_points: vec![number_point(attributes, time_unix_nano, value)],
+        }),
+        MetricValue::Gauge { value } => Data::Sum(proto::Sum {
+            data_points: vec![number_point(attributes, time_unix_nano, value)],
+            aggregation_temporality: temporality,
+            is_monotonic: false,
+        }),
+        MetricValue::Set { values } => Data::Gauge(proto::Gauge {
+            data_points: vec![number_point(
+                attributes,
+                time_unix_nano,
+                values.len() as f64,
+            )],
+        }),
+        // Distributions and sketches are converted to histograms or
+        // summaries before they're encoded, see `encode_event`.
+        MetricValue::Distribution { .. } | MetricValue::Sketch { .. } => return None,
+        MetricValue::AggregatedHistogram {
+            buckets,
+            counts,
+            count,
+            sum,
+        } => {
+            // OTLP counts each bucket on its own, plus one more bucket for
+            // values over the last bound.
+            let mut bucket_counts = Vec::with_capacity(counts.len() + 1);
+            let mut previous = 0;
+            for count in counts.into_iter().chain(std::iter::once(count)) {
+                bucket_counts.push(count.saturating_sub(previous) as u64);
+                previous = count;
+            }
+
+            Data::Histogram(proto::Histogram {
+                data_points: vec![proto::HistogramDataPoint {
+                    attributes,
+                    time_unix_nano,
+                    count: count as u64,
+                    sum,
+                    bucket_counts,
+                    explicit_bounds: buckets,
+                    ..Default::default()
+                }],
+                aggregation_temporality: temporality,
+            })
+        }
+        MetricValue::AggregatedSummary {
+            quantiles,
+            values,
+            count,
+            sum,
+        } => Data::Summary(proto::Summary {
+            data_points: vec![proto::SummaryDataPoint {
+                attributes,
+                time_unix_nano,
+                count: count as u64,
+                sum,
+                quantile_values: quantiles
+                    .into_iter()
+                    .zip(values)
+                    .map(
+                        |(quantile, value)| proto::summary_data_point::ValueAtQuantile {
+                            quantile,
+                            value,
+                        },
+                    )
+                    .collect(),
+                ..Default::default()
+            }],
+        }),
+    };
+
+    Some(proto::Metric {
+        name: metric.name,
+        description: String::new(),
+        unit: String::new(),
+        data: Some(data),
+    })
+}
+
+fn number_point(
+    attributes: Vec<proto::KeyValue>,
+    time_unix_nano: u64,
+    value: f64,
+) -> proto::NumberDataPoint {
+    proto::NumberDataPoint {
+        attributes,
+        time_unix_nano,
+        value: Some(number_data_point::Value::AsDouble(value)),
+        ..Default::default()
+    }
+}
+
+fn encode_attributes(attributes: BTreeMap<String, Value>) -> Vec<proto::KeyValue> {
+    attributes
+        .into_iter()
+        .map(|(key, value)| key_value(key, value))
+        .collect()
+}
+
+fn key_value(key: impl Into<String>, value: Value) -> proto::KeyValue {
+    proto::KeyValue {
+        key: key.into(),
+        value: Some(encode_value(value)),
+    }
+}
+
+fn encode_value(value: Value) -> proto::AnyValue {
+    let value = match value {
+        Value::Bytes(bytes) => match String::from_utf8(bytes.to_vec()) {
+            Ok(string) => any_value::Value::StringValue(string),
+            Err(error) => any_value::Value::BytesValue(error.into_bytes()),
+        },
+        Value::Integer(value) => any_value::Value::IntValue(value),
+        Value::Float(value) => any_value::Value::DoubleValue(value),
+        Value::Boolean(value) => any_value::Value::BoolValue(value),
+        Value::Timestamp(timestamp) => any_value::Value::StringValue(timestamp.to_rfc3339()),
+        Value::Map(map) => any_value::Value::KvlistValue(proto::KeyValueList {
+            values: encode_attributes(map),
+        }),
+        Value::Array(array) => any_value::Value::ArrayValue(proto::ArrayValue {
+            values: array.into_iter().map(encode_value).collect(),
+        }),
+        Value::Null => return proto::AnyValue { value: None },
+    };
+    proto::AnyValue { value: Some(value) }
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sinks::util::test::build_test_server,
+        test_util::{next_addr, runtime, shutdown_on_idle},
+    };
+    use chrono::{offset::TimeZone, Utc};
+    use futures01::{stream, Stream};
+    use headers::{Authorization, HeaderMapExt};
+    use pretty_assertions::assert_eq;
+
+    fn tags() -> BTreeMap<String, String> {
+        vec![("code".to_owned(), "200".to_owned())]
+            .into_iter()
+            .collect()
+    }
+
+    fn metric(kind: MetricKind, value: MetricValue) -> Metric {
+        Metric {
+            name: "requests".into(),
+            timestamp: Some(Utc.ymd(2020, 7, 1).and_hms(12, 30, 15)),
+            tags: Some(tags()),
+            kind,
+            value,
+        }
+    }
+
+    fn string(value: &str) -> Option<proto::AnyValue> {
+        Some(proto::AnyValue {
+            value: Some(any_value::Value::StringValue(value.into())),
+        })
+    }
+
+    fn log() -> LogEvent {
+        let mut log = LogEvent::default();
+        log.insert("message", "order placed");
+        log.insert("timestamp", Utc.ymd(2020, 7, 1).and_hms(12, 30, 15));
+        log.insert("severity_text", "INFO");
+        log.insert("trace_id", "ab".repeat(16));
+        log.insert("attributes.items", 3);
+        log.insert("resources.region", "eu");
+        log.insert("host", "web-1");
+        log
+    }
+
+    #[test]
+    fn encodes_log_events() {
+        let (resource, record) = match encode_log(log()) {
+            Record::Log { resource, record } => (resource, record),
+            Record::Metric(_) => panic!("log encoded as a metric"),
+        };
+
+        assert_eq!(
+            resource,
+            vec![proto::KeyValue {
+                key: "region".into(),
+                value: string("eu"),
+            }]
+        );
+        assert_eq!(record.body, string("order placed"));
+        assert_eq!(record.time_unix_nano, 1_593_606_615_000_000_000);
+        assert_eq!(record.severity_text, "INFO");
+        assert_eq!(record.trace_id, vec![0xab; 16]);
+        assert_eq!(
+            record.attributes,
+            vec![
+                proto::KeyValue {
+                    key: "items".into(),
+                    value: Some(proto::AnyValue {
+                        value: Some(any_value::Value::IntValue(3)),
+                    }),
+                },
+                proto::KeyValue {
+                    key: "host".into(),
+                    value: string("web-1"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn encodes_metrics_by_type() {
+        let counter = encode_metric(metric(
+            MetricKind::Incremental,
+            MetricValue::Counter { value: 2.0 },
+        ))
+        .unwrap();
+        match counter.data {
+            Some(Data::Sum(sum)) => {
+                assert!(sum.is_monotonic);
+                assert_eq!(
+                    sum.aggregation_temporality,
+                    AggregationTemporality::Delta as i32
+                );
+                assert_eq!(
+                    sum.data_points[0].value,
+                    Some(number_data_point::Value::AsDouble(2.0))
+                );
+                assert_eq!(
+                    sum.data_points[0].attributes,
+                    vec![proto::KeyValue {
+                        key: "code".into(),
+                        value: string("200"),
+                    }]
+                );
+            }
+            data => panic!("unexpected data {:?}", data),
+        }
+
+        let gauge = encode_metric(metric(
+            MetricKind::Absolute,
+            MetricValue::Gauge { value: 1.5 },
+        ))
+        .unwrap();
+        assert!(matches!(gauge.data, Some(Data::Gauge(_))));
+
+        let histogram = encode_metric(metric(
+            MetricKind::Absolute,
+            MetricValue::AggregatedHistogram {
+                buckets: vec![1.0, 2.0],
+                counts: vec![1, 3],
+                count: 6,
+                sum: 12.5,
+            },
+        ))
+        .unwrap();
+        match histogram.data {
+            Some(Data::Histogram(histogram)) => {
+                let point = &histogram.data_points[0];
+                assert_eq!(point.bucket_counts, vec![1, 2, 3]);
+                assert_eq!(point.explicit_bounds, vec![1.0, 2.0]);
+                assert_eq!(point.count, 6);
+            }
+            data => panic!("unexpected data {:?}", data),
+        }
+
+        let distributions = DistributionConversion {
+            distributions_as: DistributionsAs::Summary,
+            buckets: default_histogram_buckets(),
+            quantiles: vec![0.5],
+        };
+        let summary = encode_metric(distributions.convert(metric(
+            MetricKind::Incremental,
+            MetricValue::Distribution {
+                values: vec![1.0],
+                sample_rates: vec![4],
+            },
+        )))
+        .unwrap();
+        match summary.data {
+            Some(Data::Summary(summary)) => {
+                let point = &summary.data_points[0];
+                assert_eq!(
+                    point.quantile_values,
+                    vec![proto::summary_data_point::ValueAtQuantile {
+                        quantile: 0.5,
+                        value: 1.0,
+                    }]
+                );
+                assert_eq!(point.count, 4);
+            }
+            data => panic!("unexpected data {:?}", data),
+        }
+    }
+
+    #[test]
+    fn builds_signal_uris() {
+        let uri = |endpoint: &str, path| {
+            signal_uri(&endpoint.parse().unwrap(), path)
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(
+            uri("http://localhost:4318", LOGS_PATH),
+            "http://localhost:4318/v1/logs"
+        );
+        assert_eq!(
+            uri("https://collector/otlp/", METRICS_PATH),
+            "https://collector/otlp/v1/metrics"
+        );
+    }
+
+    #[test]
+    fn sends_logs_and_metrics_to_their_paths() {
+        let in_addr = next_addr();
+
+        let config = r#"
+        endpoint = "http://$IN_ADDR"
+
+        [auth]
+        strategy = "bearer"
+        token = "secret"
+        "#
+        .replace("$IN_ADDR", &in_addr.to_string());
+        let config: OtlpSinkConfig = toml::from_str(&config).unwrap();
+
+        let mut rt = runtime();
+        let cx = SinkContext::new_test();
+
+        let (sink, _) = config.build(cx).unwrap();
+        let (rx, trigger, server) = build_test_server(in_addr, &mut rt);
+        rt.spawn(server);
+
+        let events = vec![
+            Event::Log(log()),
+            Event::Metric(metric(
+                MetricKind::Absolute,
+                MetricValue::Gauge { value: 1.5 },
+            )),
+            Event::Log(log()),
+        ];
+        let _ = rt.block_on(sink.send_all(stream::iter_ok(events))).unwrap();
+        drop(trigger);
+
+        let mut requests = rx
+            .wait()
+            .map(Result::unwrap)
+            .map(|(parts, body)| {
+                assert_eq!(parts.headers["Content-Type"], PROTOBUF_CONTENT_TYPE);
+                assert_eq!(
+                    Some(Authorization::bearer("secret").unwrap()),
+                    parts.headers.typed_get()
+                );
+                (parts.uri.path().to_owned(), body)
+            })
+            .collect::<Vec<_>>();
+        requests.sort();
+
+        shutdown_on_idle(rt);
+
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].0, LOGS_PATH);
+        let logs = ExportLogsServiceRequest::decode(&requests[0].1[..]).unwrap();
+        assert_eq!(logs.resource_logs.len(), 1);
+        assert_eq!(logs.resource_logs[0].scope_logs[0].log_records.len(), 2);
+
+        assert_eq!(requests[1].0, METRICS_PATH);
+        let metrics = ExportMetricsServiceRequest::decode(&requests[1].1[..]).unwrap();
+        let metrics = &metrics.resource_metrics[0].scope_metrics[0].metrics;
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].name, "requests");
+    }
+}
@@ -1,7 +1,10 @@
 use crate::{
     buffers::Acker,
     event::metric::{Metric, MetricKind, MetricValue},
-    sinks::util::MetricEntry,
+    sinks::util::{
+        distribution::{default_histogram_buckets, default_summary_quantiles},
+        DistributionConversion, DistributionsAs, MetricEntry,
+    },
     topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
     Event,
 };
@@ -40,16 +43,14 @@ pub struct PrometheusSinkConfig {
     pub address: SocketAddr,
     #[serde(default = "default_histogram_buckets")]
     pub buckets: Vec<f64>,
+    #[serde(default = "default_summary_quantiles")]
+    pub quantiles: Vec<f64>,
+    #[serde(default)]
+    pub distributions_as: DistributionsAs,
     #[serde(default = "default_flush_period_secs")]
     pub flush_period_secs: u64,
 }
 
-pub fn default_histogram_buckets() -> Vec<f64> {
-    vec![
-        0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
-    ]
-}
-
 pub fn default_address() -> SocketAddr {
     use std::net::{IpAddr, Ipv4Addr};
 
@@ -148,6 +149,7 @@ fn encode_metric_header(namespace: &str, metric: &Metric) -> String {
         MetricValue::Set { .. } => "gauge",
         MetricValue::AggregatedHistogram { .. } => "histogram",
         MetricValue::AggregatedSummary { .. } => "summary",
+        MetricValue::Sketch { .. } => "summary",
     };
 
     s.push_str(&format!("# HELP {} {}\n", fullname, name));
@@ -155,7 +157,7 @@ fn encode_metric_header(namespace: &str, metric: &Metric) -> String {
     s
 }
 
-fn encode_metric_datum(namespace: &str, buckets: &[f64], expired: bool, metric: &Metric) -> String {
+fn encode_metric_datum(namespace: &str, expired: bool, metric: &Metric) -> String {
     let mut s = String::new();
     let fullname = encode_namespace(namespace, &metric.name);
 
@@ -174,47 +176,9 @@ fn encode_metric_datum(namespace: &str, buckets: &[f64], expired: bool, metric:
                 let value = if expired { 0 } else { values.len() };
                 s.push_str(&format!("{}{} {}\n", fullname, encode_tags(tags), value));
             }
-            MetricValue::Distribution {
-                values,
-                sample_rates,
-            } => {
-                // convert ditributions into aggregated histograms
-                let mut counts = Vec::new();
-                for _ in buckets {
-                    counts.push(0);
-                }
-                let mut sum = 0.0;
-                let mut count = 0;
-                for (v, c) in values.iter().zip(sample_rates.iter()) {
-                    buckets
-                        .iter()
-                        .enumerate()
-                        .skip_while(|&(_, b)| b < v)
-                        .for_each(|(i, _)| {
-                            counts[i] += c;
-                        });
-
-                    sum += v * (*c as f64);
-                    count += c;
-                }
-
-                for (b, c) in buckets.iter().zip(counts.iter()) {
-                    s.push_str(&format!(
-                        "{}_bucket{} {}\n",
-                        fullname,
-                        encode_tags_with_extra(tags, "le".to_string(), b.to_string()),
-                        c
-                    ));
-                }
-                s.push_str(&format!(
-                    "{}_bucket{} {}\n",
-                    fullname,
-                    encode_tags_with_extra(tags, "le".to_string(), "+Inf".to_string()),
-                    count
-                ));
-                let tags = encode_tags(tags);
-                s.push_str(&format!("{}_sum{} {}\n", fullname, tags, sum));
-                s.push_str(&format!("{}_count{} {}\n", fullname, tags, count));
+            MetricValue::Distribution { .. } | MetricValue::Sketch { .. } => {
+                // distributions and sketches are converted to histograms or
+                // summaries before they're encoded, see `handle`
             }
             MetricValue::AggregatedHistogram {
                 buckets,
@@ -267,7 +231,7 @@ fn encode_metric_datum(namespace: &str, buckets: &[f64], expired: bool, metric:
 fn handle(
     req: Request<Body>,
     namespace: &str,
-    buckets: &[f64],
+    distributions: &DistributionConversion,
     expired: bool,
     metrics: &IndexSet<MetricEntry>,
 ) -> Box<dyn Future<Item = Response<Body>, Error = hyper::Error> + Send> {
@@ -282,10 +246,11 @@ fn handle(
 
             for metric in metrics {
                 let name = &metric.0.name;
-                let frame = encode_metric_datum(&namespace, &buckets, expired, &metric.0);
+                let metric = distributions.convert(metric.0.clone());
+                let frame = encode_metric_datum(&namespace, expired, &metric);
 
                 if !processed_headers.contains(&name) {
-                    let header = encode_metric_header(&namespace, &metric.0);
+                    let header = encode_metric_header(&namespace, &metric);
                     s.push_str(&header);
                     processed_headers.insert(name);
                 };
@@ -330,14 +295,18 @@ impl PrometheusSink {
 
         let metrics = Arc::clone(&self.metrics);
         let namespace = self.config.namespace.clone();
-        let buckets = self.config.buckets.clone();
+        let distributions = DistributionConversion {
+            distributions_as: self.config.distributions_as,
+            buckets: self.config.buckets.clone(),
+            quantiles: self.config.quantiles.clone(),
+        };
         let last_flush_timestamp = Arc::clone(&self.last_flush_timestamp);
         let flush_period_secs = self.config.flush_period_secs;
 
         let new_service = make_service_fn(move |_| {
             let metrics = Arc::clone(&metrics);
             let namespace = namespace.clone();
-            let buckets = buckets.clone();
+            let distributions = distributions.clone();
             let last_flush_timestamp = Arc::clone(&last_flush_timestamp);
             let flush_period_secs = flush_period_secs;
 
@@ -352,7 +321,7 @@ impl PrometheusSink {
                         method = field::debug(req.method()),
                         path = field::debug(req.uri().path()),
                     )
-                    .in_scope(|| handle(req, &namespace, &buckets, expired, &metrics))
+                    .in_scope(|| handle(req, &namespace, &distributions, expired, &metrics))
                     .compat()
                 }))
             }
@@ -444,7 +413,7 @@ mod tests {
         };
 
         let header = encode_metric_header("vector", &metric);
-        let frame = encode_metric_datum("vector", &[], false, &metric);
+        let frame = encode_metric_datum("vector", false, &metric);
 
         assert_eq!(
             header,
@@ -464,7 +433,7 @@ mod tests {
         };
 
         let header = encode_metric_header("vector", &metric);
-        let frame = encode_metric_datum("vector", &[], false, &metric);
+        let frame = encode_metric_datum("vector", false, &metric);
 
         assert_eq!(
             header,
@@ -486,7 +455,7 @@ mod tests {
         };
 
         let header = encode_metric_header("", &metric);
-        let frame = encode_metric_datum("", &[], false, &metric);
+        let frame = encode_metric_datum("", false, &metric);
 
         assert_eq!(
             header,
@@ -508,7 +477,7 @@ mod tests {
         };
 
         let header = encode_metric_header("", &metric);
-        let frame = encode_metric_datum("", &[], true, &metric);
+        let frame = encode_metric_datum("", true, &metric);
 
         assert_eq!(
             header,
@@ -530,8 +499,15 @@ mod tests {
             },
         };
 
+        let metric = DistributionConversion {
+            distributions_as: DistributionsAs::Histogram,
+            buckets: vec![0.0, 2.5, 5.0],
+            quantiles: default_summary_quantiles(),
+        }
+        .convert(metric);
+
         let header = encode_metric_header("", &metric);
-        let frame = encode_metric_datum("", &[0.0, 2.5, 5.0], false, &metric);
+        let frame = encode_metric_datum("", false, &metric);
 
         assert_eq!(
             header,
@@ -540,6 +516,35 @@ mod tests {
         assert_eq!(frame, "requests_bucket{le=\"0\"} 0\nrequests_bucket{le=\"2.5\"} 6\nrequests_bucket{le=\"5\"} 8\nrequests_bucket{le=\"+Inf\"} 8\nrequests_sum 15\nrequests_count 8\n".to_owned());
     }
 
+    #[test]
+    fn test_encode_distribution_as_summary() {
+        let metric = Metric {
+            name: "requests".to_owned(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Absolute,
+            value: MetricValue::Distribution {
+                values: vec![2.0, 2.0],
+                sample_rates: vec![3, 5],
+            },
+        };
+        let metric = DistributionConversion {
+            distributions_as: DistributionsAs::Summary,
+            buckets: default_histogram_buckets(),
+            quantiles: vec![0.5, 0.99],
+        }
+        .convert(metric);
+
+        let header = encode_metric_header("", &metric);
+        let frame = encode_metric_datum("", false, &metric);
+
+        assert_eq!(
+            header,
+            "# HELP requests requests\n# TYPE requests summary\n".to_owned()
+        );
+        assert_eq!(frame, "requests{quantile=\"0.5\"} 2\nrequests{quantile=\"0.99\"} 2\nrequests_sum 16\nrequests_count 8\n".to_owned());
+    }
+
     #[test]
     fn test_encode_histogram() {
         let metric = Metric {
@@ -556,7 +561,7 @@ mod tests {
         };
 
         let header = encode_metric_header("", &metric);
-        let frame = encode_metric_datum("", &[], false, &metric);
+        let frame = encode_metric_datum("", false, &metric);
 
         assert_eq!(
             header,
@@ -581,7 +586,7 @@ mod tests {
         };
 
         let header = encode_metric_header("", &metric);
-        let frame = encode_metric_datum("", &[], false, &metric);
+        let frame = encode_metric_datum("", false, &metric);
 
         assert_eq!(
             header,
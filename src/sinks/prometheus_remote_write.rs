@@ -0,0 +1,543 @@
+use crate::{
+    event::metric::{Metric, MetricKind, MetricValue},
+    prometheus::{encode_write_request, proto, METRIC_NAME_LABEL},
+    sinks::util::{
+        distribution::{default_histogram_buckets, default_summary_quantiles},
+        http::{Auth, BatchedHttpSink, HttpSink},
+        service2::TowerRequestConfig,
+        BatchConfig, BatchSettings, DistributionConversion, DistributionsAs, Length, MetricEntry,
+        UriSerde, VecBuffer2,
+    },
+    tls::{TlsOptions, TlsSettings},
+    topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
+    Event,
+};
+use chrono::Utc;
+use futures01::{future, Sink};
+use http::{Request, Uri};
+use lazy_static::lazy_static;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    iter,
+    sync::Mutex,
+};
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteWriteConfig {
+    pub endpoint: UriSerde,
+    pub namespace: Option<String>,
+    #[serde(default)]
+    pub distributions_as: DistributionsAs,
+    #[serde(default = "default_histogram_buckets")]
+    pub buckets: Vec<f64>,
+    #[serde(default = "default_summary_quantiles")]
+    pub quantiles: Vec<f64>,
+    pub auth: Option<Auth>,
+    #[serde(default)]
+    pub batch: BatchConfig,
+    #[serde(default)]
+    pub request: TowerRequestConfig,
+    pub tls: Option<TlsOptions>,
+}
+
+lazy_static! {
+    static ref REQUEST_DEFAULTS: TowerRequestConfig = TowerRequestConfig {
+        in_flight_limit: Some(5),
+        retry_attempts: Some(5),
+        ..Default::default()
+    };
+}
+
+inventory::submit! {
+    SinkDescription::new_without_default::<RemoteWriteConfig>("prometheus_remote_write")
+}
+
+#[typetag::serde(name = "prometheus_remote_write")]
+impl SinkConfig for RemoteWriteConfig {
+    fn build(&self, cx: SinkContext) -> crate::Result<(super::RouterSink, super::Healthcheck)> {
+        let tls = TlsSettings::from_options(&self.tls)?;
+        let batch = self
+            .batch
+            .use_size_as_events()?
+            .get_settings_or_default(BatchSettings::default().events(1_000).timeout(1));
+        let request = self.request.unwrap_with(&REQUEST_DEFAULTS);
+
+        let sink = RemoteWriteSink::new(self.clone());
+        let sink = BatchedHttpSink::new(
+            sink,
+            VecBuffer2::new(batch.size),
+            request,
+            batch.timeout,
+            Some(tls),
+            &cx,
+        )
+        .sink_map_err(|e| error!("Fatal prometheus_remote_write sink error: {}", e));
+
+        // Remote-write receivers have no common healthcheck endpoint.
+        let healthcheck = Box::new(future::ok(()));
+
+        Ok((Box::new(sink), healthcheck))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn sink_type(&self) -> &'static str {
+        "prometheus_remote_write"
+    }
+
+    fn supports_dead_letter(&self) -> bool {
+        true
+    }
+}
+
+/// The series a single metric is encoded into.
+#[derive(Clone, Debug)]
+struct EncodedMetric(Vec<proto::TimeSeries>);
+
+impl Length for EncodedMetric {
+    fn len(&self) -> usize {
+        self.0.iter().map(Message::encoded_len).sum()
+    }
+}
+
+struct RemoteWriteSink {
+    config: RemoteWriteConfig,
+    distributions: DistributionConversion,
+    // Prometheus expects counters and gauges to be absolute, so incremental
+    // metrics are accumulated here the same way the `prometheus` sink does.
+    metrics: Mutex<HashSet<MetricEntry>>,
+}
+
+impl RemoteWriteSink {
+    fn new(config: RemoteWriteConfig) -> Self {
+        let distributions = DistributionConversion {
+            distributions_as: config.distributions_as,
+            buckets: config.buckets.clone(),
+            quantiles: config.quantiles.clone(),
+        };
+        Self {
+            config,
+            distributions,
+            metrics: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn to_absolute(&self, metric: Metric) -> Metric {
+        // Sets are sent as the number of values in each metric,
+        // as accumulating them would grow without bounds.
+        if metric.value.is_set() {
+            return metric;
+        }
+
+        let mut metrics = self.metrics.lock().unwrap();
+        match metric.kind {
+            MetricKind::Absolute => {
+                metrics.replace(MetricEntry(metric.clone()));
+                metric
+            }
+            MetricKind::Incremental => {
+                let new = MetricEntry(metric.to_absolute());
+                let absolute = match metrics.take(&new) {
+                    Some(MetricEntry(mut existing)) => {
+                        existing.add(&metric);
+                        existing.timestamp = metric.timestamp;
+                        existing
+                    }
+                    None => new.0,
+                };
+                metrics.insert(MetricEntry(absolute.clone()));
+                absolute
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpSink for RemoteWriteSink {
+    type Input = EncodedMetric;
+    type Output = Vec<EncodedMetric>;
+
+    fn encode_event(&self, event: Event) -> Option<Self::Input> {
+        let metric = self
+            .distributions
+            .convert(self.to_absolute(event.into_metric()));
+        let series = encode_metric(self.config.namespace.as_deref(), &metric);
+
+        if series.is_empty() {
+            None
+        } else {
+            Some(EncodedMetric(series))
+        }
+    }
+
+    async fn build_request(&self, events: Self::Output) -> crate::Result<Request<Vec<u8>>> {
+        let request = proto::WriteRequest {
+            timeseries: events.into_iter().flat_map(|metric| metric.0).collect(),
+            metadata: Vec::new(),
+        };
+        let uri: Uri = self.config.endpoint.clone().into();
+
+        let mut request = Request::post(uri)
+            .header("Content-Type", "application/x-protobuf")
+            .header("Content-Encoding", "snappy")
+            .header("X-Prometheus-Remote-Write-Version", "0.1.0")
+            .body(encode_write_request(&request))?;
+
+        if let Some(auth) = &self.config.auth {
+            auth.apply(&mut request);
+        }
+
+        Ok(request)
+    }
+}
+
+fn encode_namespace(namespace: Option<&str>, name: &str) -> String {
+    match namespace {
+        Some(namespace) if !namespace.is_empty() => format!("{}_{}", namespace, name),
+        _ => name.to_string(),
+    }
+}
+
+fn encode_metric(namespace: Option<&str>, metric: &Metric) -> Vec<proto::TimeSeries> {
+    let name = encode_namespace(namespace, &metric.name);
+    let timestamp = metric.timestamp.unwrap_or_else(Utc::now).timestamp_millis();
+    let series = |suffix: &str, value: f64, extra: Option<(&str, String)>| {
+        encode_series(
+            format!("{}{}", name, suffix),
+            &metric.tags,
+            extra,
+            value,
+            timestamp,
+        )
+    };
+
+    match &metric.value {
+        MetricValue::Counter { value } | MetricValue::Gauge { value } => {
+            vec![series("", *value, None)]
+        }
+        MetricValue::Set { values } => vec![series("", values.len() as f64, None)],
+        // Distributions and sketches are converted to histograms or
+        // summaries before they're encoded.
+        MetricValue::Distribution { .. } | MetricValue::Sketch { .. } => Vec::new(),
+        MetricValue::AggregatedHistogram {
+            buckets,
+            counts,
+            count,
+            sum,
+        } => buckets
+            .iter()
+            .zip(counts.iter())
+            .map(|(bucket, bucket_count)| {
+                series(
+                    "_bucket",
+                    *bucket_count as f64,
+                    Some(("le", bucket.to_string())),
+                )
+            })
+            .chain(iter::once(series(
+                "_bucket",
+                *count as f64,
+                Some(("le", "+Inf".to_string())),
+            )))
+            .chain(iter::once(series("_sum", *sum, None)))
+            .chain(iter::once(series("_count", *count as f64, None)))
+            .collect(),
+        MetricValue::AggregatedSummary {
+            quantiles,
+            values,
+            count,
+            sum,
+        } => quantiles
+            .iter()
+            .zip(values.iter())
+            .map(|(quantile, value)| series("", *value, Some(("quantile", quantile.to_string()))))
+            .chain(iter::once(series("_sum", *sum, None)))
+            .chain(iter::once(series("_count", *count as f64, None)))
+            .collect(),
+    }
+}
+
+fn encode_series(
+    name: String,
+    tags: &Option<BTreeMap<String, String>>,
+    extra: Option<(&str, String)>,
+    value: f64,
+    timestamp: i64,
+) -> proto::TimeSeries {
+    let mut labels = tags
+        .iter()
+        .flatten()
+        .map(|(name, value)| proto::Label {
+            name: name.clone(),
+            value: value.clone(),
+        })
+        .chain(extra.map(|(name, value)| proto::Label {
+            name: name.into(),
+            value,
+        }))
+        .chain(iter::once(proto::Label {
+            name: METRIC_NAME_LABEL.into(),
+            value: name,
+        }))
+        .collect::<Vec<_>>();
+    // Receivers require labels to be sorted by name.
+    labels.sort_by(|a, b| a.name.cmp(&b.name));
+
+    proto::TimeSeries {
+        labels,
+        samples: vec![proto::Sample { value, timestamp }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        prometheus::decode_write_request,
+        sinks::util::test::build_test_server,
+        test_util::{next_addr, runtime, shutdown_on_idle},
+    };
+    use chrono::offset::TimeZone;
+    use futures01::{stream, Stream};
+    use headers::{Authorization, HeaderMapExt};
+    use pretty_assertions::assert_eq;
+
+    fn tags() -> BTreeMap<String, String> {
+        vec![("code".to_owned(), "200".to_owned())]
+            .into_iter()
+            .collect()
+    }
+
+    fn labels(series: &proto::TimeSeries) -> Vec<(&str, &str)> {
+        series
+            .labels
+            .iter()
+            .map(|label| (label.name.as_str(), label.value.as_str()))
+            .collect()
+    }
+
+    fn sink(namespace: Option<&str>) -> RemoteWriteSink {
+        RemoteWriteSink::new(RemoteWriteConfig {
+            endpoint: "http://localhost:9090/api/v1/write"
+                .parse::<Uri>()
+                .unwrap()
+                .into(),
+            namespace: namespace.map(Into::into),
+            distributions_as: DistributionsAs::Histogram,
+            buckets: vec![1.0, 2.0],
+            quantiles: default_summary_quantiles(),
+            auth: None,
+            batch: Default::default(),
+            request: Default::default(),
+            tls: None,
+        })
+    }
+
+    fn counter(kind: MetricKind, value: f64) -> Event {
+        Metric {
+            name: "hits".to_owned(),
+            timestamp: Some(Utc.ymd(2018, 11, 14).and_hms(8, 9, 10)),
+            tags: Some(tags()),
+            kind,
+            value: MetricValue::Counter { value },
+        }
+        .into()
+    }
+
+    #[test]
+    fn accumulates_incremental_counters() {
+        let sink = sink(Some("vector"));
+
+        let values = vec![
+            counter(MetricKind::Incremental, 1.0),
+            counter(MetricKind::Incremental, 2.0),
+            counter(MetricKind::Absolute, 10.0),
+            counter(MetricKind::Incremental, 1.0),
+        ]
+        .into_iter()
+        .map(|event| {
+            let EncodedMetric(series) = sink.encode_event(event).unwrap();
+            assert_eq!(series.len(), 1);
+            assert_eq!(
+                labels(&series[0]),
+                vec![("__name__", "vector_hits"), ("code", "200")]
+            );
+            assert_eq!(series[0].samples[0].timestamp, 1542182950000);
+            series[0].samples[0].value
+        })
+        .collect::<Vec<_>>();
+
+        assert_eq!(values, vec![1.0, 3.0, 10.0, 11.0]);
+    }
+
+    #[test]
+    fn encodes_histograms() {
+        let metric = Metric {
+            name: "requests".to_owned(),
+            timestamp: None,
+            tags: Some(tags()),
+            kind: MetricKind::Absolute,
+            value: MetricValue::AggregatedHistogram {
+                buckets: vec![1.0, 2.1],
+                counts: vec![1, 3],
+                count: 4,
+                sum: 6.5,
+            },
+        };
+
+        let series = encode_metric(None, &metric);
+        let encoded = series
+            .iter()
+            .map(|series| (labels(series), series.samples[0].value))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            encoded,
+            vec![
+                (
+                    vec![
+                        ("__name__", "requests_bucket"),
+                        ("code", "200"),
+                        ("le", "1")
+                    ],
+                    1.0
+                ),
+                (
+                    vec![
+                        ("__name__", "requests_bucket"),
+                        ("code", "200"),
+                        ("le", "2.1")
+                    ],
+                    3.0
+                ),
+                (
+                    vec![
+                        ("__name__", "requests_bucket"),
+                        ("code", "200"),
+                        ("le", "+Inf")
+                    ],
+                    4.0
+                ),
+                (vec![("__name__", "requests_sum"), ("code", "200")], 6.5),
+                (vec![("__name__", "requests_count"), ("code", "200")], 4.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn encodes_summaries() {
+        let metric = Metric {
+            name: "requests".to_owned(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Absolute,
+            value: MetricValue::AggregatedSummary {
+                quantiles: vec![0.5, 0.99],
+                values: vec![2.0, 3.0],
+                count: 6,
+                sum: 12.0,
+            },
+        };
+
+        let series = encode_metric(Some("ns"), &metric);
+        let encoded = series
+            .iter()
+            .map(|series| (labels(series), series.samples[0].value))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            encoded,
+            vec![
+                (vec![("__name__", "ns_requests"), ("quantile", "0.5")], 2.0),
+                (vec![("__name__", "ns_requests"), ("quantile", "0.99")], 3.0),
+                (vec![("__name__", "ns_requests_sum")], 12.0),
+                (vec![("__name__", "ns_requests_count")], 6.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn converts_distributions() {
+        let metric = Metric {
+            name: "latency".to_owned(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Distribution {
+                values: vec![1.0, 3.0],
+                sample_rates: vec![2, 1],
+            },
+        };
+
+        let sink = sink(None);
+        let series = sink.encode_event(metric.into()).unwrap().0;
+        let encoded = series
+            .iter()
+            .map(|series| (labels(series), series.samples[0].value))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            encoded,
+            vec![
+                (vec![("__name__", "latency_bucket"), ("le", "1")], 2.0),
+                (vec![("__name__", "latency_bucket"), ("le", "2")], 2.0),
+                (vec![("__name__", "latency_bucket"), ("le", "+Inf")], 3.0),
+                (vec![("__name__", "latency_sum")], 5.0),
+                (vec![("__name__", "latency_count")], 3.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn sends_snappy_compressed_write_requests() {
+        let in_addr = next_addr();
+
+        let config = r#"
+        endpoint = "http://$IN_ADDR/api/v1/write"
+
+        [auth]
+        strategy = "bearer"
+        token = "secret"
+        "#
+        .replace("$IN_ADDR", &in_addr.to_string());
+        let config: RemoteWriteConfig = toml::from_str(&config).unwrap();
+
+        let mut rt = runtime();
+        let cx = SinkContext::new_test();
+
+        let (sink, _) = config.build(cx).unwrap();
+        let (rx, trigger, server) = build_test_server(in_addr, &mut rt);
+        rt.spawn(server);
+
+        let events = vec![
+            counter(MetricKind::Incremental, 1.0),
+            counter(MetricKind::Incremental, 2.0),
+        ];
+        let _ = rt.block_on(sink.send_all(stream::iter_ok(events))).unwrap();
+        drop(trigger);
+
+        let values = rx
+            .wait()
+            .map(Result::unwrap)
+            .flat_map(|(parts, body)| {
+                assert_eq!("/api/v1/write", parts.uri.path());
+                assert_eq!(parts.headers["Content-Encoding"], "snappy");
+                assert_eq!(
+                    Some(Authorization::bearer("secret").unwrap()),
+                    parts.headers.typed_get()
+                );
+                decode_write_request(&body).unwrap().timeseries
+            })
+            .map(|series| series.samples[0].value)
+            .collect::<Vec<_>>();
+
+        shutdown_on_idle(rt);
+
+        assert_eq!(values, vec![1.0, 3.0]);
+    }
+}
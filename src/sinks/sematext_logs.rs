@@ -79,6 +79,10 @@ impl SinkConfig for SematextLogsConfig {
     fn sink_type(&self) -> &'static str {
         "sematext"
     }
+
+    fn supports_dead_letter(&self) -> bool {
+        true
+    }
 }
 
 /// Used to map `timestamp` to `@timestamp`.
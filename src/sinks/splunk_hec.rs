@@ -112,6 +112,10 @@ impl SinkConfig for HecSinkConfig {
     fn sink_type(&self) -> &'static str {
         "splunk_hec"
     }
+
+    fn supports_dead_letter(&self) -> bool {
+        true
+    }
 }
 
 #[async_trait::async_trait]
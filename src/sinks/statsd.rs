@@ -1,18 +1,30 @@
+#[cfg(unix)]
+use crate::sinks::util::unix::{unix_healthcheck, UnixSink};
 use crate::{
     buffers::Acker,
-    event::metric::{MetricKind, MetricValue},
+    event::metric::{Metric, MetricKind, MetricValue},
     event::Event,
     sinks::util::{
-        service2::TowerCompat, BatchConfig, BatchSettings, BatchSink, Buffer, Compression,
+        distribution::{
+            default_histogram_buckets, default_summary_quantiles, histogram_samples, quantile_name,
+        },
+        service2::TowerCompat,
+        tcp::TcpSink,
+        BatchConfig, BatchSettings, BatchSink, Buffer, Compression, DistributionConversion,
+        DistributionsAs, SinkBuildError, StreamSink,
     },
+    tls::{MaybeTlsSettings, TlsConfig},
     topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
 };
+use bytes::Bytes;
 use futures::{future, FutureExt, TryFutureExt};
 use futures01::{stream::iter_ok, Sink};
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use snafu::{ResultExt, Snafu};
 use std::collections::BTreeMap;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+#[cfg(unix)]
+use std::path::PathBuf;
 use std::task::{Context, Poll};
 use tower03::{Service, ServiceBuilder};
 
@@ -47,15 +59,65 @@ impl Client {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
-#[serde(deny_unknown_fields)]
+// TODO: add back when serde-rs/serde#1358 is addressed
+// #[serde(deny_unknown_fields)]
 pub struct StatsdSinkConfig {
     pub namespace: String,
+    #[serde(flatten, deserialize_with = "deserialize_mode")]
+    pub mode: Mode,
+    pub distributions_as: Option<DistributionsAs>,
+    #[serde(default = "default_histogram_buckets")]
+    pub buckets: Vec<f64>,
+    #[serde(default = "default_summary_quantiles")]
+    pub quantiles: Vec<f64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Mode {
+    Tcp(TcpConfig),
+    Udp(UdpConfig),
+    #[cfg(unix)]
+    Unix(UnixConfig),
+}
+
+/// Configs without a `mode` are read as udp, which was the only mode before
+/// the others were added. Any other config is read by its `mode` so that its
+/// errors aren't hidden behind the fallback.
+fn deserialize_mode<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Mode, D::Error> {
+    let value = toml::Value::deserialize(deserializer)?;
+    let mode = if value.get("mode").is_some() {
+        Mode::deserialize(value)
+    } else {
+        UdpConfig::deserialize(value).map(Mode::Udp)
+    };
+    mode.map_err(de::Error::custom)
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TcpConfig {
+    pub address: String,
+    pub tls: Option<TlsConfig>,
+}
+
+/// Lines sent over UDP are packed into datagrams of at most `batch.max_bytes`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UdpConfig {
     #[serde(default = "default_address")]
     pub address: SocketAddr,
     #[serde(default)]
     pub batch: BatchConfig,
 }
 
+#[cfg(unix)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UnixConfig {
+    pub path: PathBuf,
+}
+
 pub fn default_address() -> SocketAddr {
     SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8125)
 }
@@ -67,9 +129,56 @@ inventory::submit! {
 #[typetag::serde(name = "statsd")]
 impl SinkConfig for StatsdSinkConfig {
     fn build(&self, cx: SinkContext) -> crate::Result<(super::RouterSink, super::Healthcheck)> {
-        let sink = StatsdSvc::new(self.clone(), cx.acker())?;
-        let healthcheck = StatsdSvc::healthcheck(self.clone()).boxed().compat();
-        Ok((sink, Box::new(healthcheck)))
+        let namespace = self.namespace.clone();
+        let distributions = self.distributions();
+        let convert_distributions = self.distributions_as.is_some();
+        match &self.mode {
+            Mode::Tcp(config) => {
+                let uri = config.address.parse::<http::Uri>()?;
+                let host = uri.host().ok_or(SinkBuildError::MissingHost)?.to_string();
+                let port = uri.port_u16().ok_or(SinkBuildError::MissingPort)?;
+                let tls = MaybeTlsSettings::from_config(&config.tls, false)?;
+
+                let tcp = TcpSink::new(host, port, cx.resolver(), tls);
+                let healthcheck = tcp.healthcheck();
+                let sink = StreamSink::new(tcp, cx.acker()).with_flat_map(move |event| {
+                    iter_ok(Some(Bytes::from(encode_frame(
+                        event,
+                        &namespace,
+                        &distributions,
+                        convert_distributions,
+                    ))))
+                });
+
+                Ok((Box::new(sink), healthcheck))
+            }
+            Mode::Udp(config) => {
+                let sink = StatsdSvc::new(
+                    config.clone(),
+                    namespace,
+                    distributions,
+                    convert_distributions,
+                    cx.acker(),
+                )?;
+                let healthcheck = StatsdSvc::healthcheck().boxed().compat();
+                Ok((sink, Box::new(healthcheck)))
+            }
+            #[cfg(unix)]
+            Mode::Unix(config) => {
+                let unix = UnixSink::new(config.path.clone());
+                let healthcheck = unix_healthcheck(config.path.clone());
+                let sink = StreamSink::new(unix, cx.acker()).with_flat_map(move |event| {
+                    iter_ok(Some(Bytes::from(encode_frame(
+                        event,
+                        &namespace,
+                        &distributions,
+                        convert_distributions,
+                    ))))
+                });
+
+                Ok((Box::new(sink), healthcheck))
+            }
+        }
     }
 
     fn input_type(&self) -> DataType {
@@ -81,8 +190,24 @@ impl SinkConfig for StatsdSinkConfig {
     }
 }
 
+impl StatsdSinkConfig {
+    fn distributions(&self) -> DistributionConversion {
+        DistributionConversion {
+            distributions_as: self.distributions_as.unwrap_or_default(),
+            buckets: self.buckets.clone(),
+            quantiles: self.quantiles.clone(),
+        }
+    }
+}
+
 impl StatsdSvc {
-    pub fn new(config: StatsdSinkConfig, acker: Acker) -> crate::Result<super::RouterSink> {
+    pub fn new(
+        config: UdpConfig,
+        namespace: String,
+        distributions: DistributionConversion,
+        convert_distributions: bool,
+        acker: Acker,
+    ) -> crate::Result<super::RouterSink> {
         // 1432 bytes is a recommended packet size to fit into MTU
         // https://github.com/statsd/statsd/blob/master/docs/metric_types.md#multi-metric-packets
         // However we need to leave some space for +1 extra trailing event in the buffer.
@@ -92,7 +217,6 @@ impl StatsdSvc {
             .batch
             .use_size_as_bytes()?
             .get_settings_or_default(BatchSettings::default().bytes(1300).events(1000).timeout(1));
-        let namespace = config.namespace.clone();
 
         let client = Client::new(config.address)?;
         let service = StatsdSvc { client };
@@ -106,12 +230,19 @@ impl StatsdSvc {
             acker,
         )
         .sink_map_err(|e| error!("Fatal statsd sink error: {}", e))
-        .with_flat_map(move |event| iter_ok(encode_event(event, &namespace)));
+        .with_flat_map(move |event| {
+            iter_ok(Some(encode_frame(
+                event,
+                &namespace,
+                &distributions,
+                convert_distributions,
+            )))
+        });
 
         Ok(Box::new(sink))
     }
 
-    async fn healthcheck(_config: StatsdSinkConfig) -> crate::Result<()> {
+    async fn healthcheck() -> crate::Result<()> {
         Ok(())
     }
 }
@@ -131,72 +262,177 @@ fn encode_tags(tags: &BTreeMap<String, String>) -> String {
     parts.join(",")
 }
 
-fn encode_event(event: Event, namespace: &str) -> Option<Vec<u8>> {
+/// Formats a single `name:value|type|@rate|#tags` line, with a trailing newline.
+fn encode_line(
+    metric: &Metric,
+    namespace: &str,
+    value: &str,
+    metric_type: &str,
+    sample_rate: Option<f64>,
+) -> Vec<u8> {
     let mut buf = Vec::new();
+    if namespace.is_empty() {
+        buf.push(format!("{}:{}", metric.name, value));
+    } else {
+        buf.push(format!("{}.{}:{}", namespace, metric.name, value));
+    }
+    buf.push(metric_type.to_string());
+    if let Some(sample_rate) = sample_rate {
+        buf.push(format!("@{}", sample_rate));
+    }
+    if let Some(t) = &metric.tags {
+        buf.push(format!("#{}", encode_tags(t)));
+    };
 
+    let mut body = buf.join("|").into_bytes();
+    body.push(b'\n');
+    body
+}
+
+/// Encodes a metric into statsd lines. Sample rates are only written when
+/// they are not 1, and an incremental `AggregatedHistogram` is expanded into
+/// one sampled line per non-empty bucket, at the bucket's upper bound. An
+/// incremental `AggregatedSummary` is sent as a gauge per quantile, along with
+/// `.count` and `.sum` counters.
+fn encode_event(event: Event, namespace: &str) -> Vec<Vec<u8>> {
     let metric = event.as_metric();
-    match metric.kind {
-        MetricKind::Incremental => match &metric.value {
-            MetricValue::Counter { value } => {
-                buf.push(format!("{}:{}", metric.name, value));
-                buf.push("c".to_string());
-                if let Some(t) = &metric.tags {
-                    buf.push(format!("#{}", encode_tags(t)));
-                };
-            }
-            MetricValue::Gauge { value } => {
-                buf.push(format!("{}:{:+}", metric.name, value));
-                buf.push("g".to_string());
-                if let Some(t) = &metric.tags {
-                    buf.push(format!("#{}", encode_tags(t)));
-                };
-            }
+    let line = |value: &str, metric_type, sample_rate| {
+        encode_line(metric, namespace, value, metric_type, sample_rate)
+    };
+    let sampled = |count: u32| {
+        if count == 1 {
+            None
+        } else {
+            Some(1.0 / f64::from(count))
+        }
+    };
+
+    match (&metric.kind, &metric.value) {
+        (MetricKind::Incremental, MetricValue::Counter { value }) => {
+            vec![line(&value.to_string(), "c", None)]
+        }
+        (MetricKind::Incremental, MetricValue::Gauge { value }) => {
+            vec![line(&format!("{:+}", value), "g", None)]
+        }
+        (MetricKind::Absolute, MetricValue::Gauge { value }) if *value < 0.0 => {
+            // A leading sign makes the value a delta, so reset to zero first.
+            vec![
+                line("0", "g", None),
+                line(&format!("{:+}", value), "g", None),
+            ]
+        }
+        (MetricKind::Absolute, MetricValue::Gauge { value }) => {
+            vec![line(&value.to_string(), "g", None)]
+        }
+        (
+            MetricKind::Incremental,
             MetricValue::Distribution {
                 values,
                 sample_rates,
-            } => {
-                for (val, sample_rate) in values.iter().zip(sample_rates.iter()) {
-                    buf.push(format!("{}:{}", metric.name, val));
-                    buf.push("h".to_string());
-                    if *sample_rate != 1 {
-                        buf.push(format!("@{}", 1.0 / f64::from(*sample_rate)));
-                    };
-                    if let Some(t) = &metric.tags {
-                        buf.push(format!("#{}", encode_tags(t)));
-                    };
-                }
-            }
-            MetricValue::Set { values } => {
-                for val in values {
-                    buf.push(format!("{}:{}", metric.name, val));
-                    buf.push("s".to_string());
-                    if let Some(t) = &metric.tags {
-                        buf.push(format!("#{}", encode_tags(t)));
-                    };
-                }
-            }
-            _ => {}
-        },
-        MetricKind::Absolute => {
-            if let MetricValue::Gauge { value } = &metric.value {
-                buf.push(format!("{}:{}", metric.name, value));
-                buf.push("g".to_string());
-                if let Some(t) = &metric.tags {
-                    buf.push(format!("#{}", encode_tags(t)));
+            },
+        ) => values
+            .iter()
+            .zip(sample_rates.iter())
+            .map(|(value, sample_rate)| line(&value.to_string(), "h", sampled(*sample_rate)))
+            .collect(),
+        (MetricKind::Incremental, MetricValue::Set { values }) => values
+            .iter()
+            .map(|value| line(value.as_str(), "s", None))
+            .collect(),
+        (
+            MetricKind::Incremental,
+            MetricValue::AggregatedHistogram {
+                buckets,
+                counts,
+                count,
+                ..
+            },
+        ) => histogram_samples(buckets, counts, *count)
+            .into_iter()
+            .map(|(bucket, count)| line(&bucket.to_string(), "h", sampled(count)))
+            .collect(),
+        (
+            MetricKind::Incremental,
+            MetricValue::AggregatedSummary {
+                quantiles,
+                values,
+                count,
+                sum,
+            },
+        ) => {
+            let encode = |name: String, kind, value| {
+                let metric = Metric {
+                    name,
+                    timestamp: metric.timestamp,
+                    tags: metric.tags.clone(),
+                    kind,
+                    value,
                 };
+                encode_event(Event::Metric(metric), namespace)
             };
+            let mut lines = quantiles
+                .iter()
+                .zip(values.iter())
+                .flat_map(|(quantile, value)| {
+                    encode(
+                        quantile_name(&metric.name, *quantile),
+                        MetricKind::Absolute,
+                        MetricValue::Gauge { value: *value },
+                    )
+                })
+                .collect::<Vec<_>>();
+            lines.extend(encode(
+                format!("{}.count", metric.name),
+                MetricKind::Incremental,
+                MetricValue::Counter {
+                    value: f64::from(*count),
+                },
+            ));
+            lines.extend(encode(
+                format!("{}.sum", metric.name),
+                MetricKind::Incremental,
+                MetricValue::Counter { value: *sum },
+            ));
+            lines
+        }
+        _ => {
+            warn!(
+                message = "Metric can't be represented in statsd; dropping metric.",
+                name = %metric.name,
+                rate_limit_secs = 30,
+            );
+            Vec::new()
         }
     }
+}
 
-    let mut message: String = buf.join("|");
-    if !namespace.is_empty() {
-        message = format!("{}.{}", namespace, message);
+/// Converts distributions as configured before encoding the metric. Sketches
+/// can't be sent as statsd lines, so they are always converted.
+fn encode_metric(
+    event: Event,
+    namespace: &str,
+    distributions: &DistributionConversion,
+    convert_distributions: bool,
+) -> Vec<Vec<u8>> {
+    let metric = event.into_metric();
+    let metric = if convert_distributions {
+        distributions.convert(metric)
+    } else {
+        distributions.convert_sketch(metric)
     };
+    encode_event(Event::Metric(metric), namespace)
+}
 
-    let mut body: Vec<u8> = message.into_bytes();
-    body.push(b'\n');
-
-    Some(body)
+/// Joins the lines of an event into a single frame. Every event becomes
+/// exactly one frame, even when it has no lines, as the sink acknowledges
+/// events by the number of frames it sent.
+fn encode_frame(
+    event: Event,
+    namespace: &str,
+    distributions: &DistributionConversion,
+    convert_distributions: bool,
+) -> Vec<u8> {
+    encode_metric(event, namespace, distributions, convert_distributions).concat()
 }
 
 impl Service<Vec<u8>> for StatsdSvc {
@@ -213,7 +449,10 @@ impl Service<Vec<u8>> for StatsdSvc {
         if let Some(b'\n') = frame.last() {
             frame.pop();
         };
-        self.client.send(frame.as_ref());
+        // Frames of events that had nothing to send are empty.
+        if !frame.is_empty() {
+            self.client.send(frame.as_ref());
+        }
         future::ok(())
     }
 }
@@ -223,8 +462,8 @@ mod test {
     use super::*;
     use crate::{
         buffers::Acker,
-        event::{metric::MetricKind, metric::MetricValue, Metric},
-        test_util::{collect_n, runtime},
+        event::{metric::MetricKind, metric::MetricValue, sketch::DDSketch, Metric},
+        test_util::{collect_n, next_addr, receive, runtime},
         Event,
     };
     use bytes::Bytes;
@@ -248,6 +487,58 @@ mod test {
         .collect()
     }
 
+    #[test]
+    fn parses_config_without_mode() {
+        let config: StatsdSinkConfig = toml::from_str(
+            r#"
+            namespace = "vector"
+            address = "127.0.0.1:8125"
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            config.mode,
+            Mode::Udp(UdpConfig { address, .. }) if address == "127.0.0.1:8125".parse().unwrap()
+        ));
+
+        let config: StatsdSinkConfig = toml::from_str(r#"namespace = "vector""#).unwrap();
+        assert!(matches!(
+            config.mode,
+            Mode::Udp(UdpConfig { address, .. }) if address == default_address()
+        ));
+
+        let config: StatsdSinkConfig = toml::from_str(
+            r#"
+            namespace = "vector"
+            mode = "tcp"
+            address = "127.0.0.1:8125"
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(config.mode, Mode::Tcp(_)));
+
+        assert!(toml::from_str::<StatsdSinkConfig>(
+            r#"
+            namespace = "vector"
+            mode = "tcp"
+            "#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn reports_errors_for_configured_mode() {
+        let error = toml::from_str::<StatsdSinkConfig>(
+            r#"
+            namespace = "vector"
+            mode = "tcp"
+            adress = "127.0.0.1:8125"
+            "#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("unknown field `adress`"));
+    }
+
     #[test]
     fn test_encode_tags() {
         assert_eq!(
@@ -267,8 +558,10 @@ mod test {
             value: MetricValue::Counter { value: 1.5 },
         };
         let event = Event::Metric(metric1.clone());
-        let frame = &encode_event(event, "").unwrap();
-        let metric2 = parse(from_utf8(&frame).unwrap().trim()).unwrap();
+        let frame = encode_event(event, "").remove(0);
+        let metric2 = parse(from_utf8(&frame).unwrap().trim())
+            .unwrap()
+            .into_metric();
         assert_eq!(metric1, metric2);
     }
 
@@ -283,8 +576,10 @@ mod test {
             value: MetricValue::Gauge { value: -1.5 },
         };
         let event = Event::Metric(metric1.clone());
-        let frame = &encode_event(event, "").unwrap();
-        let metric2 = parse(from_utf8(&frame).unwrap().trim()).unwrap();
+        let frame = encode_event(event, "").remove(0);
+        let metric2 = parse(from_utf8(&frame).unwrap().trim())
+            .unwrap()
+            .into_metric();
         assert_eq!(metric1, metric2);
     }
 
@@ -302,8 +597,10 @@ mod test {
             },
         };
         let event = Event::Metric(metric1.clone());
-        let frame = &encode_event(event, "").unwrap();
-        let metric2 = parse(from_utf8(&frame).unwrap().trim()).unwrap();
+        let frame = encode_event(event, "").remove(0);
+        let metric2 = parse(from_utf8(&frame).unwrap().trim())
+            .unwrap()
+            .into_metric();
         assert_eq!(metric1, metric2);
     }
 
@@ -320,17 +617,206 @@ mod test {
             },
         };
         let event = Event::Metric(metric1.clone());
-        let frame = &encode_event(event, "").unwrap();
-        let metric2 = parse(from_utf8(&frame).unwrap().trim()).unwrap();
+        let frame = encode_event(event, "").remove(0);
+        let metric2 = parse(from_utf8(&frame).unwrap().trim())
+            .unwrap()
+            .into_metric();
         assert_eq!(metric1, metric2);
     }
 
+    fn encode_lines_str(metric: Metric, namespace: &str) -> Vec<String> {
+        encode_event(Event::Metric(metric), namespace)
+            .into_iter()
+            .map(|line| String::from_utf8(line).unwrap())
+            .collect()
+    }
+
     #[test]
-    fn test_send_to_statsd() {
+    fn test_encode_absolute_gauge() {
+        let metric = Metric {
+            name: "gauge".to_owned(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Absolute,
+            value: MetricValue::Gauge { value: -2.5 },
+        };
+        assert_eq!(
+            encode_lines_str(metric, "ns"),
+            vec!["ns.gauge:0|g\n", "ns.gauge:-2.5|g\n"]
+        );
+    }
+
+    #[test]
+    fn test_encode_sampled_distribution() {
+        let metric = Metric {
+            name: "latency".to_owned(),
+            timestamp: None,
+            tags: Some(tags()),
+            kind: MetricKind::Incremental,
+            value: MetricValue::Distribution {
+                values: vec![1.5, 3.0],
+                sample_rates: vec![1, 4],
+            },
+        };
+        assert_eq!(
+            encode_lines_str(metric, ""),
+            vec![
+                "latency:1.5|h|#empty_tag:,normal_tag:value,true_tag\n",
+                "latency:3|h|@0.25|#empty_tag:,normal_tag:value,true_tag\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encode_aggregated_histogram() {
+        let metric = Metric {
+            name: "latency".to_owned(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::AggregatedHistogram {
+                buckets: vec![1.0, 2.0, 4.0],
+                counts: vec![1, 1, 5],
+                count: 7,
+                sum: 20.0,
+            },
+        };
+        assert_eq!(
+            encode_lines_str(metric, ""),
+            vec!["latency:1|h\n", "latency:4|h|@0.25\n", "latency:4|h|@0.5\n"]
+        );
+    }
+
+    #[test]
+    fn test_encode_aggregated_summary() {
+        let metric = Metric {
+            name: "latency".to_owned(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::AggregatedSummary {
+                quantiles: vec![0.5, 0.99],
+                values: vec![-1.5, 3.0],
+                count: 7,
+                sum: 20.0,
+            },
+        };
+        assert_eq!(
+            encode_lines_str(metric, ""),
+            vec![
+                "latency.50percentile:0|g\n",
+                "latency.50percentile:-1.5|g\n",
+                "latency.99percentile:3|g\n",
+                "latency.count:7|c\n",
+                "latency.sum:20|c\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encode_distributions_as_summary() {
+        let metric = Metric {
+            name: "latency".to_owned(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Distribution {
+                values: vec![2.0],
+                sample_rates: vec![3],
+            },
+        };
+        let distributions = DistributionConversion {
+            distributions_as: DistributionsAs::Summary,
+            buckets: default_histogram_buckets(),
+            quantiles: vec![0.5],
+        };
+        let lines = encode_metric(Event::Metric(metric), "", &distributions, true)
+            .into_iter()
+            .map(|line| String::from_utf8(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("latency.50percentile:"));
+        assert_eq!(lines[1..], ["latency.count:3|c\n", "latency.sum:6|c\n"]);
+    }
+
+    #[test]
+    fn test_encode_sketch_as_histogram() {
+        let mut sketch = DDSketch::new();
+        sketch.insert_n(1.5, 3);
+        let metric = Metric {
+            name: "latency".to_owned(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Sketch { sketch },
+        };
+        let distributions = DistributionConversion {
+            distributions_as: DistributionsAs::Histogram,
+            buckets: vec![1.0, 2.0],
+            quantiles: default_summary_quantiles(),
+        };
+        let lines = encode_metric(Event::Metric(metric), "", &distributions, false)
+            .into_iter()
+            .map(|line| String::from_utf8(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec!["latency:2|h|@0.3333333333333333\n"]);
+    }
+
+    #[test]
+    fn test_encode_drops_absolute_counter() {
+        let metric = Metric {
+            name: "counter".to_owned(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Absolute,
+            value: MetricValue::Counter { value: 10.0 },
+        };
+        assert!(encode_lines_str(metric, "").is_empty());
+    }
+
+    #[test]
+    fn test_send_to_statsd_over_tcp() {
         crate::test_util::trace_init();
 
+        let addr = next_addr();
         let config = StatsdSinkConfig {
             namespace: "vector".into(),
+            mode: Mode::Tcp(TcpConfig {
+                address: addr.to_string(),
+                tls: None,
+            }),
+            distributions_as: None,
+            buckets: default_histogram_buckets(),
+            quantiles: default_summary_quantiles(),
+        };
+
+        let mut rt = runtime();
+        let (sink, _healthcheck) = config.build(SinkContext::new_test()).unwrap();
+        let receiver = receive(&addr);
+
+        let event = Event::Metric(Metric {
+            name: "counter".to_owned(),
+            timestamp: None,
+            tags: Some(tags()),
+            kind: MetricKind::Incremental,
+            value: MetricValue::Counter { value: 1.5 },
+        });
+        let _ = rt.block_on(sink.send(event)).unwrap();
+
+        // Some CI machines are very slow, be generous.
+        std::thread::sleep(Duration::from_secs(2));
+
+        assert_eq!(
+            receiver.wait(),
+            vec!["vector.counter:1.5|c|#empty_tag:,normal_tag:value,true_tag"]
+        );
+    }
+
+    #[test]
+    fn test_send_to_statsd() {
+        crate::test_util::trace_init();
+
+        let config = UdpConfig {
             address: default_address(),
             batch: BatchConfig {
                 max_bytes: Some(512),
@@ -340,7 +826,18 @@ mod test {
         };
 
         let mut rt = runtime();
-        let sink = StatsdSvc::new(config, Acker::Null).unwrap();
+        let sink = StatsdSvc::new(
+            config,
+            "vector".into(),
+            DistributionConversion {
+                distributions_as: DistributionsAs::Histogram,
+                buckets: default_histogram_buckets(),
+                quantiles: default_summary_quantiles(),
+            },
+            false,
+            Acker::Null,
+        )
+        .unwrap();
 
         let mut events = Vec::new();
         let event = Event::Metric(Metric {
@@ -273,7 +273,10 @@ mod test {
     use crate::sinks::util::{BatchSink, BatchSize};
     use crate::{
         buffers::Acker,
-        event::metric::{Metric, MetricValue},
+        event::{
+            metric::{Metric, MetricValue},
+            sketch::DDSketch,
+        },
         runtime::Runtime,
         test_util::runtime,
         Event,
@@ -853,6 +856,54 @@ mod test {
         );
     }
 
+    #[test]
+    fn metric_buffer_sketches() {
+        let (sink, _rt, mut clock, sent_batches) = sink();
+
+        let sketch = |values: &[f64]| {
+            let mut sketch = DDSketch::new();
+            for value in values {
+                sketch.insert(*value);
+            }
+            MetricValue::Sketch { sketch }
+        };
+
+        let events = vec![1.0, 2.0, 3.0]
+            .into_iter()
+            .map(|value| {
+                Event::Metric(Metric {
+                    name: "sketch".into(),
+                    timestamp: None,
+                    tags: Some(tag("production")),
+                    kind: MetricKind::Incremental,
+                    value: sketch(&[value]),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let (sink, _) = clock.enter(|_| {
+            sink.sink_map_err(drop)
+                .send_all(futures01::stream::iter_ok(events.into_iter()))
+                .wait()
+                .unwrap()
+        });
+        drop(sink);
+
+        let buffer = Arc::try_unwrap(sent_batches).unwrap().into_inner().unwrap();
+
+        assert_eq!(buffer.len(), 1);
+        assert_eq!(
+            buffer[0],
+            [Metric {
+                name: "sketch".into(),
+                timestamp: None,
+                tags: Some(tag("production")),
+                kind: MetricKind::Incremental,
+                value: sketch(&[1.0, 2.0, 3.0]),
+            }]
+        );
+    }
+
     #[test]
     fn metric_buffer_compress_distribution() {
         let values = vec![2.0, 2.0, 3.0, 1.0, 2.0, 2.0, 3.0];
@@ -0,0 +1,262 @@
+use super::{
+    batch::{Batch, PushResult},
+    retries2::{RetryAction, RetryLogic},
+};
+use crate::{dead_letter::DeadLetters, event::Event};
+use futures01::{
+    future::{self, Either},
+    Future, Poll,
+};
+use std::fmt;
+use tower::Service;
+
+/// Wraps a `Batch` to keep the original events of the batch alongside the
+/// encoded ones, so that they can be handed to the sink's dead letter
+/// destination if the request carrying them fails. Events are only kept when
+/// they are passed in, which sinks do when a destination is configured.
+#[derive(Clone, Debug)]
+pub struct DeadLetterBatch<B> {
+    inner: B,
+    events: Vec<Event>,
+}
+
+impl<B> DeadLetterBatch<B> {
+    pub fn new(inner: B) -> Self {
+        Self {
+            inner,
+            events: Vec::new(),
+        }
+    }
+}
+
+impl<B: Batch> Batch for DeadLetterBatch<B> {
+    type Input = (B::Input, Option<Event>);
+    type Output = (B::Output, Vec<Event>);
+
+    fn push(&mut self, (item, event): Self::Input) -> PushResult<Self::Input> {
+        match self.inner.push(item) {
+            PushResult::Overflow(item) => PushResult::Overflow((item, event)),
+            PushResult::Ok(full) => {
+                self.events.extend(event);
+                PushResult::Ok(full)
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    fn fresh(&self) -> Self {
+        Self::new(self.inner.fresh())
+    }
+
+    fn finish(self) -> Self::Output {
+        (self.inner.finish(), self.events)
+    }
+
+    fn num_items(&self) -> usize {
+        self.inner.num_items()
+    }
+}
+
+/// A service that sends the events of every request that finally failed,
+/// either with an error or a response the retry logic doesn't consider
+/// successful, to the sink's dead letter destination.
+#[derive(Clone)]
+pub struct DeadLetterService<S, L> {
+    inner: S,
+    logic: L,
+    dead_letters: Option<DeadLetters>,
+}
+
+impl<S, L> DeadLetterService<S, L> {
+    pub fn new(inner: S, logic: L, dead_letters: Option<DeadLetters>) -> Self {
+        Self {
+            inner,
+            logic,
+            dead_letters,
+        }
+    }
+}
+
+impl<S, L, Request> Service<(Request, Vec<Event>)> for DeadLetterService<S, L>
+where
+    S: Service<Request>,
+    S::Future: Send + 'static,
+    S::Response: Send + 'static,
+    S::Error: fmt::Display + Send + 'static,
+    L: RetryLogic<Response = S::Response> + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Box<dyn Future<Item = Self::Response, Error = Self::Error> + Send + 'static>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, (request, events): (Request, Vec<Event>)) -> Self::Future {
+        let response = self.inner.call(request);
+
+        let dead_letters = match &self.dead_letters {
+            Some(dead_letters) if !events.is_empty() => dead_letters.clone(),
+            _ => return Box::new(response),
+        };
+
+        let logic = self.logic.clone();
+        Box::new(response.then(move |result| {
+            let mut events = events;
+            let reason = match &result {
+                Ok(response) => match logic.should_retry_response(response) {
+                    RetryAction::Successful => None,
+                    RetryAction::Retry(reason) | RetryAction::DontRetry(reason) => {
+                        // Items the destination did accept must not be sent
+                        // again, or replaying the dead letters duplicates them.
+                        if let Some(failed) = logic.failed_items(response) {
+                            events = events
+                                .into_iter()
+                                .enumerate()
+                                .filter(|(index, _)| failed.contains(index))
+                                .map(|(_, event)| event)
+                                .collect();
+                        }
+                        Some(reason)
+                    }
+                },
+                Err(error) => Some(error.to_string()),
+            };
+
+            // The request only completes once its events are handed over, so
+            // that the sink waits for a slow dead letter destination.
+            match reason {
+                Some(reason) if !events.is_empty() => {
+                    Either::A(dead_letters.send(events, &reason).then(|_| result))
+                }
+                _ => Either::B(future::result(result)),
+            }
+        }))
+    }
+}
+
+impl<S: fmt::Debug, L> fmt::Debug for DeadLetterService<S, L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeadLetterService")
+            .field("inner", &self.inner)
+            .field("dead_letters", &self.dead_letters)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dead_letter::REASON_FIELD;
+    use crate::sinks::util::VecBuffer;
+    use crate::test_util::block_on;
+    use futures01::{future, Stream};
+
+    #[derive(Clone, Debug)]
+    struct StatusLogic;
+
+    #[derive(Debug, snafu::Snafu)]
+    #[snafu(display("never"))]
+    struct Never;
+
+    impl RetryLogic for StatusLogic {
+        type Error = Never;
+        type Response = u16;
+
+        fn is_retriable_error(&self, _error: &Self::Error) -> bool {
+            false
+        }
+
+        fn should_retry_response(&self, response: &Self::Response) -> RetryAction {
+            match response {
+                200 => RetryAction::Successful,
+                status => RetryAction::DontRetry(format!("response status: {}", status)),
+            }
+        }
+
+        fn failed_items(&self, response: &Self::Response) -> Option<Vec<usize>> {
+            match response {
+                207 => Some(vec![1]),
+                _ => None,
+            }
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct StatusService;
+
+    impl Service<u16> for StatusService {
+        type Response = u16;
+        type Error = String;
+        type Future = future::FutureResult<u16, String>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(().into())
+        }
+
+        fn call(&mut self, status: u16) -> Self::Future {
+            match status {
+                0 => future::err("connection refused".into()),
+                status => future::ok(status),
+            }
+        }
+    }
+
+    #[test]
+    fn batch_keeps_events() {
+        let mut batch = DeadLetterBatch::new(VecBuffer::new(crate::sinks::util::BatchSize {
+            events: 2,
+            bytes: usize::max_value(),
+        }));
+
+        assert_eq!(
+            batch.push((1, Some(Event::from("a")))),
+            PushResult::Ok(false)
+        );
+        assert_eq!(batch.push((2, None)), PushResult::Ok(true));
+        assert!(matches!(
+            batch.push((3, None)),
+            PushResult::Overflow((3, None))
+        ));
+
+        let (items, events) = batch.finish();
+        assert_eq!(items, vec![1, 2]);
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn failed_requests_are_dead_lettered() {
+        let (dead_letters, rx) = DeadLetters::new("out");
+        let mut service = DeadLetterService::new(StatusService, StatusLogic, Some(dead_letters));
+
+        block_on(service.call((200, vec![Event::from("ok")]))).unwrap();
+        block_on(service.call((400, vec![Event::from("rejected")]))).unwrap();
+        block_on(service.call((0, vec![Event::from("failed")]))).unwrap_err();
+        drop(service);
+
+        let events = block_on(rx.collect()).unwrap();
+        let reasons = events
+            .iter()
+            .map(|event| event.as_log()[&REASON_FIELD.into()].to_string_lossy())
+            .collect::<Vec<_>>();
+        assert_eq!(reasons, vec!["response status: 400", "connection refused"]);
+    }
+
+    #[test]
+    fn only_failed_items_are_dead_lettered() {
+        let (dead_letters, rx) = DeadLetters::new("out");
+        let mut service = DeadLetterService::new(StatusService, StatusLogic, Some(dead_letters));
+
+        let events = vec![Event::from("a"), Event::from("b"), Event::from("c")];
+        block_on(service.call((207, events))).unwrap();
+        drop(service);
+
+        let events = block_on(rx.collect()).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].as_log()[&"message".into()], "b".into());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::event::metric::{Metric, MetricValue};
+use serde::{Deserialize, Serialize};
+
+/// What sinks that can't encode raw samples convert distributions into.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistributionsAs {
+    Histogram,
+    Summary,
+}
+
+impl Default for DistributionsAs {
+    fn default() -> Self {
+        DistributionsAs::Histogram
+    }
+}
+
+pub fn default_histogram_buckets() -> Vec<f64> {
+    vec![
+        0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+    ]
+}
+
+pub fn default_summary_quantiles() -> Vec<f64> {
+    vec![0.5, 0.75, 0.9, 0.95, 0.99]
+}
+
+/// Converts distributions and sketches into aggregated histograms or
+/// summaries, leaving every other metric as it is.
+#[derive(Clone, Debug)]
+pub struct DistributionConversion {
+    pub distributions_as: DistributionsAs,
+    pub buckets: Vec<f64>,
+    pub quantiles: Vec<f64>,
+}
+
+impl DistributionConversion {
+    pub fn convert(&self, metric: Metric) -> Metric {
+        let value = match self.distributions_as {
+            DistributionsAs::Histogram => metric.value.distribution_to_histogram(&self.buckets),
+            DistributionsAs::Summary => metric.value.distribution_to_summary(&self.quantiles),
+        };
+        match value {
+            Some(value) => Metric { value, ..metric },
+            None => metric,
+        }
+    }
+
+    /// Converts sketches only, for sinks that can send distributions as they
+    /// are but have no way to encode a sketch.
+    pub fn convert_sketch(&self, metric: Metric) -> Metric {
+        match metric.value {
+            MetricValue::Sketch { .. } => self.convert(metric),
+            _ => metric,
+        }
+    }
+}
+
+/// Splits the cumulative counts of an aggregated histogram into the number of
+/// samples in each bucket, at the bucket's upper bound. Samples above the
+/// largest bucket are counted at its bound, and empty buckets are skipped.
+pub fn histogram_samples(buckets: &[f64], counts: &[u32], count: u32) -> Vec<(f64, u32)> {
+    let mut previous = 0;
+    let mut samples = buckets
+        .iter()
+        .zip(counts.iter())
+        .filter_map(|(bucket, cumulative)| {
+            let count = cumulative.saturating_sub(previous);
+            previous = *cumulative;
+            if count > 0 {
+                Some((*bucket, count))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let overflow = count.saturating_sub(previous);
+    if let (Some(bucket), true) = (buckets.last(), overflow > 0) {
+        samples.push((*bucket, overflow));
+    }
+    samples
+}
+
+/// The name of the metric that carries one quantile of a summary, for sinks
+/// that send each quantile on its own, like `latency.99percentile`.
+pub fn quantile_name(name: &str, quantile: f64) -> String {
+    format!("{}.{}percentile", name, (quantile * 1000.0).round() / 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_histogram_buckets() {
+        assert_eq!(
+            histogram_samples(&[1.0, 2.0, 4.0], &[1, 1, 3], 5),
+            vec![(1.0, 1), (4.0, 2), (4.0, 2)]
+        );
+    }
+
+    #[test]
+    fn names_quantiles() {
+        assert_eq!(quantile_name("latency", 0.5), "latency.50percentile");
+        assert_eq!(quantile_name("latency", 0.95), "latency.95percentile");
+        assert_eq!(quantile_name("latency", 0.999), "latency.99.9percentile");
+    }
+}
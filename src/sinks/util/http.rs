@@ -1,7 +1,8 @@
 use super::{
+    dead_letter::{DeadLetterBatch, DeadLetterService},
     retries2::{RetryAction, RetryLogic},
-    service2::{TowerBatchedSink, TowerRequestSettings},
-    sink, Batch,
+    service2::{Svc, TowerCompat, TowerRequestSettings},
+    sink, Batch, BatchSink,
 };
 use crate::{
     dns::Resolver,
@@ -32,6 +33,10 @@ use tracing_futures::Instrument;
 
 pub type HttpClientFuture = <HttpClient as Service<http::Request<Body>>>::Future;
 
+type HttpRequestBuilderFuture = BoxFuture<'static, crate::Result<hyper::Request<Vec<u8>>>>;
+type BatchedHttpService<O, L> =
+    DeadLetterService<TowerCompat<Svc<HttpBatchService<HttpRequestBuilderFuture, O>, L>>, L>;
+
 #[async_trait::async_trait]
 pub trait HttpSink: Send + Sync + 'static {
     type Input;
@@ -61,16 +66,14 @@ where
     L: RetryLogic<Response = http::Response<Bytes>> + Send + 'static,
 {
     sink: Arc<T>,
-    inner: TowerBatchedSink<
-        HttpBatchService<BoxFuture<'static, crate::Result<hyper::Request<Vec<u8>>>>, B::Output>,
-        B,
-        L,
-        B::Output,
-    >,
+    inner: BatchSink<BatchedHttpService<B::Output, L>, DeadLetterBatch<B>, (B::Output, Vec<Event>)>,
+    // Events are only kept around for requests that may fail when the sink
+    // has a dead letter destination.
+    keep_events: bool,
     // An empty slot is needed to buffer an item where we encoded it but
     // the inner sink is applying back pressure. This trick is used in the `WithFlatMap`
     // sink combinator. https://docs.rs/futures/0.1.29/src/futures/sink/with_flat_map.rs.html#20
-    slot: Option<B::Input>,
+    slot: Option<(B::Input, Option<Event>)>,
 }
 
 impl<T, B> BatchedHttpSink<T, B, HttpRetryLogic>
@@ -125,11 +128,17 @@ where
             };
 
         let svc = HttpBatchService::new(cx.resolver(), tls_settings, request_builder);
-        let inner = request_settings.batch_sink(logic, svc, batch, batch_timeout, cx.acker());
+        let svc = request_settings.service(logic.clone(), svc);
+
+        let dead_letters = cx.dead_letters();
+        let keep_events = dead_letters.is_some();
+        let svc = DeadLetterService::new(svc, logic, dead_letters);
+        let inner = BatchSink::new(svc, DeadLetterBatch::new(batch), batch_timeout, cx.acker());
 
         Self {
             sink,
             inner,
+            keep_events,
             slot: None,
         }
     }
@@ -151,8 +160,14 @@ where
         }
         assert!(self.slot.is_none(), "poll_complete did not clear slot");
 
+        let event = if self.keep_events {
+            Some(item.clone())
+        } else {
+            None
+        };
+
         if let Some(item) = self.sink.encode_event(item) {
-            self.slot = Some(item);
+            self.slot = Some((item, event));
             self.poll_complete()?;
         }
 
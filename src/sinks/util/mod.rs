@@ -1,5 +1,7 @@
 pub mod batch;
 pub mod buffer;
+pub mod dead_letter;
+pub mod distribution;
 pub mod encoding;
 pub mod http;
 pub mod retries;
@@ -30,12 +32,13 @@ pub use buffer::partition::Partition;
 pub use buffer::vec::VecBuffer;
 pub use buffer::vec2::{Length, VecBuffer2};
 pub use buffer::{Buffer, Compression, PartitionBuffer, PartitionInnerBuffer};
+pub use distribution::{DistributionConversion, DistributionsAs};
 pub use service::{ServiceBuilderExt, TowerRequestConfig, TowerRequestLayer, TowerRequestSettings};
 pub use sink::{BatchSink, PartitionBatchSink, StreamSink};
 pub use uri::UriSerde;
 
 #[derive(Debug, Snafu)]
-enum SinkBuildError {
+pub enum SinkBuildError {
     #[snafu(display("Missing host in address field"))]
     MissingHost,
     #[snafu(display("Missing port in address field"))]
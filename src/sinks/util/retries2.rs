@@ -29,6 +29,13 @@ pub trait RetryLogic: Clone {
         // Treat the default as the request is successful
         RetryAction::Successful
     }
+
+    /// Returns the positions of the items of the request that failed, for
+    /// responses that report on every item separately, like bulk APIs do.
+    /// `None` means the response applies to the request as a whole.
+    fn failed_items(&self, _response: &Self::Response) -> Option<Vec<usize>> {
+        None
+    }
 }
 
 #[derive(Debug, Clone)]
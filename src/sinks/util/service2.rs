@@ -139,6 +139,21 @@ impl TowerRequestSettings {
         S::Future: Send + 'static,
         B: Batch<Output = Request>,
         Request: Send + Clone + 'static,
+    {
+        let service = self.service(retry_logic, service);
+        BatchSink::new(service, batch, batch_timeout, acker)
+    }
+
+    /// Applies the rate limit, retry, concurrency and timeout settings to
+    /// `service`, for sinks that need to wrap it further before batching.
+    pub fn service<L, S, Request>(&self, retry_logic: L, service: S) -> TowerCompat<Svc<S, L>>
+    where
+        L: RetryLogic<Response = S::Response> + Send + 'static,
+        S: Service<Request> + Clone + Send + 'static,
+        S::Error: Into<crate::Error> + Send + Sync + 'static,
+        S::Response: Send + Response,
+        S::Future: Send + 'static,
+        Request: Send + Clone + 'static,
     {
         let policy = self.retry_policy(retry_logic);
         let service = ServiceBuilder::new()
@@ -148,8 +163,7 @@ impl TowerRequestSettings {
             .timeout(self.timeout)
             .service(service);
 
-        let service = TowerCompat::new(service);
-        BatchSink::new(service, batch, batch_timeout, acker)
+        TowerCompat::new(service)
     }
 }
 
@@ -53,7 +53,7 @@ enum HealthcheckError {
     ConnectError { source: std::io::Error },
 }
 
-fn unix_healthcheck(path: PathBuf) -> Healthcheck {
+pub fn unix_healthcheck(path: PathBuf) -> Healthcheck {
     // Lazy to avoid immediately connecting
     let check = future::lazy(move || {
         UnixStream::connect(&path)
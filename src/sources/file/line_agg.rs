@@ -68,21 +68,21 @@ impl Config {
     }
 }
 
-pub(super) struct LineAgg<T, K> {
+pub(super) struct LineAgg<T, K, C> {
     /// The stream from which we read the lines.
     inner: T,
 
     /// Configuration parameters to use.
     config: Config,
 
-    /// Line per key.
+    /// Line per key, along with the context of the last line added to it.
     /// Key is usually a filename or other line source identifier.
-    buffers: HashMap<K, BytesMut>,
+    buffers: HashMap<K, (BytesMut, C)>,
 
     /// Draining queue. We switch to draining mode when we get `None` from
     /// the inner stream. In this mode we stop polling `inner` for new lines
     /// and just flush all the buffered data.
-    draining: Option<Vec<(Bytes, K)>>,
+    draining: Option<Vec<(Bytes, K, C)>>,
 
     /// A queue of key timeouts.
     timeouts: DelayQueue<K>,
@@ -91,7 +91,7 @@ pub(super) struct LineAgg<T, K> {
     expired: VecDeque<K>,
 }
 
-impl<T, K> LineAgg<T, K>
+impl<T, K, C> LineAgg<T, K, C>
 where
     K: Hash + Eq + Clone,
 {
@@ -109,21 +109,23 @@ where
     }
 }
 
-impl<T, K> Stream for LineAgg<T, K>
+impl<T, K, C> Stream for LineAgg<T, K, C>
 where
-    T: Stream<Item = (Bytes, K), Error = ()>,
+    T: Stream<Item = (Bytes, K, C), Error = ()>,
     K: Hash + Eq + Clone,
 {
-    /// `Bytes` - the line data; `K` - file name, or other line source.
-    type Item = (Bytes, K);
+    /// `Bytes` - the line data; `K` - file name, or other line source;
+    /// `C` - context of the last line that went into the data, such as the
+    /// position in the file after it.
+    type Item = (Bytes, K, C);
     type Error = ();
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
         loop {
             // If we're in draining mode, short circut here.
             if let Some(to_drain) = &mut self.draining {
-                if let Some((line, src)) = to_drain.pop() {
-                    return Ok(Async::Ready(Some((line, src))));
+                if let Some(val) = to_drain.pop() {
+                    return Ok(Async::Ready(Some(val)));
                 } else {
                     return Ok(Async::Ready(None));
                 }
@@ -135,26 +137,30 @@ where
             }
 
             match self.inner.poll() {
-                Ok(Async::Ready(Some((line, src)))) => {
+                Ok(Async::Ready(Some((line, src, context)))) => {
                     // Handle the incoming line we got from `inner`. If the
                     // handler gave us something - return it, otherwise continue
                     // with the flow.
-                    if let Some(val) = self.handle_line(line, src) {
+                    if let Some(val) = self.handle_line(line, src, context) {
                         return Ok(Async::Ready(Some(val)));
                     }
                 }
                 Ok(Async::Ready(None)) => {
                     // We got `None`, this means the `inner` stream has ended.
                     // Start flushing all existing data, stop polling `inner`.
-                    self.draining =
-                        Some(self.buffers.drain().map(|(k, v)| (v.into(), k)).collect());
+                    self.draining = Some(
+                        self.buffers
+                            .drain()
+                            .map(|(k, (v, context))| (v.into(), k, context))
+                            .collect(),
+                    );
                 }
                 Ok(Async::NotReady) => {
                     // We didn't get any lines from `inner`, so we just give
                     // a line from the expired lines queue.
                     if let Some(key) = self.expired.pop_front() {
-                        if let Some(buffered) = self.buffers.remove(&key) {
-                            return Ok(Async::Ready(Some((buffered.freeze(), key))));
+                        if let Some((buffered, context)) = self.buffers.remove(&key) {
+                            return Ok(Async::Ready(Some((buffered.freeze(), key, context))));
                         }
                     }
 
@@ -166,13 +172,13 @@ where
     }
 }
 
-impl<T, K> LineAgg<T, K>
+impl<T, K, C> LineAgg<T, K, C>
 where
-    T: Stream<Item = (Bytes, K), Error = ()>,
+    T: Stream<Item = (Bytes, K, C), Error = ()>,
     K: Hash + Eq + Clone,
 {
     /// Handle line, if we have something to output - return it.
-    fn handle_line(&mut self, line: Bytes, src: K) -> Option<(Bytes, K)> {
+    fn handle_line(&mut self, line: Bytes, src: K, context: C) -> Option<(Bytes, K, C)> {
         // Check if we already have the buffered data for the source.
         match self.buffers.entry(src) {
             Entry::Occupied(mut entry) => {
@@ -182,36 +188,36 @@ where
                     // the group.
                     Mode::ContinueThrough => {
                         if condition_matched {
-                            let buffered = entry.get_mut();
-                            add_next_line(buffered, line);
+                            add_next_line(entry.get_mut(), line, context);
                             None
                         } else {
-                            let buffered = entry.insert(line.as_ref().into());
-                            Some((buffered.freeze(), entry.key().clone()))
+                            let (buffered, buffered_context) =
+                                entry.insert((line.as_ref().into(), context));
+                            Some((buffered.freeze(), entry.key().clone(), buffered_context))
                         }
                     }
                     // All consecutive lines matching this pattern, plus one
                     // additional line, are included in the group.
                     Mode::ContinuePast => {
                         if condition_matched {
-                            let buffered = entry.get_mut();
-                            add_next_line(buffered, line);
+                            add_next_line(entry.get_mut(), line, context);
                             None
                         } else {
                             let (src, mut buffered) = entry.remove_entry();
-                            add_next_line(&mut buffered, line);
-                            Some((buffered.freeze(), src))
+                            add_next_line(&mut buffered, line, context);
+                            let (buffered, context) = buffered;
+                            Some((buffered.freeze(), src, context))
                         }
                     }
                     // All consecutive lines not matching this pattern are included
                     // in the group.
                     Mode::HaltBefore => {
                         if condition_matched {
-                            let buffered = entry.insert(line.as_ref().into());
-                            Some((buffered.freeze(), entry.key().clone()))
+                            let (buffered, buffered_context) =
+                                entry.insert((line.as_ref().into(), context));
+                            Some((buffered.freeze(), entry.key().clone(), buffered_context))
                         } else {
-                            let buffered = entry.get_mut();
-                            add_next_line(buffered, line);
+                            add_next_line(entry.get_mut(), line, context);
                             None
                         }
                     }
@@ -220,11 +226,11 @@ where
                     Mode::HaltWith => {
                         if condition_matched {
                             let (src, mut buffered) = entry.remove_entry();
-                            add_next_line(&mut buffered, line);
-                            Some((buffered.freeze(), src))
+                            add_next_line(&mut buffered, line, context);
+                            let (buffered, context) = buffered;
+                            Some((buffered.freeze(), src, context))
                         } else {
-                            let buffered = entry.get_mut();
-                            add_next_line(buffered, line);
+                            add_next_line(entry.get_mut(), line, context);
                             None
                         }
                     }
@@ -237,20 +243,21 @@ where
                     // Set the timeout and buffer this line.
                     self.timeouts
                         .insert(entry.key().clone(), self.config.timeout);
-                    entry.insert(line.as_ref().into());
+                    entry.insert((line.as_ref().into(), context));
                     None
                 } else {
                     // It's just a regular line we don't really care about.
-                    Some((line, entry.into_key()))
+                    Some((line, entry.into_key(), context))
                 }
             }
         }
     }
 }
 
-fn add_next_line(buffered: &mut BytesMut, line: Bytes) {
+fn add_next_line<C>((buffered, buffered_context): &mut (BytesMut, C), line: Bytes, context: C) {
     buffered.extend_from_slice(b"\n");
     buffered.extend_from_slice(&line);
+    *buffered_context = context;
 }
 
 #[cfg(test)]
@@ -467,27 +474,35 @@ mod tests {
 
     fn stream_from_lines<'a>(
         lines: &'a [&'static str],
-    ) -> impl Stream<Item = (Bytes, Filename), Error = ()> + 'a {
-        futures01::stream::iter_ok::<_, ()>(
-            lines
-                .iter()
-                .map(|line| (Bytes::from_static(line.as_bytes()), "test.log".to_owned())),
-        )
+    ) -> impl Stream<Item = (Bytes, Filename, ()), Error = ()> + 'a {
+        futures01::stream::iter_ok::<_, ()>(lines.iter().map(|line| {
+            (
+                Bytes::from_static(line.as_bytes()),
+                "test.log".to_owned(),
+                (),
+            )
+        }))
     }
 
-    fn collect_results<T, K>(line_agg: LineAgg<T, K>) -> Vec<(Bytes, K)>
+    fn collect_results<T, K, C>(line_agg: LineAgg<T, K, C>) -> Vec<(Bytes, K, C)>
     where
-        T: Stream<Item = (Bytes, K), Error = ()>,
+        T: Stream<Item = (Bytes, K, C), Error = ()>,
         K: Hash + Eq + Clone,
     {
         futures01::future::Future::wait(futures01::stream::Stream::collect(line_agg))
             .expect("Failed to collect test results")
     }
 
-    fn assert_results(actual: Vec<(Bytes, Filename)>, expected: &[&'static str]) {
-        let expected_mapped: Vec<(Bytes, Filename)> = expected
+    fn assert_results(actual: Vec<(Bytes, Filename, ())>, expected: &[&'static str]) {
+        let expected_mapped: Vec<(Bytes, Filename, ())> = expected
             .iter()
-            .map(|line| (Bytes::from_static(line.as_bytes()), "test.log".to_owned()))
+            .map(|line| {
+                (
+                    Bytes::from_static(line.as_bytes()),
+                    "test.log".to_owned(),
+                    (),
+                )
+            })
             .collect();
 
         assert_eq!(actual, expected_mapped);
@@ -1,14 +1,16 @@
 use crate::{
-    event::{self, Event},
+    event::{self, finalization::OrderedAcker, Event},
     internal_events::FileEventReceived,
     shutdown::ShutdownSignal,
+    sources::util::EncodingConfig,
     topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
     trace::{current_span, Instrument},
 };
 use bytes05::Bytes;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
 use file_source::{
     paths_provider::glob::{Glob, MatchOptions},
-    FileServer, Fingerprinter,
+    ByteOrderMark, Checkpoint, CheckpointsView, FileServer, Fingerprinter, ReadScheduling,
 };
 use futures::{
     compat::{Compat01As03Sink, Future01CompatExt},
@@ -44,6 +46,8 @@ enum BuildError {
     MissingDataDir { data_dir: PathBuf },
     #[snafu(display("data_dir {:?} is not writable", data_dir))]
     DataDirNotWritable { data_dir: PathBuf },
+    #[snafu(display("max_bytes_per_sec must be greater than zero"))]
+    ZeroMaxBytesPerSec,
     #[snafu(display(
         "message_start_indicator {:?} is not a valid regex: {}",
         indicator,
@@ -92,7 +96,13 @@ pub struct FileConfig {
     pub multiline: Option<MultilineConfig>,
     pub max_read_bytes: usize,
     pub oldest_first: bool,
+    pub read_scheduling: ReadScheduling,
+    pub max_bytes_per_sec: Option<u64>,
     pub remove_after: Option<u64>,
+    pub encoding: EncodingConfig,
+    /// Only advance the checkpoint of a file once the lines read up to it
+    /// have been acknowledged by the sinks.
+    pub acknowledgements: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -182,7 +192,11 @@ impl Default for FileConfig {
             multiline: None,
             max_read_bytes: 2048,
             oldest_first: false,
+            read_scheduling: ReadScheduling::Sequential,
+            max_bytes_per_sec: None,
             remove_after: None,
+            encoding: EncodingConfig::default(),
+            acknowledgements: false,
         }
     }
 }
@@ -210,6 +224,10 @@ impl SourceConfig for FileConfig {
             let _: line_agg::Config = config.try_into()?;
         }
 
+        if self.max_bytes_per_sec == Some(0) {
+            return Err(Box::new(BuildError::ZeroMaxBytesPerSec));
+        }
+
         if let Some(ref indicator) = self.message_start_indicator {
             Regex::new(indicator).with_context(|| InvalidMessageStartIndicator { indicator })?;
         }
@@ -240,17 +258,28 @@ pub fn file_source(
     let paths_provider = Glob::new(&config.include, &config.exclude, MatchOptions::default())
         .expect("invalid glob patterns");
 
+    let checkpoints = if config.acknowledgements {
+        Some(CheckpointsView::default())
+    } else {
+        None
+    };
+
     let file_server = FileServer {
         paths_provider,
         max_read_bytes: config.max_read_bytes,
         start_at_beginning: config.start_at_beginning,
         ignore_before,
         max_line_bytes: config.max_line_bytes,
+        line_delimiter: Bytes::from_static(config.encoding.line_delimiter()),
+        detect_byte_order_mark: config.encoding.charset.is_some(),
         data_dir,
         glob_minimum_cooldown,
         fingerprinter: config.fingerprinting.clone().into(),
         oldest_first: config.oldest_first,
+        read_scheduling: config.read_scheduling,
+        max_bytes_per_sec: config.max_bytes_per_sec,
         remove_after: config.remove_after.map(Duration::from_secs),
+        checkpoints: checkpoints.clone(),
     };
 
     let file_key = config.file_key.clone();
@@ -265,13 +294,37 @@ pub fn file_source(
     let multiline_config = config.multiline.clone();
     let message_start_indicator = config.message_start_indicator.clone();
     let multi_line_timeout = config.multi_line_timeout;
+    let encoding = config.encoding.clone();
     Box::new(future::lazy(move || {
         info!(message = "Starting file server.", ?include, ?exclude);
 
         // sizing here is just a guess
         let (tx, rx) = futures01::sync::mpsc::channel(100);
 
-        let messages: Box<dyn Stream<Item = (Bytes, String), Error = ()> + Send> =
+        // Lines are framed on the encoded delimiter, so that checkpoints keep
+        // pointing into the file as it is, and transcoded before anything
+        // looks at their contents.
+        let rx = rx.map(
+            move |(line, file, checkpoint, byte_order_mark): (
+                Bytes,
+                String,
+                Checkpoint,
+                Option<ByteOrderMark>,
+            )| {
+                let charset = byte_order_mark.map(byte_order_mark_charset);
+                (encoding.transcode(line, charset), file, checkpoint)
+            },
+        );
+
+        let acker = checkpoints.map(|checkpoints| {
+            let (acker, task) = OrderedAcker::new(move |(fingerprint, position): Checkpoint| {
+                checkpoints.update(fingerprint, position)
+            });
+            tokio01::spawn(task);
+            acker
+        });
+
+        let messages: Box<dyn Stream<Item = (Bytes, String, Checkpoint), Error = ()> + Send> =
             if let Some(ref multiline_config) = multiline_config {
                 Box::new(LineAgg::new(
                     rx,
@@ -295,14 +348,20 @@ pub fn file_source(
         let span2 = span.clone();
         tokio01::spawn(
             messages
-                .map(move |(msg, file): (Bytes, String)| {
-                    let _enter = span2.enter();
-                    emit!(FileEventReceived {
-                        file: &file,
-                        byte_size: msg.len(),
-                    });
-                    create_event(msg, file, &host_key, &hostname, &file_key)
-                })
+                .map(
+                    move |(msg, file, checkpoint): (Bytes, String, Checkpoint)| {
+                        let _enter = span2.enter();
+                        emit!(FileEventReceived {
+                            file: &file,
+                            byte_size: msg.len(),
+                        });
+                        let mut event = create_event(msg, file, &host_key, &hostname, &file_key);
+                        if let Some(acker) = &acker {
+                            event.as_mut_log().add_finalizer(acker.add(checkpoint));
+                        }
+                        event
+                    },
+                )
                 .forward(out.sink_map_err(|e| error!(%e)))
                 .map(|_| ())
                 .instrument(span),
@@ -323,6 +382,16 @@ pub fn file_source(
     }))
 }
 
+/// A file that starts with a byte order mark is in the charset it marks,
+/// whichever one is configured.
+fn byte_order_mark_charset(byte_order_mark: ByteOrderMark) -> &'static Encoding {
+    match byte_order_mark {
+        ByteOrderMark::Utf8 => UTF_8,
+        ByteOrderMark::Utf16Le => UTF_16LE,
+        ByteOrderMark::Utf16Be => UTF_16BE,
+    }
+}
+
 fn create_event(
     line: Bytes,
     file: String,
@@ -481,6 +550,38 @@ mod tests {
         assert_eq!(log[event::log_schema().source_type_key()], "file".into());
     }
 
+    #[test]
+    fn file_acknowledgements_attach_finalizers() {
+        let (tx, rx) = futures01::sync::mpsc::channel(10);
+        let (trigger_shutdown, shutdown, _) = ShutdownSignal::new_wired();
+
+        let dir = tempdir().unwrap();
+        let config = file::FileConfig {
+            include: vec![dir.path().join("*")],
+            start_at_beginning: true,
+            acknowledgements: true,
+            ..test_default_file_config(&dir)
+        };
+
+        let path = dir.path().join("file");
+        let mut file = File::create(&path).unwrap();
+        writeln!(&mut file, "hello").unwrap();
+
+        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+
+        let mut rt = runtime();
+
+        rt.spawn(source);
+
+        let received = wait_with_timeout(rx.take(1).collect());
+        assert_eq!(received.len(), 1);
+        assert!(!received[0].clone().into_log().take_finalizers().is_empty());
+        drop(received);
+
+        drop(trigger_shutdown);
+        shutdown_on_idle(rt);
+    }
+
     #[test]
     fn file_happy_path() {
         let n = 5;
@@ -1444,6 +1545,213 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_round_robin_reads() {
+        let (tx, rx) = futures01::sync::mpsc::channel(10);
+        let (trigger_shutdown, shutdown, _) = ShutdownSignal::new_wired();
+
+        let dir = tempdir().unwrap();
+        let config = file::FileConfig {
+            include: vec![dir.path().join("*")],
+            start_at_beginning: true,
+            read_scheduling: ReadScheduling::RoundRobin,
+            ..test_default_file_config(&dir)
+        };
+
+        let older_path = dir.path().join("z_older_file");
+        let mut older = File::create(&older_path).unwrap();
+
+        sleep();
+
+        let newer_path = dir.path().join("a_newer_file");
+        let mut newer = File::create(&newer_path).unwrap();
+
+        writeln!(&mut older, "the old file starts with a long line").unwrap();
+        writeln!(&mut older, "old line 2").unwrap();
+
+        writeln!(&mut newer, "new line 1").unwrap();
+        writeln!(&mut newer, "new line 2").unwrap();
+        writeln!(&mut newer, "new line 3").unwrap();
+        writeln!(&mut newer, "new line 4").unwrap();
+
+        sleep();
+
+        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let mut rt = runtime();
+        rt.spawn(source);
+
+        sleep();
+
+        drop(trigger_shutdown);
+        shutdown_on_idle(rt);
+
+        let received = wait_with_timeout(
+            rx.map(|event| {
+                event
+                    .as_log()
+                    .get(&event::log_schema().message_key())
+                    .unwrap()
+                    .clone()
+            })
+            .collect(),
+        );
+
+        // The newer file gets to catch up on the bytes of the long line first.
+        assert_eq!(
+            received,
+            vec![
+                "the old file starts with a long line".into(),
+                "new line 1".into(),
+                "new line 2".into(),
+                "new line 3".into(),
+                "new line 4".into(),
+                "old line 2".into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_max_bytes_per_sec() {
+        let (tx, rx) = futures01::sync::mpsc::channel(10);
+        let (trigger_shutdown, shutdown, _) = ShutdownSignal::new_wired();
+
+        let dir = tempdir().unwrap();
+        let config = file::FileConfig {
+            include: vec![dir.path().join("*")],
+            start_at_beginning: true,
+            max_bytes_per_sec: Some(10),
+            ..test_default_file_config(&dir)
+        };
+
+        let path = dir.path().join("file");
+        let mut file = File::create(&path).unwrap();
+
+        writeln!(&mut file, "the first line is read right away").unwrap();
+        writeln!(&mut file, "but the second one has to wait").unwrap();
+
+        sleep();
+
+        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let mut rt = runtime();
+        rt.spawn(source);
+
+        sleep();
+
+        drop(trigger_shutdown);
+        shutdown_on_idle(rt);
+
+        let received = wait_with_timeout(
+            rx.map(|event| {
+                event
+                    .as_log()
+                    .get(&event::log_schema().message_key())
+                    .unwrap()
+                    .clone()
+            })
+            .collect(),
+        );
+
+        assert_eq!(received, vec!["the first line is read right away".into()]);
+    }
+
+    #[test]
+    fn test_utf16le_file() {
+        let (tx, rx) = futures01::sync::mpsc::channel(10);
+        let (trigger_shutdown, shutdown, _) = ShutdownSignal::new_wired();
+
+        let dir = tempdir().unwrap();
+        let config = file::FileConfig {
+            include: vec![dir.path().join("*")],
+            start_at_beginning: true,
+            encoding: EncodingConfig {
+                charset: Some(encoding_rs::UTF_16LE),
+            },
+            ..test_default_file_config(&dir)
+        };
+
+        let path = dir.path().join("file");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(&[0xff, 0xfe]).unwrap();
+        // "\u{0a0a}" is encoded as two newline bytes.
+        for unit in "hello\n\u{0a0a}\nworld\n".encode_utf16() {
+            file.write_all(&unit.to_le_bytes()).unwrap();
+        }
+
+        sleep();
+
+        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let mut rt = runtime();
+        rt.spawn(source);
+
+        sleep();
+
+        drop(trigger_shutdown);
+        shutdown_on_idle(rt);
+
+        let received = wait_with_timeout(
+            rx.map(|event| {
+                event
+                    .as_log()
+                    .get(&event::log_schema().message_key())
+                    .unwrap()
+                    .clone()
+            })
+            .collect(),
+        );
+
+        assert_eq!(
+            received,
+            vec!["hello".into(), "\u{0a0a}".into(), "world".into()]
+        );
+    }
+
+    #[test]
+    fn test_byte_order_mark_overrides_charset() {
+        let (tx, rx) = futures01::sync::mpsc::channel(10);
+        let (trigger_shutdown, shutdown, _) = ShutdownSignal::new_wired();
+
+        let dir = tempdir().unwrap();
+        let config = file::FileConfig {
+            include: vec![dir.path().join("*")],
+            start_at_beginning: true,
+            encoding: EncodingConfig {
+                charset: Some(encoding_rs::UTF_16LE),
+            },
+            ..test_default_file_config(&dir)
+        };
+
+        let path = dir.path().join("file");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(&[0xfe, 0xff]).unwrap();
+        for unit in "hello\nworld\n".encode_utf16() {
+            file.write_all(&unit.to_be_bytes()).unwrap();
+        }
+
+        sleep();
+
+        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let mut rt = runtime();
+        rt.spawn(source);
+
+        sleep();
+
+        drop(trigger_shutdown);
+        shutdown_on_idle(rt);
+
+        let received = wait_with_timeout(
+            rx.map(|event| {
+                event
+                    .as_log()
+                    .get(&event::log_schema().message_key())
+                    .unwrap()
+                    .clone()
+            })
+            .collect(),
+        );
+
+        assert_eq!(received, vec!["hello".into(), "world".into()]);
+    }
+
     #[test]
     fn test_gzipped_file() {
         let (tx, rx) = futures01::sync::mpsc::channel(10);
@@ -1487,6 +1795,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_zstd_compressed_file() {
+        let (tx, rx) = futures01::sync::mpsc::channel(10);
+        let (trigger_shutdown, shutdown, _) = ShutdownSignal::new_wired();
+
+        let dir = tempdir().unwrap();
+        let config = file::FileConfig {
+            include: vec![PathBuf::from("tests/data/zstd_compressed.log")],
+            ..test_default_file_config(&dir)
+        };
+
+        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+        let mut rt = runtime();
+        rt.spawn(source);
+
+        sleep();
+
+        drop(trigger_shutdown);
+        shutdown_on_idle(rt);
+
+        let received = wait_with_timeout(
+            rx.map(|event| {
+                event
+                    .as_log()
+                    .get(&event::log_schema().message_key())
+                    .unwrap()
+                    .clone()
+            })
+            .collect(),
+        );
+
+        assert_eq!(
+            received,
+            vec![
+                "this is a simple file".into(),
+                "i have been compressed with zstd".into(),
+                "in order to make me smaller".into(),
+                "but you can still read me".into(),
+                "hooray".into(),
+            ]
+        );
+    }
+
     #[test]
     fn remove_file() {
         trace_init();
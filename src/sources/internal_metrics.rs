@@ -66,6 +66,7 @@ async fn run(
             break;
         }
 
+        crate::buffers::report_usage();
         let metrics = capture_metrics(&controller);
 
         let (sink, _) = out
@@ -1,6 +1,9 @@
 use crate::{
     event,
-    event::{Event, LogEvent, Value},
+    event::{
+        finalization::{BatchNotifier, OrderedAcker},
+        Event, LogEvent, Value,
+    },
     shutdown::ShutdownSignal,
     topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
 };
@@ -26,6 +29,7 @@ use std::iter::FromIterator;
 use std::path::PathBuf;
 use std::process::{Child, ChildStdout, Command, Stdio};
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time;
 use string_cache::DefaultAtom as Atom;
 use tokio::{task::spawn_blocking, time::delay_for};
@@ -68,6 +72,10 @@ pub struct JournaldConfig {
     pub journalctl_path: Option<PathBuf>,
     #[serde(default)]
     pub remap_priority: bool,
+    /// Only advance the checkpoint once the records read up to it have been
+    /// acknowledged by the sinks.
+    #[serde(default)]
+    pub acknowledgements: bool,
 }
 
 inventory::submit! {
@@ -144,9 +152,7 @@ impl JournaldConfig {
     where
         J: JournalSource + Send + 'static,
     {
-        let out = out
-            .sink_map_err(|_| ())
-            .with(|record: Record| future::ok(create_event(record)));
+        let out = out.sink_map_err(|_| ());
 
         // Retrieve the saved checkpoint, and use it to seek forward in the journald log
         let cursor = match checkpointer.get() {
@@ -162,16 +168,27 @@ impl JournaldConfig {
 
         let (journal, close) = J::new(self, cursor)?;
 
+        let acknowledgements = self.acknowledgements;
         Ok(Box::new(future::lazy(move || {
             info!(message = "Starting journald server.",);
 
+            let checkpointing = if acknowledgements {
+                let (acker, task) = OrderedAcker::new(move |cursor: String| {
+                    set_checkpoint(&mut checkpointer, &cursor)
+                });
+                tokio01::spawn(task);
+                Checkpointing::Acknowledged(acker)
+            } else {
+                Checkpointing::Immediate(checkpointer)
+            };
+
             let journald_server = JournaldServer {
                 journal,
                 include_units,
                 exclude_units,
                 channel: out,
                 shutdown: shutdown.clone(),
-                checkpointer,
+                checkpointing,
                 batch_size,
                 remap_priority,
             };
@@ -311,15 +328,23 @@ struct JournaldServer<J, T> {
     exclude_units: HashSet<String>,
     channel: T,
     shutdown: ShutdownSignal,
-    checkpointer: Checkpointer,
+    checkpointing: Checkpointing,
     batch_size: usize,
     remap_priority: bool,
 }
 
+/// How the cursor of a batch of records is saved.
+enum Checkpointing {
+    /// As soon as the batch has been sent.
+    Immediate(Checkpointer),
+    /// Once all of the events of the batch have been acknowledged.
+    Acknowledged(OrderedAcker<String>),
+}
+
 impl<J, T> JournaldServer<J, T>
 where
     J: JournalSource,
-    T: Sink<SinkItem = Record, SinkError = ()>,
+    T: Sink<SinkItem = Event, SinkError = ()>,
 {
     pub fn run(mut self) {
         let timeout = time::Duration::from_millis(500); // arbitrary timeout
@@ -330,6 +355,10 @@ where
             let mut saw_record = false;
             let mut at_end = false;
             let mut cursor: Option<String> = None;
+            let mut batch = match &self.checkpointing {
+                Checkpointing::Acknowledged(_) => Some(BatchNotifier::new_with_receiver()),
+                Checkpointing::Immediate(_) => None,
+            };
 
             for _ in 0..self.batch_size {
                 let text = match self.journal.next() {
@@ -365,7 +394,12 @@ where
                     continue;
                 }
 
-                match channel.send(record).wait() {
+                let mut event = create_event(record);
+                if let Some((notifier, _)) = &batch {
+                    event.as_mut_log().add_finalizer(Arc::clone(notifier));
+                }
+
+                match channel.send(event).wait() {
                     Ok(_) => {}
                     Err(()) => error!(message = "Could not send journald log"),
                 }
@@ -373,11 +407,15 @@ where
 
             if saw_record {
                 if let Some(cursor) = cursor {
-                    if let Err(err) = self.checkpointer.set(&cursor) {
-                        error!(
-                            message = "Could not set journald checkpoint.",
-                            error = field::display(&err)
-                        );
+                    match &mut self.checkpointing {
+                        Checkpointing::Immediate(checkpointer) => {
+                            set_checkpoint(checkpointer, &cursor)
+                        }
+                        Checkpointing::Acknowledged(acker) => {
+                            if let Some((_, receiver)) = batch.take() {
+                                acker.push(receiver, cursor);
+                            }
+                        }
                     }
                 }
             }
@@ -463,6 +501,15 @@ fn filter_unit(
 
 const CHECKPOINT_FILENAME: &str = "checkpoint.txt";
 
+fn set_checkpoint(checkpointer: &mut Checkpointer, cursor: &str) {
+    if let Err(err) = checkpointer.set(cursor) {
+        error!(
+            message = "Could not set journald checkpoint.",
+            error = field::display(&err)
+        );
+    }
+}
+
 struct Checkpointer {
     file: File,
 }
@@ -547,7 +594,7 @@ mod checkpointer_tests {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_util::{block_on, runtime, shutdown_on_idle};
+    use crate::test_util::{block_on, runtime, shutdown_on_idle, wait_for};
     use futures01::stream::Stream;
     use std::io::{self, BufReader, Cursor};
     use std::iter::FromIterator;
@@ -682,6 +729,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn checkpoints_after_acknowledgement() {
+        let (tx, rx) = futures01::sync::mpsc::channel(10);
+        let (trigger, shutdown, _) = ShutdownSignal::new_wired();
+        let tempdir = tempdir().unwrap();
+        let checkpointer =
+            Checkpointer::new(tempdir.path().to_path_buf()).expect("Creating checkpointer failed!");
+
+        let config = JournaldConfig {
+            acknowledgements: true,
+            ..Default::default()
+        };
+        let source = config
+            .source::<FakeJournal>(
+                tx,
+                shutdown,
+                checkpointer,
+                HashSet::new(),
+                HashSet::new(),
+                DEFAULT_BATCH_SIZE,
+                true,
+            )
+            .expect("Creating journald source failed");
+        let mut rt = runtime();
+        rt.spawn(source);
+
+        let mut checkpointer =
+            Checkpointer::new(tempdir.path().to_path_buf()).expect("Creating checkpointer failed!");
+        let received = block_on(rx.take(5).collect()).unwrap();
+        // Nothing can be checkpointed while the events are still held.
+        assert_eq!(checkpointer.get().unwrap(), None);
+
+        drop(received);
+        wait_for(|| checkpointer.get().unwrap() == Some("4".into()));
+
+        drop(trigger);
+        shutdown_on_idle(rt);
+    }
+
     #[test]
     fn handles_checkpoint() {
         let received = run_journal(&[], &[], Some("1"));
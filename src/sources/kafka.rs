@@ -1,5 +1,5 @@
 use crate::{
-    event::{self, Event},
+    event::{self, finalization::OrderedAcker, Event},
     kafka::KafkaAuthConfig,
     shutdown::ShutdownSignal,
     stream::StreamExt,
@@ -15,6 +15,7 @@ use rdkafka::{
     consumer::{Consumer, DefaultConsumerContext, MessageStream, StreamConsumer},
     error::KafkaError,
     message::{BorrowedMessage, Message},
+    topic_partition_list::{Offset, TopicPartitionList},
 };
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
@@ -46,6 +47,10 @@ pub struct KafkaSourceConfig {
     #[serde(default = "default_commit_interval_ms")]
     commit_interval_ms: u64,
     key_field: Option<String>,
+    /// Only store the offset of a message once every sink has acknowledged
+    /// it, instead of as soon as it was read.
+    #[serde(default)]
+    acknowledgements: bool,
     librdkafka_options: Option<HashMap<String, String>>,
     #[serde(flatten)]
     auth: KafkaAuthConfig,
@@ -102,9 +107,25 @@ fn kafka_source(
     out: mpsc::Sender<Event>,
 ) -> crate::Result<super::Source> {
     let consumer = Arc::new(create_consumer(config.clone())?);
+
+    let (acker, ack_task) = if config.acknowledgements {
+        let consumer = Arc::clone(&consumer);
+        let (acker, task) =
+            OrderedAcker::new(move |(topic, partition, offset): (String, i32, i64)| {
+                store_offset(&consumer, &topic, partition, offset)
+            });
+        (Some(acker), Some(task))
+    } else {
+        (None, None)
+    };
+
     let source = future::lazy(move || {
         let consumer_ref = Arc::clone(&consumer);
 
+        if let Some(ack_task) = ack_task {
+            tokio01::spawn(ack_task);
+        }
+
         // See https://github.com/fede1024/rust-rdkafka/issues/85#issuecomment-439141656
         let stream = OwnedConsumerStream {
             upstream: OwningHandle::new_with_fn(consumer.clone(), |c| {
@@ -154,9 +175,16 @@ fn kafka_source(
                             }
                         }
 
-                        consumer_ref.store_offset(&msg).map_err(
-                            |e| error!(message = "Cannot store offset for the message", error = ?e),
-                        )?;
+                        match &acker {
+                            Some(acker) => {
+                                let checkpoint =
+                                    (msg.topic().to_owned(), msg.partition(), msg.offset());
+                                log.add_finalizer(acker.add(checkpoint));
+                            }
+                            None => consumer_ref.store_offset(&msg).map_err(
+                                |e| error!(message = "Cannot store offset for the message", error = ?e),
+                            )?,
+                        }
                         Ok(event)
                     }
                 }
@@ -168,6 +196,20 @@ fn kafka_source(
     Ok(Box::new(source))
 }
 
+/// Stores the offset of an acknowledged message, to be committed with the
+/// next automatic commit.
+fn store_offset(consumer: &StreamConsumer, topic: &str, partition: i32, offset: i64) {
+    let mut offsets = TopicPartitionList::new();
+    // As with `store_offset`, the stored offset is the next one to consume.
+    offsets.add_partition_offset(topic, partition, Offset::Offset(offset + 1));
+    if let Err(error) = consumer.store_offsets(&offsets) {
+        error!(
+            message = "Cannot store offset for acknowledged message.",
+            ?error
+        );
+    }
+}
+
 fn create_consumer(config: KafkaSourceConfig) -> crate::Result<StreamConsumer> {
     let mut client_config = ClientConfig::new();
     client_config
@@ -244,6 +286,15 @@ mod test {
         assert!(kafka_source(config, ShutdownSignal::noop(), mpsc::channel(1).0).is_ok());
     }
 
+    #[test]
+    fn kafka_source_create_with_acknowledgements() {
+        let config = KafkaSourceConfig {
+            acknowledgements: true,
+            ..make_config()
+        };
+        assert!(kafka_source(config, ShutdownSignal::noop(), mpsc::channel(1).0).is_ok());
+    }
+
     #[test]
     fn kafka_source_create_incorrect_auto_offset_reset() {
         let config = KafkaSourceConfig {
@@ -0,0 +1,100 @@
+//! Finds the log files of the pods on a node.
+
+use file_source::paths_provider::PathsProvider;
+use std::path::{Path, PathBuf};
+
+/// Provides the log files the kubelet keeps for each container, laid out as
+/// `<pods_directory>/<namespace>_<pod name>_<pod uid>/<container name>/<n>.log`.
+///
+/// Rotated files get a suffix after `.log`, so only the files being written to
+/// are followed, while the file server keeps reading a file it was already
+/// following until its end after it has been rotated.
+pub struct K8sPathsProvider {
+    pattern: String,
+}
+
+impl K8sPathsProvider {
+    pub fn new(pods_directory: &Path) -> Self {
+        let pattern = pods_directory
+            .join("*")
+            .join("*")
+            .join("*.log")
+            .to_string_lossy()
+            .into_owned();
+        Self { pattern }
+    }
+}
+
+impl PathsProvider for K8sPathsProvider {
+    type IntoIter = Vec<PathBuf>;
+
+    fn paths(&self) -> Self::IntoIter {
+        glob::glob(&self.pattern)
+            .expect("failed to read glob pattern")
+            .filter_map(Result::ok)
+            .collect()
+    }
+}
+
+/// What the path of a container log file tells about the container.
+#[derive(Debug, PartialEq)]
+pub struct LogFileInfo<'a> {
+    pub pod_namespace: &'a str,
+    pub pod_name: &'a str,
+    pub pod_uid: &'a str,
+    pub container_name: &'a str,
+}
+
+/// Parses the path of a container log file. Namespaces and pod names can't
+/// contain underscores, so they can be split off of the pod directory name.
+pub fn parse_log_file_path(path: &str) -> Option<LogFileInfo<'_>> {
+    let mut components = path.rsplit('/');
+    let _file = components.next()?;
+    let container_name = components.next()?;
+    let mut pod = components.next()?.splitn(3, '_');
+
+    Some(LogFileInfo {
+        pod_namespace: pod.next()?,
+        pod_name: pod.next()?,
+        pod_uid: pod.next()?,
+        container_name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn parses_log_file_paths() {
+        assert_eq!(
+            parse_log_file_path(
+                "/var/log/pods/kube-system_coredns-66bff467f8-7w5ns_2b5a0a8b-5b4f-4a1e-9d1c-6cf2d4e2a8b1/coredns/0.log"
+            ),
+            Some(LogFileInfo {
+                pod_namespace: "kube-system",
+                pod_name: "coredns-66bff467f8-7w5ns",
+                pod_uid: "2b5a0a8b-5b4f-4a1e-9d1c-6cf2d4e2a8b1",
+                container_name: "coredns",
+            })
+        );
+        assert_eq!(parse_log_file_path("/var/log/pods/coredns/0.log"), None);
+        assert_eq!(parse_log_file_path("0.log"), None);
+    }
+
+    #[test]
+    fn provides_container_log_files() {
+        let dir = tempdir().unwrap();
+        let container = dir.path().join("default_web_1234").join("nginx");
+        fs::create_dir_all(&container).unwrap();
+        for file in &["0.log", "0.log.20200610-100000", "0.log.20200609-100000.gz"] {
+            fs::write(container.join(file), "").unwrap();
+        }
+        fs::write(dir.path().join("stray.log"), "").unwrap();
+
+        let paths = K8sPathsProvider::new(dir.path()).paths();
+        assert_eq!(paths, vec![container.join("0.log")]);
+    }
+}
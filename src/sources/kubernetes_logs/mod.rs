@@ -0,0 +1,404 @@
+//! Collects the logs of the pods on a Kubernetes node from the files the
+//! kubelet keeps for them, enriched with the metadata of their pods.
+
+use crate::{
+    event::{self, Event, LogEvent, PathComponent},
+    internal_events::{KubernetesLogsEventReceived, KubernetesLogsParseFailed},
+    shutdown::ShutdownSignal,
+    topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
+    trace::{current_span, Instrument},
+    transforms::{
+        merge::{Merge, MergeConfig},
+        Transform,
+    },
+};
+use bytes05::Bytes;
+use file_source::{Checkpoint, FileServer, Fingerprinter, ReadScheduling};
+use futures::{
+    compat::{Compat01As03Sink, Future01CompatExt},
+    future::{FutureExt, TryFutureExt},
+};
+use futures01::{sync::mpsc, Future, Sink, Stream};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{env, path::PathBuf, time::Duration};
+use string_cache::DefaultAtom as Atom;
+use tokio::task::spawn_blocking;
+
+mod k8s_paths_provider;
+mod parser;
+mod pod_metadata;
+
+use k8s_paths_provider::{parse_log_file_path, K8sPathsProvider, LogFileInfo};
+pub use pod_metadata::MetadataConfig;
+use pod_metadata::{MetadataSource, PodMetadata, PodMetadataStore};
+
+/// The environment variable the name of the node Vector runs on is read from,
+/// typically set through the downward API.
+const SELF_NODE_NAME_ENV_KEY: &str = "VECTOR_SELF_NODE_NAME";
+
+lazy_static! {
+    static ref FILE: Atom = Atom::from("file");
+    static ref STREAM: Atom = Atom::from("stream");
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct KubernetesLogsConfig {
+    pub pods_directory: PathBuf,
+    /// Only the metadata of the pods on this node is requested.
+    pub self_node_name: Option<String>,
+    pub data_dir: Option<PathBuf>,
+    pub auto_partial_merge: bool,
+    pub max_line_bytes: usize,
+    pub glob_minimum_cooldown: u64, // millis
+    pub metadata: MetadataConfig,
+}
+
+impl Default for KubernetesLogsConfig {
+    fn default() -> Self {
+        Self {
+            pods_directory: PathBuf::from("/var/log/pods"),
+            self_node_name: None,
+            data_dir: None,
+            auto_partial_merge: true,
+            max_line_bytes: 100 * 1024,
+            glob_minimum_cooldown: 1000, // millis
+            metadata: MetadataConfig::default(),
+        }
+    }
+}
+
+inventory::submit! {
+    SourceDescription::new::<KubernetesLogsConfig>("kubernetes_logs")
+}
+
+#[typetag::serde(name = "kubernetes_logs")]
+impl SourceConfig for KubernetesLogsConfig {
+    fn build(
+        &self,
+        name: &str,
+        globals: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: mpsc::Sender<Event>,
+    ) -> crate::Result<super::Source> {
+        let data_dir = globals.resolve_and_make_data_subdir(self.data_dir.as_ref(), name)?;
+        let node_name = self
+            .self_node_name
+            .clone()
+            .or_else(|| env::var(SELF_NODE_NAME_ENV_KEY).ok());
+        let metadata_source = MetadataSource::new(&self.metadata, node_name.as_deref())?;
+
+        let fut = run(self.clone(), data_dir, metadata_source, shutdown, out)
+            .boxed()
+            .compat();
+        Ok(Box::new(fut))
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn source_type(&self) -> &'static str {
+        "kubernetes_logs"
+    }
+}
+
+async fn run(
+    config: KubernetesLogsConfig,
+    data_dir: PathBuf,
+    metadata_source: MetadataSource,
+    shutdown: ShutdownSignal,
+    out: mpsc::Sender<Event>,
+) -> Result<(), ()> {
+    info!(
+        message = "Starting kubernetes_logs source.",
+        pods_directory = ?config.pods_directory
+    );
+
+    // Load the metadata before reading any logs, so the first events are
+    // enriched as well.
+    let (store, refresh_requests) =
+        PodMetadataStore::new(config.metadata.deleted_pod_grace_period());
+    metadata_source.refresh(&store).await;
+    tokio::spawn(metadata_source.run(
+        store.clone(),
+        config.metadata.refresh_interval(),
+        refresh_requests,
+        shutdown.clone(),
+    ));
+
+    let file_server = FileServer {
+        paths_provider: K8sPathsProvider::new(&config.pods_directory),
+        max_read_bytes: 2048,
+        start_at_beginning: false,
+        ignore_before: None,
+        max_line_bytes: config.max_line_bytes,
+        line_delimiter: Bytes::from_static(b"\n"),
+        detect_byte_order_mark: false,
+        data_dir,
+        glob_minimum_cooldown: Duration::from_millis(config.glob_minimum_cooldown),
+        // Every line starts with a timestamp, so the first one tells files
+        // apart, and files are picked up as soon as their first line is
+        // complete.
+        fingerprinter: Fingerprinter::FirstLineChecksum {
+            max_line_length: config.max_line_bytes,
+        },
+        oldest_first: false,
+        read_scheduling: ReadScheduling::Sequential,
+        max_bytes_per_sec: None,
+        remove_after: None,
+        checkpoints: None,
+    };
+
+    let mut partial_events_merger = if config.auto_partial_merge {
+        Some(partial_events_merger())
+    } else {
+        None
+    };
+
+    // sizing here is just a guess
+    let (tx, rx) = futures01::sync::mpsc::channel(100);
+
+    // Once file server ends this will run until it has finished processing remaining
+    // logs in the queue.
+    let span = current_span();
+    let span2 = span.clone();
+    tokio01::spawn(
+        rx.filter_map(move |(line, file, _, _): (Bytes, String, Checkpoint, _)| {
+            let _enter = span2.enter();
+            emit!(KubernetesLogsEventReceived {
+                file: &file,
+                byte_size: line.len(),
+            });
+            let event = create_event(line, &file, &store);
+            match &mut partial_events_merger {
+                Some(merger) => merger.transform(event),
+                None => Some(event),
+            }
+        })
+        .forward(out.sink_map_err(|e| error!(%e)))
+        .map(|_| ())
+        .instrument(span),
+    );
+
+    let span = info_span!("file_server");
+    spawn_blocking(move || {
+        let _enter = span.enter();
+        let result = file_server.run(Compat01As03Sink::new(tx), shutdown.compat());
+        // Panic if we encounter any error originating from the file server.
+        // We're at the `spawn_blocking` call, the panic will be caught and
+        // passed to the `JoinHandle` error, similar to the usual threads.
+        result.unwrap();
+    })
+    .await
+    .map_err(|error| error!(message = "File server unexpectedly stopped.", %error))
+}
+
+/// Merges the lines container runtimes split, separately for each stream of
+/// each file.
+fn partial_events_merger() -> Merge {
+    Merge::from(MergeConfig {
+        partial_event_marker_field: event::PARTIAL.clone(),
+        merge_fields: vec![event::log_schema().message_key().clone()],
+        stream_discriminant_fields: vec![FILE.clone(), STREAM.clone()],
+    })
+}
+
+fn create_event(line: Bytes, file: &str, store: &PodMetadataStore) -> Event {
+    let mut event = match parser::parse(line.clone()) {
+        Ok(parsed) => {
+            let mut event = Event::from(parsed.message);
+            let log = event.as_mut_log();
+            log.insert(event::log_schema().timestamp_key(), parsed.timestamp);
+            log.insert(STREAM.clone(), parsed.stream);
+            if parsed.partial {
+                log.insert(event::PARTIAL.clone(), true);
+            }
+            event
+        }
+        Err(error) => {
+            emit!(KubernetesLogsParseFailed {
+                file,
+                error: error.into(),
+            });
+            Event::from(line)
+        }
+    };
+
+    let log = event.as_mut_log();
+    log.insert(event::log_schema().source_type_key(), "kubernetes_logs");
+    log.insert(FILE.clone(), file.to_owned());
+    if let Some(info) = parse_log_file_path(file) {
+        let metadata = store.get(info.pod_uid);
+        annotate(log, &info, metadata.as_ref());
+    }
+
+    event
+}
+
+/// Adds the pod and container a log line was written by under `kubernetes`.
+/// Namespace, pod and container names are known from the path of the log
+/// file even when the pod metadata isn't.
+fn annotate(log: &mut LogEvent, info: &LogFileInfo<'_>, metadata: Option<&PodMetadata>) {
+    let insert = |log: &mut LogEvent, keys: &[&str], value: String| {
+        let path = std::iter::once("kubernetes")
+            .chain(keys.iter().copied())
+            .map(|key| PathComponent::Key(key.to_owned()))
+            .collect();
+        log.insert_path(path, value);
+    };
+
+    let (namespace, pod_name) = match metadata {
+        Some(metadata) => (metadata.namespace.as_str(), metadata.name.as_str()),
+        None => (info.pod_namespace, info.pod_name),
+    };
+    insert(log, &["pod_namespace"], namespace.to_owned());
+    insert(log, &["pod_name"], pod_name.to_owned());
+    insert(log, &["pod_uid"], info.pod_uid.to_owned());
+    insert(log, &["container_name"], info.container_name.to_owned());
+
+    if let Some(metadata) = metadata {
+        if let Some(image) = metadata.container_images.get(info.container_name) {
+            insert(log, &["container_image"], image.clone());
+        }
+        // Label keys often contain dots, which must not nest them further.
+        for (key, value) in &metadata.labels {
+            insert(log, &["pod_labels", key.as_str()], value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event::Value,
+        test_util::{collect_n, runtime},
+    };
+    use chrono::{TimeZone, Utc};
+    use std::fs;
+    use tempfile::tempdir;
+
+    const POD_DIRECTORY: &str = "shop_web-6d4cf56db6-x2x8z_b1e8a1d4-0f3c-4d56-9a4e-2e1f3b7c9d10";
+
+    const PODS: &str = r#"{
+        "items": [{
+            "metadata": {
+                "name": "web-6d4cf56db6-x2x8z",
+                "namespace": "shop",
+                "uid": "b1e8a1d4-0f3c-4d56-9a4e-2e1f3b7c9d10",
+                "labels": {"app": "web", "app.kubernetes.io/version": "1.2"}
+            },
+            "spec": {"containers": [{"name": "nginx", "image": "nginx:1.19"}]}
+        }]
+    }"#;
+
+    #[test]
+    fn reads_and_enriches_pod_logs() {
+        let mut rt = runtime();
+        let dir = tempdir().unwrap();
+        let pods_directory = dir.path().join("pods");
+        let container = pods_directory.join(POD_DIRECTORY).join("nginx");
+        fs::create_dir_all(&container).unwrap();
+        fs::write(
+            container.join("0.log"),
+            concat!(
+                "2020-06-10T10:00:00.000000000Z stdout P hello \n",
+                "2020-06-10T10:00:00.000000001Z stderr F oops\n",
+                "2020-06-10T10:00:00.000000002Z stdout F world\n",
+            ),
+        )
+        .unwrap();
+        let metadata_file = dir.path().join("pods.json");
+        fs::write(&metadata_file, PODS).unwrap();
+        let data_dir = dir.path().join("data");
+        fs::create_dir_all(&data_dir).unwrap();
+
+        let config = KubernetesLogsConfig {
+            pods_directory,
+            data_dir: Some(data_dir),
+            glob_minimum_cooldown: 0,
+            metadata: MetadataConfig::File {
+                path: metadata_file,
+                refresh_interval_secs: 60,
+                deleted_pod_grace_period_secs: 60,
+            },
+            ..Default::default()
+        };
+        let (tx, rx) = mpsc::channel(10);
+        let (trigger_shutdown, shutdown, _) = ShutdownSignal::new_wired();
+        let source = config
+            .build("in", &GlobalOptions::default(), shutdown, tx)
+            .unwrap();
+        rt.spawn(source);
+
+        let events = rt
+            .block_on_std(async move { collect_n(rx, 2).compat().await })
+            .unwrap();
+        drop(trigger_shutdown);
+
+        let fields = |event: &Event, keys: &[&str]| {
+            keys.iter()
+                .map(|key| event.as_log().get(&Atom::from(*key)).cloned())
+                .collect::<Vec<_>>()
+        };
+        let keys = &[
+            "message",
+            "stream",
+            "kubernetes.pod_namespace",
+            "kubernetes.pod_name",
+            "kubernetes.container_name",
+            "kubernetes.container_image",
+            "kubernetes.pod_labels.app",
+        ];
+
+        assert_eq!(
+            fields(&events[0], keys),
+            vec![
+                Some(Value::from("oops")),
+                Some(Value::from("stderr")),
+                Some(Value::from("shop")),
+                Some(Value::from("web-6d4cf56db6-x2x8z")),
+                Some(Value::from("nginx")),
+                Some(Value::from("nginx:1.19")),
+                Some(Value::from("web")),
+            ]
+        );
+        assert_eq!(
+            fields(&events[1], &keys[..2]),
+            vec![
+                Some(Value::from("hello world")),
+                Some(Value::from("stdout"))
+            ]
+        );
+        assert_eq!(
+            events[1].as_log()[&event::log_schema().timestamp_key()],
+            Value::from(Utc.ymd(2020, 6, 10).and_hms_nano(10, 0, 0, 0))
+        );
+        assert_eq!(events[1].as_log().get(&*event::PARTIAL), None);
+    }
+
+    #[test]
+    fn annotates_from_path_without_metadata() {
+        let file = format!("/var/log/pods/{}/nginx/0.log", POD_DIRECTORY);
+        let event = create_event(
+            Bytes::from_static(b"2020-06-10T10:00:00Z stdout P partial"),
+            &file,
+            &PodMetadataStore::default(),
+        );
+        let log = event.as_log();
+
+        assert_eq!(
+            log[&Atom::from("kubernetes.pod_namespace")],
+            Value::from("shop")
+        );
+        assert_eq!(
+            log[&Atom::from("kubernetes.container_name")],
+            Value::from("nginx")
+        );
+        assert_eq!(log.get(&Atom::from("kubernetes.pod_labels")), None);
+        assert_eq!(log[&*event::PARTIAL], Value::from(true));
+        assert_eq!(log[&*FILE], Value::from(file));
+    }
+}
@@ -0,0 +1,168 @@
+//! Parsers for the formats container runtimes write log files in.
+
+use bytes05::Bytes;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use snafu::{OptionExt, ResultExt, Snafu};
+
+/// A line of a container log file.
+#[derive(Debug, PartialEq)]
+pub struct LogLine {
+    pub message: Bytes,
+    pub stream: String,
+    pub timestamp: DateTime<Utc>,
+    /// Whether the runtime split a longer line and more of it follows.
+    pub partial: bool,
+}
+
+#[derive(Debug, Snafu)]
+pub enum ParseError {
+    #[snafu(display("Invalid Docker JSON log line: {}", source))]
+    InvalidDockerLine { source: serde_json::Error },
+    #[snafu(display("Invalid CRI log line: missing {}", field))]
+    InvalidCriLine { field: &'static str },
+    #[snafu(display("Invalid CRI log line: unknown tag {:?}", tag))]
+    UnknownCriTag { tag: String },
+    #[snafu(display("Invalid log line timestamp: {}", source))]
+    InvalidTimestamp { source: chrono::ParseError },
+}
+
+/// Parses a line written by either Docker's `json-file` logging driver or a
+/// CRI runtime such as containerd or CRI-O, telling them apart by the
+/// opening brace of the former.
+pub fn parse(line: Bytes) -> Result<LogLine, ParseError> {
+    if line.starts_with(b"{") {
+        parse_docker(&line)
+    } else {
+        parse_cri(line)
+    }
+}
+
+#[derive(Deserialize)]
+struct DockerLine {
+    log: String,
+    stream: String,
+    time: DateTime<Utc>,
+}
+
+/// Parses `{"log":"message\n","stream":"stdout","time":"2020-06-10T10:00:00.000000000Z"}`.
+/// Docker splits lines longer than 16KiB, leaving the trailing newline off of
+/// all but the last part.
+fn parse_docker(line: &[u8]) -> Result<LogLine, ParseError> {
+    let DockerLine {
+        mut log,
+        stream,
+        time,
+    } = serde_json::from_slice(line).context(InvalidDockerLine)?;
+
+    let partial = !log.ends_with('\n');
+    if !partial {
+        log.pop();
+    }
+
+    Ok(LogLine {
+        message: log.into(),
+        stream,
+        timestamp: time,
+        partial,
+    })
+}
+
+/// Parses `2020-06-10T10:00:00.000000000Z stdout F message`, where the tag is
+/// `P` for all but the last part of a split line and `F` otherwise.
+fn parse_cri(line: Bytes) -> Result<LogLine, ParseError> {
+    let mut fields = line.splitn(4, |&b| b == b' ');
+    let mut next = |field: &'static str| fields.next().context(InvalidCriLine { field });
+    let timestamp = next("timestamp")?;
+    let stream = next("stream")?;
+    let tag = next("tag")?;
+    // The message is whatever follows the tag, which may be empty.
+    let offset = timestamp.len() + stream.len() + tag.len() + 3;
+
+    let timestamp = DateTime::parse_from_rfc3339(&String::from_utf8_lossy(timestamp))
+        .context(InvalidTimestamp)?
+        .with_timezone(&Utc);
+    let partial = match tag {
+        b"P" => true,
+        b"F" => false,
+        _ => {
+            return Err(ParseError::UnknownCriTag {
+                tag: String::from_utf8_lossy(tag).into_owned(),
+            })
+        }
+    };
+    let stream = String::from_utf8_lossy(stream).into_owned();
+    let message = if offset <= line.len() {
+        line.slice(offset..)
+    } else {
+        Bytes::new()
+    };
+
+    Ok(LogLine {
+        message,
+        stream,
+        timestamp,
+        partial,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn ts() -> DateTime<Utc> {
+        Utc.ymd(2020, 6, 10).and_hms_nano(10, 0, 0, 123_456_789)
+    }
+
+    fn line(message: &str, stream: &str, partial: bool) -> LogLine {
+        LogLine {
+            message: Bytes::from(message.to_owned()),
+            stream: stream.into(),
+            timestamp: ts(),
+            partial,
+        }
+    }
+
+    #[test]
+    fn parses_docker_lines() {
+        let parsed = parse(Bytes::from_static(
+            br#"{"log":"hello world\n","stream":"stdout","time":"2020-06-10T10:00:00.123456789Z"}"#,
+        ))
+        .unwrap();
+        assert_eq!(parsed, line("hello world", "stdout", false));
+
+        let parsed = parse(Bytes::from_static(
+            br#"{"log":"hello ","stream":"stderr","time":"2020-06-10T10:00:00.123456789Z"}"#,
+        ))
+        .unwrap();
+        assert_eq!(parsed, line("hello ", "stderr", true));
+
+        assert!(parse(Bytes::from_static(br#"{"log":"no stream\n"}"#)).is_err());
+    }
+
+    #[test]
+    fn parses_cri_lines() {
+        let parsed = parse(Bytes::from_static(
+            b"2020-06-10T10:00:00.123456789Z stdout F hello world",
+        ))
+        .unwrap();
+        assert_eq!(parsed, line("hello world", "stdout", false));
+
+        let parsed = parse(Bytes::from_static(
+            b"2020-06-10T12:00:00.123456789+02:00 stderr P hello ",
+        ))
+        .unwrap();
+        assert_eq!(parsed, line("hello ", "stderr", true));
+
+        let parsed = parse(Bytes::from_static(
+            b"2020-06-10T10:00:00.123456789Z stdout F",
+        ))
+        .unwrap();
+        assert_eq!(parsed, line("", "stdout", false));
+
+        assert!(parse(Bytes::from_static(b"2020-06-10T10:00:00Z stdout X hi")).is_err());
+        assert!(parse(Bytes::from_static(b"yesterday stdout F hi")).is_err());
+        assert!(parse(Bytes::from_static(b"plain text")).is_err());
+    }
+}
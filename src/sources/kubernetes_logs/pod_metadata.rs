@@ -0,0 +1,600 @@
+//! The metadata of the pods on a node, which their logs are enriched with.
+
+use crate::{
+    dns::Resolver,
+    hyper::body_to_bytes,
+    internal_events::KubernetesLogsMetadataRefreshFailed,
+    shutdown::ShutdownSignal,
+    sinks::util::http::{Auth, HttpClient},
+    tls::{TlsOptions, TlsSettings},
+    topology::config::Format,
+};
+use futures::{
+    channel::mpsc,
+    compat::Future01CompatExt,
+    future::FutureExt,
+    stream::{self, StreamExt},
+};
+use http::Uri;
+use hyper::{Body, Request};
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::time::{delay_until, interval_at, Instant};
+
+const SERVICE_ACCOUNT_TOKEN_FILE: &str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+const SERVICE_ACCOUNT_CA_FILE: &str = "/var/run/secrets/kubernetes.io/serviceaccount/ca.crt";
+
+/// Refreshes requested by lookups of unknown pods are at least this far apart.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where pod metadata is read from.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MetadataConfig {
+    /// The Kubernetes API server, by default the one of the cluster Vector
+    /// runs in, authenticated as its service account.
+    Api {
+        endpoint: Option<String>,
+        token_file: Option<PathBuf>,
+        tls: Option<TlsOptions>,
+        #[serde(default = "default_refresh_interval_secs")]
+        refresh_interval_secs: u64,
+        #[serde(default = "default_deleted_pod_grace_period_secs")]
+        deleted_pod_grace_period_secs: u64,
+    },
+    /// A JSON or YAML file holding a list of pods, as written by
+    /// `kubectl get pods --output json`.
+    File {
+        path: PathBuf,
+        #[serde(default = "default_refresh_interval_secs")]
+        refresh_interval_secs: u64,
+        #[serde(default = "default_deleted_pod_grace_period_secs")]
+        deleted_pod_grace_period_secs: u64,
+    },
+}
+
+fn default_refresh_interval_secs() -> u64 {
+    30
+}
+
+fn default_deleted_pod_grace_period_secs() -> u64 {
+    60
+}
+
+impl Default for MetadataConfig {
+    fn default() -> Self {
+        MetadataConfig::Api {
+            endpoint: None,
+            token_file: None,
+            tls: None,
+            refresh_interval_secs: default_refresh_interval_secs(),
+            deleted_pod_grace_period_secs: default_deleted_pod_grace_period_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+enum BuildError {
+    #[snafu(display(
+        "No Kubernetes API endpoint given, and KUBERNETES_SERVICE_HOST and KUBERNETES_SERVICE_PORT are not set"
+    ))]
+    MissingEndpoint,
+}
+
+#[derive(Debug, Snafu)]
+enum FetchError {
+    #[snafu(display("Unexpected status: {}", status))]
+    UnexpectedStatus { status: http::StatusCode },
+    #[snafu(display("Invalid pod list: {}", errors.join(", ")))]
+    InvalidPodList { errors: Vec<String> },
+}
+
+/// The metadata of a pod that its logs are enriched with.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PodMetadata {
+    pub namespace: String,
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    /// The image of each container, by container name.
+    pub container_images: HashMap<String, String>,
+}
+
+/// The metadata of the known pods, by pod UID, shared between the task
+/// refreshing it and the events being enriched with it.
+#[derive(Clone, Debug, Default)]
+pub struct PodMetadataStore {
+    state: Arc<RwLock<StoreState>>,
+    deleted_pod_grace_period: Duration,
+    refresh_requests: Option<mpsc::UnboundedSender<()>>,
+}
+
+#[derive(Debug, Default)]
+struct StoreState {
+    pods: HashMap<String, StoredPod>,
+    /// Pods that were looked up before their metadata was read.
+    requested: HashSet<String>,
+    /// Pods that the latest refresh didn't find either, which don't request
+    /// another refresh until the next one.
+    unknown: HashSet<String>,
+}
+
+#[derive(Debug)]
+struct StoredPod {
+    metadata: PodMetadata,
+    deleted_at: Option<Instant>,
+}
+
+impl PodMetadataStore {
+    /// Creates an empty store, along with the refreshes requested by lookups
+    /// of pods the store doesn't know yet.
+    pub fn new(deleted_pod_grace_period: Duration) -> (Self, mpsc::UnboundedReceiver<()>) {
+        let (tx, rx) = mpsc::unbounded();
+        let store = Self {
+            state: Default::default(),
+            deleted_pod_grace_period,
+            refresh_requests: Some(tx),
+        };
+        (store, rx)
+    }
+
+    /// Returns the metadata of a pod. A pod that isn't known yet was most
+    /// likely started since the last refresh, so a refresh is requested
+    /// rather than waiting for the next one.
+    pub fn get(&self, uid: &str) -> Option<PodMetadata> {
+        {
+            let state = self.state.read().unwrap();
+            if let Some(pod) = state.pods.get(uid) {
+                return Some(pod.metadata.clone());
+            }
+            if state.requested.contains(uid) || state.unknown.contains(uid) {
+                return None;
+            }
+        }
+
+        let requested = self.state.write().unwrap().requested.insert(uid.to_owned());
+        if let (true, Some(refresh_requests)) = (requested, &self.refresh_requests) {
+            // The refreshing task is gone once the source shuts down.
+            let _ = refresh_requests.unbounded_send(());
+        }
+        None
+    }
+
+    /// Replaces the metadata with freshly read metadata. Pods that are no
+    /// longer listed are kept for the grace period, as the last lines they
+    /// wrote may still be read after they were deleted.
+    fn replace(&self, pods: HashMap<String, PodMetadata>, now: Instant) {
+        let grace_period = self.deleted_pod_grace_period;
+        let mut state = self.state.write().unwrap();
+        let StoreState {
+            pods: stored,
+            requested,
+            unknown,
+        } = &mut *state;
+
+        stored.retain(|uid, pod| {
+            pods.contains_key(uid) || {
+                let deleted_at = *pod.deleted_at.get_or_insert(now);
+                now.duration_since(deleted_at) < grace_period
+            }
+        });
+        for (uid, metadata) in pods {
+            stored.insert(
+                uid,
+                StoredPod {
+                    metadata,
+                    deleted_at: None,
+                },
+            );
+        }
+
+        *unknown = requested
+            .drain()
+            .filter(|uid| !stored.contains_key(uid))
+            .collect();
+    }
+}
+
+/// The parts of the Kubernetes `PodList` object that are used.
+#[derive(Deserialize)]
+struct PodList {
+    items: Vec<Pod>,
+}
+
+#[derive(Deserialize)]
+struct Pod {
+    metadata: ObjectMeta,
+    #[serde(default)]
+    spec: PodSpec,
+}
+
+#[derive(Deserialize)]
+struct ObjectMeta {
+    name: String,
+    namespace: String,
+    uid: String,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+}
+
+#[derive(Default, Deserialize)]
+struct PodSpec {
+    #[serde(default)]
+    containers: Vec<Container>,
+}
+
+#[derive(Deserialize)]
+struct Container {
+    name: String,
+    image: Option<String>,
+}
+
+impl PodList {
+    fn into_metadata(self) -> HashMap<String, PodMetadata> {
+        self.items
+            .into_iter()
+            .map(|pod| {
+                let container_images = pod
+                    .spec
+                    .containers
+                    .into_iter()
+                    .filter_map(|container| Some((container.name, container.image?)))
+                    .collect();
+                let metadata = PodMetadata {
+                    namespace: pod.metadata.namespace,
+                    name: pod.metadata.name,
+                    labels: pod.metadata.labels,
+                    container_images,
+                };
+                (pod.metadata.uid, metadata)
+            })
+            .collect()
+    }
+}
+
+/// Reads the metadata of the pods on a node.
+pub enum MetadataSource {
+    Api {
+        client: HttpClient,
+        url: Uri,
+        token_file: Option<PathBuf>,
+    },
+    File {
+        path: PathBuf,
+    },
+}
+
+impl MetadataSource {
+    /// Creates the source of pod metadata, limited to the pods scheduled to
+    /// `node_name` if it's known.
+    pub fn new(config: &MetadataConfig, node_name: Option<&str>) -> crate::Result<Self> {
+        match config {
+            MetadataConfig::Api {
+                endpoint,
+                token_file,
+                tls,
+                ..
+            } => {
+                let endpoint = match endpoint {
+                    Some(endpoint) => endpoint.clone(),
+                    None => in_cluster_endpoint().ok_or(BuildError::MissingEndpoint)?,
+                };
+                let mut url = format!("{}/api/v1/pods", endpoint.trim_end_matches('/'));
+                if let Some(node_name) = node_name {
+                    let query = url::form_urlencoded::Serializer::new(String::new())
+                        .append_pair("fieldSelector", &format!("spec.nodeName={}", node_name))
+                        .finish();
+                    url.push('?');
+                    url.push_str(&query);
+                }
+                let url = url.parse::<Uri>().context(super::super::UriParseError)?;
+
+                let tls = match tls {
+                    Some(tls) => Some(tls.clone()),
+                    None if Path::new(SERVICE_ACCOUNT_CA_FILE).exists() => Some(TlsOptions {
+                        ca_file: Some(SERVICE_ACCOUNT_CA_FILE.into()),
+                        ..Default::default()
+                    }),
+                    None => None,
+                };
+                let tls = TlsSettings::from_options(&tls)?;
+
+                let token_file = token_file.clone().or_else(|| {
+                    Some(PathBuf::from(SERVICE_ACCOUNT_TOKEN_FILE)).filter(|path| path.exists())
+                });
+
+                Ok(MetadataSource::Api {
+                    client: HttpClient::new(Resolver, tls)?,
+                    url,
+                    token_file,
+                })
+            }
+            MetadataConfig::File { path, .. } => Ok(MetadataSource::File { path: path.clone() }),
+        }
+    }
+
+    async fn fetch(&self) -> crate::Result<HashMap<String, PodMetadata>> {
+        let (content, format) = match self {
+            MetadataSource::Api {
+                client,
+                url,
+                token_file,
+            } => {
+                let mut request = Request::get(url.clone()).body(Body::empty())?;
+                if let Some(token_file) = token_file {
+                    // Service account tokens are rotated, so the file is
+                    // read again for every request.
+                    let token = tokio::fs::read_to_string(token_file).await?;
+                    Auth::Bearer {
+                        token: token.trim().to_owned(),
+                    }
+                    .apply(&mut request);
+                }
+
+                let response = client.clone().send(request).await?;
+                let (parts, body) = response.into_parts();
+                if !parts.status.is_success() {
+                    return Err(FetchError::UnexpectedStatus {
+                        status: parts.status,
+                    }
+                    .into());
+                }
+                let body = body_to_bytes(body).await?;
+                (String::from_utf8_lossy(&body).into_owned(), Format::Json)
+            }
+            MetadataSource::File { path } => {
+                let format = match Format::from_path(path) {
+                    Some(Format::Yaml) => Format::Yaml,
+                    _ => Format::Json,
+                };
+                (tokio::fs::read_to_string(path).await?, format)
+            }
+        };
+
+        let pods = format
+            .deserialize::<PodList>(&content)
+            .map_err(|errors| FetchError::InvalidPodList { errors })?;
+        Ok(pods.into_metadata())
+    }
+
+    /// Replaces the metadata in `store` with freshly read metadata. When
+    /// reading fails, the metadata already in `store` is kept.
+    pub async fn refresh(&self, store: &PodMetadataStore) {
+        match self.fetch().await {
+            Ok(pods) => store.replace(pods, Instant::now()),
+            Err(error) => emit!(KubernetesLogsMetadataRefreshFailed { error }),
+        }
+    }
+
+    /// Refreshes `store` every `refresh_interval`, and whenever a lookup of
+    /// an unknown pod requests it, until shut down.
+    pub async fn run(
+        self,
+        store: PodMetadataStore,
+        refresh_interval: Duration,
+        refresh_requests: mpsc::UnboundedReceiver<()>,
+        shutdown: ShutdownSignal,
+    ) -> Result<(), ()> {
+        let start = Instant::now() + refresh_interval;
+        let ticks = interval_at(start, refresh_interval).map(|_| ());
+        let mut refreshes = stream::select(ticks, refresh_requests).take_until(shutdown.compat());
+        let mut last_refresh = Instant::now();
+        while refreshes.next().await.is_some() {
+            delay_until(last_refresh + MIN_REFRESH_INTERVAL).await;
+            // Requests made in the meantime are served by the same refresh.
+            while let Some(Some(())) = refreshes.next().now_or_never() {}
+
+            self.refresh(&store).await;
+            last_refresh = Instant::now();
+        }
+        Ok(())
+    }
+}
+
+impl MetadataConfig {
+    pub fn refresh_interval(&self) -> Duration {
+        let secs = match self {
+            MetadataConfig::Api {
+                refresh_interval_secs,
+                ..
+            }
+            | MetadataConfig::File {
+                refresh_interval_secs,
+                ..
+            } => *refresh_interval_secs,
+        };
+        Duration::from_secs(secs)
+    }
+
+    pub fn deleted_pod_grace_period(&self) -> Duration {
+        let secs = match self {
+            MetadataConfig::Api {
+                deleted_pod_grace_period_secs,
+                ..
+            }
+            | MetadataConfig::File {
+                deleted_pod_grace_period_secs,
+                ..
+            } => *deleted_pod_grace_period_secs,
+        };
+        Duration::from_secs(secs)
+    }
+}
+
+fn in_cluster_endpoint() -> Option<String> {
+    let host = env::var("KUBERNETES_SERVICE_HOST").ok()?;
+    let port = env::var("KUBERNETES_SERVICE_PORT").ok()?;
+    Some(format!("https://{}:{}", host, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_util::{next_addr, runtime},
+        Error,
+    };
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Response, Server,
+    };
+    use std::fs;
+    use tempfile::tempdir;
+
+    const PODS: &str = r#"{
+        "kind": "PodList",
+        "apiVersion": "v1",
+        "items": [{
+            "metadata": {
+                "name": "web-6d4cf56db6-x2x8z",
+                "namespace": "shop",
+                "uid": "b1e8a1d4-0f3c-4d56-9a4e-2e1f3b7c9d10",
+                "labels": {"app": "web", "app.kubernetes.io/version": "1.2"}
+            },
+            "spec": {
+                "nodeName": "node-1",
+                "containers": [{"name": "nginx", "image": "nginx:1.19"}]
+            }
+        }]
+    }"#;
+
+    const UID: &str = "b1e8a1d4-0f3c-4d56-9a4e-2e1f3b7c9d10";
+
+    fn expected() -> HashMap<String, PodMetadata> {
+        let labels = vec![("app", "web"), ("app.kubernetes.io/version", "1.2")]
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+            .collect();
+        let container_images = vec![("nginx".to_owned(), "nginx:1.19".to_owned())]
+            .into_iter()
+            .collect();
+        vec![(
+            UID.to_owned(),
+            PodMetadata {
+                namespace: "shop".into(),
+                name: "web-6d4cf56db6-x2x8z".into(),
+                labels,
+                container_images,
+            },
+        )]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn reads_metadata_from_file() {
+        let mut rt = runtime();
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pods.json");
+        fs::write(&path, PODS).unwrap();
+
+        let config = MetadataConfig::File {
+            path,
+            refresh_interval_secs: 1,
+            deleted_pod_grace_period_secs: 1,
+        };
+        let source = MetadataSource::new(&config, None).unwrap();
+        assert_eq!(rt.block_on_std(source.fetch()).unwrap(), expected());
+    }
+
+    #[test]
+    fn reads_metadata_from_api() {
+        let mut rt = runtime();
+        let addr = next_addr();
+        let dir = tempdir().unwrap();
+        let token_file = dir.path().join("token");
+        fs::write(&token_file, "secret\n").unwrap();
+
+        let make_svc = make_service_fn(|_| async {
+            Ok::<_, Error>(service_fn(|request: Request<Body>| async move {
+                let authorized = request
+                    .headers()
+                    .get("Authorization")
+                    .map_or(false, |value| value == "Bearer secret");
+                let selected = request.uri().path() == "/api/v1/pods"
+                    && request.uri().query() == Some("fieldSelector=spec.nodeName%3Dnode-1");
+                let response = if authorized && selected {
+                    Response::new(Body::from(PODS))
+                } else {
+                    Response::builder()
+                        .status(http::StatusCode::FORBIDDEN)
+                        .body(Body::empty())
+                        .unwrap()
+                };
+                Ok::<_, Error>(response)
+            }))
+        });
+        rt.spawn_std(async move {
+            if let Err(error) = Server::bind(&addr).serve(make_svc).await {
+                error!(message = "server error", %error);
+            }
+        });
+
+        let config = MetadataConfig::Api {
+            endpoint: Some(format!("http://{}", addr)),
+            token_file: Some(token_file),
+            tls: None,
+            refresh_interval_secs: 1,
+            deleted_pod_grace_period_secs: 1,
+        };
+        let source = MetadataSource::new(&config, Some("node-1")).unwrap();
+        assert_eq!(rt.block_on_std(source.fetch()).unwrap(), expected());
+
+        let source = MetadataSource::new(&config, Some("node-2")).unwrap();
+        assert!(rt.block_on_std(source.fetch()).is_err());
+    }
+    #[test]
+    fn encodes_node_name() {
+        let config = MetadataConfig::Api {
+            endpoint: Some("http://localhost:8001/".into()),
+            token_file: None,
+            tls: None,
+            refresh_interval_secs: 1,
+            deleted_pod_grace_period_secs: 1,
+        };
+        match MetadataSource::new(&config, Some("node&1")).unwrap() {
+            MetadataSource::Api { url, .. } => assert_eq!(
+                url.to_string(),
+                "http://localhost:8001/api/v1/pods?fieldSelector=spec.nodeName%3Dnode%261"
+            ),
+            MetadataSource::File { .. } => panic!("expected an API source"),
+        }
+    }
+
+    #[test]
+    fn keeps_deleted_pods_for_grace_period() {
+        let (store, _requests) = PodMetadataStore::new(Duration::from_secs(60));
+        let now = Instant::now();
+
+        store.replace(expected(), now);
+        store.replace(HashMap::new(), now + Duration::from_secs(30));
+        assert_eq!(store.get(UID), expected().remove(UID));
+
+        store.replace(HashMap::new(), now + Duration::from_secs(90));
+        assert_eq!(store.get(UID), None);
+    }
+
+    #[test]
+    fn requests_refresh_for_unknown_pods() {
+        let (store, mut requests) = PodMetadataStore::new(Duration::from_secs(60));
+
+        assert_eq!(store.get(UID), None);
+        assert_eq!(store.get(UID), None);
+        assert_eq!(requests.try_next().unwrap(), Some(()));
+        assert!(requests.try_next().is_err());
+
+        // A pod the refresh didn't find doesn't request another one.
+        store.replace(HashMap::new(), Instant::now());
+        assert_eq!(store.get(UID), None);
+        assert!(requests.try_next().is_err());
+
+        store.replace(expected(), Instant::now());
+        assert_eq!(store.get(UID), expected().remove(UID));
+    }
+}
@@ -0,0 +1,1023 @@
+use crate::{
+    event::{
+        self,
+        metric::{Metric, MetricKind, MetricValue},
+        Event, LogEvent, Value,
+    },
+    internal_events::{OtlpEventsReceived, OtlpRequestError},
+    otlp::{
+        encode_message,
+        proto::{
+            self, any_value, metric::Data, number_data_point, AggregationTemporality,
+            ExportLogsServiceRequest, ExportLogsServiceResponse, ExportMetricsServiceRequest,
+            ExportMetricsServiceResponse,
+        },
+        timestamp_from_nanos, ATTRIBUTES_KEY, LOGS_PATH, METRICS_PATH, PROTOBUF_CONTENT_TYPE,
+        RESOURCES_KEY, SEVERITY_NUMBER_KEY, SEVERITY_TEXT_KEY, SPAN_ID_KEY, TRACE_ID_KEY,
+    },
+    shutdown::ShutdownSignal,
+    tls::{MaybeTlsSettings, TlsConfig},
+    topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use chrono::Utc;
+use futures::{
+    compat::{AsyncRead01CompatExt, Future01CompatExt, Stream01CompatExt},
+    FutureExt, TryFutureExt, TryStreamExt,
+};
+use futures01::{sync::mpsc, Sink};
+use http::{header::CONTENT_TYPE, HeaderMap, HeaderValue, Method, Request, Response, StatusCode};
+use hyper::{
+    body::HttpBody,
+    server::accept,
+    service::{make_service_fn, service_fn},
+    Body, Server,
+};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+
+/// The gRPC method of each signal.
+const LOGS_METHOD: &str = "/opentelemetry.proto.collector.logs.v1.LogsService/Export";
+const METRICS_METHOD: &str = "/opentelemetry.proto.collector.metrics.v1.MetricsService/Export";
+
+const GRPC_CONTENT_TYPE: &str = "application/grpc";
+
+/// Offered to TLS clients through ALPN, as gRPC clients require HTTP/2 to be
+/// negotiated, while OTLP/HTTP clients may still use HTTP/1.1.
+const ALPN_PROTOCOLS: &[u8] = b"\x02h2\x08http/1.1";
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OtlpConfig {
+    address: SocketAddr,
+    #[serde(default = "default_max_request_bytes")]
+    max_request_bytes: usize,
+    tls: Option<TlsConfig>,
+}
+
+/// The default maximum message size of gRPC servers.
+fn default_max_request_bytes() -> usize {
+    4 * 1024 * 1024
+}
+
+inventory::submit! {
+    SourceDescription::new_without_default::<OtlpConfig>("otlp")
+}
+
+#[typetag::serde(name = "otlp")]
+impl SourceConfig for OtlpConfig {
+    fn build(
+        &self,
+        _: &str,
+        _: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: mpsc::Sender<Event>,
+    ) -> crate::Result<super::Source> {
+        let tls = MaybeTlsSettings::from_config(&self.tls, true)?;
+        let incoming = tls
+            .bind_with_alpn(&self.address, ALPN_PROTOCOLS)?
+            .incoming();
+
+        info!(message = "building otlp server", addr = %self.address);
+
+        let max_request_bytes = self.max_request_bytes;
+        let service = make_service_fn(move |_| {
+            let out = out.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    handle(request, max_request_bytes, out.clone())
+                }))
+            }
+        });
+
+        let fut = async move {
+            let incoming = incoming.compat().map_ok(|s| s.compat().compat());
+            let _ = Server::builder(accept::from_stream(incoming))
+                .serve(service)
+                .with_graceful_shutdown(shutdown.clone().compat().map(|_| ()))
+                .await;
+            // We need to drop the last copy of ShutdownSignalToken only after server has shut down.
+            drop(shutdown);
+            Ok(())
+        };
+        Ok(Box::new(fut.boxed().compat()))
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Any
+    }
+
+    fn source_type(&self) -> &'static str {
+        "otlp"
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum RequestError {
+    #[snafu(display("Unknown method {} {}", method, path))]
+    UnknownMethod { method: Method, path: String },
+    #[snafu(display("Could not read request body: {}", source))]
+    ReadBody { source: hyper::Error },
+    #[snafu(display("Request body is larger than {} bytes", max_bytes))]
+    TooLarge { max_bytes: usize },
+    #[snafu(display("Invalid gRPC message frame"))]
+    InvalidFrame,
+    #[snafu(display("Compressed gRPC messages are not supported"))]
+    CompressedFrame,
+    #[snafu(display("Could not decode request: {}", source))]
+    Decode { source: prost::DecodeError },
+    #[snafu(display("Downstream is closed"))]
+    ShuttingDown,
+}
+
+impl RequestError {
+    fn status(&self) -> StatusCode {
+        match self {
+            RequestError::UnknownMethod { .. } => StatusCode::NOT_FOUND,
+            RequestError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            RequestError::ReadBody { .. }
+            | RequestError::InvalidFrame
+            | RequestError::CompressedFrame
+            | RequestError::Decode { .. } => StatusCode::BAD_REQUEST,
+            RequestError::ShuttingDown => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    /// The gRPC status code, see https://github.com/grpc/grpc/blob/master/doc/statuscodes.md
+    fn grpc_status(&self) -> u16 {
+        match self {
+            RequestError::UnknownMethod { .. } | RequestError::CompressedFrame => 12,
+            RequestError::TooLarge { .. } => 8,
+            RequestError::ReadBody { .. }
+            | RequestError::InvalidFrame
+            | RequestError::Decode { .. } => 3,
+            RequestError::ShuttingDown => 14,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Signal {
+    Logs,
+    Metrics,
+}
+
+/// Serves both OTLP/HTTP, with binary protobuf bodies, and unary OTLP/gRPC
+/// calls. They are told apart by their content type.
+async fn handle(
+    request: Request<Body>,
+    max_request_bytes: usize,
+    out: mpsc::Sender<Event>,
+) -> Result<Response<ResponseBody>, Infallible> {
+    let grpc = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |value| value.starts_with(GRPC_CONTENT_TYPE));
+
+    let response = match export(request, grpc, max_request_bytes, out).await {
+        Ok(body) if grpc => ResponseBody::grpc(body),
+        Ok(body) => ResponseBody::http(body),
+        Err(error) => {
+            emit!(OtlpRequestError { error: &error });
+            if grpc {
+                ResponseBody::grpc_error(&error)
+            } else {
+                ResponseBody::http_error(&error)
+            }
+        }
+    };
+    Ok(response)
+}
+
+/// Forwards the events of an export request, returning the encoded response
+/// message.
+async fn export(
+    request: Request<Body>,
+    grpc: bool,
+    max_request_bytes: usize,
+    out: mpsc::Sender<Event>,
+) -> Result<Vec<u8>, RequestError> {
+    let (parts, body) = request.into_parts();
+    let signal = match (&parts.method, parts.uri.path(), grpc) {
+        (&Method::POST, LOGS_METHOD, true) | (&Method::POST, LOGS_PATH, false) => Signal::Logs,
+        (&Method::POST, METRICS_METHOD, true) | (&Method::POST, METRICS_PATH, false) => {
+            Signal::Metrics
+        }
+        (method, path, _) => {
+            return Err(RequestError::UnknownMethod {
+                method: method.clone(),
+                path: path.into(),
+            })
+        }
+    };
+
+    let body = read_body(body, max_request_bytes).await?;
+    let message = if grpc {
+        decode_grpc_frame(&body)?
+    } else {
+        &body[..]
+    };
+
+    let (events, response) = match signal {
+        Signal::Logs => {
+            let request = ExportLogsServiceRequest::decode(message).context(Decode)?;
+            (
+                decode_logs(request),
+                encode_message(&ExportLogsServiceResponse {}),
+            )
+        }
+        Signal::Metrics => {
+            let request = ExportMetricsServiceRequest::decode(message).context(Decode)?;
+            (
+                decode_metrics(request),
+                encode_message(&ExportMetricsServiceResponse {}),
+            )
+        }
+    };
+
+    emit!(OtlpEventsReceived {
+        count: events.len(),
+        byte_size: body.len(),
+    });
+
+    out.send_all(futures01::stream::iter_ok(events))
+        .compat()
+        .await
+        .map_err(|_| RequestError::ShuttingDown)?;
+    Ok(response)
+}
+
+/// Reads a request body, giving up as soon as it grows larger than
+/// `max_bytes` rather than buffering all of it.
+async fn read_body(body: Body, max_bytes: usize) -> Result<Bytes, RequestError> {
+    body.map_err(|source| RequestError::ReadBody { source })
+        .try_fold(BytesMut::new(), |mut store, bytes| async move {
+            if store.len() + bytes.len() > max_bytes {
+                return Err(RequestError::TooLarge { max_bytes });
+            }
+            store.extend_from_slice(&bytes);
+            Ok(store)
+        })
+        .await
+        .map(BytesMut::freeze)
+}
+
+/// Reads the single message of a unary gRPC call, which is prefixed by a
+/// compression flag and its length.
+fn decode_grpc_frame(body: &[u8]) -> Result<&[u8], RequestError> {
+    if body.len() < 5 {
+        return Err(RequestError::InvalidFrame);
+    }
+    if body[0] != 0 {
+        return Err(RequestError::CompressedFrame);
+    }
+
+    let len = u32::from_be_bytes([body[1], body[2], body[3], body[4]]) as usize;
+    body.get(5..5 + len).ok_or(RequestError::InvalidFrame)
+}
+
+fn encode_grpc_frame(message: &[u8]) -> BytesMut {
+    let mut frame = BytesMut::with_capacity(message.len() + 5);
+    frame.put_u8(0);
+    frame.put_u32_be(message.len() as u32);
+    frame.put_slice(message);
+    frame
+}
+
+/// A response body that can end with trailers, which gRPC uses to report the
+/// status of a call.
+struct ResponseBody {
+    data: Option<bytes05::Bytes>,
+    trailers: Option<HeaderMap>,
+}
+
+impl ResponseBody {
+    fn http(message: Vec<u8>) -> Response<Self> {
+        let mut response = Response::new(Self {
+            data: Some(message.into()),
+            trailers: None,
+        });
+        response.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static(PROTOBUF_CONTENT_TYPE),
+        );
+        response
+    }
+
+    fn http_error(error: &RequestError) -> Response<Self> {
+        let mut response = Response::new(Self {
+            data: Some(error.to_string().into()),
+            trailers: None,
+        });
+        *response.status_mut() = error.status();
+        response
+    }
+
+    fn grpc(message: Vec<u8>) -> Response<Self> {
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", HeaderValue::from(0));
+
+        let mut response = Response::new(Self {
+            data: Some(encode_grpc_frame(&message).to_vec().into()),
+            trailers: Some(trailers),
+        });
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(GRPC_CONTENT_TYPE));
+        response
+    }
+
+    /// Failed calls are answered with a trailers-only response, which carries
+    /// the status in its headers.
+    fn grpc_error(error: &RequestError) -> Response<Self> {
+        let mut response = Response::new(Self {
+            data: None,
+            trailers: None,
+        });
+        let headers = response.headers_mut();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(GRPC_CONTENT_TYPE));
+        headers.insert("grpc-status", HeaderValue::from(error.grpc_status()));
+        if let Ok(message) = HeaderValue::from_str(&error.to_string()) {
+            headers.insert("grpc-message", message);
+        }
+        response
+    }
+}
+
+impl HttpBody for ResponseBody {
+    type Data = bytes05::Bytes;
+    type Error = Infallible;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Poll::Ready(self.data.take().map(Ok))
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(self.trailers.take()))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.data.is_none() && self.trailers.is_none()
+    }
+}
+
+/// Converts each log record into a log event, with its body as the message
+/// and its attributes and those of its resource as maps.
+fn decode_logs(request: ExportLogsServiceRequest) -> Vec<Event> {
+    let mut events = Vec::new();
+    for resource_logs in request.resource_logs {
+        let resources = resource_logs
+            .resource
+            .map(|resource| decode_attributes(resource.attributes))
+            .unwrap_or_default();
+
+        for record in resource_logs
+            .scope_logs
+            .into_iter()
+            .flat_map(|scope_logs| scope_logs.log_records)
+        {
+            let mut log = LogEvent::default();
+
+            let message = record
+                .body
+                .map(decode_value)
+                .unwrap_or_else(|| Value::from(""));
+            log.insert(event::log_schema().message_key().clone(), message);
+
+            let timestamp = timestamp_from_nanos(record.time_unix_nano)
+                .or_else(|| timestamp_from_nanos(record.observed_time_unix_nano))
+                .unwrap_or_else(Utc::now);
+            log.insert(event::log_schema().timestamp_key().clone(), timestamp);
+            log.insert(event::log_schema().source_type_key().clone(), "otlp");
+
+            if !record.severity_text.is_empty() {
+                log.insert_flat(SEVERITY_TEXT_KEY, record.severity_text);
+            }
+            if record.severity_number != 0 {
+                log.insert_flat(SEVERITY_NUMBER_KEY, record.severity_number as i64);
+            }
+            if !record.trace_id.is_empty() {
+                log.insert_flat(TRACE_ID_KEY, to_hex(&record.trace_id));
+            }
+            if !record.span_id.is_empty() {
+                log.insert_flat(SPAN_ID_KEY, to_hex(&record.span_id));
+            }
+
+            let attributes = decode_attributes(record.attributes);
+            if !attributes.is_empty() {
+                log.insert_flat(ATTRIBUTES_KEY, attributes);
+            }
+            if !resources.is_empty() {
+                log.insert_flat(RESOURCES_KEY, resources.clone());
+            }
+
+            events.push(Event::Log(log));
+        }
+    }
+    events
+}
+
+/// Converts each data point into a metric, tagged with its attributes and
+/// those of its resource.
+fn decode_metrics(request: ExportMetricsServiceRequest) -> Vec<Event> {
+    let mut events = Vec::new();
+    for resource_metrics in request.resource_metrics {
+        let resource_tags = resource_metrics
+            .resource
+            .map(|resource| decode_tags(resource.attributes, BTreeMap::new()))
+            .unwrap_or_default();
+
+        for metric in resource_metrics
+            .scope_metrics
+            .into_iter()
+            .flat_map(|scope_metrics| scope_metrics.metrics)
+        {
+            let name = metric.name;
+            let tags = |attributes| {
+                let tags = decode_tags(attributes, resource_tags.clone());
+                if tags.is_empty() {
+                    None
+                } else {
+                    Some(tags)
+                }
+            };
+            let new_metric = |attributes, time_unix_nano, kind, value| {
+                Event::Metric(Metric {
+                    name: name.clone(),
+                    timestamp: timestamp_from_nanos(time_unix_nano),
+                    tags: tags(attributes),
+                    kind,
+                    value,
+                })
+            };
+
+            match metric.data {
+                Some(Data::Gauge(gauge)) => {
+                    events.extend(gauge.data_points.into_iter().map(|point| {
+                        let value = number_value(&point);
+                        new_metric(
+                            point.attributes,
+                            point.time_unix_nano,
+                            MetricKind::Absolute,
+                            MetricValue::Gauge { value },
+                        )
+                    }))
+                }
+                Some(Data::Sum(sum)) => {
+                    let kind = kind_of(sum.aggregation_temporality);
+                    let monotonic = sum.is_monotonic;
+                    events.extend(sum.data_points.into_iter().map(|point| {
+                        let value = number_value(&point);
+                        let value = if monotonic {
+                            MetricValue::Counter { value }
+                        } else {
+                            MetricValue::Gauge { value }
+                        };
+                        new_metric(point.attributes, point.time_unix_nano, kind, value)
+                    }))
+                }
+                Some(Data::Histogram(histogram)) => {
+                    let kind = kind_of(histogram.aggregation_temporality);
+                    events.extend(histogram.data_points.into_iter().map(|point| {
+                        // Vector's bucket counts are cumulative, like those
+                        // of Prometheus, while OTLP counts each bucket on its
+                        // own and adds one for values over the last bound.
+                        let counts = point
+                            .bucket_counts
+                            .iter()
+                            .take(point.explicit_bounds.len())
+                            .scan(0, |total, count| {
+                                *total += *count as u32;
+                                Some(*total)
+                            })
+                            .collect();
+                        let value = MetricValue::AggregatedHistogram {
+                            buckets: point.explicit_bounds,
+                            counts,
+                            count: point.count as u32,
+                            sum: point.sum,
+                        };
+                        new_metric(point.attributes, point.time_unix_nano, kind, value)
+                    }))
+                }
+                Some(Data::Summary(summary)) => {
+                    events.extend(summary.data_points.into_iter().map(|point| {
+                        let (quantiles, values) = point
+                            .quantile_values
+                            .iter()
+                            .map(|quantile| (quantile.quantile, quantile.value))
+                            .unzip();
+                        let value = MetricValue::AggregatedSummary {
+                            quantiles,
+                            values,
+                            count: point.count as u32,
+                            sum: point.sum,
+                        };
+                        new_metric(
+                            point.attributes,
+                            point.time_unix_nano,
+                            MetricKind::Absolute,
+                            value,
+                        )
+                    }))
+                }
+                None => {}
+            }
+        }
+    }
+    events
+}
+
+fn kind_of(temporality: i32) -> MetricKind {
+    match AggregationTemporality::from_i32(temporality) {
+        Some(AggregationTemporality::Delta) => MetricKind::Incremental,
+        _ => MetricKind::Absolute,
+    }
+}
+
+fn number_value(point: &proto::NumberDataPoint) -> f64 {
+    match point.value {
+        Some(number_data_point::Value::AsDouble(value)) => value,
+        Some(number_data_point::Value::AsInt(value)) => value as f64,
+        None => 0.0,
+    }
+}
+
+fn decode_attributes(attributes: Vec<proto::KeyValue>) -> BTreeMap<String, Value> {
+    attributes
+        .into_iter()
+        .map(|attribute| {
+            let value = attribute.value.map(decode_value).unwrap_or(Value::Null);
+            (attribute.key, value)
+        })
+        .collect()
+}
+
+/// Adds the attributes to `tags`, with nested values rendered as JSON.
+fn decode_tags(
+    attributes: Vec<proto::KeyValue>,
+    mut tags: BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    for (key, value) in decode_attributes(attributes) {
+        let value = match value {
+            Value::Map(_) | Value::Array(_) => {
+                serde_json::to_string(&value).expect("Value is serializable")
+            }
+            value => value.to_string_lossy(),
+        };
+        tags.insert(key, value);
+    }
+    tags
+}
+
+fn decode_value(value: proto::AnyValue) -> Value {
+    match value.value {
+        Some(any_value::Value::StringValue(value)) => value.into(),
+        Some(any_value::Value::BoolValue(value)) => value.into(),
+        Some(any_value::Value::IntValue(value)) => value.into(),
+        Some(any_value::Value::DoubleValue(value)) => value.into(),
+        Some(any_value::Value::ArrayValue(array)) => {
+            Value::Array(array.values.into_iter().map(decode_value).collect())
+        }
+        Some(any_value::Value::KvlistValue(list)) => decode_attributes(list.values).into(),
+        Some(any_value::Value::BytesValue(value)) => Value::Bytes(value.into()),
+        None => Value::Null,
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        runtime::Runtime,
+        test_util::{self, collect_n, runtime},
+        tls::TlsOptions,
+    };
+    use chrono::TimeZone;
+    use hyper::client::HttpConnector;
+    use hyper_openssl::HttpsConnector;
+    use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+    use pretty_assertions::assert_eq;
+
+    fn source(rt: &mut Runtime) -> (mpsc::Receiver<Event>, SocketAddr) {
+        source_with(rt, default_max_request_bytes(), None)
+    }
+
+    fn source_with(
+        rt: &mut Runtime,
+        max_request_bytes: usize,
+        tls: Option<TlsConfig>,
+    ) -> (mpsc::Receiver<Event>, SocketAddr) {
+        test_util::trace_init();
+        let (sender, recv) = mpsc::channel(100);
+        let address = test_util::next_addr();
+        rt.spawn(
+            OtlpConfig {
+                address,
+                max_request_bytes,
+                tls,
+            }
+            .build(
+                "default",
+                &GlobalOptions::default(),
+                ShutdownSignal::noop(),
+                sender,
+            )
+            .unwrap(),
+        );
+        (recv, address)
+    }
+
+    fn attribute(key: &str, value: any_value::Value) -> proto::KeyValue {
+        proto::KeyValue {
+            key: key.into(),
+            value: Some(proto::AnyValue { value: Some(value) }),
+        }
+    }
+
+    fn string(value: &str) -> any_value::Value {
+        any_value::Value::StringValue(value.into())
+    }
+
+    fn resource() -> Option<proto::Resource> {
+        Some(proto::Resource {
+            attributes: vec![attribute("service.name", string("checkout"))],
+            dropped_attributes_count: 0,
+        })
+    }
+
+    fn logs_request() -> ExportLogsServiceRequest {
+        ExportLogsServiceRequest {
+            resource_logs: vec![proto::ResourceLogs {
+                resource: resource(),
+                scope_logs: vec![proto::ScopeLogs {
+                    scope: None,
+                    log_records: vec![proto::LogRecord {
+                        time_unix_nano: 1_593_606_615_000_000_000,
+                        severity_number: 9,
+                        severity_text: "INFO".into(),
+                        body: Some(proto::AnyValue {
+                            value: Some(string("order placed")),
+                        }),
+                        attributes: vec![attribute("items", any_value::Value::IntValue(3))],
+                        trace_id: vec![0xab; 16],
+                        ..Default::default()
+                    }],
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        }
+    }
+
+    fn number_point(attributes: Vec<proto::KeyValue>, value: f64) -> proto::NumberDataPoint {
+        proto::NumberDataPoint {
+            attributes,
+            time_unix_nano: 1_593_606_615_000_000_000,
+            value: Some(number_data_point::Value::AsDouble(value)),
+            ..Default::default()
+        }
+    }
+
+    fn metrics_request(metrics: Vec<proto::Metric>) -> ExportMetricsServiceRequest {
+        ExportMetricsServiceRequest {
+            resource_metrics: vec![proto::ResourceMetrics {
+                resource: resource(),
+                scope_metrics: vec![proto::ScopeMetrics {
+                    scope: None,
+                    metrics,
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        }
+    }
+
+    fn metric(name: &str, data: Data) -> proto::Metric {
+        proto::Metric {
+            name: name.into(),
+            description: String::new(),
+            unit: String::new(),
+            data: Some(data),
+        }
+    }
+
+    #[test]
+    fn decodes_log_records() {
+        let events = decode_logs(logs_request());
+        assert_eq!(events.len(), 1);
+        let log = events[0].as_log();
+
+        assert_eq!(log[&"message".into()], "order placed".into());
+        assert_eq!(
+            log[&"timestamp".into()],
+            Utc.ymd(2020, 7, 1).and_hms(12, 30, 15).into()
+        );
+        assert_eq!(log[&"severity_text".into()], "INFO".into());
+        assert_eq!(log[&"severity_number".into()], 9.into());
+        assert_eq!(log[&"trace_id".into()], "ab".repeat(16).into());
+        assert_eq!(log[&"attributes.items".into()], 3.into());
+        let resources = vec![("service.name".to_owned(), Value::from("checkout"))];
+        assert_eq!(
+            log.get_flat("resources"),
+            Some(&Value::Map(resources.into_iter().collect()))
+        );
+    }
+
+    #[test]
+    fn decodes_metrics_by_type() {
+        let request = metrics_request(vec![
+            metric(
+                "requests",
+                Data::Sum(proto::Sum {
+                    data_points: vec![number_point(vec![attribute("code", string("200"))], 7.0)],
+                    aggregation_temporality: AggregationTemporality::Delta as i32,
+                    is_monotonic: true,
+                }),
+            ),
+            metric(
+                "connections",
+                Data::Sum(proto::Sum {
+                    data_points: vec![number_point(vec![], 3.0)],
+                    aggregation_temporality: AggregationTemporality::Cumulative as i32,
+                    is_monotonic: false,
+                }),
+            ),
+            metric(
+                "latency",
+                Data::Histogram(proto::Histogram {
+                    data_points: vec![proto::HistogramDataPoint {
+                        count: 6,
+                        sum: 12.5,
+                        bucket_counts: vec![1, 2, 3],
+                        explicit_bounds: vec![1.0, 2.0],
+                        ..Default::default()
+                    }],
+                    aggregation_temporality: AggregationTemporality::Cumulative as i32,
+                }),
+            ),
+            metric(
+                "duration",
+                Data::Summary(proto::Summary {
+                    data_points: vec![proto::SummaryDataPoint {
+                        count: 10,
+                        sum: 20.0,
+                        quantile_values: vec![proto::summary_data_point::ValueAtQuantile {
+                            quantile: 0.5,
+                            value: 1.5,
+                        }],
+                        ..Default::default()
+                    }],
+                }),
+            ),
+        ]);
+
+        let metrics = decode_metrics(request)
+            .into_iter()
+            .map(Event::into_metric)
+            .collect::<Vec<_>>();
+        let tags = |extra: &[(&str, &str)]| {
+            Some(
+                extra
+                    .iter()
+                    .chain(&[("service.name", "checkout")])
+                    .map(|(key, value)| ((*key).into(), (*value).into()))
+                    .collect(),
+            )
+        };
+        let timestamp = Some(Utc.ymd(2020, 7, 1).and_hms(12, 30, 15));
+
+        assert_eq!(
+            metrics,
+            vec![
+                Metric {
+                    name: "requests".into(),
+                    timestamp,
+                    tags: tags(&[("code", "200")]),
+                    kind: MetricKind::Incremental,
+                    value: MetricValue::Counter { value: 7.0 },
+                },
+                Metric {
+                    name: "connections".into(),
+                    timestamp,
+                    tags: tags(&[]),
+                    kind: MetricKind::Absolute,
+                    value: MetricValue::Gauge { value: 3.0 },
+                },
+                Metric {
+                    name: "latency".into(),
+                    timestamp: None,
+                    tags: tags(&[]),
+                    kind: MetricKind::Absolute,
+                    value: MetricValue::AggregatedHistogram {
+                        buckets: vec![1.0, 2.0],
+                        counts: vec![1, 3],
+                        count: 6,
+                        sum: 12.5,
+                    },
+                },
+                Metric {
+                    name: "duration".into(),
+                    timestamp: None,
+                    tags: tags(&[]),
+                    kind: MetricKind::Absolute,
+                    value: MetricValue::AggregatedSummary {
+                        quantiles: vec![0.5],
+                        values: vec![1.5],
+                        count: 10,
+                        sum: 20.0,
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn receives_http_requests() {
+        let mut rt = runtime();
+        let (rx, addr) = source(&mut rt);
+
+        let metrics = metrics_request(vec![metric(
+            "temperature",
+            Data::Gauge(proto::Gauge {
+                data_points: vec![number_point(vec![], 21.5)],
+            }),
+        )]);
+
+        rt.block_on_std(async move {
+            let client = reqwest::Client::new();
+            let response = client
+                .post(&format!("http://{}/v1/logs", addr))
+                .header("Content-Type", PROTOBUF_CONTENT_TYPE)
+                .body(encode_message(&logs_request()))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), 200);
+            assert_eq!(response.headers()["Content-Type"], PROTOBUF_CONTENT_TYPE);
+
+            let status = client
+                .post(&format!("http://{}/v1/metrics", addr))
+                .body(encode_message(&metrics))
+                .send()
+                .await
+                .unwrap()
+                .status();
+            assert_eq!(status, 200);
+
+            let status = client
+                .post(&format!("http://{}/v1/metrics", addr))
+                .body("not protobuf")
+                .send()
+                .await
+                .unwrap()
+                .status();
+            assert_eq!(status, 400);
+
+            let status = client
+                .post(&format!("http://{}/v1/traces", addr))
+                .body(vec![])
+                .send()
+                .await
+                .unwrap()
+                .status();
+            assert_eq!(status, 404);
+
+            let events = collect_n(rx, 2).compat().await.unwrap();
+            assert_eq!(events[0].as_log()[&"message".into()], "order placed".into());
+            assert_eq!(events[1].as_metric().name, "temperature");
+        });
+    }
+
+    #[test]
+    fn receives_grpc_requests() {
+        let mut rt = runtime();
+        let (rx, addr) = source(&mut rt);
+
+        rt.block_on_std(async move {
+            let client = hyper::Client::builder()
+                .http2_only(true)
+                .build_http::<Body>();
+            let request = Request::post(format!("http://{}{}", addr, LOGS_METHOD))
+                .header(CONTENT_TYPE, GRPC_CONTENT_TYPE)
+                .header("te", "trailers")
+                .body(Body::from(
+                    encode_grpc_frame(&encode_message(&logs_request())).to_vec(),
+                ))
+                .unwrap();
+
+            let response = client.request(request).await.unwrap();
+            assert_eq!(response.status(), 200);
+            let mut body = response.into_body();
+            let mut message = Vec::new();
+            while let Some(chunk) = body.data().await {
+                message.extend_from_slice(&chunk.unwrap());
+            }
+            let trailers = body.trailers().await.unwrap().unwrap();
+
+            assert_eq!(decode_grpc_frame(&message).unwrap(), &[] as &[u8]);
+            assert_eq!(trailers["grpc-status"], "0");
+
+            let events = collect_n(rx, 1).compat().await.unwrap();
+            assert_eq!(events[0].as_log()[&"message".into()], "order placed".into());
+        });
+    }
+
+    #[test]
+    fn receives_grpc_requests_over_tls() {
+        let mut rt = runtime();
+        let tls = TlsConfig {
+            enabled: Some(true),
+            options: TlsOptions {
+                crt_file: Some("tests/data/localhost.crt".into()),
+                key_file: Some("tests/data/localhost.key".into()),
+                ..Default::default()
+            },
+        };
+        let (rx, addr) = source_with(&mut rt, default_max_request_bytes(), Some(tls));
+
+        rt.block_on_std(async move {
+            let mut connector = HttpConnector::new();
+            connector.enforce_http(false);
+            let mut ssl = SslConnector::builder(SslMethod::tls()).unwrap();
+            ssl.set_verify(SslVerifyMode::NONE);
+            ssl.set_alpn_protos(ALPN_PROTOCOLS).unwrap();
+            // Unlike over plaintext, HTTP/2 isn't forced on the client, so
+            // that it is only used if the server negotiates it.
+            let client = hyper::Client::builder()
+                .build::<_, Body>(HttpsConnector::with_connector(connector, ssl).unwrap());
+            let request = Request::post(format!("https://{}{}", addr, LOGS_METHOD))
+                .header(CONTENT_TYPE, GRPC_CONTENT_TYPE)
+                .header("te", "trailers")
+                .body(Body::from(
+                    encode_grpc_frame(&encode_message(&logs_request())).to_vec(),
+                ))
+                .unwrap();
+
+            let response = client.request(request).await.unwrap();
+            assert_eq!(response.status(), 200);
+            assert_eq!(response.version(), http::Version::HTTP_2);
+            let mut body = response.into_body();
+            while let Some(chunk) = body.data().await {
+                chunk.unwrap();
+            }
+            let trailers = body.trailers().await.unwrap().unwrap();
+            assert_eq!(trailers["grpc-status"], "0");
+
+            let events = collect_n(rx, 1).compat().await.unwrap();
+            assert_eq!(events[0].as_log()[&"message".into()], "order placed".into());
+        });
+    }
+
+    #[test]
+    fn rejects_large_requests() {
+        let mut rt = runtime();
+        let (_rx, addr) = source_with(&mut rt, 16, None);
+
+        rt.block_on_std(async move {
+            let status = reqwest::Client::new()
+                .post(&format!("http://{}/v1/logs", addr))
+                .body(encode_message(&logs_request()))
+                .send()
+                .await
+                .unwrap()
+                .status();
+            assert_eq!(status, 413);
+        });
+    }
+
+    #[test]
+    fn rejects_compressed_grpc_frames() {
+        let mut frame = encode_grpc_frame(b"message");
+        frame[0] = 1;
+
+        assert!(matches!(
+            decode_grpc_frame(&frame),
+            Err(RequestError::CompressedFrame)
+        ));
+        assert!(matches!(
+            decode_grpc_frame(&frame[..3]),
+            Err(RequestError::InvalidFrame)
+        ));
+    }
+}
@@ -1,35 +1,107 @@
 use crate::{
+    dns::Resolver,
+    event::metric::{Metric, MetricKind, MetricValue},
     hyper::body_to_bytes,
-    internal_events::{PrometheusHttpError, PrometheusParseError, PrometheusRequestCompleted},
+    internal_events::{
+        PrometheusFileSdError, PrometheusHttpError, PrometheusParseError,
+        PrometheusRequestCompleted,
+    },
     shutdown::ShutdownSignal,
-    stream::StreamExt as VStreamExt,
-    topology::config::GlobalOptions,
+    sinks::util::http::{Auth, HttpClient},
+    tls::{TlsOptions, TlsSettings},
+    topology::config::{DataType, Format, GlobalOptions, SourceConfig},
     Event,
 };
-use futures::{FutureExt, TryFutureExt};
-use futures01::{sync::mpsc, Future, Sink, Stream as Stream01};
-use hyper::{Body, Client, Request};
-use hyper_openssl::HttpsConnector;
+use bytes::Bytes;
+use chrono::Utc;
+use futures::{
+    compat::Future01CompatExt,
+    future::{join_all, FutureExt, TryFutureExt},
+    stream::StreamExt,
+};
+use futures01::{sync::mpsc, Sink};
+use glob::glob;
+use http::{uri::PathAndQuery, StatusCode, Uri};
+use hyper::{Body, Request};
 use serde::{Deserialize, Serialize};
-use snafu::ResultExt;
-use std::time::{Duration, Instant};
-use tokio01::timer::Interval;
+use snafu::{ResultExt, Snafu};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tokio::time::{interval, timeout};
 
 pub mod parser;
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 struct PrometheusConfig {
+    #[serde(default)]
     hosts: Vec<String>,
+    #[serde(default)]
+    targets: Vec<TargetGroup>,
+    file_sd: Option<FileSdConfig>,
     #[serde(default = "default_scrape_interval_secs")]
     scrape_interval_secs: u64,
+    #[serde(default = "default_scrape_timeout_secs")]
+    scrape_timeout_secs: u64,
+    #[serde(default = "default_metrics_path")]
+    metrics_path: String,
+    auth: Option<Auth>,
+    tls: Option<TlsOptions>,
+}
+
+/// A group of targets sharing the same labels, as used by Prometheus' file
+/// based service discovery.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct TargetGroup {
+    targets: Vec<String>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+struct FileSdConfig {
+    files: Vec<PathBuf>,
+    #[serde(default = "default_refresh_interval_secs")]
+    refresh_interval_secs: u64,
 }
 
 pub fn default_scrape_interval_secs() -> u64 {
     15
 }
 
+pub fn default_scrape_timeout_secs() -> u64 {
+    10
+}
+
+fn default_metrics_path() -> String {
+    "/metrics".into()
+}
+
+fn default_refresh_interval_secs() -> u64 {
+    30
+}
+
+impl Default for PrometheusConfig {
+    fn default() -> Self {
+        Self {
+            hosts: Vec::new(),
+            targets: Vec::new(),
+            file_sd: None,
+            scrape_interval_secs: default_scrape_interval_secs(),
+            scrape_timeout_secs: default_scrape_timeout_secs(),
+            metrics_path: default_metrics_path(),
+            auth: None,
+            tls: None,
+        }
+    }
+}
+
 #[typetag::serde(name = "prometheus")]
-impl crate::topology::config::SourceConfig for PrometheusConfig {
+impl SourceConfig for PrometheusConfig {
     fn build(
         &self,
         _name: &str,
@@ -37,16 +109,42 @@ impl crate::topology::config::SourceConfig for PrometheusConfig {
         shutdown: ShutdownSignal,
         out: mpsc::Sender<Event>,
     ) -> crate::Result<super::Source> {
-        let mut urls = Vec::new();
-        for host in self.hosts.iter() {
-            let base_uri = host.parse::<http::Uri>().context(super::UriParseError)?;
-            urls.push(format!("{}metrics", base_uri));
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+
+        let mut targets = Vec::new();
+        for host in &self.hosts {
+            targets.push(Target::new(
+                host,
+                &self.metrics_path,
+                scheme,
+                BTreeMap::new(),
+            )?);
+        }
+        for group in &self.targets {
+            targets.extend(group.resolve(&self.metrics_path, scheme)?);
         }
-        Ok(prometheus(urls, self.scrape_interval_secs, shutdown, out))
+
+        let file_sd = match &self.file_sd {
+            Some(config) => Some(FileSd::new(config, &self.metrics_path, scheme)?),
+            None => None,
+        };
+
+        let tls = TlsSettings::from_options(&self.tls)?;
+        let scraper = Scraper {
+            client: HttpClient::new(Resolver, tls)?,
+            auth: self.auth.clone(),
+            timeout: Duration::from_secs(self.scrape_timeout_secs),
+        };
+
+        let period = Duration::from_secs(self.scrape_interval_secs);
+        let fut = run(scraper, targets, file_sd, period, out, shutdown)
+            .boxed()
+            .compat();
+        Ok(Box::new(fut))
     }
 
-    fn output_type(&self) -> crate::topology::config::DataType {
-        crate::topology::config::DataType::Metric
+    fn output_type(&self) -> DataType {
+        DataType::Metric
     }
 
     fn source_type(&self) -> &'static str {
@@ -54,74 +152,478 @@ impl crate::topology::config::SourceConfig for PrometheusConfig {
     }
 }
 
-fn prometheus(
-    urls: Vec<String>,
-    interval: u64,
-    shutdown: ShutdownSignal,
-    out: mpsc::Sender<Event>,
-) -> super::Source {
-    let out = out.sink_map_err(|e| error!("error sending metric: {:?}", e));
-
-    let task = Interval::new(Instant::now(), Duration::from_secs(interval))
-        .map_err(|e| error!("timer error: {:?}", e))
-        .take_until(shutdown)
-        .map(move |_| futures01::stream::iter_ok(urls.clone()))
-        .flatten()
-        .map(move |url| {
-            let https = HttpsConnector::new().expect("TLS initialization failed");
-            let client = Client::builder().build(https);
-
-            let request = Request::get(&url)
-                .body(Body::empty())
-                .expect("error creating request");
+#[derive(Debug, Snafu)]
+enum ScrapeError {
+    #[snafu(display("Unexpected status: {}", status))]
+    UnexpectedStatus { status: StatusCode },
+    #[snafu(display("Scrape timed out after {:?}", timeout))]
+    Timeout { timeout: Duration },
+}
 
-            client
-                .request(request)
-                .and_then(|response| body_to_bytes(response.into_body()).boxed())
-                .compat()
-                .map(|body| {
-                    emit!(PrometheusRequestCompleted);
-
-                    let packet = String::from_utf8_lossy(&body);
-                    let metrics = parser::parse(&packet)
-                        .map_err(|error| {
-                            emit!(PrometheusParseError { error });
-                        })
-                        .unwrap_or_default()
-                        .into_iter()
-                        .map(Event::Metric);
-
-                    futures01::stream::iter_ok(metrics)
-                })
-                .flatten_stream()
-                .map_err(|error| {
-                    emit!(PrometheusHttpError { error });
-                })
+#[derive(Clone, Debug, PartialEq)]
+struct Target {
+    url: Uri,
+    labels: BTreeMap<String, String>,
+}
+
+impl Target {
+    /// Builds the URL to scrape from a host, defaulting to `scheme` and
+    /// appending `metrics_path` to any path the host already has.
+    fn new(
+        host: &str,
+        metrics_path: &str,
+        scheme: &str,
+        labels: BTreeMap<String, String>,
+    ) -> crate::Result<Self> {
+        let host = if host.contains("://") {
+            host.to_owned()
+        } else {
+            format!("{}://{}", scheme, host)
+        };
+        let base = host.parse::<Uri>().context(super::UriParseError)?;
+
+        let path = format!(
+            "{}/{}",
+            base.path().trim_end_matches('/'),
+            metrics_path.trim_start_matches('/')
+        );
+        let mut parts = base.into_parts();
+        parts.path_and_query = Some(path.parse::<PathAndQuery>().context(super::UriParseError)?);
+
+        Ok(Self {
+            url: Uri::from_parts(parts)?,
+            labels,
+        })
+    }
+
+    /// The `up` gauge, tagged with the target labels and its `instance`.
+    fn up(&self, up: bool) -> Event {
+        let mut tags = self.labels.clone();
+        tags.entry("instance".into()).or_insert_with(|| {
+            self.url
+                .authority()
+                .map(|authority| authority.to_string())
+                .unwrap_or_default()
+        });
+
+        Event::Metric(Metric {
+            name: "up".into(),
+            timestamp: Some(Utc::now()),
+            tags: Some(tags),
+            kind: MetricKind::Absolute,
+            value: MetricValue::Gauge {
+                value: if up { 1.0 } else { 0.0 },
+            },
         })
-        .flatten()
-        .forward(out)
-        .map(|_| info!("finished sending"));
+    }
+}
+
+impl TargetGroup {
+    fn resolve(&self, metrics_path: &str, scheme: &str) -> crate::Result<Vec<Target>> {
+        self.targets
+            .iter()
+            .map(|host| Target::new(host, metrics_path, scheme, self.labels.clone()))
+            .collect()
+    }
+}
+
+/// Targets discovered from JSON or YAML files of target groups. The files are
+/// re-read every `refresh_interval_secs`, so targets can change without a
+/// config reload.
+struct FileSd {
+    patterns: Vec<String>,
+    refresh_interval: Duration,
+    metrics_path: String,
+    scheme: &'static str,
+    targets: HashMap<PathBuf, Vec<Target>>,
+    refreshed: Option<Instant>,
+}
+
+impl FileSd {
+    fn new(config: &FileSdConfig, metrics_path: &str, scheme: &'static str) -> crate::Result<Self> {
+        let mut patterns = Vec::new();
+        for path in &config.files {
+            let pattern = path
+                .to_str()
+                .ok_or_else(|| format!("Path {:?} is not valid UTF-8.", path))?;
+            glob::Pattern::new(pattern)?;
+            patterns.push(pattern.to_owned());
+        }
+
+        Ok(Self {
+            patterns,
+            refresh_interval: Duration::from_secs(config.refresh_interval_secs),
+            metrics_path: metrics_path.to_owned(),
+            scheme,
+            targets: HashMap::new(),
+            refreshed: None,
+        })
+    }
+
+    async fn refresh(&mut self) {
+        if let Some(refreshed) = self.refreshed {
+            if refreshed.elapsed() < self.refresh_interval {
+                return;
+            }
+        }
+        self.refreshed = Some(Instant::now());
 
-    Box::new(task)
+        let mut targets = HashMap::new();
+        for path in self.paths() {
+            match self.load(&path).await {
+                Ok(loaded) => {
+                    targets.insert(path, loaded);
+                }
+                Err(error) => {
+                    emit!(PrometheusFileSdError {
+                        path: path.clone(),
+                        error,
+                    });
+                    // Keep scraping the targets from the last good read.
+                    if let Some(previous) = self.targets.remove(&path) {
+                        targets.insert(path, previous);
+                    }
+                }
+            }
+        }
+        self.targets = targets;
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        let mut paths = self
+            .patterns
+            .iter()
+            .filter_map(|pattern| glob(pattern).ok())
+            .flat_map(|paths| paths.filter_map(Result::ok))
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    async fn load(&self, path: &Path) -> crate::Result<Vec<Target>> {
+        let content = tokio::fs::read_to_string(path).await?;
+        let format = match Format::from_path(path) {
+            Some(Format::Yaml) => Format::Yaml,
+            _ => Format::Json,
+        };
+        let groups = format
+            .deserialize::<Vec<TargetGroup>>(&content)
+            .map_err(|errors| errors.join(", "))?;
+
+        let mut targets = Vec::new();
+        for group in groups {
+            targets.extend(group.resolve(&self.metrics_path, self.scheme)?);
+        }
+        Ok(targets)
+    }
+
+    fn targets(&self) -> impl Iterator<Item = &Target> {
+        self.targets.values().flatten()
+    }
+}
+
+#[derive(Clone)]
+struct Scraper {
+    client: HttpClient,
+    auth: Option<Auth>,
+    timeout: Duration,
+}
+
+impl Scraper {
+    /// Scrapes a target, returning its metrics along with its `up` gauge.
+    async fn scrape(&self, target: &Target) -> Vec<Event> {
+        let body = match timeout(self.timeout, self.fetch(target)).await {
+            Ok(result) => result,
+            Err(_) => Err(ScrapeError::Timeout {
+                timeout: self.timeout,
+            }
+            .into()),
+        };
+
+        let metrics = match body {
+            Ok(body) => {
+                emit!(PrometheusRequestCompleted);
+                let packet = String::from_utf8_lossy(&body);
+                match parser::parse(&packet) {
+                    Ok(metrics) => Some(metrics),
+                    Err(error) => {
+                        emit!(PrometheusParseError { error });
+                        None
+                    }
+                }
+            }
+            Err(error) => {
+                emit!(PrometheusHttpError {
+                    error,
+                    url: target.url.clone(),
+                });
+                None
+            }
+        };
+
+        let up = target.up(metrics.is_some());
+        metrics
+            .unwrap_or_default()
+            .into_iter()
+            .map(|mut metric| {
+                if !target.labels.is_empty() {
+                    metric
+                        .tags
+                        .get_or_insert_with(BTreeMap::new)
+                        .extend(target.labels.clone());
+                }
+                Event::Metric(metric)
+            })
+            .chain(std::iter::once(up))
+            .collect()
+    }
+
+    async fn fetch(&self, target: &Target) -> crate::Result<Bytes> {
+        let mut request = Request::get(target.url.clone()).body(Body::empty())?;
+        if let Some(auth) = &self.auth {
+            auth.apply(&mut request);
+        }
+
+        let response = self.client.clone().send(request).await?;
+        let (parts, body) = response.into_parts();
+        if !parts.status.is_success() {
+            return Err(ScrapeError::UnexpectedStatus {
+                status: parts.status,
+            }
+            .into());
+        }
+
+        Ok(body_to_bytes(body).await?)
+    }
+}
+
+async fn run(
+    scraper: Scraper,
+    targets: Vec<Target>,
+    mut file_sd: Option<FileSd>,
+    period: Duration,
+    mut out: mpsc::Sender<Event>,
+    shutdown: ShutdownSignal,
+) -> Result<(), ()> {
+    let mut interval = interval(period).take_until(shutdown.compat());
+
+    while interval.next().await.is_some() {
+        if let Some(file_sd) = &mut file_sd {
+            file_sd.refresh().await;
+        }
+
+        let discovered = file_sd.iter().flat_map(|file_sd| file_sd.targets());
+        let scrapes = targets
+            .iter()
+            .chain(discovered)
+            .map(|target| scraper.scrape(target));
+        let events = join_all(scrapes).await.into_iter().flatten();
+
+        let (sink, _) = out
+            .send_all(futures01::stream::iter_ok(events))
+            .compat()
+            .await
+            .map_err(|error| error!(message = "error sending metric", %error))?;
+        out = sink;
+    }
+
+    info!("finished sending");
+    Ok(())
 }
 
-#[cfg(feature = "sinks-prometheus")]
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::{
-        hyper::body_to_bytes,
-        sinks::prometheus::PrometheusSinkConfig,
-        test_util::{block_on, next_addr, runtime},
-        topology::{self, config},
+        test_util::{collect_n, next_addr, runtime},
         Error,
     };
-    use futures::TryFutureExt;
     use hyper::service::{make_service_fn, service_fn};
-    use hyper::{Body, Client, Response, Server};
+    use hyper::{Response, Server};
     use pretty_assertions::assert_eq;
-    use std::{thread, time::Duration};
+    use std::fs;
+    use tempfile::tempdir;
+    #[cfg(feature = "sinks-prometheus")]
+    use {
+        crate::{
+            sinks::prometheus::PrometheusSinkConfig,
+            test_util::block_on,
+            topology::{self, config},
+        },
+        hyper::Client,
+        std::thread,
+    };
+
+    fn labels(labels: &[(&str, &str)]) -> BTreeMap<String, String> {
+        labels
+            .iter()
+            .map(|(name, value)| ((*name).into(), (*value).into()))
+            .collect()
+    }
+
+    #[test]
+    fn builds_target_urls() {
+        let url = |host: &str, metrics_path: &str, scheme: &str| {
+            Target::new(host, metrics_path, scheme, BTreeMap::new())
+                .unwrap()
+                .url
+                .to_string()
+        };
+
+        assert_eq!(
+            url("http://localhost:9090", "/metrics", "http"),
+            "http://localhost:9090/metrics"
+        );
+        assert_eq!(
+            url("localhost:9090", "/metrics", "https"),
+            "https://localhost:9090/metrics"
+        );
+        assert_eq!(
+            url("http://localhost:9090/prefix/", "metrics", "http"),
+            "http://localhost:9090/prefix/metrics"
+        );
+        assert_eq!(
+            url("http://localhost:9090", "/federate", "http"),
+            "http://localhost:9090/federate"
+        );
+        assert!(Target::new("http://local host", "/metrics", "http", BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn discovers_targets_from_files() {
+        let mut rt = runtime();
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("web.json"),
+            r#"[{"targets": ["web:9100"], "labels": {"job": "web"}}]"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("db.yml"),
+            "- targets: [\"db:9100\", \"db:9187\"]\n",
+        )
+        .unwrap();
+
+        let config = FileSdConfig {
+            files: vec![dir.path().join("*.json"), dir.path().join("*.yml")],
+            refresh_interval_secs: 0,
+        };
+        let mut file_sd = FileSd::new(&config, "/metrics", "http").unwrap();
+
+        let targets = |file_sd: &FileSd| {
+            let mut targets = file_sd
+                .targets()
+                .map(|target| (target.url.to_string(), target.labels.clone()))
+                .collect::<Vec<_>>();
+            targets.sort();
+            targets
+        };
+        let expected = vec![
+            ("http://db:9100/metrics".to_owned(), labels(&[])),
+            ("http://db:9187/metrics".to_owned(), labels(&[])),
+            (
+                "http://web:9100/metrics".to_owned(),
+                labels(&[("job", "web")]),
+            ),
+        ];
+
+        rt.block_on_std(file_sd.refresh());
+        assert_eq!(targets(&file_sd), expected);
+
+        // Unreadable files keep their last discovered targets.
+        fs::write(dir.path().join("web.json"), "not json").unwrap();
+        rt.block_on_std(file_sd.refresh());
+        assert_eq!(targets(&file_sd), expected);
+
+        fs::remove_file(dir.path().join("db.yml")).unwrap();
+        rt.block_on_std(file_sd.refresh());
+        assert_eq!(targets(&file_sd), expected[2..].to_vec());
+    }
+
+    #[test]
+    fn scrapes_targets_with_labels_and_up() {
+        let mut rt = runtime();
+        let in_addr = next_addr();
+        let down_addr = next_addr();
+
+        let make_svc = make_service_fn(|_| async {
+            Ok::<_, Error>(service_fn(|request: Request<Body>| async move {
+                let authorized = request
+                    .headers()
+                    .get("Authorization")
+                    .map_or(false, |value| value == "Bearer secret");
+                let response = if authorized {
+                    Response::new(Body::from("requests_total{code=\"200\"} 100\n"))
+                } else {
+                    Response::builder()
+                        .status(StatusCode::UNAUTHORIZED)
+                        .body(Body::empty())
+                        .unwrap()
+                };
+                Ok::<_, Error>(response)
+            }))
+        });
+
+        rt.spawn_std(async move {
+            if let Err(e) = Server::bind(&in_addr).serve(make_svc).await {
+                error!("server error: {:?}", e);
+            }
+        });
+
+        let (tx, rx) = mpsc::channel(10);
+        let source = PrometheusConfig {
+            hosts: vec![format!("http://{}", down_addr)],
+            targets: vec![TargetGroup {
+                targets: vec![in_addr.to_string()],
+                labels: labels(&[("job", "api")]),
+            }],
+            auth: Some(Auth::Bearer {
+                token: "secret".into(),
+            }),
+            ..Default::default()
+        }
+        .build("in", &GlobalOptions::default(), ShutdownSignal::noop(), tx)
+        .unwrap();
+        rt.spawn(source);
+
+        let events = rt
+            .block_on_std(async move { collect_n(rx, 3).compat().await })
+            .unwrap();
+        let metrics = events
+            .into_iter()
+            .map(|event| {
+                let metric = event.into_metric();
+                (metric.name, metric.tags, metric.value)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            metrics,
+            vec![
+                (
+                    "up".into(),
+                    Some(labels(&[("instance", &down_addr.to_string())])),
+                    MetricValue::Gauge { value: 0.0 },
+                ),
+                (
+                    "requests_total".into(),
+                    Some(labels(&[("code", "200"), ("job", "api")])),
+                    MetricValue::Gauge { value: 100.0 },
+                ),
+                (
+                    "up".into(),
+                    Some(labels(&[
+                        ("instance", &in_addr.to_string()),
+                        ("job", "api")
+                    ])),
+                    MetricValue::Gauge { value: 1.0 },
+                ),
+            ]
+        );
+    }
 
+    #[cfg(feature = "sinks-prometheus")]
     #[test]
     fn test_prometheus_routing() {
         let mut rt = runtime();
@@ -175,6 +677,7 @@ mod test {
             PrometheusConfig {
                 hosts: vec![format!("http://{}", in_addr)],
                 scrape_interval_secs: 1,
+                ..Default::default()
             },
         );
         config.add_sink(
@@ -184,6 +687,8 @@ mod test {
                 address: out_addr,
                 namespace: "vector".into(),
                 buckets: vec![1.0, 2.0, 4.0],
+                quantiles: vec![],
+                distributions_as: Default::default(),
                 flush_period_secs: 1,
             },
         );
@@ -206,6 +711,7 @@ mod test {
             .lines()
             .collect::<Vec<_>>();
 
+        let up = format!("vector_up{{instance=\"{}\"}} 1", in_addr);
         assert_eq!(lines, vec![
             "# HELP vector_promhttp_metric_handler_requests_total promhttp_metric_handler_requests_total",
             "# TYPE vector_promhttp_metric_handler_requests_total counter",
@@ -233,6 +739,9 @@ mod test {
             "vector_rpc_duration_seconds{code=\"200\",quantile=\"0.99\"} 76656",
             "vector_rpc_duration_seconds_sum{code=\"200\"} 17560473",
             "vector_rpc_duration_seconds_count{code=\"200\"} 2693",
+            "# HELP vector_up up",
+            "# TYPE vector_up gauge",
+            up.as_str(),
             ],
         );
 
@@ -0,0 +1,313 @@
+use crate::{
+    event::{
+        metric::{Metric, MetricKind, MetricValue},
+        Event,
+    },
+    internal_events::{PrometheusRemoteWriteParseError, PrometheusRemoteWriteReceived},
+    prometheus::{decode_write_request, proto, DecodeError, METRIC_NAME_LABEL},
+    shutdown::ShutdownSignal,
+    sources::util::{ErrorMessage, HttpSource},
+    tls::TlsConfig,
+    topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
+};
+use bytes05::Bytes;
+use chrono::{TimeZone, Utc};
+use futures01::sync::mpsc;
+use proto::metric_metadata::MetricType;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+};
+use warp::http::{HeaderMap, StatusCode};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PrometheusRemoteWriteConfig {
+    address: SocketAddr,
+    tls: Option<TlsConfig>,
+}
+
+inventory::submit! {
+    SourceDescription::new_without_default::<PrometheusRemoteWriteConfig>("prometheus_remote_write")
+}
+
+#[typetag::serde(name = "prometheus_remote_write")]
+impl SourceConfig for PrometheusRemoteWriteConfig {
+    fn build(
+        &self,
+        _: &str,
+        _: &GlobalOptions,
+        shutdown: ShutdownSignal,
+        out: mpsc::Sender<Event>,
+    ) -> crate::Result<super::Source> {
+        RemoteWriteSource.run(self.address, "", &self.tls, out, shutdown)
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn source_type(&self) -> &'static str {
+        "prometheus_remote_write"
+    }
+}
+
+#[derive(Clone)]
+struct RemoteWriteSource;
+
+impl HttpSource for RemoteWriteSource {
+    fn build_event(&self, body: Bytes, _: HeaderMap) -> Result<Vec<Event>, ErrorMessage> {
+        let events = decode_write_request(&body)
+            .and_then(decode_metrics)
+            .map_err(|error| {
+                let message = ErrorMessage::new(StatusCode::BAD_REQUEST, error.to_string());
+                emit!(PrometheusRemoteWriteParseError { error });
+                message
+            })?;
+        emit!(PrometheusRemoteWriteReceived {
+            count: events.len(),
+            byte_size: body.len(),
+        });
+        Ok(events)
+    }
+}
+
+/// Converts each sample into a metric with the series labels as tags.
+/// Without type metadata every series is a gauge, while the cumulative series
+/// of counters, histograms and summaries become absolute counters. Requests
+/// with a timestamp that can't be represented are rejected as a whole.
+fn decode_metrics(request: proto::WriteRequest) -> Result<Vec<Event>, DecodeError> {
+    let types = request
+        .metadata
+        .iter()
+        .map(|metadata| (metadata.metric_family_name.as_str(), metadata.r#type))
+        .collect::<HashMap<_, _>>();
+
+    request
+        .timeseries
+        .into_iter()
+        .flat_map(|series| {
+            let mut name = String::new();
+            let tags = series
+                .labels
+                .into_iter()
+                .filter_map(|label| {
+                    if label.name == METRIC_NAME_LABEL {
+                        name = label.value;
+                        None
+                    } else {
+                        Some((label.name, label.value))
+                    }
+                })
+                .collect::<BTreeMap<_, _>>();
+            let tags = if tags.is_empty() { None } else { Some(tags) };
+            let is_counter = is_cumulative(&name, &types);
+
+            series
+                .samples
+                .into_iter()
+                .map(|sample| {
+                    let value = if is_counter {
+                        MetricValue::Counter {
+                            value: sample.value,
+                        }
+                    } else {
+                        MetricValue::Gauge {
+                            value: sample.value,
+                        }
+                    };
+                    let timestamp = Utc.timestamp_millis_opt(sample.timestamp).single().ok_or(
+                        DecodeError::InvalidTimestamp {
+                            timestamp: sample.timestamp,
+                        },
+                    )?;
+                    Ok(Event::Metric(Metric {
+                        name: name.clone(),
+                        timestamp: Some(timestamp),
+                        tags: tags.clone(),
+                        kind: MetricKind::Absolute,
+                        value,
+                    }))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn is_cumulative(name: &str, types: &HashMap<&str, i32>) -> bool {
+    let family_type = |family: &str| types.get(family).copied().and_then(MetricType::from_i32);
+
+    if family_type(name) == Some(MetricType::Counter) {
+        return true;
+    }
+    ["_bucket", "_sum", "_count"].iter().any(|suffix| {
+        name.ends_with(suffix)
+            && match family_type(&name[..name.len() - suffix.len()]) {
+                Some(MetricType::Histogram) | Some(MetricType::Summary) => true,
+                _ => false,
+            }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        prometheus::encode_write_request,
+        runtime::Runtime,
+        test_util::{self, collect_n, runtime},
+    };
+    use futures::compat::Future01CompatExt;
+    use pretty_assertions::assert_eq;
+
+    fn source(rt: &mut Runtime) -> (mpsc::Receiver<Event>, SocketAddr) {
+        test_util::trace_init();
+        let (sender, recv) = mpsc::channel(100);
+        let address = test_util::next_addr();
+        rt.spawn(
+            PrometheusRemoteWriteConfig { address, tls: None }
+                .build(
+                    "default",
+                    &GlobalOptions::default(),
+                    ShutdownSignal::noop(),
+                    sender,
+                )
+                .unwrap(),
+        );
+        (recv, address)
+    }
+
+    fn series(name: &str, labels: &[(&str, &str)], value: f64) -> proto::TimeSeries {
+        proto::TimeSeries {
+            labels: labels
+                .iter()
+                .chain(&[(METRIC_NAME_LABEL, name)])
+                .map(|(name, value)| proto::Label {
+                    name: (*name).into(),
+                    value: (*value).into(),
+                })
+                .collect(),
+            samples: vec![proto::Sample {
+                value,
+                timestamp: 1542182950000,
+            }],
+        }
+    }
+
+    fn metadata(name: &str, r#type: MetricType) -> proto::MetricMetadata {
+        proto::MetricMetadata {
+            r#type: r#type as i32,
+            metric_family_name: name.into(),
+            help: String::new(),
+            unit: String::new(),
+        }
+    }
+
+    #[test]
+    fn decodes_series_by_type() {
+        let request = proto::WriteRequest {
+            timeseries: vec![
+                series("hits", &[("code", "200")], 12.0),
+                series("temperature", &[], 21.5),
+                series("latency_bucket", &[("le", "0.5")], 3.0),
+            ],
+            metadata: vec![
+                metadata("hits", MetricType::Counter),
+                metadata("latency", MetricType::Histogram),
+            ],
+        };
+
+        let metrics = decode_metrics(request)
+            .unwrap()
+            .into_iter()
+            .map(Event::into_metric)
+            .collect::<Vec<_>>();
+        let timestamp = Some(Utc.ymd(2018, 11, 14).and_hms(8, 9, 10));
+
+        assert_eq!(
+            metrics,
+            vec![
+                Metric {
+                    name: "hits".into(),
+                    timestamp,
+                    tags: Some(vec![("code".into(), "200".into())].into_iter().collect()),
+                    kind: MetricKind::Absolute,
+                    value: MetricValue::Counter { value: 12.0 },
+                },
+                Metric {
+                    name: "temperature".into(),
+                    timestamp,
+                    tags: None,
+                    kind: MetricKind::Absolute,
+                    value: MetricValue::Gauge { value: 21.5 },
+                },
+                Metric {
+                    name: "latency_bucket".into(),
+                    timestamp,
+                    tags: Some(vec![("le".into(), "0.5".into())].into_iter().collect()),
+                    kind: MetricKind::Absolute,
+                    value: MetricValue::Counter { value: 3.0 },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_range_timestamps() {
+        let mut series = series("hits", &[], 1.0);
+        series.samples[0].timestamp = i64::max_value();
+        let request = proto::WriteRequest {
+            timeseries: vec![series],
+            metadata: vec![],
+        };
+
+        assert!(matches!(
+            decode_metrics(request),
+            Err(DecodeError::InvalidTimestamp { .. })
+        ));
+    }
+
+    #[test]
+    fn receives_remote_write_requests() {
+        let mut rt = runtime();
+        let (rx, addr) = source(&mut rt);
+
+        let request = proto::WriteRequest {
+            timeseries: vec![
+                series("hits", &[], 1.0),
+                series("temperature", &[("room", "kitchen")], 21.5),
+            ],
+            metadata: vec![],
+        };
+
+        rt.block_on_std(async move {
+            let client = reqwest::Client::new();
+            let status = client
+                .post(&format!("http://{}/", addr))
+                .header("Content-Encoding", "snappy")
+                .body(encode_write_request(&request))
+                .send()
+                .await
+                .unwrap()
+                .status();
+            assert_eq!(status, 200);
+
+            let status = client
+                .post(&format!("http://{}/", addr))
+                .body("not snappy")
+                .send()
+                .await
+                .unwrap()
+                .status();
+            assert_eq!(status, 400);
+
+            let events = collect_n(rx, 2).compat().await.unwrap();
+            let names = events
+                .into_iter()
+                .map(|event| event.into_metric().name)
+                .collect::<Vec<_>>();
+            assert_eq!(names, vec!["hits", "temperature"]);
+        });
+    }
+}
@@ -94,7 +94,13 @@ impl SourceConfig for SocketConfig {
                     .host_key
                     .clone()
                     .unwrap_or_else(|| event::log_schema().host_key().clone());
-                Ok(udp::udp(config.address, host_key, shutdown, out))
+                Ok(udp::udp(
+                    config.address,
+                    host_key,
+                    config.encoding,
+                    shutdown,
+                    out,
+                ))
             }
             #[cfg(unix)]
             Mode::Unix(config) => {
@@ -105,6 +111,7 @@ impl SourceConfig for SocketConfig {
                 Ok(unix::unix(
                     config.path,
                     config.max_length,
+                    config.encoding,
                     host_key,
                     shutdown,
                     out,
@@ -134,6 +141,7 @@ mod test {
     use crate::runtime::Runtime;
     use crate::shutdown::{ShutdownSignal, SourceShutdownCoordinator};
     use crate::sinks::util::tcp::TcpSink;
+    use crate::sources::util::EncodingConfig;
     use crate::test_util::{
         block_on, collect_n, next_addr, runtime, send_lines, send_lines_tls, wait_for_tcp, CollectN,
     };
@@ -147,6 +155,7 @@ mod test {
         sync::{mpsc, oneshot},
         Future, Stream,
     };
+    use std::io::Write;
     use std::net::UdpSocket;
     #[cfg(unix)]
     use std::path::PathBuf;
@@ -187,6 +196,39 @@ mod test {
         );
     }
 
+    #[test]
+    fn tcp_transcodes_charset() {
+        let (tx, rx) = mpsc::channel(1);
+
+        let addr = next_addr();
+
+        let server = SocketConfig::from(TcpConfig {
+            encoding: EncodingConfig {
+                charset: Some(encoding_rs::WINDOWS_1252),
+            },
+            ..TcpConfig::new(addr.into())
+        })
+        .build(
+            "default",
+            &GlobalOptions::default(),
+            ShutdownSignal::noop(),
+            tx,
+        )
+        .unwrap();
+        let mut rt = runtime();
+        rt.spawn(server);
+        wait_for_tcp(addr);
+
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        stream.write_all(b"caf\xe9\n").unwrap();
+
+        let event = rx.wait().next().unwrap().unwrap();
+        assert_eq!(
+            event.as_log()[&event::log_schema().message_key()],
+            "caf\u{e9}".into()
+        );
+    }
+
     #[test]
     fn tcp_it_includes_source_type() {
         let (tx, rx) = mpsc::channel(1);
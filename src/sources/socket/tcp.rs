@@ -1,11 +1,11 @@
 use crate::{
     event::{self, Event},
     internal_events::TcpEventReceived,
-    sources::util::{SocketListenAddr, TcpSource},
+    sources::util::{EncodingConfig, SocketListenAddr, TcpSource},
     tls::TlsConfig,
 };
 use bytes::Bytes;
-use codec::{self, BytesDelimitedCodec};
+use codec::{self, BytesDelimitedCodec, CharsetDecoder};
 use serde::{Deserialize, Serialize};
 use string_cache::DefaultAtom as Atom;
 use tracing::field;
@@ -20,6 +20,8 @@ pub struct TcpConfig {
     pub shutdown_timeout_secs: u64,
     pub host_key: Option<Atom>,
     pub tls: Option<TlsConfig>,
+    #[serde(default)]
+    pub encoding: EncodingConfig,
 }
 
 fn default_max_length() -> usize {
@@ -38,6 +40,7 @@ impl TcpConfig {
             host_key: None,
             shutdown_timeout_secs: default_shutdown_timeout_secs(),
             tls: Default::default(),
+            encoding: Default::default(),
         }
     }
 }
@@ -48,10 +51,15 @@ pub struct RawTcpSource {
 }
 
 impl TcpSource for RawTcpSource {
-    type Decoder = BytesDelimitedCodec;
+    type Decoder = CharsetDecoder<BytesDelimitedCodec>;
 
     fn decoder(&self) -> Self::Decoder {
-        BytesDelimitedCodec::new_with_max_length(b'\n', self.config.max_length)
+        self.config
+            .encoding
+            .decoder(BytesDelimitedCodec::new_with_max_length(
+                b'\n',
+                self.config.max_length,
+            ))
     }
 
     fn build_event(&self, frame: Bytes, host: Bytes) -> Option<Event> {
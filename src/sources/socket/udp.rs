@@ -2,7 +2,7 @@ use crate::{
     event::{self, Event},
     internal_events::{UdpEventReceived, UdpSocketError},
     shutdown::ShutdownSignal,
-    sources::Source,
+    sources::{util::EncodingConfig, Source},
     stream::StreamExt,
 };
 use bytes::Bytes;
@@ -19,6 +19,8 @@ use tokio01::net::udp::{UdpFramed, UdpSocket};
 pub struct UdpConfig {
     pub address: SocketAddr,
     pub host_key: Option<Atom>,
+    #[serde(default)]
+    pub encoding: EncodingConfig,
 }
 
 impl UdpConfig {
@@ -26,6 +28,7 @@ impl UdpConfig {
         Self {
             address,
             host_key: None,
+            encoding: Default::default(),
         }
     }
 }
@@ -33,6 +36,7 @@ impl UdpConfig {
 pub fn udp(
     address: SocketAddr,
     host_key: Atom,
+    encoding: EncodingConfig,
     shutdown: ShutdownSignal,
     out: mpsc::Sender<Event>,
 ) -> Source {
@@ -50,7 +54,8 @@ pub fn udp(
             let host_key = host_key.clone();
             // UDP processes messages per packet, where messages are separated by newline.
             // And stretch to end of packet.
-            UdpFramed::with_decode(socket, BytesDelimitedCodec::new(b'\n'), true)
+            let decoder = encoding.decoder(BytesDelimitedCodec::new(b'\n'));
+            UdpFramed::with_decode(socket, decoder, true)
                 .take_until(shutdown)
                 .map(move |(line, addr): (Bytes, _)| {
                     let byte_size = line.len();
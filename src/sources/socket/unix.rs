@@ -2,7 +2,10 @@ use crate::{
     event::{self, Event},
     internal_events::UnixSocketEventReceived,
     shutdown::ShutdownSignal,
-    sources::{util::build_unix_source, Source},
+    sources::{
+        util::{build_unix_source, EncodingConfig},
+        Source,
+    },
 };
 use bytes::Bytes;
 use futures01::sync::mpsc;
@@ -16,6 +19,8 @@ pub struct UnixConfig {
     #[serde(default = "default_max_length")]
     pub max_length: usize,
     pub host_key: Option<String>,
+    #[serde(default)]
+    pub encoding: EncodingConfig,
 }
 
 fn default_max_length() -> usize {
@@ -28,6 +33,7 @@ impl UnixConfig {
             path,
             max_length: default_max_length(),
             host_key: None,
+            encoding: Default::default(),
         }
     }
 }
@@ -52,9 +58,18 @@ fn build_event(host_key: &str, received_from: Option<Bytes>, line: &str) -> Opti
 pub fn unix(
     path: PathBuf,
     max_length: usize,
+    encoding: EncodingConfig,
     host_key: String,
     shutdown: ShutdownSignal,
     out: mpsc::Sender<Event>,
 ) -> Source {
-    build_unix_source(path, max_length, host_key, shutdown, out, build_event)
+    build_unix_source(
+        path,
+        max_length,
+        encoding,
+        host_key,
+        shutdown,
+        out,
+        build_event,
+    )
 }
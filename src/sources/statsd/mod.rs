@@ -1,10 +1,22 @@
-use crate::{shutdown::ShutdownSignal, stream::StreamExt, topology::config::GlobalOptions, Event};
-use futures01::{future, sync::mpsc, Future, Sink, Stream};
-use parser::parse;
-use serde::{Deserialize, Serialize};
+use crate::{
+    event::{self, Event},
+    internal_events::{StatsdEventReceived, StatsdInvalidRecord, StatsdSocketError},
+    shutdown::ShutdownSignal,
+    sources::util::{SocketListenAddr, TcpSource},
+    stream::StreamExt,
+    tls::{MaybeTlsSettings, TlsConfig},
+    topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
+};
+use bytes::Bytes;
+use codec::BytesDelimitedCodec;
+use futures01::{future, stream, sync::mpsc, Future, Sink, Stream};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::PathBuf;
+#[cfg(unix)]
+use tokio01::net::{UnixDatagram, UnixDatagramFramed};
 use tokio01::{
-    self,
     codec::BytesCodec,
     net::{UdpFramed, UdpSocket},
 };
@@ -12,13 +24,69 @@ use tracing::field;
 
 pub mod parser;
 
-#[derive(Deserialize, Serialize, Debug)]
-struct StatsdConfig {
-    address: SocketAddr,
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct StatsdConfig {
+    #[serde(flatten, deserialize_with = "deserialize_mode")]
+    pub mode: Mode,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Mode {
+    Tcp(TcpConfig),
+    Udp(UdpConfig),
+    #[cfg(unix)]
+    Unix(UnixConfig),
+}
+
+/// Configs without a `mode` are read as udp, which was the only mode before
+/// the others were added. Any other config is read by its `mode` so that its
+/// errors aren't hidden behind the fallback.
+fn deserialize_mode<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Mode, D::Error> {
+    let value = toml::Value::deserialize(deserializer)?;
+    let mode = if value.get("mode").is_some() {
+        Mode::deserialize(value)
+    } else {
+        UdpConfig::deserialize(value).map(Mode::Udp)
+    };
+    mode.map_err(de::Error::custom)
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UdpConfig {
+    pub address: SocketAddr,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TcpConfig {
+    pub address: SocketListenAddr,
+    pub tls: Option<TlsConfig>,
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+
+#[cfg(unix)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UnixConfig {
+    pub path: PathBuf,
+}
+
+/// Longest line accepted over tcp.
+const MAX_LINE_LENGTH: usize = 100 * 1024;
+
+inventory::submit! {
+    SourceDescription::new_without_default::<StatsdConfig>("statsd")
 }
 
 #[typetag::serde(name = "statsd")]
-impl crate::topology::config::SourceConfig for StatsdConfig {
+impl SourceConfig for StatsdConfig {
     fn build(
         &self,
         _name: &str,
@@ -26,11 +94,25 @@ impl crate::topology::config::SourceConfig for StatsdConfig {
         shutdown: ShutdownSignal,
         out: mpsc::Sender<Event>,
     ) -> crate::Result<super::Source> {
-        Ok(statsd(self.address, shutdown, out))
+        match &self.mode {
+            Mode::Tcp(config) => {
+                let tls = MaybeTlsSettings::from_config(&config.tls, true)?;
+                StatsdTcpSource.run(
+                    config.address,
+                    config.shutdown_timeout_secs,
+                    tls,
+                    shutdown,
+                    out,
+                )
+            }
+            Mode::Udp(config) => Ok(statsd_udp(config.address, shutdown, out)),
+            #[cfg(unix)]
+            Mode::Unix(config) => Ok(statsd_unix(config.path.clone(), shutdown, out)),
+        }
     }
 
-    fn output_type(&self) -> crate::topology::config::DataType {
-        crate::topology::config::DataType::Metric
+    fn output_type(&self) -> DataType {
+        DataType::Any
     }
 
     fn source_type(&self) -> &'static str {
@@ -38,7 +120,34 @@ impl crate::topology::config::SourceConfig for StatsdConfig {
     }
 }
 
-fn statsd(addr: SocketAddr, shutdown: ShutdownSignal, out: mpsc::Sender<Event>) -> super::Source {
+/// Parses one line into a metric, or a log event for DogStatsD events and
+/// service checks. Invalid lines are reported and dropped.
+fn parse_event(line: &str) -> Option<Event> {
+    match parser::parse(line) {
+        Ok(mut parsed) => {
+            if let Event::Log(log) = &mut parsed {
+                log.insert(event::log_schema().source_type_key(), "statsd");
+            }
+            emit!(StatsdEventReceived {
+                byte_size: line.len()
+            });
+            Some(parsed)
+        }
+        Err(error) => {
+            emit!(StatsdInvalidRecord {
+                error: &error,
+                text: line
+            });
+            None
+        }
+    }
+}
+
+fn statsd_udp(
+    addr: SocketAddr,
+    shutdown: ShutdownSignal,
+    out: mpsc::Sender<Event>,
+) -> super::Source {
     let out = out.sink_map_err(|e| error!("error sending metric: {:?}", e));
 
     Box::new(
@@ -54,38 +163,85 @@ fn statsd(addr: SocketAddr, shutdown: ShutdownSignal, out: mpsc::Sender<Event>)
             future::ok(socket)
         })
         .and_then(move |socket| {
-            let metrics_in = UdpFramed::new(socket, BytesCodec::new())
+            let events_in = UdpFramed::new(socket, BytesCodec::new())
                 .take_until(shutdown)
                 .map(|(bytes, _sock)| {
                     let packet = String::from_utf8_lossy(bytes.as_ref());
-                    let metrics = packet
-                        .lines()
-                        .map(parse)
-                        .filter_map(|res| res.map_err(|e| error!("{}", e)).ok())
-                        .map(Event::Metric)
-                        .collect::<Vec<_>>();
-                    futures01::stream::iter_ok::<_, std::io::Error>(metrics)
+                    let events = packet.lines().filter_map(parse_event).collect::<Vec<_>>();
+                    stream::iter_ok::<_, std::io::Error>(events)
                 })
                 .flatten()
-                .map_err(|e| error!("error reading datagram: {:?}", e));
+                .map_err(|error| emit!(StatsdSocketError { error, mode: "udp" }));
+
+            events_in.forward(out).map(|_| info!("finished sending"))
+        }),
+    )
+}
+
+/// DogStatsD clients send the same datagrams over a Unix socket as over udp,
+/// so each one is parsed the same way.
+#[cfg(unix)]
+fn statsd_unix(path: PathBuf, shutdown: ShutdownSignal, out: mpsc::Sender<Event>) -> super::Source {
+    let out = out.sink_map_err(|e| error!("error sending metric: {:?}", e));
 
-            metrics_in.forward(out).map(|_| info!("finished sending"))
+    Box::new(
+        future::lazy(move || {
+            let socket = UnixDatagram::bind(&path).expect("failed to bind to unix datagram socket");
+
+            info!(message = "listening.", ?path, r#type = "unix");
+
+            future::ok(socket)
+        })
+        .and_then(move |socket| {
+            let events_in = UnixDatagramFramed::<PathBuf, _>::new(socket, BytesCodec::new())
+                .take_until(shutdown)
+                .map(|(bytes, _addr)| {
+                    let packet = String::from_utf8_lossy(bytes.as_ref());
+                    let events = packet.lines().filter_map(parse_event).collect::<Vec<_>>();
+                    stream::iter_ok::<_, std::io::Error>(events)
+                })
+                .flatten()
+                .map_err(|error| {
+                    emit!(StatsdSocketError {
+                        error,
+                        mode: "unix"
+                    })
+                });
+
+            events_in.forward(out).map(|_| info!("finished sending"))
         }),
     )
 }
 
+#[derive(Debug, Clone)]
+struct StatsdTcpSource;
+
+impl TcpSource for StatsdTcpSource {
+    type Decoder = BytesDelimitedCodec;
+
+    fn decoder(&self) -> Self::Decoder {
+        BytesDelimitedCodec::new_with_max_length(b'\n', MAX_LINE_LENGTH)
+    }
+
+    fn build_event(&self, line: Bytes, _host: Bytes) -> Option<Event> {
+        parse_event(&String::from_utf8_lossy(&line))
+    }
+}
+
 #[cfg(feature = "sinks-prometheus")]
 #[cfg(test)]
 mod test {
-    use super::StatsdConfig;
+    #[cfg(unix)]
+    use super::UnixConfig;
+    use super::{Mode, StatsdConfig, TcpConfig, UdpConfig};
     use crate::{
         sinks::prometheus::PrometheusSinkConfig,
-        test_util::{block_on, next_addr, runtime, shutdown_on_idle},
+        test_util::{block_on, next_addr, runtime, shutdown_on_idle, wait_for_tcp},
         topology::{self, config},
     };
     use futures::{TryFutureExt, TryStreamExt};
     use futures01::Stream;
-    use std::{thread, time::Duration};
+    use std::{io::Write, net::TcpStream, thread, time::Duration};
 
     fn parse_count(lines: &[&str], prefix: &str) -> usize {
         lines
@@ -98,12 +254,103 @@ mod test {
     }
 
     #[test]
-    fn test_statsd() {
+    fn test_statsd_udp() {
+        let in_addr = next_addr();
+        let socket = std::net::UdpSocket::bind(&next_addr()).unwrap();
+
+        test_statsd(Mode::Udp(UdpConfig { address: in_addr }), |packet| {
+            socket.send_to(packet, &in_addr).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_statsd_tcp() {
         let in_addr = next_addr();
+        let mut stream = None;
+
+        test_statsd(
+            Mode::Tcp(TcpConfig {
+                address: in_addr.into(),
+                tls: None,
+                shutdown_timeout_secs: 30,
+            }),
+            |packet| {
+                stream
+                    .get_or_insert_with(|| {
+                        wait_for_tcp(in_addr);
+                        TcpStream::connect(in_addr).unwrap()
+                    })
+                    .write_all(packet)
+                    .unwrap();
+            },
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_statsd_unix() {
+        use std::os::unix::net::UnixDatagram;
+
+        let path = crate::test_util::temp_file();
+        let socket = UnixDatagram::unbound().unwrap();
+
+        test_statsd(Mode::Unix(UnixConfig { path: path.clone() }), |packet| {
+            crate::test_util::wait_for(|| path.exists());
+            socket.send_to(packet, &path).unwrap();
+        });
+    }
+
+    #[test]
+    fn parses_config_without_mode() {
+        config::Config::load(
+            r#"
+            [sources.in]
+            type = "statsd"
+            address = "127.0.0.1:8125"
+
+            [sinks.out]
+            type = "blackhole"
+            inputs = ["in"]
+            "#
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let config: StatsdConfig = toml::from_str(r#"address = "127.0.0.1:8125""#).unwrap();
+        assert!(matches!(
+            config.mode,
+            Mode::Udp(UdpConfig { address }) if address == "127.0.0.1:8125".parse().unwrap()
+        ));
+
+        let config: StatsdConfig = toml::from_str(
+            r#"
+            mode = "tcp"
+            address = "127.0.0.1:8125"
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(config.mode, Mode::Tcp(_)));
+
+        assert!(toml::from_str::<StatsdConfig>(r#"path = "/tmp/statsd.sock""#).is_err());
+    }
+
+    #[test]
+    fn reports_errors_for_configured_mode() {
+        let error = toml::from_str::<StatsdConfig>(
+            r#"
+            mode = "tcp"
+            adress = "127.0.0.1:8125"
+            "#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("unknown field `adress`"));
+    }
+
+    fn test_statsd(mode: Mode, mut send: impl FnMut(&[u8])) {
         let out_addr = next_addr();
 
         let mut config = config::Config::empty();
-        config.add_source("in", StatsdConfig { address: in_addr });
+        config.add_source("in", StatsdConfig { mode });
         config.add_sink(
             "out",
             &["in"],
@@ -111,6 +358,8 @@ mod test {
                 address: out_addr,
                 namespace: "vector".into(),
                 buckets: vec![1.0, 2.0, 4.0],
+                quantiles: vec![],
+                distributions_as: Default::default(),
                 flush_period_secs: 1,
             },
         );
@@ -119,16 +368,10 @@ mod test {
 
         let (topology, _crash) = rt.block_on_std(topology::start(config, false)).unwrap();
 
-        let bind_addr = next_addr();
-        let socket = std::net::UdpSocket::bind(&bind_addr).unwrap();
-
         for _ in 0..100 {
-            socket
-                .send_to(
-                    b"foo:1|c|#a,b:b\nbar:42|g\nfoo:1|c|#a,b:c\nglork:3|h|@0.1\nmilliglork:3000|ms|@0.1\nset:0|s\nset:1|s\n",
-                    &in_addr,
-                )
-                .unwrap();
+            send(
+                b"foo:1|c|#a,b:b\nbar:42|g\nfoo:1|c|#a,b:c\nglork:3|h|@0.1\nmilliglork:3000|ms|@0.1\nset:0|s\nset:1|s\n",
+            );
             // Space things out slightly to try to avoid dropped packets
             thread::sleep(Duration::from_millis(10));
         }
@@ -219,7 +462,7 @@ mod test {
 
             // Recheck that set is also reseted------------
 
-            socket.send_to(b"set:0|s\nset:1|s\n", &in_addr).unwrap();
+            send(b"set:0|s\nset:1|s\n");
             // Space things out slightly to try to avoid dropped packets
             thread::sleep(Duration::from_millis(10));
             // Give packets some time to flow through
@@ -1,4 +1,9 @@
-use crate::event::metric::{Metric, MetricKind, MetricValue};
+use crate::event::{
+    self,
+    metric::{Metric, MetricKind, MetricValue},
+    Event, Value,
+};
+use chrono::{DateTime, TimeZone, Utc};
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::{
@@ -12,7 +17,19 @@ lazy_static! {
     static ref NONALPHANUM: Regex = Regex::new(r"[^a-zA-Z_\-0-9\.]").unwrap();
 }
 
-pub fn parse(packet: &str) -> Result<Metric, ParseError> {
+/// Parses a single line into a metric, or into a log event for DogStatsD
+/// events (`_e{`) and service checks (`_sc`).
+pub fn parse(packet: &str) -> Result<Event, ParseError> {
+    if packet.starts_with("_e{") {
+        parse_dogstatsd_event(packet)
+    } else if packet.starts_with("_sc|") {
+        parse_service_check(packet)
+    } else {
+        parse_metric(packet).map(Event::Metric)
+    }
+}
+
+fn parse_metric(packet: &str) -> Result<Metric, ParseError> {
     // https://docs.datadoghq.com/developers/dogstatsd/datagram_shell/#datagram-format
     let key_and_body = packet.splitn(2, ':').collect::<Vec<_>>();
     if key_and_body.len() != 2 {
@@ -124,6 +141,130 @@ pub fn parse(packet: &str) -> Result<Metric, ParseError> {
     Ok(metric)
 }
 
+/// `_e{<TITLE_LENGTH>,<TEXT_LENGTH>}:<TITLE>|<TEXT>|d:<TIMESTAMP>|h:<HOSTNAME>|...`
+fn parse_dogstatsd_event(packet: &str) -> Result<Event, ParseError> {
+    // https://docs.datadoghq.com/developers/dogstatsd/datagram_shell/#events
+    let header_end = packet.find("}:").ok_or(ParseError::Malformed(
+        "event should have a '}:' terminated header",
+    ))?;
+    let lengths = packet[3..header_end].splitn(2, ',').collect::<Vec<_>>();
+    if lengths.len() != 2 {
+        return Err(ParseError::Malformed(
+            "event header should have title and text lengths",
+        ));
+    }
+    let title_length: usize = lengths[0].parse()?;
+    let text_length: usize = lengths[1].parse()?;
+
+    let body = &packet[header_end + 2..];
+    let title = body.get(..title_length).ok_or(ParseError::Malformed(
+        "event title is shorter than its length",
+    ))?;
+    let text = body
+        .get(title_length..)
+        .filter(|rest| rest.starts_with('|'))
+        .and_then(|rest| rest.get(1..=text_length))
+        .ok_or(ParseError::Malformed(
+            "event text is shorter than its length",
+        ))?;
+    let metadata = &body[title_length + 1 + text_length..];
+
+    let mut event = Event::from(text.replace("\\n", "\n"));
+    let log = event.as_mut_log();
+    log.insert("title", title);
+
+    for field in metadata.split('|').filter(|field| !field.is_empty()) {
+        match field.splitn(2, ':').collect::<Vec<_>>().as_slice() {
+            ["d", timestamp] => {
+                log.insert(
+                    event::log_schema().timestamp_key(),
+                    parse_timestamp(timestamp)?,
+                );
+            }
+            ["h", host] => {
+                log.insert(event::log_schema().host_key(), *host);
+            }
+            ["k", key] => {
+                log.insert("aggregation_key", *key);
+            }
+            ["p", priority] => {
+                log.insert("priority", *priority);
+            }
+            ["s", source_type_name] => {
+                log.insert("source_type_name", *source_type_name);
+            }
+            ["t", alert_type] => {
+                log.insert("alert_type", *alert_type);
+            }
+            _ if field.starts_with('#') => insert_tags(log, field)?,
+            _ => return Err(ParseError::Malformed("unknown event metadata field")),
+        }
+    }
+
+    Ok(event)
+}
+
+/// `_sc|<NAME>|<STATUS>|d:<TIMESTAMP>|h:<HOSTNAME>|#<TAGS>|m:<MESSAGE>`
+fn parse_service_check(packet: &str) -> Result<Event, ParseError> {
+    // https://docs.datadoghq.com/developers/dogstatsd/datagram_shell/#service-checks
+    // The message may contain pipes, so it is always the last field.
+    let (packet, message) = match packet.find("|m:") {
+        Some(index) => (&packet[..index], Some(&packet[index + 3..])),
+        None => (packet, None),
+    };
+
+    let parts = packet.split('|').collect::<Vec<_>>();
+    if parts.len() < 3 {
+        return Err(ParseError::Malformed(
+            "service check should have a name and a status",
+        ));
+    }
+    let status: i64 = parts[2].parse()?;
+    if status < 0 || status > 3 {
+        return Err(ParseError::Malformed(
+            "service check status should be between 0 and 3",
+        ));
+    }
+
+    let mut event = Event::from(message.unwrap_or("").replace("\\n", "\n"));
+    let log = event.as_mut_log();
+    log.insert("name", parts[1]);
+    log.insert("status", status);
+
+    for field in &parts[3..] {
+        match field.splitn(2, ':').collect::<Vec<_>>().as_slice() {
+            ["d", timestamp] => {
+                log.insert(
+                    event::log_schema().timestamp_key(),
+                    parse_timestamp(timestamp)?,
+                );
+            }
+            ["h", host] => {
+                log.insert(event::log_schema().host_key(), *host);
+            }
+            _ if field.starts_with('#') => insert_tags(log, field)?,
+            _ => return Err(ParseError::Malformed("unknown service check field")),
+        }
+    }
+
+    Ok(event)
+}
+
+fn parse_timestamp(input: &str) -> Result<DateTime<Utc>, ParseError> {
+    Utc.timestamp_opt(input.parse()?, 0)
+        .single()
+        .ok_or(ParseError::Malformed("timestamp is out of range"))
+}
+
+fn insert_tags(log: &mut event::LogEvent, input: &str) -> Result<(), ParseError> {
+    let tags = parse_tags(input)?
+        .into_iter()
+        .map(|(key, value)| (key, Value::from(value)))
+        .collect::<BTreeMap<_, _>>();
+    log.insert("tags", tags);
+    Ok(())
+}
+
 fn parse_sampling(input: &str) -> Result<f64, ParseError> {
     if !input.starts_with('@') || input.len() < 2 {
         return Err(ParseError::Malformed(
@@ -227,20 +368,25 @@ impl From<ParseFloatError> for ParseError {
 
 #[cfg(test)]
 mod test {
-    use super::{parse, sanitize_key, sanitize_sampling};
-    use crate::event::metric::{Metric, MetricKind, MetricValue};
+    use super::{parse, sanitize_key, sanitize_sampling, ParseError};
+    use crate::event::{
+        self,
+        metric::{Metric, MetricKind, MetricValue},
+        Event, Value,
+    };
+    use chrono::{DateTime, TimeZone, Utc};
 
     #[test]
     fn basic_counter() {
         assert_eq!(
             parse("foo:1|c"),
-            Ok(Metric {
+            Ok(Event::Metric(Metric {
                 name: "foo".into(),
                 timestamp: None,
                 tags: None,
                 kind: MetricKind::Incremental,
                 value: MetricValue::Counter { value: 1.0 },
-            }),
+            })),
         );
     }
 
@@ -248,7 +394,7 @@ mod test {
     fn tagged_counter() {
         assert_eq!(
             parse("foo:1|c|#tag1,tag2:value"),
-            Ok(Metric {
+            Ok(Event::Metric(Metric {
                 name: "foo".into(),
                 timestamp: None,
                 tags: Some(
@@ -261,7 +407,7 @@ mod test {
                 ),
                 kind: MetricKind::Incremental,
                 value: MetricValue::Counter { value: 1.0 },
-            }),
+            })),
         );
     }
 
@@ -269,13 +415,13 @@ mod test {
     fn sampled_counter() {
         assert_eq!(
             parse("bar:2|c|@0.1"),
-            Ok(Metric {
+            Ok(Event::Metric(Metric {
                 name: "bar".into(),
                 timestamp: None,
                 tags: None,
                 kind: MetricKind::Incremental,
                 value: MetricValue::Counter { value: 20.0 },
-            }),
+            })),
         );
     }
 
@@ -283,13 +429,13 @@ mod test {
     fn zero_sampled_counter() {
         assert_eq!(
             parse("bar:2|c|@0"),
-            Ok(Metric {
+            Ok(Event::Metric(Metric {
                 name: "bar".into(),
                 timestamp: None,
                 tags: None,
                 kind: MetricKind::Incremental,
                 value: MetricValue::Counter { value: 2.0 },
-            }),
+            })),
         );
     }
 
@@ -297,7 +443,7 @@ mod test {
     fn sampled_timer() {
         assert_eq!(
             parse("glork:320|ms|@0.1"),
-            Ok(Metric {
+            Ok(Event::Metric(Metric {
                 name: "glork".into(),
                 timestamp: None,
                 tags: None,
@@ -306,7 +452,7 @@ mod test {
                     values: vec![0.320],
                     sample_rates: vec![10],
                 },
-            }),
+            })),
         );
     }
 
@@ -314,7 +460,7 @@ mod test {
     fn sampled_tagged_histogram() {
         assert_eq!(
             parse("glork:320|h|@0.1|#region:us-west1,production,e:"),
-            Ok(Metric {
+            Ok(Event::Metric(Metric {
                 name: "glork".into(),
                 timestamp: None,
                 tags: Some(
@@ -331,7 +477,7 @@ mod test {
                     values: vec![320.0],
                     sample_rates: vec![10],
                 },
-            }),
+            })),
         );
     }
 
@@ -339,13 +485,13 @@ mod test {
     fn simple_gauge() {
         assert_eq!(
             parse("gaugor:333|g"),
-            Ok(Metric {
+            Ok(Event::Metric(Metric {
                 name: "gaugor".into(),
                 timestamp: None,
                 tags: None,
                 kind: MetricKind::Absolute,
                 value: MetricValue::Gauge { value: 333.0 },
-            }),
+            })),
         );
     }
 
@@ -353,23 +499,23 @@ mod test {
     fn signed_gauge() {
         assert_eq!(
             parse("gaugor:-4|g"),
-            Ok(Metric {
+            Ok(Event::Metric(Metric {
                 name: "gaugor".into(),
                 timestamp: None,
                 tags: None,
                 kind: MetricKind::Incremental,
                 value: MetricValue::Gauge { value: -4.0 },
-            }),
+            })),
         );
         assert_eq!(
             parse("gaugor:+10|g"),
-            Ok(Metric {
+            Ok(Event::Metric(Metric {
                 name: "gaugor".into(),
                 timestamp: None,
                 tags: None,
                 kind: MetricKind::Incremental,
                 value: MetricValue::Gauge { value: 10.0 },
-            }),
+            })),
         );
     }
 
@@ -377,7 +523,7 @@ mod test {
     fn sets() {
         assert_eq!(
             parse("uniques:765|s"),
-            Ok(Metric {
+            Ok(Event::Metric(Metric {
                 name: "uniques".into(),
                 timestamp: None,
                 tags: None,
@@ -385,7 +531,84 @@ mod test {
                 value: MetricValue::Set {
                     values: vec!["765".into()].into_iter().collect()
                 },
-            }),
+            })),
+        );
+    }
+
+    #[test]
+    fn dogstatsd_event() {
+        let event = parse(
+            "_e{5,11}:Dea|h|line\\nbreak|d:1542182950|h:web-1|p:low|t:warning|#env:prod,canary",
+        )
+        .unwrap()
+        .into_log();
+
+        assert_eq!(
+            event[event::log_schema().message_key()],
+            "line\nbreak".into()
+        );
+        assert_eq!(event[&"title".into()], "Dea|h".into());
+        assert_eq!(
+            event[event::log_schema().timestamp_key()],
+            Utc.timestamp(1542182950, 0).into()
+        );
+        assert_eq!(event[event::log_schema().host_key()], "web-1".into());
+        assert_eq!(event[&"priority".into()], "low".into());
+        assert_eq!(event[&"alert_type".into()], "warning".into());
+        assert_eq!(event.get(&"tags.env".into()), Some(&"prod".into()));
+        assert_eq!(event.get(&"tags.canary".into()), Some(&"true".into()));
+    }
+
+    #[test]
+    fn dogstatsd_event_with_wrong_lengths() {
+        assert_eq!(
+            parse("_e{5,20}:title|text"),
+            Err(ParseError::Malformed(
+                "event text is shorter than its length"
+            ))
+        );
+        assert!(parse("_e{x,4}:title|text").is_err());
+    }
+
+    #[test]
+    fn service_check() {
+        let event = parse("_sc|db.up|2|d:1542182950|#region:eu|m:connection refused | retrying")
+            .unwrap()
+            .into_log();
+
+        assert_eq!(event[&"name".into()], "db.up".into());
+        assert_eq!(event[&"status".into()], Value::Integer(2));
+        assert_eq!(
+            event[event::log_schema().message_key()],
+            "connection refused | retrying".into()
+        );
+        assert_eq!(
+            event[event::log_schema().timestamp_key()],
+            Utc.timestamp(1542182950, 0).into()
+        );
+        assert_eq!(event.get(&"tags.region".into()), Some(&"eu".into()));
+        assert!(event.get(&"host".into()).is_none());
+    }
+
+    #[test]
+    fn dogstatsd_event_with_out_of_range_timestamp() {
+        assert_eq!(
+            parse("_e{5,4}:title|text|d:9223372036854775807"),
+            Err(ParseError::Malformed("timestamp is out of range"))
+        );
+        assert_eq!(
+            parse("_sc|db.up|0|d:9223372036854775807"),
+            Err(ParseError::Malformed("timestamp is out of range"))
+        );
+    }
+
+    #[test]
+    fn service_check_with_invalid_status() {
+        assert_eq!(
+            parse("_sc|db.up|7"),
+            Err(ParseError::Malformed(
+                "service check status should be between 0 and 3"
+            ))
         );
     }
 
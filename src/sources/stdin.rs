@@ -1,17 +1,19 @@
 use crate::{
     event::{self, Event},
     shutdown::ShutdownSignal,
+    sources::util::EncodingConfig,
     stream::StreamExt,
     topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
 };
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures::compat::Compat;
 use futures01::{sync::mpsc, Future, Sink, Stream};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use snafu::Snafu;
-use std::{io, sync::Mutex, thread};
+use std::{io, iter, sync::Mutex, thread};
 use tokio::sync::broadcast::{channel, Sender};
+use tokio01::codec::{Decoder, LinesCodec};
 
 #[derive(Debug, Snafu)]
 enum BuildError {
@@ -29,6 +31,7 @@ pub struct StdinConfig {
     #[serde(default = "default_max_length")]
     pub max_length: usize,
     pub host_key: Option<String>,
+    pub encoding: EncodingConfig,
 }
 
 impl Default for StdinConfig {
@@ -36,6 +39,7 @@ impl Default for StdinConfig {
         StdinConfig {
             max_length: default_max_length(),
             host_key: None,
+            encoding: EncodingConfig::default(),
         }
     }
 }
@@ -103,6 +107,9 @@ where
     // Although it's possible to implement this in a lock free, maybe even wait free manner,
     // this should be easier to reason about and performance shouldn't suffer since this procedure
     // is cold compared to the rest of the source.
+    //
+    // As stdin is only read once, it is decoded with the encoding of the source that started
+    // the background thread.
 
     let host_key = config
         .host_key
@@ -117,12 +124,13 @@ where
         None => {
             let (sender, receiver) = channel(1024);
             *guard = Some(sender.clone());
+            let decoder = config.encoding.decoder(LinesCodec::new());
 
             // Start the background thread
             thread::spawn(move || {
                 info!("Capturing STDIN.");
 
-                for line in stdin.lines() {
+                for line in decode_lines(stdin, decoder) {
                     match line {
                         Err(e) => {
                             error!(message = "Unable to read from source.", error = %e);
@@ -172,6 +180,37 @@ where
     ))
 }
 
+/// Reads lines like `BufRead::lines` does, but framed by `decoder`, so that they can be
+/// transcoded first.
+fn decode_lines<R, D>(mut reader: R, mut decoder: D) -> impl Iterator<Item = io::Result<String>>
+where
+    R: io::BufRead,
+    D: Decoder<Item = String, Error = io::Error>,
+{
+    let mut buffer = BytesMut::new();
+    let mut eof = false;
+    iter::from_fn(move || loop {
+        if eof {
+            return decoder.decode_eof(&mut buffer).transpose();
+        }
+        match decoder.decode(&mut buffer) {
+            Ok(Some(line)) => return Some(Ok(line)),
+            Ok(None) => {}
+            Err(error) => return Some(Err(error)),
+        }
+        let len = match reader.fill_buf() {
+            Ok(available) => {
+                buffer.extend_from_slice(available);
+                available.len()
+            }
+            Err(ref error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Some(Err(error)),
+        };
+        reader.consume(len);
+        eof = len == 0;
+    })
+}
+
 fn create_event(line: Bytes, host_key: &str, hostname: &Option<String>) -> Event {
     let mut event = Event::from(line);
 
@@ -212,6 +251,23 @@ mod tests {
         assert_eq!(log[event::log_schema().source_type_key()], "stdin".into());
     }
 
+    #[test]
+    fn stdin_decodes_charset() {
+        let encoding = EncodingConfig {
+            charset: Some(encoding_rs::UTF_16LE),
+        };
+        let input = "hello\r\nw\u{f6}rld"
+            .encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes().to_vec())
+            .collect::<Vec<u8>>();
+
+        let lines = decode_lines(Cursor::new(input), encoding.decoder(LinesCodec::new()))
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(lines, vec!["hello", "w\u{f6}rld"]);
+    }
+
     #[test]
     fn stdin_decodes_line() {
         crate::test_util::trace_init();
@@ -1,4 +1,4 @@
-use super::util::{SocketListenAddr, TcpSource};
+use super::util::{EncodingConfig, SocketListenAddr, TcpSource};
 #[cfg(unix)]
 use crate::sources::util::build_unix_source;
 use crate::{
@@ -11,6 +11,7 @@ use crate::{
 };
 use bytes::Bytes;
 use chrono::{Datelike, Utc};
+use codec::CharsetDecoder;
 use derive_is_enum_variant::is_enum_variant;
 use futures01::{future, sync::mpsc, Future, Sink, Stream};
 use serde::{Deserialize, Serialize};
@@ -35,6 +36,8 @@ pub struct SyslogConfig {
     pub max_length: usize,
     /// The host key of the log. (This differs from `hostname`)
     pub host_key: Option<String>,
+    #[serde(default)]
+    pub encoding: EncodingConfig,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, is_enum_variant)]
@@ -63,6 +66,7 @@ impl SyslogConfig {
             mode,
             host_key: None,
             max_length: default_max_length(),
+            encoding: Default::default(),
         }
     }
 }
@@ -90,16 +94,25 @@ impl SourceConfig for SyslogConfig {
                 let source = SyslogTcpSource {
                     max_length: self.max_length,
                     host_key,
+                    encoding: self.encoding.clone(),
                 };
                 let shutdown_secs = 30;
                 let tls = MaybeTlsSettings::from_config(&tls, true)?;
                 source.run(address, shutdown_secs, tls, shutdown, out)
             }
-            Mode::Udp { address } => Ok(udp(address, self.max_length, host_key, shutdown, out)),
+            Mode::Udp { address } => Ok(udp(
+                address,
+                self.max_length,
+                host_key,
+                self.encoding.clone(),
+                shutdown,
+                out,
+            )),
             #[cfg(unix)]
             Mode::Unix { path } => Ok(build_unix_source(
                 path,
                 self.max_length,
+                self.encoding.clone(),
                 host_key,
                 shutdown,
                 out,
@@ -121,13 +134,15 @@ impl SourceConfig for SyslogConfig {
 struct SyslogTcpSource {
     max_length: usize,
     host_key: String,
+    encoding: EncodingConfig,
 }
 
 impl TcpSource for SyslogTcpSource {
-    type Decoder = LinesCodec;
+    type Decoder = CharsetDecoder<LinesCodec>;
 
     fn decoder(&self) -> Self::Decoder {
-        LinesCodec::new_with_max_length(self.max_length)
+        self.encoding
+            .decoder(LinesCodec::new_with_max_length(self.max_length))
     }
 
     fn build_event(&self, frame: String, host: Bytes) -> Option<Event> {
@@ -146,6 +161,7 @@ pub fn udp(
     addr: SocketAddr,
     _max_length: usize,
     host_key: String,
+    encoding: EncodingConfig,
     shutdown: ShutdownSignal,
     out: mpsc::Sender<Event>,
 ) -> super::Source {
@@ -166,7 +182,9 @@ pub fn udp(
         .and_then(move |socket| {
             let host_key = host_key.clone();
 
-            let lines_in = UdpFramed::new(socket, BytesCodec::new())
+            // Every datagram is transcoded on its own.
+            let decoder = encoding.decoder(BytesCodec::new());
+            let lines_in = UdpFramed::with_decode(socket, decoder, true)
                 .take_until(shutdown)
                 .filter_map(move |(bytes, received_from)| {
                     let host_key = host_key.clone();
@@ -0,0 +1,130 @@
+use codec::CharsetDecoder;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+
+/// The character encoding of the data a source reads, which is transcoded to
+/// UTF-8 before events are created from it.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EncodingConfig {
+    /// Any label from the WHATWG Encoding Standard, such as `utf-16le`,
+    /// `iso-8859-1` or `shift_jis`. Without one the data is passed on as is.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_charset",
+        serialize_with = "serialize_charset"
+    )]
+    pub charset: Option<&'static Encoding>,
+}
+
+impl EncodingConfig {
+    /// Wraps a decoder, so that it frames the data after it has been
+    /// transcoded.
+    pub fn decoder<D>(&self, inner: D) -> CharsetDecoder<D> {
+        CharsetDecoder::new(inner, self.charset)
+    }
+
+    /// The bytes a newline is encoded as, for sources that have to frame the
+    /// data before transcoding it.
+    pub fn line_delimiter(&self) -> &'static [u8] {
+        match self.charset {
+            Some(charset) if charset == UTF_16LE => b"\n\0",
+            Some(charset) if charset == UTF_16BE => b"\0\n",
+            _ => b"\n",
+        }
+    }
+
+    /// Transcodes a single line, from `detected` instead of the configured
+    /// charset when the data was found to be in another one, such as by its
+    /// byte order mark. Sources that frame the data themselves take the mark
+    /// off, so lines are decoded as they are.
+    pub fn transcode(
+        &self,
+        line: bytes05::Bytes,
+        detected: Option<&'static Encoding>,
+    ) -> bytes05::Bytes {
+        let charset = match detected.or(self.charset) {
+            Some(charset) => charset,
+            None => return line,
+        };
+        match charset.decode_without_bom_handling(&line).0 {
+            Cow::Borrowed(decoded) => line.slice_ref(decoded.as_bytes()),
+            Cow::Owned(decoded) => decoded.into(),
+        }
+    }
+}
+
+fn deserialize_charset<'de, D>(des: D) -> Result<Option<&'static Encoding>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let label: Option<String> = Deserialize::deserialize(des)?;
+    label
+        .map(|label| {
+            Encoding::for_label(label.as_bytes())
+                .ok_or_else(|| de::Error::custom(format!("unknown charset {:?}", label)))
+        })
+        .transpose()
+}
+
+fn serialize_charset<S>(charset: &Option<&'static Encoding>, ser: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    charset.map(Encoding::name).serialize(ser)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use encoding_rs::WINDOWS_1252;
+
+    #[derive(Debug, Deserialize)]
+    struct Config {
+        encoding: EncodingConfig,
+    }
+
+    #[test]
+    fn parse_charset() {
+        let config: Config = toml::from_str(
+            r#"
+            [encoding]
+            charset = "ISO-8859-1"
+            "#,
+        )
+        .unwrap();
+        // Browsers treat ISO-8859-1 as windows-1252, its superset.
+        assert_eq!(config.encoding.charset, Some(WINDOWS_1252));
+
+        let config: Config = toml::from_str("encoding = {}").unwrap();
+        assert_eq!(config.encoding.charset, None);
+
+        assert!(toml::from_str::<Config>(r#"encoding = { charset = "klingon" }"#).is_err());
+    }
+
+    #[test]
+    fn transcode_lines() {
+        let config = EncodingConfig {
+            charset: Some(UTF_16LE),
+        };
+        assert_eq!(config.line_delimiter(), b"\n\0");
+        assert_eq!(
+            config.transcode(bytes05::Bytes::from_static(b"h\0i\0"), None),
+            "hi"
+        );
+        assert_eq!(
+            config.transcode(bytes05::Bytes::from_static(b"\0h\0i"), Some(UTF_16BE)),
+            "hi"
+        );
+
+        let config = EncodingConfig {
+            charset: Some(WINDOWS_1252),
+        };
+        assert_eq!(config.line_delimiter(), b"\n");
+        assert_eq!(
+            config.transcode(bytes05::Bytes::from_static(b"caf\xe9"), None),
+            "caf\u{e9}"
+        );
+    }
+}
@@ -1,3 +1,9 @@
+#[cfg(any(
+    feature = "sources-file",
+    feature = "sources-socket",
+    feature = "sources-stdin"
+))]
+mod encoding;
 #[cfg(feature = "sources-http")]
 mod http;
 #[cfg(feature = "sources-socket")]
@@ -5,6 +11,12 @@ mod tcp;
 #[cfg(all(unix, feature = "sources-socket"))]
 mod unix;
 
+#[cfg(any(
+    feature = "sources-file",
+    feature = "sources-socket",
+    feature = "sources-stdin"
+))]
+pub use self::encoding::EncodingConfig;
 #[cfg(feature = "sources-http")]
 pub use self::http::{ErrorMessage, HttpSource};
 #[cfg(feature = "sources-socket")]
@@ -1,6 +1,11 @@
 use crate::{
-    async_read::AsyncAllowReadExt, emit, event::Event, internal_events::UnixSocketError,
-    shutdown::ShutdownSignal, sources::Source, stream::StreamExt,
+    async_read::AsyncAllowReadExt,
+    emit,
+    event::Event,
+    internal_events::UnixSocketError,
+    shutdown::ShutdownSignal,
+    sources::{util::EncodingConfig, Source},
+    stream::StreamExt,
 };
 use bytes::Bytes;
 use futures01::{future, sync::mpsc, Future, Sink, Stream};
@@ -21,6 +26,7 @@ use tracing_futures::Instrument;
 pub fn build_unix_source(
     path: PathBuf,
     max_length: usize,
+    encoding: EncodingConfig,
     host_key: String,
     shutdown: ShutdownSignal,
     out: mpsc::Sender<Event>,
@@ -64,7 +70,7 @@ pub fn build_unix_source(
                     path.map(|p| p.to_string_lossy().into_owned().into());
                 let lines_in = FramedRead::new(
                     socket.allow_read_until(shutdown.clone()),
-                    LinesCodec::new_with_max_length(max_length),
+                    encoding.decoder(LinesCodec::new_with_max_length(max_length)),
                 )
                 .filter_map(move |line| build_event(&host_key, received_from.clone(), &line))
                 .map_err(move |error| {
@@ -1,5 +1,5 @@
 use crate::{
-    event::{self, Value},
+    event::{self, Metric, Value},
     Event,
 };
 use bytes::Bytes;
@@ -146,12 +146,14 @@ fn render_fields(src: &str, event: &Event) -> Result<String, Vec<Atom>> {
                 .get(1)
                 .map(|s| Atom::from(s.as_str().trim()))
                 .expect("src should match regex");
-            if let Some(val) = event.as_log().get(&key) {
-                val.to_string_lossy()
-            } else {
+            let value = match event {
+                Event::Log(log) => log.get(&key).map(Value::to_string_lossy),
+                Event::Metric(metric) => render_metric_field(&key, metric),
+            };
+            value.unwrap_or_else(|| {
                 missing_fields.push(key.clone());
                 String::new()
-            }
+            })
         })
         .into_owned();
     if missing_fields.is_empty() {
@@ -161,12 +163,22 @@ fn render_fields(src: &str, event: &Event) -> Result<String, Vec<Atom>> {
     }
 }
 
+/// Metrics expose their `name` and each tag as `tags.<key>`.
+fn render_metric_field(key: &str, metric: &Metric) -> Option<String> {
+    match key {
+        "name" => Some(metric.name.clone()),
+        _ if key.starts_with("tags.") => metric.tags.as_ref()?.get(&key[5..]).cloned(),
+        _ => None,
+    }
+}
+
 fn render_timestamp(src: &str, event: &Event) -> String {
     let timestamp = match event {
         Event::Log(log) => log
             .get(&event::log_schema().timestamp_key())
-            .and_then(Value::as_timestamp),
-        _ => None,
+            .and_then(Value::as_timestamp)
+            .copied(),
+        Event::Metric(metric) => metric.timestamp,
     };
     if let Some(ts) = timestamp {
         ts.format(src).to_string()
@@ -215,6 +227,7 @@ impl Serialize for Template {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::event::metric::{MetricKind, MetricValue};
     use chrono::TimeZone;
 
     #[test]
@@ -417,6 +430,33 @@ mod tests {
         )
     }
 
+    #[test]
+    fn render_metric() {
+        let event = Event::Metric(Metric {
+            name: "hits".into(),
+            timestamp: Some(Utc.ymd(2001, 2, 3).and_hms(4, 5, 6)),
+            tags: Some(
+                vec![("service".to_owned(), "api".to_owned())]
+                    .into_iter()
+                    .collect(),
+            ),
+            kind: MetricKind::Incremental,
+            value: MetricValue::Counter { value: 1.0 },
+        });
+
+        let template = Template::try_from("{{ tags.service }}.{{ name }}-%F").unwrap();
+        assert_eq!(
+            Ok(Bytes::from("api.hits-2001-02-03")),
+            template.render(&event)
+        );
+
+        let template = Template::try_from("{{ tags.region }}.{{ name }}").unwrap();
+        assert_eq!(
+            Err(vec![Atom::from("tags.region")]),
+            template.render(&event)
+        );
+    }
+
     #[test]
     fn strftime_error() {
         assert_eq!(
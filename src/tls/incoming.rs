@@ -3,7 +3,7 @@ use super::{
     TcpBind, TlsError, TlsSettings,
 };
 use futures01::{try_ready, Async, Future, Stream};
-use openssl::ssl::{HandshakeError, SslAcceptor, SslMethod};
+use openssl::ssl::{self, AlpnError, HandshakeError, SslAcceptor, SslMethod};
 use snafu::ResultExt;
 use std::{
     fmt::{self, Debug, Formatter},
@@ -55,13 +55,20 @@ impl<I: Stream + Debug> Debug for MaybeTlsIncoming<I> {
 }
 
 impl TlsSettings {
-    pub(crate) fn acceptor(&self) -> Result<SslAcceptor> {
+    pub(crate) fn acceptor(&self, alpn_protocols: Option<&'static [u8]>) -> Result<SslAcceptor> {
         match self.identity {
             None => Err(TlsError::MissingRequiredIdentity),
             Some(_) => {
                 let mut acceptor =
                     SslAcceptor::mozilla_intermediate(SslMethod::tls()).context(CreateAcceptor)?;
                 self.apply_context(&mut acceptor)?;
+                if let Some(protocols) = alpn_protocols {
+                    // Clients that don't offer any of the protocols are still
+                    // accepted, just without negotiating one.
+                    acceptor.set_alpn_select_callback(move |_, client_protocols| {
+                        ssl::select_next_proto(protocols, client_protocols).ok_or(AlpnError::NOACK)
+                    });
+                }
                 Ok(acceptor.build())
             }
         }
@@ -70,10 +77,29 @@ impl TlsSettings {
 
 impl MaybeTlsSettings {
     pub(crate) fn bind(&self, addr: &SocketAddr) -> Result<MaybeTlsListener> {
+        self.bind_listener(addr, None)
+    }
+
+    /// Like `bind`, but negotiates one of `protocols` with TLS clients through
+    /// ALPN. The protocols are given in the ALPN wire format, each prefixed by
+    /// its length, in order of preference.
+    pub(crate) fn bind_with_alpn(
+        &self,
+        addr: &SocketAddr,
+        protocols: &'static [u8],
+    ) -> Result<MaybeTlsListener> {
+        self.bind_listener(addr, Some(protocols))
+    }
+
+    fn bind_listener(
+        &self,
+        addr: &SocketAddr,
+        alpn_protocols: Option<&'static [u8]>,
+    ) -> Result<MaybeTlsListener> {
         let listener = TcpListener::bind(addr).context(TcpBind)?;
 
         let acceptor = match self {
-            Self::Tls(tls) => Some(tls.acceptor()?),
+            Self::Tls(tls) => Some(tls.acceptor(alpn_protocols)?),
             Self::Raw(()) => None,
         };
 
@@ -0,0 +1,273 @@
+use crate::api::{ComponentKind, ComponentMetrics, ComponentStatus};
+use exitcode::ExitCode;
+use hyper::{client::HttpConnector, Client, Uri};
+use std::{
+    collections::HashMap,
+    io::Write,
+    time::{Duration, Instant},
+};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(rename_all = "kebab-case")]
+pub struct Opts {
+    /// Address of the management API of the Vector instance to observe. The
+    /// instance must be running with the `api` option enabled.
+    #[structopt(short, long, default_value = "http://127.0.0.1:8686")]
+    url: Uri,
+
+    /// How often to refresh the table, in seconds.
+    #[structopt(short, long, default_value = "1")]
+    interval: u64,
+}
+
+const HEADERS: [&str; 9] = [
+    "KIND", "NAME", "TYPE", "IN/S", "OUT/S", "BYTES/S", "ERRORS", "BUFFER", "HEALTHY",
+];
+
+/// Continuously renders a table of the components of a running instance along
+/// with their throughput. Events come in to transforms and sinks, and go out
+/// of sources and transforms, so a filter's drop rate or a transform's fan-out
+/// shows as the difference between the two.
+pub async fn cmd(opts: &Opts) -> ExitCode {
+    let uri = match components_uri(&opts.url) {
+        Ok(uri) => uri,
+        Err(error) => {
+            eprintln!("Invalid API address {}: {}", opts.url, error);
+            return exitcode::USAGE;
+        }
+    };
+
+    let client = Client::new();
+    let mut previous: Option<(Instant, HashMap<String, ComponentMetrics>)> = None;
+    let mut interval = tokio::time::interval(Duration::from_secs(opts.interval.max(1)));
+
+    loop {
+        interval.tick().await;
+
+        let output = match fetch(&client, &uri).await {
+            Ok(statuses) => {
+                let now = Instant::now();
+                let elapsed = previous
+                    .as_ref()
+                    .map(|(then, metrics)| (now - *then, metrics));
+                let table = render(&statuses, elapsed);
+
+                let metrics = statuses
+                    .into_iter()
+                    .map(|status| (status.component.name, status.metrics))
+                    .collect();
+                previous = Some((now, metrics));
+                table
+            }
+            Err(error) => {
+                previous = None;
+                format!("Unable to reach Vector at {}: {}\n", opts.url, error)
+            }
+        };
+
+        // Clear the screen and move the cursor to the top left corner.
+        print!("\x1B[2J\x1B[H{}", output);
+        let _ = std::io::stdout().flush();
+    }
+}
+
+fn components_uri(base: &Uri) -> Result<Uri, http::Error> {
+    let mut parts = base.clone().into_parts();
+    parts.path_and_query = Some("/components".parse().expect("static path is valid"));
+    Ok(Uri::from_parts(parts)?)
+}
+
+async fn fetch(client: &Client<HttpConnector>, uri: &Uri) -> crate::Result<Vec<ComponentStatus>> {
+    let response = client.get(uri.clone()).await?;
+    if !response.status().is_success() {
+        return Err(format!("unexpected response status {}", response.status()).into());
+    }
+
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+fn render(
+    statuses: &[ComponentStatus],
+    previous: Option<(Duration, &HashMap<String, ComponentMetrics>)>,
+) -> String {
+    let rows = statuses
+        .iter()
+        .map(|status| {
+            let metrics = &status.metrics;
+            let healthy = if status.component.healthy {
+                "yes"
+            } else {
+                "no"
+            };
+            let last = previous.and_then(|(elapsed, last)| {
+                last.get(&status.component.name)
+                    .map(|metrics| (elapsed, metrics))
+            });
+
+            let events_in = match status.component.kind {
+                ComponentKind::Source => "-".into(),
+                ComponentKind::Transform | ComponentKind::Sink => rate(
+                    metrics.events_received,
+                    last.map(|(e, m)| (e, m.events_received)),
+                ),
+            };
+            let events_out = match status.component.kind {
+                ComponentKind::Source | ComponentKind::Transform => rate(
+                    metrics.events_processed,
+                    last.map(|(e, m)| (e, m.events_processed)),
+                ),
+                ComponentKind::Sink => "-".into(),
+            };
+
+            vec![
+                status.component.kind.as_str().to_string(),
+                status.component.name.clone(),
+                status.component.component_type.clone(),
+                events_in,
+                events_out,
+                rate(
+                    metrics.bytes_processed,
+                    last.map(|(e, m)| (e, m.bytes_processed)),
+                ),
+                metrics.processing_errors.to_string(),
+                metrics
+                    .buffer_events
+                    .map(|events| events.to_string())
+                    .unwrap_or_else(|| "-".into()),
+                healthy.to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let widths = HEADERS
+        .iter()
+        .enumerate()
+        .map(|(i, header)| {
+            rows.iter()
+                .map(|row| row[i].len())
+                .chain(std::iter::once(header.len()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+
+    let headers = HEADERS.iter().map(|header| header.to_string()).collect();
+    std::iter::once(headers)
+        .chain(rows)
+        .map(|row: Vec<String>| {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ");
+            format!("{}\n", line.trim_end())
+        })
+        .collect()
+}
+
+/// Per second rate of a counter since the previous sample. Counters that went
+/// backwards belong to a restarted instance and have no meaningful rate.
+fn rate(current: u64, previous: Option<(Duration, u64)>) -> String {
+    match previous {
+        Some((elapsed, previous)) if current >= previous && elapsed.as_secs_f64() > 0.0 => {
+            format!("{:.1}", (current - previous) as f64 / elapsed.as_secs_f64())
+        }
+        _ => "-".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Component;
+
+    fn status(
+        name: &str,
+        kind: ComponentKind,
+        received: u64,
+        processed: u64,
+        buffer: Option<i64>,
+    ) -> ComponentStatus {
+        ComponentStatus {
+            component: Component {
+                name: name.into(),
+                kind,
+                component_type: "test".into(),
+                inputs: vec![],
+                healthy: true,
+            },
+            metrics: ComponentMetrics {
+                events_received: received,
+                events_processed: processed,
+                bytes_processed: processed * 10,
+                processing_errors: 0,
+                buffer_events: buffer,
+            },
+        }
+    }
+
+    #[test]
+    fn renders_rates() {
+        let mut previous = HashMap::new();
+        previous.insert(
+            "in".to_string(),
+            status("in", ComponentKind::Source, 0, 10, None).metrics,
+        );
+        previous.insert(
+            "filter".to_string(),
+            status("filter", ComponentKind::Transform, 10, 10, Some(0)).metrics,
+        );
+
+        let statuses = vec![
+            status("in", ComponentKind::Source, 0, 30, None),
+            status("filter", ComponentKind::Transform, 30, 20, Some(0)),
+            status("out", ComponentKind::Sink, 5, 5, Some(3)),
+        ];
+        let table = render(&statuses, Some((Duration::from_secs(2), &previous)));
+        let lines = table.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("KIND"));
+        let source = lines[1].split_whitespace().collect::<Vec<_>>();
+        assert_eq!(
+            source,
+            vec!["source", "in", "test", "-", "10.0", "100.0", "0", "-", "yes"]
+        );
+        let transform = lines[2].split_whitespace().collect::<Vec<_>>();
+        assert_eq!(
+            transform,
+            vec![
+                "transform",
+                "filter",
+                "test",
+                "10.0",
+                "5.0",
+                "50.0",
+                "0",
+                "0",
+                "yes"
+            ]
+        );
+        let sink = lines[3].split_whitespace().collect::<Vec<_>>();
+        assert_eq!(
+            sink,
+            vec!["sink", "out", "test", "-", "-", "-", "0", "3", "yes"]
+        );
+    }
+
+    #[test]
+    fn rate_ignores_restarted_counters() {
+        assert_eq!(rate(5, Some((Duration::from_secs(1), 10))), "-");
+        assert_eq!(rate(5, None), "-");
+        assert_eq!(rate(15, Some((Duration::from_secs(1), 10))), "5.0");
+    }
+
+    #[test]
+    fn builds_components_uri() {
+        let uri = components_uri(&"http://localhost:8686".parse().unwrap()).unwrap();
+        assert_eq!(uri, "http://localhost:8686/components");
+    }
+}
@@ -1,24 +1,44 @@
 use super::{
     config::{DataType, SinkContext, TransformContext},
-    fanout::{self, Fanout},
+    fanout::{self, Fanout, OutputsFanout},
     task::Task,
     ConfigDiff,
 };
-use crate::{buffers, dns::Resolver, event::Event, shutdown::SourceShutdownCoordinator};
+use crate::{
+    buffers,
+    dead_letter::{DeadLetterConfig, DeadLetters},
+    dns::Resolver,
+    event::Event,
+    internal_events::ComponentProcessingError,
+    shutdown::SourceShutdownCoordinator,
+    transforms::ERRORS_OUTPUT,
+};
 use futures01::{
     future::{lazy, Either},
     sync::mpsc,
     Future, Stream,
 };
-use std::{collections::HashMap, time::Duration};
+use metrics_core::Label;
+use metrics_runtime::data::Counter;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 use tokio01::util::FutureExt;
 
+/// The events a sink sends to its dead letter destination.
+pub type DeadLetterStream = Box<dyn Stream<Item = Event, Error = ()> + Send>;
+
 pub struct Pieces {
     pub inputs: HashMap<String, (buffers::BufferInputCloner, Vec<String>)>,
     pub outputs: HashMap<String, fanout::ControlChannel>,
+    /// Outputs of transforms besides their default one, as they appear in
+    /// `outputs`.
+    pub named_outputs: HashMap<String, Vec<String>>,
     pub tasks: HashMap<String, Task>,
     pub source_tasks: HashMap<String, Task>,
     pub healthchecks: HashMap<String, Task>,
+    pub dead_letters: HashMap<String, (DeadLetterConfig, DeadLetterStream)>,
     pub shutdown_coordinator: SourceShutdownCoordinator,
 }
 
@@ -64,7 +84,7 @@ pub fn check(config: &super::Config) -> Result<Vec<String>, Vec<String>> {
         }
 
         for input in inputs {
-            if !config.sources.contains_key(&input) && !config.transforms.contains_key(&input) {
+            if config.input_component(&input).is_none() {
                 errors.push(format!(
                     "Input {:?} for {} {:?} doesn't exist.",
                     input, output_type, name
@@ -73,21 +93,53 @@ pub fn check(config: &super::Config) -> Result<Vec<String>, Vec<String>> {
         }
     }
 
+    for (name, sink) in &config.sinks {
+        if sink.dead_letter.is_some() && !sink.inner.supports_dead_letter() {
+            errors.push(format!(
+                "Sink {:?} of type {:?} doesn't support a dead letter destination.",
+                name,
+                sink.inner.sink_type()
+            ));
+        }
+        if let Some(DeadLetterConfig::Component(target)) = &sink.dead_letter {
+            if !config.sinks.contains_key(target) && !config.transforms.contains_key(target) {
+                errors.push(format!(
+                    "Dead letter destination {:?} for sink {:?} doesn't exist.",
+                    target, name
+                ));
+            }
+        }
+    }
+
+    for (name, transform) in &config.transforms {
+        for output in transform.inner.named_outputs() {
+            let output = format!("{}.{}", name, output);
+            if config.sources.contains_key(&output)
+                || config.transforms.contains_key(&output)
+                || config.sinks.contains_key(&output)
+            {
+                errors.push(format!(
+                    "Output {:?} of transform {:?} has the same name as a component.",
+                    output, name
+                ));
+            }
+        }
+    }
+
+    let consumed = config
+        .transforms
+        .values()
+        .flat_map(|transform| &transform.inputs)
+        .chain(config.sinks.values().flat_map(|sink| &sink.inputs))
+        .filter_map(|input| config.input_component(input))
+        .collect::<HashSet<_>>();
     let source_names = config.sources.keys().map(|name| ("source", name.clone()));
     let transform_names = config
         .transforms
         .keys()
         .map(|name| ("transform", name.clone()));
     for (input_type, name) in transform_names.chain(source_names) {
-        if !config
-            .transforms
-            .iter()
-            .any(|(_, transform)| transform.inputs.contains(&name))
-            && !config
-                .sinks
-                .iter()
-                .any(|(_, sink)| sink.inputs.contains(&name))
-        {
+        if !consumed.contains(name.as_str()) {
             warnings.push(format!(
                 "{} {:?} has no consumers",
                 capitalize(input_type),
@@ -96,8 +148,12 @@ pub fn check(config: &super::Config) -> Result<Vec<String>, Vec<String>> {
         }
     }
 
+    // Cycles among the inputs are reported by the typecheck, so only look for
+    // the ones dead letters add once there are none.
     if let Err(type_errors) = config.typecheck() {
         errors.extend(type_errors);
+    } else if let Err(dead_letter_errors) = config.check_dead_letters() {
+        errors.extend(dead_letter_errors);
     }
 
     if errors.is_empty() {
@@ -111,9 +167,11 @@ pub fn check(config: &super::Config) -> Result<Vec<String>, Vec<String>> {
 pub fn build_pieces(config: &super::Config, diff: &ConfigDiff) -> Result<Pieces, Vec<String>> {
     let mut inputs = HashMap::new();
     let mut outputs = HashMap::new();
+    let mut named_outputs = HashMap::new();
     let mut tasks = HashMap::new();
     let mut source_tasks = HashMap::new();
     let mut healthchecks = HashMap::new();
+    let mut dead_letters = HashMap::new();
     let mut shutdown_coordinator = SourceShutdownCoordinator::default();
 
     let mut errors = vec![];
@@ -141,8 +199,10 @@ pub fn build_pieces(config: &super::Config, diff: &ConfigDiff) -> Result<Pieces,
             Ok(server) => server,
         };
 
+        let counters = ComponentCounters::new("source", typetag, name);
+
         let (output, control) = Fanout::new();
-        let pump = rx.forward(output).map(|_| ());
+        let pump = counters.count_processed(rx).forward(output).map(|_| ());
         let pump = Task::new(&name, &typetag, pump);
 
         // The force_shutdown_tripwire is a Future that when it resolves means that this source
@@ -150,10 +210,11 @@ pub fn build_pieces(config: &super::Config, diff: &ConfigDiff) -> Result<Pieces,
         // forcibly shut down.  We accomplish this by select()-ing on the server Task with the
         // force_shutdown_tripwire.  That means that if the force_shutdown_tripwire resolves while
         // the server Task is still running the Task will simply be dropped on the floor.
+        let source_counters = counters.clone();
         let server = server
             .select(force_shutdown_tripwire)
             .map(|_| debug!("Finished"))
-            .map_err(|_| ());
+            .map_err(move |_| source_counters.processing_error());
         let server = Task::new(&name, &typetag, server);
 
         outputs.insert(name.clone(), control);
@@ -169,7 +230,8 @@ pub fn build_pieces(config: &super::Config, diff: &ConfigDiff) -> Result<Pieces,
     {
         let trans_inputs = &transform.inputs;
 
-        let typetag = &transform.inner.transform_type();
+        let typetag = transform.inner.transform_type();
+        let transform_outputs = transform.inner.named_outputs();
 
         let cx = TransformContext { resolver };
 
@@ -182,16 +244,53 @@ pub fn build_pieces(config: &super::Config, diff: &ConfigDiff) -> Result<Pieces,
             Ok(transform) => transform,
         };
 
+        let usage = buffers::BufferUsage::new(name);
         let (input_tx, input_rx) = futures01::sync::mpsc::channel(100);
-        let input_tx = buffers::BufferInputCloner::Memory(input_tx, buffers::WhenFull::Block);
+        let input_rx = usage.meter_output(input_rx);
+        let input_tx =
+            buffers::BufferInputCloner::Memory(input_tx, buffers::WhenFull::Block, usage);
 
         let (output, control) = Fanout::new();
 
-        let transform = transform
-            .transform_stream(filter_event_type(input_rx, input_type))
-            .forward(output)
-            .map(|_| debug!("Finished"));
-        let task = Task::new(&name, &typetag, transform);
+        let counters = ComponentCounters::new("transform", typetag, name);
+        let transform_counters = counters.clone();
+        let input_rx: Box<dyn Stream<Item = Event, Error = ()> + Send> =
+            Box::new(counters.count_received(filter_event_type(input_rx, input_type)));
+        let task = if transform_outputs.is_empty() {
+            let transform = transform.transform_stream(input_rx);
+            let transform = counters
+                .count_processed(transform)
+                .map_err(move |()| transform_counters.processing_error())
+                .forward(output)
+                .map(|_| debug!("Finished"));
+            Task::new(&name, &typetag, transform)
+        } else {
+            let mut output = OutputsFanout::new(output);
+            let mut output_names = Vec::new();
+            for transform_output in transform_outputs {
+                let (fanout, control) = Fanout::new();
+                output.add(transform_output, fanout);
+
+                let output_name = format!("{}.{}", name, transform_output);
+                outputs.insert(output_name.clone(), control);
+                output_names.push(output_name);
+            }
+            named_outputs.insert(name.clone(), output_names);
+
+            let events_counters = counters.clone();
+            let transform = transform
+                .transform_stream_with_outputs(input_rx)
+                .inspect(move |(output, event)| {
+                    events_counters.processed(event);
+                    if *output == Some(ERRORS_OUTPUT) {
+                        events_counters.event_failed();
+                    }
+                })
+                .map_err(move |()| transform_counters.processing_error())
+                .forward(output)
+                .map(|_| debug!("Finished"));
+            Task::new(&name, &typetag, transform)
+        };
 
         inputs.insert(name.clone(), (input_tx, trans_inputs.clone()));
         outputs.insert(name.clone(), control);
@@ -210,7 +309,9 @@ pub fn build_pieces(config: &super::Config, diff: &ConfigDiff) -> Result<Pieces,
         let typetag = sink.inner.sink_type();
         let input_type = sink.inner.input_type();
 
-        let buffer = sink.buffer.build(&config.global.data_dir, &name);
+        let buffer = sink
+            .buffer
+            .build(&config.global.data_dir, &name, input_type);
         let (tx, rx, acker) = match buffer {
             Err(error) => {
                 errors.push(format!("Sink \"{}\": {}", name, error));
@@ -219,7 +320,22 @@ pub fn build_pieces(config: &super::Config, diff: &ConfigDiff) -> Result<Pieces,
             Ok(buffer) => buffer,
         };
 
-        let cx = SinkContext { resolver, acker };
+        let counters = ComponentCounters::new("sink", typetag, name);
+
+        let sink_dead_letters = sink.dead_letter.as_ref().map(|config| {
+            let (handle, rx) = DeadLetters::new(name);
+            let dead_letter_counters = counters.clone();
+            let rx: DeadLetterStream =
+                Box::new(rx.inspect(move |_| dead_letter_counters.event_failed()));
+            dead_letters.insert(name.clone(), (config.clone(), rx));
+            handle
+        });
+
+        let cx = SinkContext {
+            resolver,
+            acker,
+            dead_letters: sink_dead_letters,
+        };
 
         let (sink, healthcheck) = match sink.inner.build(cx) {
             Err(error) => {
@@ -229,8 +345,11 @@ pub fn build_pieces(config: &super::Config, diff: &ConfigDiff) -> Result<Pieces,
             Ok((sink, healthcheck)) => (sink, healthcheck),
         };
 
-        let sink = filter_event_type(rx, input_type)
-            .forward(sink)
+        let sink_counters = counters.clone();
+        let rx = counters.count_received(rx);
+        let sink = counters
+            .count_processed(rx)
+            .forward(sink.sink_map_err(move |()| sink_counters.processing_error()))
             .map(|_| debug!("Finished"));
         let task = Task::new(&name, &typetag, sink);
 
@@ -258,9 +377,11 @@ pub fn build_pieces(config: &super::Config, diff: &ConfigDiff) -> Result<Pieces,
         let pieces = Pieces {
             inputs,
             outputs,
+            named_outputs,
             tasks,
             source_tasks,
             healthchecks,
+            dead_letters,
             shutdown_coordinator,
         };
 
@@ -270,6 +391,113 @@ pub fn build_pieces(config: &super::Config, diff: &ConfigDiff) -> Result<Pieces,
     }
 }
 
+/// The `events_received`, `events_processed`, `bytes_processed` and
+/// `processing_errors` counters of a single component. These are recorded by
+/// the topology itself, so they exist for every component regardless of its
+/// implementation. Events are received as they enter transforms and sinks,
+/// and processed as they leave sources and transforms or enter sinks.
+///
+/// The counters are registered once when the component is built, so that
+/// counting an event doesn't have to build their labels again.
+#[derive(Clone)]
+struct ComponentCounters {
+    kind: &'static str,
+    typetag: &'static str,
+    name: String,
+    handles: Option<CounterHandles>,
+}
+
+#[derive(Clone)]
+struct CounterHandles {
+    /// Sources have no inputs, so they don't get this counter.
+    events_received: Option<Counter>,
+    events_processed: Counter,
+    bytes_processed: Counter,
+    processing_errors: Counter,
+}
+
+impl ComponentCounters {
+    fn new(kind: &'static str, typetag: &'static str, name: &str) -> Self {
+        let handles = crate::metrics::SINK.get().map(|sink| {
+            let mut sink = sink.clone();
+            let labels = vec![
+                Label::new("component_kind", kind),
+                Label::new("component_type", typetag),
+                Label::new("component_name", name.to_owned()),
+            ];
+            let mut counter = |metric| sink.counter_with_labels(metric, labels.clone());
+            CounterHandles {
+                events_received: if kind == "source" {
+                    None
+                } else {
+                    Some(counter("events_received"))
+                },
+                events_processed: counter("events_processed"),
+                bytes_processed: counter("bytes_processed"),
+                processing_errors: counter("processing_errors"),
+            }
+        });
+
+        Self {
+            kind,
+            typetag,
+            name: name.to_owned(),
+            handles,
+        }
+    }
+
+    fn count_received<S>(&self, stream: S) -> impl Stream<Item = Event, Error = S::Error>
+    where
+        S: Stream<Item = Event>,
+    {
+        let counters = self.clone();
+        stream.inspect(move |_| counters.received())
+    }
+
+    fn count_processed<S>(&self, stream: S) -> impl Stream<Item = Event, Error = S::Error>
+    where
+        S: Stream<Item = Event>,
+    {
+        let counters = self.clone();
+        stream.inspect(move |event| counters.processed(event))
+    }
+
+    fn received(&self) {
+        if let Some(counter) = self
+            .handles
+            .as_ref()
+            .and_then(|handles| handles.events_received.as_ref())
+        {
+            counter.increment();
+        }
+    }
+
+    fn processed(&self, event: &Event) {
+        if let Some(handles) = &self.handles {
+            handles.events_processed.increment();
+            handles.bytes_processed.record(event.size_of() as u64);
+        }
+    }
+
+    /// A single event failed to be processed, like an event a parser sends
+    /// to its errors output or one a sink gives up on delivering.
+    fn event_failed(&self) {
+        if let Some(handles) = &self.handles {
+            handles.processing_errors.increment();
+        }
+    }
+
+    /// The task of the component failed as a whole.
+    fn processing_error(&self) {
+        emit!(ComponentProcessingError {
+            component_kind: self.kind,
+            component_type: self.typetag,
+            component_name: &self.name,
+        });
+        self.event_failed();
+    }
+}
+
 fn capitalize(s: &str) -> String {
     let mut s = s.to_owned();
     if let Some(r) = s.get_mut(0..1) {
@@ -278,7 +506,7 @@ fn capitalize(s: &str) -> String {
     s
 }
 
-fn filter_event_type<S>(
+pub(crate) fn filter_event_type<S>(
     stream: S,
     data_type: DataType,
 ) -> Box<dyn Stream<Item = Event, Error = ()> + Send>
@@ -0,0 +1,92 @@
+//! Support for the config file formats.
+
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+/// A config format, either given explicitly or detected from the file
+/// extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Toml,
+    Json,
+    Yaml,
+}
+
+/// An explicitly requested format, `None` meaning it is detected from the
+/// file extension.
+pub type FormatHint = Option<Format>;
+
+impl Default for Format {
+    fn default() -> Self {
+        Format::Toml
+    }
+}
+
+impl Format {
+    /// Detects the format from the file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Some(Format::Toml),
+            Some("json") => Some(Format::Json),
+            Some("yaml") | Some("yml") => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Resolves the format of `path`, falling back to TOML for unknown
+    /// extensions.
+    pub fn resolve(hint: FormatHint, path: &Path) -> Self {
+        hint.or_else(|| Self::from_path(path)).unwrap_or_default()
+    }
+
+    /// Deserializes `content` in this format.
+    pub fn deserialize<T: DeserializeOwned>(self, content: &str) -> Result<T, Vec<String>> {
+        match self {
+            Format::Toml => toml::from_str(content).map_err(|e| vec![e.to_string()]),
+            Format::Json => serde_json::from_str(content).map_err(|e| vec![e.to_string()]),
+            Format::Yaml => serde_yaml::from_str(content).map_err(|e| vec![e.to_string()]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::path::PathBuf;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Sample {
+        name: String,
+        inputs: Vec<String>,
+    }
+
+    #[test]
+    fn detects_format_from_extension() {
+        let resolve = |hint, path: &str| Format::resolve(hint, &PathBuf::from(path));
+
+        assert_eq!(resolve(None, "vector.toml"), Format::Toml);
+        assert_eq!(resolve(None, "vector.json"), Format::Json);
+        assert_eq!(resolve(None, "vector.yaml"), Format::Yaml);
+        assert_eq!(resolve(None, "vector.yml"), Format::Yaml);
+        assert_eq!(resolve(None, "vector.conf"), Format::Toml);
+        assert_eq!(resolve(Some(Format::Json), "vector.conf"), Format::Json);
+        assert_eq!(resolve(Some(Format::Yaml), "vector.toml"), Format::Yaml);
+    }
+
+    #[test]
+    fn deserializes_every_format() {
+        let expected = Sample {
+            name: "foo".into(),
+            inputs: vec!["in".into()],
+        };
+
+        let toml = "name = \"foo\"\ninputs = [\"in\"]";
+        let json = r#"{"name": "foo", "inputs": ["in"]}"#;
+        let yaml = "name: foo\ninputs:\n  - in";
+
+        assert_eq!(Format::Toml.deserialize::<Sample>(toml).unwrap(), expected);
+        assert_eq!(Format::Json.deserialize::<Sample>(json).unwrap(), expected);
+        assert_eq!(Format::Yaml.deserialize::<Sample>(yaml).unwrap(), expected);
+    }
+}
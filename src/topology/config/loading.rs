@@ -0,0 +1,338 @@
+//! Loading of configs from files and directories.
+//!
+//! Besides single config files, a config can be split over a directory. Config
+//! files directly in the directory are loaded like any other config file,
+//! while each file in its `sources`, `transforms` and `sinks` subdirectories
+//! holds a single component that is named after the file. Any config file can
+//! also `include` other config files, relative to itself.
+//!
+//! Config files can be written in TOML, JSON or YAML. Unless it is given
+//! explicitly, the format of a file is detected from its extension.
+
+use super::{vars, Config, Format, FormatHint};
+use glob::glob;
+use indexmap::IndexMap;
+use serde::de::DeserializeOwned;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// Reads a config and interpolates environment variables into it.
+pub(super) fn interpolate_env(mut input: impl Read) -> Result<String, Vec<String>> {
+    let mut source_string = String::new();
+    input
+        .read_to_string(&mut source_string)
+        .map_err(|e| vec![e.to_string()])?;
+
+    let mut vars = std::env::vars().collect::<HashMap<_, _>>();
+    if !vars.contains_key("HOSTNAME") {
+        if let Some(hostname) = hostname::get_hostname() {
+            vars.insert("HOSTNAME".into(), hostname);
+        }
+    }
+    Ok(vars::interpolate(&source_string, &vars))
+}
+
+/// Loads and merges the config files in `paths` and the config directories in
+/// `dirs`.
+pub fn load_from_paths(
+    paths: &[(PathBuf, FormatHint)],
+    dirs: &[PathBuf],
+) -> Result<Config, Vec<String>> {
+    let mut config = Config::empty();
+    let mut errors = Vec::new();
+
+    let loaded = paths
+        .iter()
+        .map(|(path, format)| (path, load_file(path, *format)))
+        .chain(dirs.iter().map(|dir| (dir, load_dir(dir))));
+    for (path, result) in loaded {
+        match result.and_then(|loaded| config.append(loaded).map_err(|errs| prefix(path, errs))) {
+            Ok(()) => {}
+            Err(errs) => errors.extend(errs),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(config)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Loads a config file along with the config files it includes.
+pub fn load_file(path: &Path, format: FormatHint) -> Result<Config, Vec<String>> {
+    load_file_included(path, format, &mut Vec::new())
+}
+
+fn load_file_included(
+    path: &Path,
+    format: FormatHint,
+    including: &mut Vec<PathBuf>,
+) -> Result<Config, Vec<String>> {
+    let format = Format::resolve(format, path);
+    trace!(
+        message = "Parsing config.",
+        path = ?path,
+        ?format
+    );
+
+    let mut config = Config::load_format(open(path)?, format).map_err(|errs| prefix(path, errs))?;
+
+    let canonical =
+        fs::canonicalize(path).map_err(|error| prefix(path, vec![error.to_string()]))?;
+    if including.contains(&canonical) {
+        return Err(vec![format!("Config file {:?} includes itself.", path)]);
+    }
+
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut errors = Vec::new();
+    including.push(canonical);
+    for include in std::mem::take(&mut config.include) {
+        let pattern = base.join(include);
+        let included = match expand(&pattern) {
+            Ok(included) => included,
+            Err(error) => {
+                errors.push(format!("{:?}: {}", path, error));
+                continue;
+            }
+        };
+
+        for included in included {
+            match load_file_included(&included, None, including).and_then(|loaded| {
+                config
+                    .append(loaded)
+                    .map_err(|errs| prefix(&included, errs))
+            }) {
+                Ok(()) => {}
+                Err(errs) => errors.extend(errs),
+            }
+        }
+    }
+    including.pop();
+
+    if errors.is_empty() {
+        Ok(config)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Loads a config directory, see the module documentation.
+pub fn load_dir(dir: &Path) -> Result<Config, Vec<String>> {
+    let mut config = Config::empty();
+    let mut errors = Vec::new();
+
+    for path in config_files(dir)? {
+        match load_file(&path, None)
+            .and_then(|loaded| config.append(loaded).map_err(|errs| prefix(&path, errs)))
+        {
+            Ok(()) => {}
+            Err(errs) => errors.extend(errs),
+        }
+    }
+
+    load_components(
+        &dir.join("sources"),
+        "source",
+        &mut config.sources,
+        &mut errors,
+    );
+    load_components(
+        &dir.join("transforms"),
+        "transform",
+        &mut config.transforms,
+        &mut errors,
+    );
+    load_components(&dir.join("sinks"), "sink", &mut config.sinks, &mut errors);
+
+    if errors.is_empty() {
+        Ok(config)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Loads each config file in `dir` as a single component named after the file.
+fn load_components<T: DeserializeOwned>(
+    dir: &Path,
+    kind: &str,
+    components: &mut IndexMap<String, T>,
+    errors: &mut Vec<String>,
+) {
+    if !dir.is_dir() {
+        return;
+    }
+
+    let paths = match config_files(dir) {
+        Ok(paths) => paths,
+        Err(errs) => {
+            errors.extend(errs);
+            return;
+        }
+    };
+
+    for path in paths {
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) => name.to_owned(),
+            None => {
+                errors.push(format!("{:?}: file name is not valid UTF-8", path));
+                continue;
+            }
+        };
+
+        let component = open(&path)
+            .and_then(interpolate_env)
+            .and_then(|source| Format::resolve(None, &path).deserialize(&source))
+            .map_err(|errs| prefix(&path, errs));
+        match component {
+            Ok(_) if components.contains_key(&name) => {
+                errors.push(format!("duplicate {} name found: {}", kind, name));
+            }
+            Ok(component) => {
+                components.insert(name, component);
+            }
+            Err(errs) => errors.extend(errs),
+        }
+    }
+}
+
+/// Lists the config files directly in `dir`, in a stable order.
+fn config_files(dir: &Path) -> Result<Vec<PathBuf>, Vec<String>> {
+    let entries = fs::read_dir(dir).map_err(|error| {
+        vec![format!(
+            "Could not read config directory {:?}: {}",
+            dir, error
+        )]
+    })?;
+
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && Format::from_path(path).is_some())
+        .collect::<Vec<_>>();
+    paths.sort();
+    Ok(paths)
+}
+
+fn expand(pattern: &Path) -> Result<Vec<PathBuf>, String> {
+    let pattern_str = pattern
+        .to_str()
+        .ok_or_else(|| format!("Included path {:?} is not valid UTF-8.", pattern))?;
+    let mut paths = glob(pattern_str)
+        .map_err(|error| format!("Invalid included path {:?}: {}", pattern, error))?
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+
+    if paths.is_empty() {
+        return Err(format!("Included path {:?} not found.", pattern));
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn open(path: &Path) -> Result<File, Vec<String>> {
+    File::open(path).map_err(|error| {
+        if let std::io::ErrorKind::NotFound = error.kind() {
+            error!(message = "Config file not found in path.", ?path);
+            vec![format!("Config file not found in path: {:?}.", path)]
+        } else {
+            error!(message = "Error opening config file.", %error);
+            vec![format!("Could not open config file {:?}: {}", path, error)]
+        }
+    })
+}
+
+fn prefix(path: &Path, errors: Vec<String>) -> Vec<String> {
+    errors
+        .into_iter()
+        .map(|error| format!("{:?}: {}", path, error))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn write(path: &Path, contents: &str) {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        File::create(path)
+            .unwrap()
+            .write_all(contents.as_bytes())
+            .unwrap();
+    }
+
+    #[cfg(all(feature = "sources-stdin", feature = "sinks-console"))]
+    #[test]
+    fn loads_included_files() {
+        let dir = tempdir().unwrap();
+        write(
+            &dir.path().join("vector.toml"),
+            r#"
+            include = ["sinks/*.toml"]
+
+            [sources.in]
+            type = "stdin"
+            "#,
+        );
+        write(
+            &dir.path().join("sinks/out.toml"),
+            r#"
+            [sinks.out]
+            type = "console"
+            inputs = ["in"]
+            encoding = "json"
+            "#,
+        );
+
+        let config = load_file(&dir.path().join("vector.toml"), None).unwrap();
+        assert!(config.sources.contains_key("in"));
+        assert!(config.sinks.contains_key("out"));
+        assert!(config.include.is_empty());
+    }
+
+    #[test]
+    fn rejects_include_cycles() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("vector.toml");
+        write(&path, r#"include = ["vector.toml"]"#);
+
+        let errors = load_file(&path, None).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![format!("Config file {:?} includes itself.", path)]
+        );
+    }
+
+    #[cfg(all(
+        feature = "sources-stdin",
+        feature = "transforms-sampler",
+        feature = "sinks-console"
+    ))]
+    #[test]
+    fn names_components_after_files() {
+        let dir = tempdir().unwrap();
+        write(&dir.path().join("vector.toml"), r#"data_dir = "/tmp""#);
+        write(&dir.path().join("sources/in.toml"), r#"type = "stdin""#);
+        write(
+            &dir.path().join("transforms/sample.yaml"),
+            "type: sampler\ninputs: [\"in\"]\nrate: 10\n",
+        );
+        write(
+            &dir.path().join("sinks/out.json"),
+            r#"{"type": "console", "inputs": ["sample"], "encoding": "json"}"#,
+        );
+
+        let config = load_dir(dir.path()).unwrap();
+        assert_eq!(config.global.data_dir, Some("/tmp".into()));
+        assert_eq!(config.sources.keys().collect::<Vec<_>>(), vec!["in"]);
+        assert_eq!(config.transforms.keys().collect::<Vec<_>>(), vec!["sample"]);
+        assert_eq!(config.sinks.keys().collect::<Vec<_>>(), vec!["out"]);
+    }
+}
@@ -1,20 +1,24 @@
 use crate::{
     buffers::Acker,
     conditions,
+    dead_letter::{DeadLetterConfig, DeadLetters},
     dns::Resolver,
     event::{self, Event, Metric},
     shutdown::ShutdownSignal,
     sinks, sources, transforms,
 };
 use component::ComponentDescription;
+pub use format::{Format, FormatHint};
 use futures01::sync::mpsc;
 use indexmap::IndexMap; // IndexMap preserves insertion order, allowing us to output errors in the same order they are present in the file
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
 use std::fs::DirBuilder;
-use std::{collections::HashMap, path::PathBuf};
+use std::path::PathBuf;
 
 pub mod component;
+pub mod format;
+pub mod loading;
 mod validation;
 mod vars;
 pub mod watcher;
@@ -32,6 +36,10 @@ pub struct Config {
     pub transforms: IndexMap<String, TransformOuter>,
     #[serde(default)]
     pub tests: Vec<TestDefinition>,
+    /// Other config files to load along with this one, relative to it. Glob
+    /// patterns are supported.
+    #[serde(default, skip_serializing)]
+    pub include: Vec<PathBuf>,
 }
 
 #[derive(Default, Debug, Deserialize, Serialize)]
@@ -43,6 +51,11 @@ pub struct GlobalOptions {
         default
     )]
     pub log_schema: event::LogSchema,
+    #[serde(
+        skip_serializing_if = "crate::serde::skip_serializing_if_default",
+        default
+    )]
+    pub api: crate::api::Options,
 }
 
 pub fn default_data_dir() -> Option<PathBuf> {
@@ -148,6 +161,8 @@ pub struct SinkOuter {
     #[serde(default = "healthcheck_default")]
     pub healthcheck: bool,
     pub inputs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dead_letter: Option<DeadLetterConfig>,
     #[serde(flatten)]
     pub inner: Box<dyn SinkConfig>,
 }
@@ -159,12 +174,20 @@ pub trait SinkConfig: core::fmt::Debug + Send + Sync {
     fn input_type(&self) -> DataType;
 
     fn sink_type(&self) -> &'static str;
+
+    /// Whether the sink hands the events it fails to deliver to
+    /// `SinkContext::dead_letters`. Sinks that don't can't be configured with
+    /// a `dead_letter` destination.
+    fn supports_dead_letter(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct SinkContext {
     pub(super) acker: Acker,
     pub(super) resolver: Resolver,
+    pub(super) dead_letters: Option<DeadLetters>,
 }
 
 impl SinkContext {
@@ -173,6 +196,7 @@ impl SinkContext {
         Self {
             acker: Acker::Null,
             resolver: Resolver,
+            dead_letters: None,
         }
     }
 
@@ -183,6 +207,12 @@ impl SinkContext {
     pub fn resolver(&self) -> Resolver {
         self.resolver
     }
+
+    /// Handle for the events this sink permanently fails to deliver, if the
+    /// sink has a `dead_letter` destination configured.
+    pub fn dead_letters(&self) -> Option<DeadLetters> {
+        self.dead_letters.clone()
+    }
 }
 
 pub type SinkDescription = ComponentDescription<Box<dyn SinkConfig>>;
@@ -206,6 +236,13 @@ pub trait TransformConfig: core::fmt::Debug + Send + Sync {
 
     fn transform_type(&self) -> &'static str;
 
+    /// Names of the outputs the transform has besides its default one, which
+    /// other components consume by using `transform_name.output_name` as an
+    /// input. Events are sent to them through `Transform::transform_outputs`.
+    fn named_outputs(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
     /// Allows a transform configuration to expand itself into multiple "child"
     /// transformations to replace it. This allows a transform to act as a macro
     /// for various patterns.
@@ -292,11 +329,13 @@ impl Config {
             global: GlobalOptions {
                 data_dir: None,
                 log_schema: event::LogSchema::default(),
+                api: Default::default(),
             },
             sources: IndexMap::new(),
             sinks: IndexMap::new(),
             transforms: IndexMap::new(),
             tests: Vec::new(),
+            include: Vec::new(),
         }
     }
 
@@ -311,6 +350,7 @@ impl Config {
             healthcheck: true,
             inner: Box::new(sink),
             inputs,
+            dead_letter: None,
         };
 
         self.sinks.insert(name.to_string(), sink);
@@ -331,6 +371,22 @@ impl Config {
         self.transforms.insert(name.to_string(), transform);
     }
 
+    /// Returns the name of the component an input refers to, which is the
+    /// input itself unless it names an output of a transform, or `None` if
+    /// there is no such component.
+    pub fn input_component<'a>(&self, input: &'a str) -> Option<&'a str> {
+        if self.sources.contains_key(input) || self.transforms.contains_key(input) {
+            return Some(input);
+        }
+
+        let dot = input.rfind('.')?;
+        let (name, output) = (&input[..dot], &input[dot + 1..]);
+        self.transforms
+            .get(name)
+            .filter(|transform| transform.inner.named_outputs().contains(&output))
+            .map(|_| name)
+    }
+
     /// Some component configs can act like macros and expand themselves into
     /// multiple replacement configs. Returns a map of components to their
     /// expanded child names.
@@ -373,21 +429,14 @@ impl Config {
         }
     }
 
-    pub fn load(mut input: impl std::io::Read) -> Result<Self, Vec<String>> {
-        let mut source_string = String::new();
-        input
-            .read_to_string(&mut source_string)
-            .map_err(|e| vec![e.to_string()])?;
+    pub fn load(input: impl std::io::Read) -> Result<Self, Vec<String>> {
+        Self::load_format(input, Format::Toml)
+    }
 
-        let mut vars = std::env::vars().collect::<HashMap<_, _>>();
-        if !vars.contains_key("HOSTNAME") {
-            if let Some(hostname) = hostname::get_hostname() {
-                vars.insert("HOSTNAME".into(), hostname);
-            }
-        }
-        let with_vars = vars::interpolate(&source_string, &vars);
+    pub fn load_format(input: impl std::io::Read, format: Format) -> Result<Self, Vec<String>> {
+        let with_vars = loading::interpolate_env(input)?;
 
-        toml::from_str(&with_vars).map_err(|e| vec![e.to_string()])
+        format.deserialize(&with_vars)
     }
 
     pub fn append(&mut self, with: Self) -> Result<(), Vec<String>> {
@@ -437,6 +486,14 @@ impl Config {
             }
         }
 
+        if with.global.api != Default::default() {
+            if self.global.api != Default::default() && self.global.api != with.global.api {
+                errors.push("conflicting values for 'api' found".to_owned());
+            } else {
+                self.global.api = with.global.api;
+            }
+        }
+
         with.sources.keys().for_each(|k| {
             if self.sources.contains_key(k) {
                 errors.push(format!("duplicate source name found: {}", k));
@@ -472,6 +529,10 @@ impl Config {
     pub fn typecheck(&self) -> Result<(), Vec<String>> {
         validation::typecheck(self)
     }
+
+    pub fn check_dead_letters(&self) -> Result<(), Vec<String>> {
+        validation::check_dead_letters(self)
+    }
 }
 
 impl Clone for Config {
@@ -1,10 +1,82 @@
-use crate::topology::{config::DataType, Config};
-use std::collections::HashMap;
+use crate::{
+    dead_letter::DeadLetterConfig,
+    topology::{config::DataType, Config},
+};
+use std::collections::{HashMap, HashSet};
 
 pub fn typecheck(config: &Config) -> Result<(), Vec<String>> {
     Graph::from(config).typecheck()
 }
 
+/// Sinks send the events they fail to deliver to their dead letter
+/// destination. A destination that leads back to the sink, directly or
+/// through other components and their dead letter destinations, would send
+/// failing events around forever.
+pub fn check_dead_letters(config: &Config) -> Result<(), Vec<String>> {
+    // Every component along with the components it sends events to.
+    let mut outputs = HashMap::<&str, Vec<&str>>::new();
+    let transform_inputs = config
+        .transforms
+        .iter()
+        .map(|(name, transform)| (name, &transform.inputs));
+    let sink_inputs = config.sinks.iter().map(|(name, sink)| (name, &sink.inputs));
+    for (name, inputs) in transform_inputs.chain(sink_inputs) {
+        for input in inputs {
+            let input = config.input_component(input).unwrap_or(input);
+            outputs.entry(input).or_default().push(name);
+        }
+    }
+    for (name, sink) in &config.sinks {
+        if let Some(DeadLetterConfig::Component(target)) = &sink.dead_letter {
+            outputs.entry(name).or_default().push(target);
+        }
+    }
+
+    let mut errors = Vec::new();
+    for (name, sink) in &config.sinks {
+        if let Some(DeadLetterConfig::Component(target)) = &sink.dead_letter {
+            let mut visited = HashSet::new();
+            if let Some(mut chain) = find_chain(&outputs, target, name, &mut visited) {
+                chain.insert(0, name);
+                errors.push(format!(
+                    "Dead letters of sink {:?} loop back to it in the chain [ {} ]",
+                    name,
+                    chain.join(" -> ")
+                ));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Finds the components events pass through on their way from `from` to
+/// `to`, both included.
+fn find_chain<'a>(
+    outputs: &HashMap<&'a str, Vec<&'a str>>,
+    from: &'a str,
+    to: &str,
+    visited: &mut HashSet<&'a str>,
+) -> Option<Vec<&'a str>> {
+    if from == to {
+        return Some(vec![from]);
+    }
+    if !visited.insert(from) {
+        return None;
+    }
+
+    outputs.get(from)?.iter().find_map(|next| {
+        find_chain(outputs, *next, to, visited).map(|mut chain| {
+            chain.insert(0, from);
+            chain
+        })
+    })
+}
+
 #[derive(Debug, Clone)]
 enum Node {
     Source {
@@ -131,17 +203,25 @@ impl From<&Config> for Graph {
             graph.add_source(name, config.output_type());
         }
 
-        for (name, config) in config.transforms.iter() {
+        // Named outputs of a transform have the type of the transform itself.
+        let inputs = |inputs: &[String]| {
+            inputs
+                .iter()
+                .map(|input| config.input_component(input).unwrap_or(input))
+                .collect::<Vec<_>>()
+        };
+
+        for (name, transform) in config.transforms.iter() {
             graph.add_transform(
                 name,
-                config.inner.input_type(),
-                config.inner.output_type(),
-                config.inputs.clone(),
+                transform.inner.input_type(),
+                transform.inner.output_type(),
+                inputs(&transform.inputs),
             );
         }
 
-        for (name, config) in config.sinks.iter() {
-            graph.add_sink(name, config.inner.input_type(), config.inputs.clone());
+        for (name, sink) in config.sinks.iter() {
+            graph.add_sink(name, sink.inner.input_type(), inputs(&sink.inputs));
         }
 
         graph
@@ -102,7 +102,13 @@ fn create_watcher(
 #[cfg(unix)]
 fn add_paths(watcher: &mut RecommendedWatcher, config_paths: &[PathBuf]) -> Result<(), Error> {
     for path in config_paths {
-        watcher.watch(path, RecursiveMode::NonRecursive)?;
+        // Config directories hold components in subdirectories.
+        let mode = if path.is_dir() {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher.watch(path, mode)?;
     }
     Ok(())
 }
@@ -140,9 +140,68 @@ impl Sink for Fanout {
     }
 }
 
+/// Routes the events of a transform with named outputs to the fanout of the
+/// output each of them is tagged with, `None` being the default output.
+pub struct OutputsFanout {
+    primary: Fanout,
+    named: Vec<(&'static str, Fanout)>,
+}
+
+impl OutputsFanout {
+    pub fn new(primary: Fanout) -> Self {
+        Self {
+            primary,
+            named: vec![],
+        }
+    }
+
+    pub fn add(&mut self, output: &'static str, fanout: Fanout) {
+        self.named.push((output, fanout));
+    }
+}
+
+impl Sink for OutputsFanout {
+    type SinkItem = (Option<&'static str>, Event);
+    type SinkError = ();
+
+    fn start_send(
+        &mut self,
+        (output, event): Self::SinkItem,
+    ) -> StartSend<Self::SinkItem, Self::SinkError> {
+        let fanout = match output {
+            None => &mut self.primary,
+            Some(output) => match self.named.iter_mut().find(|(name, _)| *name == output) {
+                Some((_, fanout)) => fanout,
+                None => {
+                    debug!(message = "Dropping event sent to an undeclared output.", %output);
+                    return Ok(AsyncSink::Ready);
+                }
+            },
+        };
+
+        Ok(match fanout.start_send(event)? {
+            AsyncSink::Ready => AsyncSink::Ready,
+            AsyncSink::NotReady(event) => AsyncSink::NotReady((output, event)),
+        })
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        let mut all_complete = self.primary.poll_complete()?.is_ready();
+        for (_, fanout) in &mut self.named {
+            all_complete &= fanout.poll_complete()?.is_ready();
+        }
+
+        if all_complete {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{ControlMessage, Fanout};
+    use super::{ControlMessage, Fanout, OutputsFanout};
     use crate::test_util::{self, runtime, CollectCurrent};
     use crate::Event;
     use futures01::sync::mpsc;
@@ -451,4 +510,39 @@ mod tests {
         );
         assert_eq!(CollectCurrent::new(rx_a2).wait().unwrap().1, vec![rec3]);
     }
+
+    #[test]
+    fn outputs_fanout_routes_by_output() {
+        let (tx_default, rx_default) = mpsc::unbounded();
+        let tx_default = Box::new(tx_default.sink_map_err(|_| unreachable!()));
+        let (tx_errors, rx_errors) = mpsc::unbounded();
+        let tx_errors = Box::new(tx_errors.sink_map_err(|_| unreachable!()));
+
+        let mut primary = Fanout::new().0;
+        primary.add("default".to_string(), tx_default);
+        let mut errors = Fanout::new().0;
+        errors.add("errors".to_string(), tx_errors);
+
+        let mut outputs = OutputsFanout::new(primary);
+        outputs.add("errors", errors);
+
+        let rec1 = Event::from("line 1".to_string());
+        let rec2 = Event::from("line 2".to_string());
+        let rec3 = Event::from("line 3".to_string());
+
+        let _outputs = outputs
+            .send_all(stream::iter_ok(vec![
+                (None, rec1.clone()),
+                (Some("errors"), rec2.clone()),
+                (Some("unknown"), rec3),
+            ]))
+            .wait()
+            .unwrap();
+
+        assert_eq!(
+            CollectCurrent::new(rx_default).wait().unwrap().1,
+            vec![rec1]
+        );
+        assert_eq!(CollectCurrent::new(rx_errors).wait().unwrap().1, vec![rec2]);
+    }
 }
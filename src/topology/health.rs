@@ -0,0 +1,100 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Health of a single instance of a component. A new instance is registered
+/// every time a component is added or rebuilt, so that the tasks of a
+/// replaced instance can't affect the health of its successor.
+#[derive(Debug, Default)]
+pub struct ComponentHealth {
+    healthcheck_failed: AtomicBool,
+    finished: AtomicBool,
+}
+
+impl ComponentHealth {
+    pub fn healthcheck_failed(&self) {
+        self.healthcheck_failed.store(true, Ordering::Relaxed);
+    }
+
+    pub fn finished(&self) {
+        self.finished.store(true, Ordering::Relaxed);
+    }
+
+    /// A component is healthy while its healthcheck hasn't failed and none
+    /// of its tasks has finished.
+    pub fn is_healthy(&self) -> bool {
+        !self.healthcheck_failed.load(Ordering::Relaxed) && !self.finished.load(Ordering::Relaxed)
+    }
+}
+
+/// Health of every component of a running topology, updated as healthchecks
+/// complete and tasks finish.
+#[derive(Debug, Clone, Default)]
+pub struct HealthRegistry {
+    components: Arc<Mutex<HashMap<String, Arc<ComponentHealth>>>>,
+}
+
+impl HealthRegistry {
+    /// Registers a new instance of the component, replacing the previous one.
+    pub fn register(&self, name: &str) -> Arc<ComponentHealth> {
+        let health = Arc::new(ComponentHealth::default());
+        self.components
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), Arc::clone(&health));
+        health
+    }
+
+    /// Returns the current instance of the component, registering one if
+    /// there is none yet.
+    pub(super) fn get(&self, name: &str) -> Arc<ComponentHealth> {
+        Arc::clone(
+            self.components
+                .lock()
+                .unwrap()
+                .entry(name.to_string())
+                .or_default(),
+        )
+    }
+
+    pub(super) fn remove(&self, name: &str) {
+        self.components.lock().unwrap().remove(name);
+    }
+
+    /// Unknown components are reported as unhealthy.
+    pub fn is_healthy(&self, name: &str) -> bool {
+        self.components
+            .lock()
+            .unwrap()
+            .get(name)
+            .map_or(false, |health| health.is_healthy())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaced_instance_does_not_affect_successor() {
+        let registry = HealthRegistry::default();
+        assert!(!registry.is_healthy("out"));
+
+        let old = registry.register("out");
+        assert!(registry.is_healthy("out"));
+
+        let new = registry.register("out");
+        old.finished();
+        assert!(registry.is_healthy("out"));
+
+        new.healthcheck_failed();
+        assert!(!registry.is_healthy("out"));
+
+        registry.remove("out");
+        assert!(!registry.is_healthy("out"));
+    }
+}
@@ -9,21 +9,24 @@
 pub mod builder;
 pub mod config;
 mod fanout;
+mod health;
 mod task;
 pub mod unit_test;
 
 pub use self::config::Config;
 pub use self::config::SinkContext;
+pub use self::health::HealthRegistry;
 
-use crate::topology::{builder::Pieces, task::Task};
+use crate::topology::{builder::Pieces, health::ComponentHealth, task::Task};
 
-use crate::buffers;
 use crate::shutdown::SourceShutdownCoordinator;
+use crate::{api, buffers, dead_letter};
 use futures::compat::Future01CompatExt;
 use futures01::{future, sync::mpsc, Future, Stream};
 use indexmap::IndexMap;
 use std::collections::{HashMap, HashSet};
 use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio01::timer;
 use tracing_futures::Instrument;
@@ -34,12 +37,14 @@ type TaskHandle = tokio::task::JoinHandle<Result<(), ()>>;
 #[allow(dead_code)]
 pub struct RunningTopology {
     inputs: HashMap<String, buffers::BufferInputCloner>,
+    dead_letter_inputs: dead_letter::DeadLetterInputs,
     outputs: HashMap<String, fanout::ControlChannel>,
     source_tasks: HashMap<String, TaskHandle>,
     tasks: HashMap<String, TaskHandle>,
     shutdown_coordinator: SourceShutdownCoordinator,
     config: Config,
     abort_tx: mpsc::UnboundedSender<()>,
+    health: HealthRegistry,
 }
 
 pub async fn start(
@@ -64,12 +69,14 @@ pub async fn start_validated(
 
     let mut running_topology = RunningTopology {
         inputs: HashMap::new(),
+        dead_letter_inputs: Default::default(),
         outputs: HashMap::new(),
         config: Config::empty(),
         shutdown_coordinator: SourceShutdownCoordinator::default(),
         source_tasks: HashMap::new(),
         tasks: HashMap::new(),
         abort_tx,
+        health: HealthRegistry::default(),
     };
 
     if !running_topology
@@ -115,6 +122,54 @@ pub fn take_healthchecks(diff: &ConfigDiff, pieces: &mut Pieces) -> Vec<(String,
 }
 
 impl RunningTopology {
+    /// The health of the components, which keeps being updated while the
+    /// topology runs.
+    pub fn health(&self) -> HealthRegistry {
+        self.health.clone()
+    }
+
+    /// Describes every component of the currently running config. A
+    /// component is considered healthy while its healthcheck hasn't failed
+    /// and all of its tasks are still running.
+    pub fn components(&self) -> Vec<api::Component> {
+        let sources = self.config.sources.iter().map(|(name, source)| {
+            (
+                name,
+                api::ComponentKind::Source,
+                source.source_type(),
+                Vec::new(),
+            )
+        });
+        let transforms = self.config.transforms.iter().map(|(name, transform)| {
+            (
+                name,
+                api::ComponentKind::Transform,
+                transform.inner.transform_type(),
+                transform.inputs.clone(),
+            )
+        });
+        let sinks = self.config.sinks.iter().map(|(name, sink)| {
+            (
+                name,
+                api::ComponentKind::Sink,
+                sink.inner.sink_type(),
+                sink.inputs.clone(),
+            )
+        });
+
+        sources
+            .chain(transforms)
+            .chain(sinks)
+            .map(|(name, kind, component_type, inputs)| api::Component {
+                name: name.clone(),
+                kind,
+                component_type: component_type.to_string(),
+                inputs,
+                healthy: self.health.is_healthy(name),
+            })
+            .collect()
+    }
+
     /// Returned future will finish once all current sources have finished.
     pub fn sources_finished(&self) -> impl Future<Item = (), Error = ()> {
         self.shutdown_coordinator.shutdown_tripwire()
@@ -129,6 +184,10 @@ impl RunningTopology {
     /// returned future is dropped then everything from this RunningTopology instance is fully
     /// dropped.
     pub fn stop(self) -> impl Future<Item = (), Error = ()> {
+        // Dead letter pumps outlive the topology until their sinks finish, so
+        // they must not keep the inputs of the other components open.
+        self.dead_letter_inputs.lock().unwrap().clear();
+
         // Create handy handles collections of all tasks for the subsequent operations.
         let mut wait_handles = Vec::new();
         // We need a Vec here since source compnents have two tasks. One for pump in self.tasks,
@@ -288,13 +347,28 @@ impl RunningTopology {
         pieces: &mut Pieces,
         require_healthy: bool,
     ) -> bool {
+        // New and changed components start out with a fresh health, which
+        // their healthchecks and tasks report to from here on.
+        for name in diff
+            .sources
+            .changed_and_added()
+            .chain(diff.transforms.changed_and_added())
+            .chain(diff.sinks.changed_and_added())
+        {
+            self.health.register(name);
+        }
+
         let healthchecks = take_healthchecks(diff, pieces)
             .into_iter()
-            .map(|(_, task)| task);
-        let healthchecks = futures01::future::join_all(healthchecks).map(|_| ());
+            .map(|(name, task)| {
+                let health = self.health.get(&name);
+                task.map_err(move |()| health.healthcheck_failed())
+            })
+            .collect::<Vec<_>>();
 
         info!("Running healthchecks.");
         if require_healthy {
+            let healthchecks = futures01::future::join_all(healthchecks).map(|_| ());
             let success = tokio::spawn(healthchecks.compat())
                 .await
                 .expect("Task panicked or runtime shutdown unexpectedly");
@@ -307,7 +381,11 @@ impl RunningTopology {
                 false
             }
         } else {
-            tokio::spawn(healthchecks.compat());
+            // Spawned one by one, so that a failing healthcheck doesn't
+            // cancel the others before they report their results.
+            for healthcheck in healthchecks {
+                tokio::spawn(healthcheck.compat());
+            }
             true
         }
     }
@@ -335,6 +413,7 @@ impl RunningTopology {
 
             let previous = self.tasks.remove(name).unwrap();
             drop(previous); // detach and forget
+            self.health.remove(name);
 
             self.remove_outputs(name);
             source_shutdown_complete_futures
@@ -379,6 +458,7 @@ impl RunningTopology {
 
             let previous = self.tasks.remove(name).unwrap();
             drop(previous); // detach and forget
+            self.health.remove(name);
 
             self.remove_inputs(&name);
             self.remove_outputs(&name);
@@ -390,6 +470,7 @@ impl RunningTopology {
 
             let previous = self.tasks.remove(name).unwrap();
             drop(previous); // detach and forget
+            self.health.remove(name);
 
             self.remove_inputs(&name);
         }
@@ -407,6 +488,9 @@ impl RunningTopology {
         // it as an input
         for name in diff.transforms.changed_and_added() {
             self.setup_outputs(&name, new_pieces);
+            for output in new_pieces.named_outputs.remove(&name).unwrap_or_default() {
+                self.setup_outputs(&output, new_pieces);
+            }
         }
 
         for name in &diff.transforms.to_change {
@@ -466,17 +550,26 @@ impl RunningTopology {
     fn spawn_sink(&mut self, name: &str, new_pieces: &mut builder::Pieces) {
         let task = new_pieces.tasks.remove(name).unwrap();
         let span = info_span!("sink", name = %task.name(), r#type = %task.typetag());
-        let task = handle_errors(task, self.abort_tx.clone()).instrument(span);
+        let task = handle_errors(task, self.abort_tx.clone());
+        let task = report_finished(task, self.health.get(name)).instrument(span);
         let spawned = tokio::spawn(task.compat());
         if let Some(previous) = self.tasks.insert(name.to_string(), spawned) {
             drop(previous); // detach and forget
         }
+
+        // The pump finishes on its own once the sink drops its dead letter handles.
+        if let Some((config, rx)) = new_pieces.dead_letters.remove(name) {
+            let pump = dead_letter::pump(name, &config, rx, self.dead_letter_inputs.clone())
+                .instrument(info_span!("dead_letter", sink = %name));
+            tokio::spawn(pump.compat());
+        }
     }
 
     fn spawn_transform(&mut self, name: &str, new_pieces: &mut builder::Pieces) {
         let task = new_pieces.tasks.remove(name).unwrap();
         let span = info_span!("transform", name = %task.name(), r#type = %task.typetag());
-        let task = handle_errors(task, self.abort_tx.clone()).instrument(span);
+        let task = handle_errors(task, self.abort_tx.clone());
+        let task = report_finished(task, self.health.get(name)).instrument(span);
         let spawned = tokio::spawn(task.compat());
         if let Some(previous) = self.tasks.insert(name.to_string(), spawned) {
             drop(previous); // detach and forget
@@ -486,7 +579,9 @@ impl RunningTopology {
     fn spawn_source(&mut self, name: &str, new_pieces: &mut builder::Pieces) {
         let task = new_pieces.tasks.remove(name).unwrap();
         let span = info_span!("source", name = %task.name(), r#type = %task.typetag());
-        let task = handle_errors(task, self.abort_tx.clone()).instrument(span.clone());
+        let health = self.health.get(name);
+        let task = handle_errors(task, self.abort_tx.clone());
+        let task = report_finished(task, health.clone()).instrument(span.clone());
         let spawned = tokio::spawn(task.compat());
         if let Some(previous) = self.tasks.insert(name.to_string(), spawned) {
             drop(previous); // detach and forget
@@ -496,17 +591,25 @@ impl RunningTopology {
             .takeover_source(name, &mut new_pieces.shutdown_coordinator);
 
         let source_task = new_pieces.source_tasks.remove(name).unwrap();
-        let source_task = handle_errors(source_task, self.abort_tx.clone()).instrument(span);
+        let source_task = handle_errors(source_task, self.abort_tx.clone());
+        let source_task = report_finished(source_task, health).instrument(span);
         self.source_tasks
             .insert(name.to_string(), tokio::spawn(source_task.compat()));
     }
 
     fn remove_outputs(&mut self, name: &str) {
         self.outputs.remove(name);
+
+        if let Some(transform) = self.config.transforms.get(name) {
+            for output in transform.inner.named_outputs() {
+                self.outputs.remove(&format!("{}.{}", name, output));
+            }
+        }
     }
 
     fn remove_inputs(&mut self, name: &str) {
         self.inputs.remove(name);
+        self.dead_letter_inputs.lock().unwrap().remove(name);
 
         let sink_inputs = self.config.sinks.get(name).map(|s| &s.inputs);
         let trans_inputs = self.config.transforms.get(name).map(|t| &t.inputs);
@@ -562,6 +665,10 @@ impl RunningTopology {
                 .unbounded_send(fanout::ControlMessage::Add(name.to_string(), tx.get()));
         }
 
+        self.dead_letter_inputs
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), tx.clone());
         self.inputs.insert(name.to_string(), tx);
     }
 
@@ -601,6 +708,10 @@ impl RunningTopology {
                 .unbounded_send(fanout::ControlMessage::Replace(name.to_string(), tx.get()));
         }
 
+        self.dead_letter_inputs
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), tx.clone());
         self.inputs.insert(name.to_string(), tx);
     }
 }
@@ -699,6 +810,18 @@ fn handle_errors(
         })
 }
 
+/// Marks the component as finished once the task completes, whether it
+/// succeeded or not.
+fn report_finished(
+    task: impl Future<Item = (), Error = ()>,
+    health: Arc<ComponentHealth>,
+) -> impl Future<Item = (), Error = ()> {
+    task.then(move |result| {
+        health.finished();
+        result
+    })
+}
+
 /// If the closure returns false, then the element is removed
 fn retain<T>(vec: &mut Vec<T>, mut retain_filter: impl FnMut(&mut T) -> bool) {
     let mut i = 0;
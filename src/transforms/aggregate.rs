@@ -0,0 +1,540 @@
+use super::util::runtime_transform::{RuntimeTransform, Timer};
+use crate::{
+    event::metric::{Metric, MetricValue},
+    topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
+    Event,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    mem,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AggregateConfig {
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    pub group_by: Option<Vec<String>>,
+    #[serde(default)]
+    pub output: OutputKind,
+    #[serde(default = "default_expire_after_secs")]
+    pub expire_after_secs: u64,
+    #[serde(default)]
+    pub sketch_distributions: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputKind {
+    Absolute,
+    Incremental,
+}
+
+impl Default for OutputKind {
+    fn default() -> Self {
+        OutputKind::Incremental
+    }
+}
+
+fn default_interval_secs() -> u64 {
+    10
+}
+
+fn default_expire_after_secs() -> u64 {
+    300
+}
+
+inventory::submit! {
+    TransformDescription::new::<AggregateConfig>("aggregate")
+}
+
+impl Default for AggregateConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: default_interval_secs(),
+            group_by: None,
+            output: OutputKind::default(),
+            expire_after_secs: default_expire_after_secs(),
+            sketch_distributions: false,
+        }
+    }
+}
+
+#[typetag::serde(name = "aggregate")]
+impl TransformConfig for AggregateConfig {
+    fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn super::Transform>> {
+        if self.interval_secs == 0 {
+            return Err("`interval_secs` must be greater than zero".into());
+        }
+        Ok(Box::new(Aggregate::new(self)))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn transform_type(&self) -> &'static str {
+        "aggregate"
+    }
+}
+
+type SeriesKey = (String, Option<BTreeMap<String, String>>);
+
+struct Series {
+    metric: Metric,
+    updated: bool,
+    // Windows in a row during which the series wasn't updated.
+    idle_windows: u64,
+}
+
+/// Merges incremental metrics of the same series and emits them once per
+/// interval. Absolute metrics and summaries can't be merged and pass through.
+pub struct Aggregate {
+    interval_secs: u64,
+    group_by: Option<Vec<String>>,
+    output: OutputKind,
+    // Running totals of series that weren't updated for this many windows are
+    // dropped, so that series with short lived tags don't pile up.
+    expire_after_windows: u64,
+    // Distributions are merged into sketches, which keep a bounded size and
+    // can be merged again by downstream aggregators.
+    sketch_distributions: bool,
+    series: BTreeMap<SeriesKey, Series>,
+}
+
+impl Aggregate {
+    pub fn new(config: &AggregateConfig) -> Self {
+        let interval_secs = config.interval_secs.max(1);
+        Self {
+            interval_secs: config.interval_secs,
+            group_by: config.group_by.clone(),
+            output: config.output,
+            expire_after_windows: ((config.expire_after_secs + interval_secs - 1) / interval_secs)
+                .max(1),
+            sketch_distributions: config.sketch_distributions,
+            series: BTreeMap::new(),
+        }
+    }
+
+    fn flush<F>(&mut self, mut emit_fn: F)
+    where
+        F: FnMut(Event),
+    {
+        let timestamp = Some(Utc::now());
+        match self.output {
+            OutputKind::Incremental => {
+                for (_, series) in mem::take(&mut self.series) {
+                    emit_fn(Event::Metric(Metric {
+                        timestamp,
+                        ..series.metric
+                    }));
+                }
+            }
+            OutputKind::Absolute => {
+                // Running totals are kept for counters, gauges and histograms.
+                // Sets and distributions would grow without bound, so they
+                // are only ever emitted for the current window, and stay
+                // incremental as they don't hold every value seen so far.
+                // Sketches do the same, so that they can be merged again.
+                let mut finished = Vec::new();
+                for (key, series) in self.series.iter_mut() {
+                    if !series.updated {
+                        series.idle_windows += 1;
+                        if series.idle_windows >= self.expire_after_windows {
+                            finished.push(key.clone());
+                        }
+                        continue;
+                    }
+
+                    series.updated = false;
+                    series.idle_windows = 0;
+                    match series.metric.value {
+                        MetricValue::Set { .. }
+                        | MetricValue::Distribution { .. }
+                        | MetricValue::Sketch { .. } => {
+                            emit_fn(Event::Metric(Metric {
+                                timestamp,
+                                ..series.metric.clone()
+                            }));
+                            finished.push(key.clone());
+                        }
+                        _ => emit_fn(Event::Metric(Metric {
+                            timestamp,
+                            ..series.metric.to_absolute()
+                        })),
+                    }
+                }
+                for key in finished {
+                    self.series.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+impl RuntimeTransform for Aggregate {
+    fn hook_process<F>(&mut self, event: Event, mut emit_fn: F)
+    where
+        F: FnMut(Event),
+    {
+        let mut metric = event.into_metric();
+        if metric.kind.is_absolute() {
+            emit_fn(Event::Metric(metric));
+            return;
+        }
+        if let MetricValue::AggregatedSummary { .. } = metric.value {
+            emit_fn(Event::Metric(metric));
+            return;
+        }
+        if self.sketch_distributions {
+            if let Some(value) = metric.value.distribution_to_sketch() {
+                metric.value = value;
+            }
+        }
+
+        if let Some(group_by) = &self.group_by {
+            metric.tags = metric
+                .tags
+                .map(|tags| {
+                    tags.into_iter()
+                        .filter(|(key, _)| group_by.contains(key))
+                        .collect::<BTreeMap<_, _>>()
+                })
+                .filter(|tags| !tags.is_empty());
+        }
+
+        match self
+            .series
+            .entry((metric.name.clone(), metric.tags.clone()))
+        {
+            Entry::Occupied(mut entry) => {
+                let series = entry.get_mut();
+                if can_merge(&series.metric.value, &metric.value) {
+                    series.metric.add(&metric);
+                } else {
+                    // The series changed type, so start over with the new value.
+                    let previous = mem::replace(&mut series.metric, metric);
+                    emit_fn(Event::Metric(previous));
+                }
+                series.updated = true;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(Series {
+                    metric,
+                    updated: true,
+                    idle_windows: 0,
+                });
+            }
+        }
+    }
+
+    fn hook_shutdown<F>(&mut self, emit_fn: F)
+    where
+        F: FnMut(Event),
+    {
+        self.flush(emit_fn);
+    }
+
+    fn timer_handler<F>(&mut self, _timer: Timer, emit_fn: F)
+    where
+        F: FnMut(Event),
+    {
+        self.flush(emit_fn);
+    }
+
+    fn timers(&self) -> Vec<Timer> {
+        vec![Timer {
+            id: 0,
+            interval_seconds: self.interval_secs,
+        }]
+    }
+}
+
+fn can_merge(value: &MetricValue, other: &MetricValue) -> bool {
+    match (value, other) {
+        (
+            MetricValue::AggregatedHistogram { buckets, .. },
+            MetricValue::AggregatedHistogram {
+                buckets: buckets2, ..
+            },
+        ) => buckets == buckets2,
+        _ => mem::discriminant(value) == mem::discriminant(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{event::metric::MetricKind, test_util::runtime, transforms::Transform};
+    use futures01::{stream, Stream};
+
+    fn counter(name: &str, tags: &[(&str, &str)], value: f64) -> Event {
+        let tags = tags
+            .iter()
+            .map(|(key, value)| ((*key).to_owned(), (*value).to_owned()))
+            .collect::<BTreeMap<_, _>>();
+        Event::Metric(Metric {
+            name: name.into(),
+            timestamp: None,
+            tags: if tags.is_empty() { None } else { Some(tags) },
+            kind: MetricKind::Incremental,
+            value: MetricValue::Counter { value },
+        })
+    }
+
+    fn process(transform: &mut Aggregate, events: Vec<Event>) -> Vec<Metric> {
+        let mut output = Vec::new();
+        for event in events {
+            transform.hook_process(event, |event| output.push(event));
+        }
+        transform.flush(|event| output.push(event));
+        output.into_iter().map(Event::into_metric).collect()
+    }
+
+    fn values(metrics: &[Metric]) -> Vec<(&str, MetricKind, &MetricValue)> {
+        metrics
+            .iter()
+            .map(|metric| (metric.name.as_str(), metric.kind.clone(), &metric.value))
+            .collect()
+    }
+
+    #[test]
+    fn merges_series_per_window() {
+        let mut transform = Aggregate::new(&AggregateConfig::default());
+
+        let metrics = process(
+            &mut transform,
+            vec![
+                counter("hits", &[("code", "200")], 1.0),
+                counter("hits", &[("code", "500")], 1.0),
+                counter("hits", &[("code", "200")], 2.0),
+            ],
+        );
+        assert_eq!(metrics.len(), 2);
+        assert_eq!(
+            metrics[0].tags,
+            Some(vec![("code".into(), "200".into())].into_iter().collect())
+        );
+        assert_eq!(metrics[0].value, MetricValue::Counter { value: 3.0 });
+        assert_eq!(metrics[1].value, MetricValue::Counter { value: 1.0 });
+        assert!(metrics.iter().all(|metric| metric.timestamp.is_some()));
+
+        // The next window starts from scratch.
+        let metrics = process(
+            &mut transform,
+            vec![counter("hits", &[("code", "200")], 4.0)],
+        );
+        assert_eq!(
+            values(&metrics),
+            vec![(
+                "hits",
+                MetricKind::Incremental,
+                &MetricValue::Counter { value: 4.0 }
+            )]
+        );
+    }
+
+    #[test]
+    fn groups_by_tags() {
+        let mut transform = Aggregate::new(&AggregateConfig {
+            group_by: Some(vec!["region".into()]),
+            ..Default::default()
+        });
+
+        let metrics = process(
+            &mut transform,
+            vec![
+                counter("hits", &[("region", "eu"), ("host", "a")], 1.0),
+                counter("hits", &[("region", "eu"), ("host", "b")], 2.0),
+                counter("hits", &[("host", "c")], 5.0),
+            ],
+        );
+        assert_eq!(metrics.len(), 2);
+        assert_eq!(metrics[0].tags, None);
+        assert_eq!(metrics[0].value, MetricValue::Counter { value: 5.0 });
+        assert_eq!(
+            metrics[1].tags,
+            Some(vec![("region".into(), "eu".into())].into_iter().collect())
+        );
+        assert_eq!(metrics[1].value, MetricValue::Counter { value: 3.0 });
+    }
+
+    #[test]
+    fn outputs_running_totals() {
+        let mut transform = Aggregate::new(&AggregateConfig {
+            output: OutputKind::Absolute,
+            ..Default::default()
+        });
+        let set = Event::Metric(Metric {
+            name: "users".into(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Set {
+                values: vec!["alice".into()].into_iter().collect(),
+            },
+        });
+
+        let metrics = process(
+            &mut transform,
+            vec![counter("hits", &[], 1.0), counter("hits", &[], 2.0), set],
+        );
+        assert_eq!(
+            values(&metrics),
+            vec![
+                (
+                    "hits",
+                    MetricKind::Absolute,
+                    &MetricValue::Counter { value: 3.0 }
+                ),
+                (
+                    "users",
+                    MetricKind::Incremental,
+                    &MetricValue::Set {
+                        values: vec!["alice".into()].into_iter().collect()
+                    }
+                ),
+            ]
+        );
+
+        // Idle series are not repeated, and totals carry over between windows.
+        assert!(process(&mut transform, vec![]).is_empty());
+        let metrics = process(&mut transform, vec![counter("hits", &[], 4.0)]);
+        assert_eq!(
+            values(&metrics),
+            vec![(
+                "hits",
+                MetricKind::Absolute,
+                &MetricValue::Counter { value: 7.0 }
+            )]
+        );
+    }
+
+    #[test]
+    fn expires_idle_series() {
+        let mut transform = Aggregate::new(&AggregateConfig {
+            output: OutputKind::Absolute,
+            interval_secs: 10,
+            expire_after_secs: 25,
+            ..Default::default()
+        });
+
+        process(
+            &mut transform,
+            vec![counter("hits", &[("container", "a")], 1.0)],
+        );
+        let metrics = process(
+            &mut transform,
+            vec![counter("hits", &[("container", "b")], 1.0)],
+        );
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(transform.series.len(), 2);
+
+        // Series are dropped after three idle windows, rounding up.
+        process(&mut transform, vec![]);
+        assert_eq!(transform.series.len(), 2);
+        process(&mut transform, vec![]);
+        assert_eq!(transform.series.len(), 1);
+        process(&mut transform, vec![]);
+        assert!(transform.series.is_empty());
+
+        // A returning series starts over from its new value.
+        let metrics = process(
+            &mut transform,
+            vec![counter("hits", &[("container", "a")], 2.0)],
+        );
+        assert_eq!(
+            values(&metrics),
+            vec![(
+                "hits",
+                MetricKind::Absolute,
+                &MetricValue::Counter { value: 2.0 }
+            )]
+        );
+    }
+
+    #[test]
+    fn merges_distributions_into_sketches() {
+        let mut transform = Aggregate::new(&AggregateConfig {
+            output: OutputKind::Absolute,
+            sketch_distributions: true,
+            ..Default::default()
+        });
+        let distribution = |values: Vec<f64>| {
+            Event::Metric(Metric {
+                name: "latency".into(),
+                timestamp: None,
+                tags: None,
+                kind: MetricKind::Incremental,
+                value: MetricValue::Distribution {
+                    sample_rates: vec![1; values.len()],
+                    values,
+                },
+            })
+        };
+
+        let metrics = process(
+            &mut transform,
+            vec![distribution(vec![1.0, 2.0]), distribution(vec![3.0])],
+        );
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].kind, MetricKind::Incremental);
+        match &metrics[0].value {
+            MetricValue::Sketch { sketch } => {
+                assert_eq!(sketch.count(), 3);
+                assert_eq!(sketch.sum(), 6.0);
+            }
+            other => panic!("unexpected value: {:?}", other),
+        }
+
+        // Sketches only hold the samples of their own window.
+        let metrics = process(&mut transform, vec![distribution(vec![4.0])]);
+        match &metrics[0].value {
+            MetricValue::Sketch { sketch } => assert_eq!(sketch.count(), 1),
+            other => panic!("unexpected value: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn passes_through_absolute_metrics() {
+        let mut transform = Aggregate::new(&AggregateConfig::default());
+        let gauge = Metric {
+            name: "temperature".into(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Absolute,
+            value: MetricValue::Gauge { value: 21.5 },
+        };
+
+        let mut output = Vec::new();
+        transform.hook_process(Event::Metric(gauge.clone()), |event| output.push(event));
+        assert_eq!(output, vec![Event::Metric(gauge)]);
+    }
+
+    #[test]
+    fn flushes_on_shutdown() {
+        let transform = Aggregate::new(&AggregateConfig {
+            interval_secs: 3600,
+            ..Default::default()
+        });
+        let events = vec![counter("hits", &[], 1.0), counter("hits", &[], 2.0)];
+
+        let stream =
+            Transform::transform_stream(Box::new(transform), Box::new(stream::iter_ok(events)));
+        let metrics = runtime()
+            .block_on(stream.collect())
+            .unwrap()
+            .into_iter()
+            .map(Event::into_metric)
+            .collect::<Vec<_>>();
+
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].value, MetricValue::Counter { value: 3.0 });
+    }
+}
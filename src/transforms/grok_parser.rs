@@ -1,4 +1,4 @@
-use super::Transform;
+use super::{Transform, TransformOutputs, ERRORS_OUTPUT};
 use crate::{
     event::{self, Event, PathComponent, PathIter},
     topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
@@ -25,6 +25,7 @@ pub struct GrokParserConfig {
     pub field: Option<Atom>,
     #[derivative(Default(value = "true"))]
     pub drop_field: bool,
+    pub drop_failed: bool,
     pub types: HashMap<String, String>,
 }
 
@@ -51,6 +52,7 @@ impl TransformConfig for GrokParserConfig {
                     pattern: p,
                     field: field.clone(),
                     drop_field: self.drop_field,
+                    drop_failed: self.drop_failed,
                     types,
                     paths: HashMap::new(),
                 })
@@ -69,19 +71,25 @@ impl TransformConfig for GrokParserConfig {
     fn transform_type(&self) -> &'static str {
         "grok_parser"
     }
+
+    fn named_outputs(&self) -> Vec<&'static str> {
+        vec![ERRORS_OUTPUT]
+    }
 }
 
 pub struct GrokParser {
     pattern: Pattern,
     field: Atom,
     drop_field: bool,
+    drop_failed: bool,
     types: HashMap<String, Conversion>,
     paths: HashMap<String, Vec<PathComponent>>,
 }
 
-impl Transform for GrokParser {
-    fn transform(&mut self, event: Event) -> Option<Event> {
-        let mut event = event.into_log();
+impl GrokParser {
+    /// Parses the event in place, returning whether the pattern matched.
+    fn parse(&mut self, event: &mut Event) -> bool {
+        let event = event.as_mut_log();
         let value = event.get(&self.field).map(|s| s.to_string_lossy());
 
         if let Some(value) = value {
@@ -113,6 +121,7 @@ impl Transform for GrokParser {
                 if drop_field {
                     event.remove(&self.field);
                 }
+                return true;
             } else {
                 debug!(message = "No fields captured from grok pattern.");
             }
@@ -124,7 +133,25 @@ impl Transform for GrokParser {
             );
         }
 
-        Some(Event::Log(event))
+        false
+    }
+}
+
+impl Transform for GrokParser {
+    fn transform(&mut self, mut event: Event) -> Option<Event> {
+        if !self.parse(&mut event) && self.drop_failed {
+            return None;
+        }
+
+        Some(event)
+    }
+
+    fn transform_outputs(&mut self, outputs: &mut TransformOutputs, mut event: Event) {
+        if self.parse(&mut event) {
+            outputs.push(event);
+        } else {
+            outputs.push_error(event, self.drop_failed);
+        }
     }
 }
 
@@ -135,6 +162,7 @@ mod tests {
     use crate::{
         event,
         topology::config::{TransformConfig, TransformContext},
+        transforms::TransformOutputs,
         Event,
     };
     use pretty_assertions::assert_eq;
@@ -152,6 +180,7 @@ mod tests {
             pattern: pattern.into(),
             field: field.map(|s| s.into()),
             drop_field,
+            drop_failed: false,
             types: types.iter().map(|&(k, v)| (k.into(), v.into())).collect(),
         }
         .build(TransformContext::new_test())
@@ -295,4 +324,26 @@ mod tests {
 
         assert_eq!(expected, serde_json::to_value(&event.all_fields()).unwrap());
     }
+
+    #[test]
+    fn grok_parser_sends_failed_match_to_errors_output() {
+        let mut parser = GrokParserConfig {
+            pattern: "%{NUMBER:status}".into(),
+            drop_failed: true,
+            ..Default::default()
+        }
+        .build(TransformContext::new_test())
+        .unwrap();
+
+        let mut outputs = TransformOutputs::default();
+        parser.transform_outputs(&mut outputs, Event::from("Help I'm stuck!"));
+
+        let outputs = outputs.into_events().collect::<Vec<_>>();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].0, Some("errors"));
+        assert_eq!(
+            outputs[0].1.as_log()[&event::log_schema().message_key()],
+            "Help I'm stuck!".into()
+        );
+    }
 }
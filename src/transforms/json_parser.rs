@@ -1,4 +1,4 @@
-use super::Transform;
+use super::{Transform, TransformOutputs, ERRORS_OUTPUT};
 use crate::{
     event::{self, Event},
     internal_events::{JsonEventProcessed, JsonFailedParse},
@@ -41,6 +41,10 @@ impl TransformConfig for JsonParserConfig {
     fn transform_type(&self) -> &'static str {
         "json_parser"
     }
+
+    fn named_outputs(&self) -> Vec<&'static str> {
+        vec![ERRORS_OUTPUT]
+    }
 }
 
 #[derive(Debug)]
@@ -70,8 +74,9 @@ impl From<JsonParserConfig> for JsonParser {
     }
 }
 
-impl Transform for JsonParser {
-    fn transform(&mut self, mut event: Event) -> Option<Event> {
+impl JsonParser {
+    /// Parses the event in place, returning whether parsing succeeded.
+    fn parse(&mut self, event: &mut Event) -> bool {
         let log = event.as_mut_log();
         let to_parse = log.get(&self.field).map(|s| s.as_bytes());
 
@@ -121,19 +126,36 @@ impl Transform for JsonParser {
                     }
                 }
             }
-        } else if self.drop_invalid {
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Transform for JsonParser {
+    fn transform(&mut self, mut event: Event) -> Option<Event> {
+        if !self.parse(&mut event) && self.drop_invalid {
             return None;
         }
 
         Some(event)
     }
+
+    fn transform_outputs(&mut self, outputs: &mut TransformOutputs, mut event: Event) {
+        if self.parse(&mut event) {
+            outputs.push(event);
+        } else {
+            outputs.push_error(event, self.drop_invalid);
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::{JsonParser, JsonParserConfig};
     use crate::event::{self, Event};
-    use crate::transforms::Transform;
+    use crate::transforms::{Transform, TransformOutputs};
     use serde_json::json;
     use string_cache::DefaultAtom as Atom;
 
@@ -532,4 +554,29 @@ mod test {
         assert_eq!(event[&Atom::from("message.greeting")], "hello".into());
         assert_eq!(event[&Atom::from("message.name")], "bob".into());
     }
+
+    #[test]
+    fn json_parser_sends_invalid_to_errors_output() {
+        let mut parser = JsonParser::from(JsonParserConfig {
+            drop_invalid: true,
+            ..Default::default()
+        });
+
+        let mut outputs = TransformOutputs::default();
+        parser.transform_outputs(&mut outputs, Event::from(r#"{"greeting": "hello"}"#));
+        parser.transform_outputs(&mut outputs, Event::from(r#"{"greeting": "hello","#));
+
+        let outputs = outputs.into_events().collect::<Vec<_>>();
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].0, None);
+        assert_eq!(
+            outputs[0].1.as_log()[&Atom::from("greeting")],
+            "hello".into()
+        );
+        assert_eq!(outputs[1].0, Some("errors"));
+        assert_eq!(
+            outputs[1].1.as_log()[&event::log_schema().message_key()],
+            r#"{"greeting": "hello","#.into()
+        );
+    }
 }
@@ -1,5 +1,8 @@
 use super::util::{table_to_set, table_to_timestamp, timestamp_to_table};
-use crate::event::metric::{Metric, MetricKind, MetricValue};
+use crate::event::{
+    metric::{Metric, MetricKind, MetricValue},
+    proto,
+};
 use rlua::prelude::*;
 use std::collections::BTreeMap;
 
@@ -93,6 +96,28 @@ impl<'a> ToLua<'a> for Metric {
                 aggregated_summary.set("sum", sum)?;
                 tbl.set("aggregated_summary", aggregated_summary)?;
             }
+            MetricValue::Sketch { sketch } => {
+                // Sketches are passed through as their bins, so they can be
+                // merged again after the script emits them.
+                let proto::Sketch {
+                    positive,
+                    negative,
+                    zero_count,
+                    count,
+                    sum,
+                    min,
+                    max,
+                } = sketch.into();
+                let sketch = ctx.create_table()?;
+                sketch.set("positive", positive)?;
+                sketch.set("negative", negative)?;
+                sketch.set("zero_count", zero_count)?;
+                sketch.set("count", count)?;
+                sketch.set("sum", sum)?;
+                sketch.set("min", min)?;
+                sketch.set("max", max)?;
+                tbl.set("sketch", sketch)?;
+            }
         }
 
         Ok(LuaValue::Table(tbl))
@@ -159,11 +184,28 @@ impl<'a> FromLua<'a> for Metric {
                 count: aggregated_summary.get("count")?,
                 sum: aggregated_summary.get("sum")?,
             }
+        } else if let Some(sketch) = table.get::<_, Option<LuaTable>>("sketch")? {
+            let sketch = proto::Sketch {
+                positive: sketch
+                    .get::<_, Option<BTreeMap<i32, u32>>>("positive")?
+                    .unwrap_or_default(),
+                negative: sketch
+                    .get::<_, Option<BTreeMap<i32, u32>>>("negative")?
+                    .unwrap_or_default(),
+                zero_count: sketch.get::<_, Option<u32>>("zero_count")?.unwrap_or(0),
+                count: sketch.get("count")?,
+                sum: sketch.get("sum")?,
+                min: sketch.get("min")?,
+                max: sketch.get("max")?,
+            };
+            MetricValue::Sketch {
+                sketch: sketch.into(),
+            }
         } else {
             return Err(LuaError::FromLuaConversionError {
                 from: value.type_name(),
                 to: "Metric",
-                message: Some("Cannot find metric value, expected presence one of \"counter\", \"gauge\", \"set\", \"distribution\", \"aggregated_histogram\", \"aggregated_summary\"".to_string()),
+                message: Some("Cannot find metric value, expected presence one of \"counter\", \"gauge\", \"set\", \"distribution\", \"aggregated_histogram\", \"aggregated_summary\", \"sketch\"".to_string()),
             });
         };
 
@@ -180,6 +222,7 @@ impl<'a> FromLua<'a> for Metric {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::event::sketch::DDSketch;
     use chrono::{offset::TimeZone, Utc};
 
     fn assert_metric(metric: Metric, assertions: Vec<&'static str>) {
@@ -358,6 +401,37 @@ mod test {
         assert_metric(metric, assertions)
     }
 
+    #[test]
+    fn to_lua_sketch() {
+        let mut sketch = DDSketch::new();
+        sketch.insert_n(-1.0, 2);
+        sketch.insert_n(0.0, 1);
+        sketch.insert_n(8.0, 3);
+        let metric = Metric {
+            name: "example sketch".into(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Sketch { sketch },
+        };
+        let assertions = vec![
+            "type(metric.sketch) == 'table'",
+            "metric.sketch.count == 6",
+            "metric.sketch.zero_count == 1",
+            "metric.sketch.sum == 22",
+            "metric.sketch.min == -1",
+            "metric.sketch.max == 8",
+            "type(metric.sketch.positive) == 'table'",
+            "type(metric.sketch.negative) == 'table'",
+        ];
+        assert_metric(metric.clone(), assertions);
+
+        Lua::new().context(|ctx| {
+            ctx.globals().set("metric", metric.clone()).unwrap();
+            assert_eq!(ctx.globals().get::<_, Metric>("metric").unwrap(), metric);
+        });
+    }
+
     #[test]
     fn from_lua_counter_minimal() {
         let value = r#"{
@@ -0,0 +1,226 @@
+use super::Transform;
+use crate::{
+    template::Template,
+    topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
+    Event,
+};
+use indexmap::IndexMap;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MapTagsConfig {
+    #[serde(default)]
+    pub derive: Vec<DeriveConfig>,
+    #[serde(default)]
+    pub rename: IndexMap<String, String>,
+    pub name: Option<Template>,
+}
+
+/// Matches `pattern` against the value of `tag`, adding a tag for each named
+/// capture group that matched.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DeriveConfig {
+    pub tag: String,
+    pub pattern: String,
+}
+
+inventory::submit! {
+    TransformDescription::new_without_default::<MapTagsConfig>("map_tags")
+}
+
+#[typetag::serde(name = "map_tags")]
+impl TransformConfig for MapTagsConfig {
+    fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
+        let derive = self
+            .derive
+            .iter()
+            .map(|derive| {
+                Regex::new(&derive.pattern)
+                    .map(|pattern| (derive.tag.clone(), pattern))
+                    .context(super::InvalidRegex)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Box::new(MapTags {
+            derive,
+            rename: self.rename.clone(),
+            name: self.name.clone(),
+        }))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn transform_type(&self) -> &'static str {
+        "map_tags"
+    }
+}
+
+/// Derives tags, then renames them, then renders the metric name.
+pub struct MapTags {
+    derive: Vec<(String, Regex)>,
+    rename: IndexMap<String, String>,
+    name: Option<Template>,
+}
+
+impl Transform for MapTags {
+    fn transform(&mut self, mut event: Event) -> Option<Event> {
+        let metric = event.as_mut_metric();
+        let mut tags = metric.tags.take().unwrap_or_default();
+
+        for (tag, pattern) in &self.derive {
+            let captures = match tags.get(tag).and_then(|value| pattern.captures(value)) {
+                Some(captures) => captures,
+                None => continue,
+            };
+            let derived = pattern
+                .capture_names()
+                .flatten()
+                .filter_map(|name| {
+                    let value = captures.name(name)?.as_str().to_owned();
+                    Some((name.to_owned(), value))
+                })
+                .collect::<Vec<_>>();
+            tags.extend(derived);
+        }
+
+        for (from, to) in &self.rename {
+            if let Some(value) = tags.remove(from) {
+                tags.insert(to.clone(), value);
+            }
+        }
+
+        metric.tags = if tags.is_empty() { None } else { Some(tags) };
+
+        if let Some(template) = &self.name {
+            match template.render_string(&event) {
+                Ok(name) => event.as_mut_metric().name = name,
+                Err(missing_keys) => {
+                    warn!(
+                        message = "Failed to render metric name; keeping the original.",
+                        ?missing_keys,
+                        rate_limit_secs = 30,
+                    );
+                }
+            }
+        }
+
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::metric::{Metric, MetricKind, MetricValue};
+    use std::collections::BTreeMap;
+
+    fn parse_config(s: &str) -> MapTagsConfig {
+        toml::from_str(s).unwrap()
+    }
+
+    fn transform_metric(config: &str, tags: &[(&str, &str)]) -> Metric {
+        let event = Event::Metric(Metric {
+            name: "requests".into(),
+            timestamp: None,
+            tags: Some(
+                tags.iter()
+                    .map(|(key, value)| ((*key).to_owned(), (*value).to_owned()))
+                    .collect(),
+            ),
+            kind: MetricKind::Incremental,
+            value: MetricValue::Counter { value: 1.0 },
+        });
+
+        let mut transform = parse_config(config)
+            .build(TransformContext::new_test())
+            .unwrap();
+        transform.transform(event).unwrap().into_metric()
+    }
+
+    fn tags(tags: &[(&str, &str)]) -> Option<BTreeMap<String, String>> {
+        Some(
+            tags.iter()
+                .map(|(key, value)| ((*key).to_owned(), (*value).to_owned()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn renames_tags() {
+        let metric = transform_metric(
+            r#"
+            rename.hostname = "host"
+            rename.missing = "other"
+            "#,
+            &[("hostname", "web-1"), ("code", "200")],
+        );
+
+        assert_eq!(metric.tags, tags(&[("code", "200"), ("host", "web-1")]));
+    }
+
+    #[test]
+    fn derives_tags_from_captures() {
+        let metric = transform_metric(
+            r#"
+            [[derive]]
+            tag = "path"
+            pattern = '^/api/(?P<version>v\d+)/(?P<resource>\w+)'
+
+            [[derive]]
+            tag = "code"
+            pattern = '^(?P<class>\d)'
+            "#,
+            &[("path", "/api/v2/users/42"), ("code", "404")],
+        );
+
+        assert_eq!(
+            metric.tags,
+            tags(&[
+                ("class", "4"),
+                ("code", "404"),
+                ("path", "/api/v2/users/42"),
+                ("resource", "users"),
+                ("version", "v2"),
+            ])
+        );
+    }
+
+    #[test]
+    fn renames_metrics() {
+        let metric = transform_metric(
+            r#"
+            rename.app = "service"
+            name = "{{ tags.service }}.{{ name }}"
+            "#,
+            &[("app", "billing")],
+        );
+        assert_eq!(metric.name, "billing.requests");
+        assert_eq!(metric.tags, tags(&[("service", "billing")]));
+
+        let metric = transform_metric(r#"name = "{{ tags.missing }}.{{ name }}""#, &[]);
+        assert_eq!(metric.name, "requests");
+        assert_eq!(metric.tags, None);
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        let config = parse_config(
+            r#"
+            [[derive]]
+            tag = "path"
+            pattern = "(unclosed"
+            "#,
+        );
+        assert!(config.build(TransformContext::new_test()).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+use super::Transform;
+use crate::{
+    event::{self, Value},
+    topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
+    Event,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct MetricToLogConfig {
+    pub host_tag: Option<String>,
+}
+
+inventory::submit! {
+    TransformDescription::new::<MetricToLogConfig>("metric_to_log")
+}
+
+#[typetag::serde(name = "metric_to_log")]
+impl TransformConfig for MetricToLogConfig {
+    fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
+        Ok(Box::new(MetricToLog::new(self.host_tag.clone())))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn transform_type(&self) -> &'static str {
+        "metric_to_log"
+    }
+}
+
+pub struct MetricToLog {
+    host_tag: Option<String>,
+}
+
+impl MetricToLog {
+    pub fn new(host_tag: Option<String>) -> Self {
+        Self { host_tag }
+    }
+}
+
+impl Transform for MetricToLog {
+    fn transform(&mut self, event: Event) -> Option<Event> {
+        let metric = event.into_metric();
+        let timestamp = metric.timestamp.unwrap_or_else(Utc::now);
+        let host = self
+            .host_tag
+            .as_ref()
+            .and_then(|tag| metric.tags.as_ref()?.get(tag).cloned());
+
+        let fields = match serde_json::to_value(&metric) {
+            Ok(JsonValue::Object(fields)) => fields,
+            Ok(_) => unreachable!("metrics serialize to a map"),
+            Err(error) => {
+                warn!(message = "Failed to serialize metric.", %error, rate_limit_secs = 30);
+                return None;
+            }
+        };
+
+        let mut log = Event::new_empty_log();
+        let log_mut = log.as_mut_log();
+        for (key, value) in fields {
+            // The timestamp is inserted as a timestamp below, rather than the
+            // string it serializes to.
+            if key != "timestamp" && !value.is_null() {
+                log_mut.insert_flat(key, Value::from(value));
+            }
+        }
+        log_mut.insert(event::log_schema().timestamp_key(), timestamp);
+        if let Some(host) = host {
+            log_mut.insert(event::log_schema().host_key(), host);
+        }
+
+        Some(log)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::metric::{Metric, MetricKind, MetricValue};
+    use chrono::{offset::TimeZone, DateTime};
+    use std::collections::BTreeMap;
+
+    fn ts() -> DateTime<Utc> {
+        Utc.ymd(2018, 11, 14).and_hms_nano(8, 9, 10, 11)
+    }
+
+    fn fields(event: Event) -> BTreeMap<String, Value> {
+        event.into_log().into_iter().collect()
+    }
+
+    #[test]
+    fn transform_counter() {
+        let metric = Metric {
+            name: "hits".into(),
+            timestamp: Some(ts()),
+            tags: Some(
+                vec![
+                    ("code".to_owned(), "200".to_owned()),
+                    ("host".to_owned(), "web-1".to_owned()),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            kind: MetricKind::Incremental,
+            value: MetricValue::Counter { value: 2.0 },
+        };
+
+        let mut transform = MetricToLog::new(Some("host".into()));
+        let log = fields(transform.transform(Event::Metric(metric)).unwrap());
+
+        let mut tags = BTreeMap::new();
+        tags.insert("code".to_owned(), Value::from("200"));
+        tags.insert("host".to_owned(), Value::from("web-1"));
+        let mut counter = BTreeMap::new();
+        counter.insert("value".to_owned(), Value::from(2.0));
+
+        let expected = vec![
+            ("counter".to_owned(), Value::from(counter)),
+            ("host".to_owned(), Value::from("web-1")),
+            ("kind".to_owned(), Value::from("incremental")),
+            ("name".to_owned(), Value::from("hits")),
+            ("tags".to_owned(), Value::from(tags)),
+            ("timestamp".to_owned(), Value::from(ts())),
+        ]
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+        assert_eq!(log, expected);
+    }
+
+    #[test]
+    fn transform_distribution_without_tags() {
+        let metric = Metric {
+            name: "latency".into(),
+            timestamp: Some(ts()),
+            tags: None,
+            kind: MetricKind::Absolute,
+            value: MetricValue::Distribution {
+                values: vec![1.5, 2.0],
+                sample_rates: vec![1, 10],
+            },
+        };
+
+        let mut transform = MetricToLog::new(Some("host".into()));
+        let log = fields(transform.transform(Event::Metric(metric)).unwrap());
+
+        let mut distribution = BTreeMap::new();
+        distribution.insert(
+            "values".to_owned(),
+            Value::from(vec![Value::from(1.5), Value::from(2.0)]),
+        );
+        distribution.insert(
+            "sample_rates".to_owned(),
+            Value::from(vec![Value::from(1), Value::from(10)]),
+        );
+
+        let expected = vec![
+            ("distribution".to_owned(), Value::from(distribution)),
+            ("kind".to_owned(), Value::from("absolute")),
+            ("name".to_owned(), Value::from("latency")),
+            ("timestamp".to_owned(), Value::from(ts())),
+        ]
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+        assert_eq!(log, expected);
+    }
+}
@@ -7,6 +7,8 @@ mod util;
 pub mod add_fields;
 #[cfg(feature = "transforms-add_tags")]
 pub mod add_tags;
+#[cfg(feature = "transforms-aggregate")]
+pub mod aggregate;
 #[cfg(feature = "transforms-ansi_stripper")]
 pub mod ansi_stripper;
 #[cfg(feature = "transforms-aws_ec2_metadata")]
@@ -33,8 +35,12 @@ pub mod log_to_metric;
 pub mod logfmt_parser;
 #[cfg(feature = "transforms-lua")]
 pub mod lua;
+#[cfg(feature = "transforms-map_tags")]
+pub mod map_tags;
 #[cfg(feature = "transforms-merge")]
 pub mod merge;
+#[cfg(feature = "transforms-metric_to_log")]
+pub mod metric_to_log;
 #[cfg(feature = "transforms-regex_parser")]
 pub mod regex_parser;
 #[cfg(feature = "transforms-remove_fields")]
@@ -58,6 +64,9 @@ pub mod wasm;
 
 use futures01::Stream;
 
+/// The output that parsers send the events they failed to parse to.
+pub const ERRORS_OUTPUT: &str = "errors";
+
 pub trait Transform: Send {
     fn transform(&mut self, event: Event) -> Option<Event>;
 
@@ -67,6 +76,31 @@ pub trait Transform: Send {
         }
     }
 
+    /// Like `transform_into`, but can also send events to the named outputs
+    /// declared by the transform's `TransformConfig::named_outputs`.
+    fn transform_outputs(&mut self, outputs: &mut TransformOutputs, event: Event) {
+        self.transform_into(&mut outputs.primary, event);
+    }
+
+    fn transform_stream_with_outputs(
+        self: Box<Self>,
+        input_rx: Box<dyn Stream<Item = Event, Error = ()> + Send>,
+    ) -> Box<dyn Stream<Item = (Option<&'static str>, Event), Error = ()> + Send>
+    where
+        Self: 'static,
+    {
+        let mut me = self;
+        Box::new(
+            input_rx
+                .map(move |event| {
+                    let mut outputs = TransformOutputs::default();
+                    me.transform_outputs(&mut outputs, event);
+                    futures01::stream::iter_ok(outputs.into_events())
+                })
+                .flatten(),
+        )
+    }
+
     fn transform_stream(
         self: Box<Self>,
         input_rx: Box<dyn Stream<Item = Event, Error = ()> + Send>,
@@ -87,6 +121,44 @@ pub trait Transform: Send {
     }
 }
 
+/// Events produced by a transform for a single input event, sorted by the
+/// output they are sent to.
+#[derive(Debug, Default)]
+pub struct TransformOutputs {
+    primary: Vec<Event>,
+    named: Vec<(&'static str, Event)>,
+}
+
+impl TransformOutputs {
+    /// Sends an event to the default output.
+    pub fn push(&mut self, event: Event) {
+        self.primary.push(event);
+    }
+
+    pub fn push_named(&mut self, output: &'static str, event: Event) {
+        self.named.push((output, event));
+    }
+
+    /// Sends an event that failed to be processed to the `errors` output and,
+    /// unless `drop` is set, passes it through the default output as well.
+    pub fn push_error(&mut self, event: Event, drop: bool) {
+        if !drop {
+            self.primary.push(event.clone());
+        }
+        self.named.push((ERRORS_OUTPUT, event));
+    }
+
+    /// Yields each event along with the name of its output, `None` being the
+    /// default output.
+    pub fn into_events(self) -> impl Iterator<Item = (Option<&'static str>, Event)> {
+        self.primary.into_iter().map(|event| (None, event)).chain(
+            self.named
+                .into_iter()
+                .map(|(output, event)| (Some(output), event)),
+        )
+    }
+}
+
 #[derive(Debug, Snafu)]
 enum BuildError {
     #[snafu(display("Invalid regular expression: {}", source))]
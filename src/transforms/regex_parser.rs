@@ -1,4 +1,4 @@
-use super::Transform;
+use super::{Transform, TransformOutputs, ERRORS_OUTPUT};
 use crate::{
     event::{self, Event, Value},
     internal_events::{RegexEventProcessed, RegexFailedMatch, RegexMissingField},
@@ -52,6 +52,10 @@ impl TransformConfig for RegexParserConfig {
     fn transform_type(&self) -> &'static str {
         "regex"
     }
+
+    fn named_outputs(&self) -> Vec<&'static str> {
+        vec![ERRORS_OUTPUT]
+    }
 }
 
 pub struct RegexParser {
@@ -188,8 +192,9 @@ impl RegexParser {
     }
 }
 
-impl Transform for RegexParser {
-    fn transform(&mut self, mut event: Event) -> Option<Event> {
+impl RegexParser {
+    /// Parses the event in place, returning whether parsing succeeded.
+    fn parse(&mut self, event: &mut Event) -> bool {
         let log = event.as_mut_log();
         let value = log.get(&self.field).map(|s| s.as_bytes());
         emit!(RegexEventProcessed);
@@ -200,11 +205,7 @@ impl Transform for RegexParser {
                 Some(id) => id,
                 None => {
                     emit!(RegexFailedMatch { value });
-                    if self.drop_failed {
-                        return None;
-                    } else {
-                        return Some(event);
-                    }
+                    return false;
                 }
             };
 
@@ -212,7 +213,7 @@ impl Transform for RegexParser {
                 Some(capture_locs) => capture_locs,
                 None => {
                     error!(message = "Cannot find capture locations for pattern", %id, rate_limit_secs = 30);
-                    return None;
+                    return false;
                 }
             };
 
@@ -227,7 +228,7 @@ impl Transform for RegexParser {
                             log.remove(target_field);
                         } else {
                             error!(message = "target field already exists", %target_field, rate_limit_secs = 30);
-                            return Some(event);
+                            return true;
                         }
                     }
                 }
@@ -257,7 +258,7 @@ impl Transform for RegexParser {
                 if self.drop_field {
                     log.remove(&self.field);
                 }
-                return Some(event);
+                return true;
             } else {
                 emit!(RegexFailedMatch { value });
             }
@@ -265,10 +266,24 @@ impl Transform for RegexParser {
             emit!(RegexMissingField { field: &self.field });
         }
 
-        if self.drop_failed {
-            None
+        false
+    }
+}
+
+impl Transform for RegexParser {
+    fn transform(&mut self, mut event: Event) -> Option<Event> {
+        if !self.parse(&mut event) && self.drop_failed {
+            return None;
+        }
+
+        Some(event)
+    }
+
+    fn transform_outputs(&mut self, outputs: &mut TransformOutputs, mut event: Event) {
+        if self.parse(&mut event) {
+            outputs.push(event);
         } else {
-            Some(event)
+            outputs.push_error(event, self.drop_failed);
         }
     }
 }
@@ -279,6 +294,7 @@ mod tests {
     use crate::event::{LogEvent, Value};
     use crate::{
         topology::config::{TransformConfig, TransformContext},
+        transforms::TransformOutputs,
         Event,
     };
 
@@ -443,6 +459,33 @@ mod tests {
         assert!(log.is_none());
     }
 
+    #[test]
+    fn sends_failed_match_to_errors_output() {
+        let mut parser = toml::from_str::<RegexParserConfig>(
+            r#"
+                patterns = ['^(?P<status>\d+)$']
+                drop_failed = true
+            "#,
+        )
+        .unwrap()
+        .build(TransformContext::new_test())
+        .unwrap();
+
+        let mut outputs = TransformOutputs::default();
+        parser.transform_outputs(&mut outputs, Event::from("1234"));
+        parser.transform_outputs(&mut outputs, Event::from("asdf"));
+
+        let outputs = outputs
+            .into_events()
+            .map(|(output, event)| (output, event.into_log()))
+            .collect::<Vec<_>>();
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].0, None);
+        assert_eq!(outputs[0].1[&"status".into()], "1234".into());
+        assert_eq!(outputs[1].0, Some("errors"));
+        assert_eq!(outputs[1].1[&"message".into()], "asdf".into());
+    }
+
     #[test]
     fn handles_valid_optional_capture() {
         let log = do_transform("1234", r#"['(?P<status>\d+)?']"#, "").unwrap();
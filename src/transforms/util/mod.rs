@@ -1,2 +1,2 @@
-#[cfg(any(feature = "transforms-lua"))]
+#[cfg(any(feature = "transforms-aggregate", feature = "transforms-lua"))]
 pub mod runtime_transform;
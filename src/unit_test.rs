@@ -1,9 +1,12 @@
 use crate::{
     config_paths, event,
-    topology::{config::Config, unit_test::UnitTest},
+    topology::{
+        config::{loading, FormatHint},
+        unit_test::UnitTest,
+    },
 };
 use colored::*;
-use std::{fs::File, path::PathBuf};
+use std::path::PathBuf;
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -12,28 +15,22 @@ pub struct Opts {
     /// Any number of Vector config files to test. If none are specified the
     /// default config path `/etc/vector/vector.toml` will be targeted.
     paths: Vec<PathBuf>,
-}
 
-fn build_tests(i: usize, path: &PathBuf) -> Result<Vec<UnitTest>, Vec<String>> {
-    let file = match File::open(path) {
-        Ok(f) => f,
-        Err(error) => {
-            if let std::io::ErrorKind::NotFound = error.kind() {
-                return Err(vec![format!(
-                    "Config file not found in path '{}'",
-                    path.to_str().unwrap_or("")
-                )]);
-            } else {
-                return Err(vec![format!(
-                    "Could not open file '{}': {}",
-                    path.to_str().unwrap_or(""),
-                    error
-                )]);
-            }
-        }
-    };
+    /// Vector config files in TOML format to test.
+    #[structopt(name = "config-toml", long)]
+    paths_toml: Vec<PathBuf>,
+
+    /// Vector config files in JSON format to test.
+    #[structopt(name = "config-json", long)]
+    paths_json: Vec<PathBuf>,
+
+    /// Vector config files in YAML format to test.
+    #[structopt(name = "config-yaml", long)]
+    paths_yaml: Vec<PathBuf>,
+}
 
-    let mut config = match Config::load(file) {
+fn build_tests(i: usize, path: &PathBuf, format: FormatHint) -> Result<Vec<UnitTest>, Vec<String>> {
+    let mut config = match loading::load_file(path, format) {
         Err(load_errs) => {
             return Err(load_errs);
         }
@@ -52,17 +49,23 @@ pub fn cmd(opts: &Opts) -> exitcode::ExitCode {
     let mut failed_files: Vec<(String, Vec<(String, Vec<String>)>)> = Vec::new();
     let mut inspected_files: Vec<(String, Vec<(String, Vec<String>)>)> = Vec::new();
 
-    let paths = config_paths::expand(opts.paths.clone()).unwrap_or_else(|| {
+    let paths = config_paths::with_formats(
+        &opts.paths,
+        &opts.paths_toml,
+        &opts.paths_json,
+        &opts.paths_yaml,
+    );
+    let paths = config_paths::expand(paths).unwrap_or_else(|| {
         std::process::exit(exitcode::CONFIG);
     });
 
-    for (i, p) in paths.iter().enumerate() {
+    for (i, (p, format)) in paths.iter().enumerate() {
         let path_str = p.to_str().unwrap_or("");
         if i > 0 {
             println!();
         }
         println!("Running {} tests", path_str);
-        match build_tests(i, p) {
+        match build_tests(i, p, *format) {
             Ok(mut tests) => {
                 let mut aggregated_test_errors = Vec::new();
                 let mut aggregated_test_inspections = Vec::new();
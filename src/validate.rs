@@ -1,11 +1,11 @@
 use crate::{
     config_paths, event,
-    topology::{self, builder::Pieces, Config, ConfigDiff},
+    topology::{self, builder::Pieces, config::loading, Config, ConfigDiff},
 };
 use colored::*;
 use exitcode::ExitCode;
 use futures::compat::Future01CompatExt;
-use std::{fmt, fs::File, path::PathBuf};
+use std::{fmt, path::PathBuf};
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -33,6 +33,23 @@ pub struct Opts {
     /// Any number of Vector config files to validate. If none are specified the
     /// default config path `/etc/vector/vector.toml` will be targeted.
     paths: Vec<PathBuf>,
+
+    /// Vector config files in TOML format to validate.
+    #[structopt(name = "config-toml", long)]
+    paths_toml: Vec<PathBuf>,
+
+    /// Vector config files in JSON format to validate.
+    #[structopt(name = "config-json", long)]
+    paths_json: Vec<PathBuf>,
+
+    /// Vector config files in YAML format to validate.
+    #[structopt(name = "config-yaml", long)]
+    paths_yaml: Vec<PathBuf>,
+
+    /// Any number of Vector config directories to validate. If specified
+    /// without any config files, the default config path isn't targeted.
+    #[structopt(name = "config-dir", long)]
+    config_dirs: Vec<PathBuf>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -85,44 +102,40 @@ pub async fn validate(opts: &Opts, color: bool) -> ExitCode {
 /// Err Some contains only succesfully validated configs.
 fn validate_config(opts: &Opts, fmt: &mut Formatter) -> Result<Config, Option<Config>> {
     // Prepare paths
-    let paths = if let Some(paths) = config_paths::prepare(opts.paths.clone()) {
+    let paths = config_paths::with_formats(
+        &opts.paths,
+        &opts.paths_toml,
+        &opts.paths_json,
+        &opts.paths_yaml,
+    );
+    let paths = if paths.is_empty() && !opts.config_dirs.is_empty() {
+        Vec::new()
+    } else if let Some(paths) = config_paths::prepare(paths) {
         paths
     } else {
         fmt.error("No config file paths");
         return Err(None);
     };
 
-    // Validate configuration files
-    let to_valdiate = paths.len();
+    // Validate configuration files and directories
+    let to_valdiate = paths.len() + opts.config_dirs.len();
     let mut validated = 0;
     let mut full_config = Config::empty();
-    for config_path in paths {
-        let file = match File::open(&config_path) {
-            Ok(file) => file,
-            Err(error) => {
-                if let std::io::ErrorKind::NotFound = error.kind() {
-                    fmt.error(format!("File {:?} not found", config_path));
-                } else {
-                    fmt.error(format!(
-                        "Failed opening file {:?} with error {:?}",
-                        config_path, error
-                    ));
-                }
-                continue;
-            }
-        };
-
-        trace!(
-            message = "Parsing config.",
-            path = ?config_path
+    let loaded = paths
+        .iter()
+        .map(|(path, format)| (path, loading::load_file(path, *format)))
+        .chain(
+            opts.config_dirs
+                .iter()
+                .map(|dir| (dir, loading::load_dir(dir))),
         );
-
+    for (config_path, loaded) in loaded {
         let mut sub_failed = |title: String, errors| {
             fmt.title(title);
             fmt.sub_error(errors);
         };
 
-        let mut config = match Config::load(file) {
+        let mut config = match loaded {
             Ok(config) => config,
             Err(errors) => {
                 sub_failed(format!("Failed to parse {:?}", config_path), errors);
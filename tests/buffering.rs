@@ -1,5 +1,4 @@
 #![allow(clippy::match_bool)]
-#![cfg(feature = "leveldb")]
 
 use futures01::{Future, Sink};
 use prost::Message;
@@ -12,6 +11,21 @@ use vector::{buffers::BufferConfig, runtime, sinks};
 
 mod support;
 
+#[cfg(feature = "leveldb")]
+fn leveldb(max_size: usize) -> BufferConfig {
+    BufferConfig::Disk {
+        max_size,
+        when_full: Default::default(),
+    }
+}
+
+fn disk_v2(max_size: usize) -> BufferConfig {
+    BufferConfig::DiskV2 {
+        max_size,
+        when_full: Default::default(),
+    }
+}
+
 fn terminate_gracefully(mut rt: runtime::Runtime, topology: topology::RunningTopology) {
     rt.block_on(topology.stop()).unwrap();
     test_util::shutdown_on_idle(rt);
@@ -22,8 +36,7 @@ fn terminate_abruptly(rt: runtime::Runtime, topology: topology::RunningTopology)
     drop(topology);
 }
 
-#[test]
-fn test_buffering() {
+fn buffering(buffer: fn(usize) -> BufferConfig) {
     test_util::trace_init();
 
     let data_dir = tempdir().unwrap();
@@ -49,10 +62,7 @@ fn test_buffering() {
         let mut config = config::Config::empty();
         config.add_source("in", source_config);
         config.add_sink("out", &["in"], sink_config);
-        config.sinks["out"].buffer = BufferConfig::Disk {
-            max_size,
-            when_full: Default::default(),
-        };
+        config.sinks["out"].buffer = buffer(max_size);
         config.global.data_dir = Some(data_dir.clone());
         config
     };
@@ -92,10 +102,7 @@ fn test_buffering() {
         let mut config = config::Config::empty();
         config.add_source("in", source_config);
         config.add_sink("out", &["in"], sink_config);
-        config.sinks["out"].buffer = BufferConfig::Disk {
-            max_size,
-            when_full: Default::default(),
-        };
+        config.sinks["out"].buffer = buffer(max_size);
         config.global.data_dir = Some(data_dir);
         config
     };
@@ -122,8 +129,7 @@ fn test_buffering() {
     assert_eq!(input_events2, &output_events[num_events..]);
 }
 
-#[test]
-fn test_max_size() {
+fn max_size(buffer: fn(usize) -> BufferConfig) {
     test_util::trace_init();
 
     let data_dir = tempdir().unwrap();
@@ -151,10 +157,7 @@ fn test_max_size() {
         let mut config = config::Config::empty();
         config.add_source("in", source_config);
         config.add_sink("out", &["in"], sink_config);
-        config.sinks["out"].buffer = BufferConfig::Disk {
-            max_size,
-            when_full: Default::default(),
-        };
+        config.sinks["out"].buffer = buffer(max_size);
         config.global.data_dir = Some(data_dir.clone());
         config
     };
@@ -193,10 +196,7 @@ fn test_max_size() {
         let mut config = config::Config::empty();
         config.add_source("in", source_config);
         config.add_sink("out", &["in"], sink_config);
-        config.sinks["out"].buffer = BufferConfig::Disk {
-            max_size,
-            when_full: Default::default(),
-        };
+        config.sinks["out"].buffer = buffer(max_size);
         config.global.data_dir = Some(data_dir);
         config
     };
@@ -214,8 +214,7 @@ fn test_max_size() {
     assert_eq!(&input_events[..num_events / 2], &output_events[..]);
 }
 
-#[test]
-fn test_max_size_resume() {
+fn max_size_resume(buffer: fn(usize) -> BufferConfig) {
     test_util::trace_init();
 
     let data_dir = tempdir().unwrap();
@@ -239,10 +238,7 @@ fn test_max_size_resume() {
         &["in1", "in2"],
         sinks::socket::SocketSinkConfig::make_basic_tcp_config(out_addr.to_string()),
     );
-    config.sinks["out"].buffer = BufferConfig::Disk {
-        max_size,
-        when_full: Default::default(),
-    };
+    config.sinks["out"].buffer = buffer(max_size);
     config.global.data_dir = Some(data_dir);
 
     // Use a multi-thread runtime here.
@@ -275,8 +271,7 @@ fn test_max_size_resume() {
     assert_eq!(num_events * 2, output_lines.len());
 }
 
-#[test]
-fn test_reclaim_disk_space() {
+fn reclaim_disk_space(buffer: fn(usize) -> BufferConfig) {
     test_util::trace_init();
 
     let data_dir = tempdir().unwrap();
@@ -294,10 +289,7 @@ fn test_reclaim_disk_space() {
         let mut config = config::Config::empty();
         config.add_source("in", source_config);
         config.add_sink("out", &["in"], sink_config);
-        config.sinks["out"].buffer = BufferConfig::Disk {
-            max_size,
-            when_full: Default::default(),
-        };
+        config.sinks["out"].buffer = buffer(max_size);
         config.global.data_dir = Some(data_dir.clone());
         config
     };
@@ -339,10 +331,7 @@ fn test_reclaim_disk_space() {
         let mut config = config::Config::empty();
         config.add_source("in", source_config);
         config.add_sink("out", &["in"], sink_config);
-        config.sinks["out"].buffer = BufferConfig::Disk {
-            max_size,
-            when_full: Default::default(),
-        };
+        config.sinks["out"].buffer = buffer(max_size);
         config.global.data_dir = Some(data_dir.clone());
         config
     };
@@ -375,6 +364,50 @@ fn test_reclaim_disk_space() {
     assert!(after_disk_size < before_disk_size / 2);
 }
 
+#[cfg(feature = "leveldb")]
+#[test]
+fn test_buffering() {
+    buffering(leveldb);
+}
+
+#[test]
+fn test_buffering_disk_v2() {
+    buffering(disk_v2);
+}
+
+#[cfg(feature = "leveldb")]
+#[test]
+fn test_max_size() {
+    max_size(leveldb);
+}
+
+#[test]
+fn test_max_size_disk_v2() {
+    max_size(disk_v2);
+}
+
+#[cfg(feature = "leveldb")]
+#[test]
+fn test_max_size_resume() {
+    max_size_resume(leveldb);
+}
+
+#[test]
+fn test_max_size_resume_disk_v2() {
+    max_size_resume(disk_v2);
+}
+
+#[cfg(feature = "leveldb")]
+#[test]
+fn test_reclaim_disk_space() {
+    reclaim_disk_space(leveldb);
+}
+
+#[test]
+fn test_reclaim_disk_space_disk_v2() {
+    reclaim_disk_space(disk_v2);
+}
+
 fn compute_disk_size(dir: impl AsRef<std::path::Path>) -> u64 {
     walkdir::WalkDir::new(dir)
         .into_iter()
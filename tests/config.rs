@@ -1,7 +1,11 @@
-use vector::topology::{self, Config, ConfigDiff};
+use vector::topology::{self, config::Format, Config, ConfigDiff};
 
 fn load(config: &str) -> Result<Vec<String>, Vec<String>> {
-    Config::load(config.as_bytes())
+    load_format(config, Format::Toml)
+}
+
+fn load_format(config: &str, format: Format) -> Result<Vec<String>, Vec<String>> {
+    Config::load_format(config.as_bytes(), format)
         .and_then(|c| topology::builder::check_build(&c, &ConfigDiff::initial(&c)))
         .map(|(_topology, warnings)| warnings)
 }
@@ -51,6 +55,85 @@ fn happy_path() {
     .unwrap();
 }
 
+#[cfg(all(
+    feature = "sources-socket",
+    feature = "transforms-sampler",
+    feature = "sinks-socket"
+))]
+#[test]
+fn happy_path_json_and_yaml() {
+    load_format(
+        r#"
+        {
+          "sources": {
+            "in": {"type": "socket", "mode": "tcp", "address": "127.0.0.1:1235"}
+          },
+          "transforms": {
+            "sampler": {"type": "sampler", "inputs": ["in"], "rate": 10, "pass_list": ["error"]}
+          },
+          "sinks": {
+            "out": {"type": "socket", "mode": "tcp", "inputs": ["sampler"], "encoding": "text", "address": "127.0.0.1:9999"}
+          }
+        }
+      "#,
+        Format::Json,
+    )
+    .unwrap();
+
+    load_format(
+        r#"
+sources:
+  in:
+    type: socket
+    mode: tcp
+    address: "127.0.0.1:1235"
+
+transforms:
+  sampler:
+    type: sampler
+    inputs: ["in"]
+    rate: 10
+    pass_list: ["error"]
+
+sinks:
+  out:
+    type: socket
+    mode: tcp
+    inputs: ["sampler"]
+    encoding: text
+    address: "127.0.0.1:9999"
+"#,
+        Format::Yaml,
+    )
+    .unwrap();
+}
+
+#[cfg(all(feature = "sources-socket", feature = "sinks-socket"))]
+#[test]
+fn interpolates_env_in_every_format() {
+    std::env::set_var("VECTOR_TEST_SINK_ADDRESS", "127.0.0.1:9999");
+
+    load_format(
+        r#"{
+          "sources": {"in": {"type": "socket", "mode": "tcp", "address": "127.0.0.1:1235"}},
+          "sinks": {"out": {"type": "socket", "mode": "tcp", "inputs": ["in"], "encoding": "text", "address": "${VECTOR_TEST_SINK_ADDRESS}"}}
+        }"#,
+        Format::Json,
+    )
+    .unwrap();
+
+    load_format(
+        r#"
+sources:
+  in: {type: socket, mode: tcp, address: "127.0.0.1:1235"}
+sinks:
+  out: {type: socket, mode: tcp, inputs: ["in"], encoding: text, address: "$VECTOR_TEST_SINK_ADDRESS"}
+"#,
+        Format::Yaml,
+    )
+    .unwrap();
+}
+
 #[test]
 fn early_eof() {
     let err = load("[sinks]\n[sin").unwrap_err();
@@ -174,6 +257,47 @@ fn nonexistant_input() {
     );
 }
 
+#[cfg(all(
+    feature = "sources-socket",
+    feature = "transforms-json_parser",
+    feature = "sinks-socket"
+))]
+#[test]
+fn named_transform_outputs() {
+    let err = load(
+        r#"
+        [sources.in]
+        type = "socket"
+        mode = "tcp"
+        address = "127.0.0.1:1235"
+
+        [transforms.parse]
+        type = "json_parser"
+        inputs = ["in"]
+
+        [sinks.out]
+        type = "socket"
+        mode = "tcp"
+        inputs = ["parse", "parse.errors"]
+        encoding = "text"
+        address = "127.0.0.1:9999"
+
+        [sinks.nope]
+        type = "socket"
+        mode = "tcp"
+        inputs = ["parse.nope"]
+        encoding = "text"
+        address = "127.0.0.1:9999"
+      "#,
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err,
+        vec!["Input \"parse.nope\" for sink \"nope\" doesn't exist."]
+    );
+}
+
 #[cfg(all(
     feature = "sources-socket",
     feature = "transforms-sampler",
@@ -468,6 +592,95 @@ fn cycle() {
     )
 }
 
+#[cfg(all(
+    feature = "sources-stdin",
+    feature = "transforms-sampler",
+    feature = "sinks-http"
+))]
+#[test]
+fn dead_letter_cycles() {
+    let errors = load(
+        r#"
+        [sources.in]
+        type = "stdin"
+
+        [sinks.a]
+        type = "http"
+        inputs = ["in"]
+        uri = "https://localhost"
+        encoding = "json"
+        dead_letter = "b"
+
+        [sinks.b]
+        type = "http"
+        inputs = ["in"]
+        uri = "https://localhost"
+        encoding = "json"
+        dead_letter = "a"
+      "#,
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        errors,
+        vec![
+            r#"Dead letters of sink "a" loop back to it in the chain [ a -> b -> a ]"#,
+            r#"Dead letters of sink "b" loop back to it in the chain [ b -> a -> b ]"#,
+        ]
+    );
+
+    let errors = load(
+        r#"
+        [sources.in]
+        type = "stdin"
+
+        [transforms.retry]
+        type = "sampler"
+        inputs = ["in"]
+        rate = 1
+        pass_list = []
+
+        [sinks.out]
+        type = "http"
+        inputs = ["retry"]
+        uri = "https://localhost"
+        encoding = "json"
+        dead_letter = "retry"
+      "#,
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        errors,
+        vec![r#"Dead letters of sink "out" loop back to it in the chain [ out -> retry -> out ]"#]
+    );
+}
+
+#[cfg(all(feature = "sources-stdin", feature = "sinks-socket"))]
+#[test]
+fn dead_letter_unsupported() {
+    let errors = load(
+        r#"
+        [sources.in]
+        type = "stdin"
+
+        [sinks.out]
+        type = "socket"
+        mode = "tcp"
+        inputs = ["in"]
+        address = "127.0.0.1:9999"
+        encoding = "text"
+        dead_letter = { path = "/tmp/dead.log" }
+      "#,
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        errors,
+        vec![r#"Sink "out" of type "socket" doesn't support a dead letter destination."#]
+    );
+}
+
 #[cfg(all(feature = "sources-socket", feature = "sinks-socket"))]
 #[test]
 fn disabled_healthcheck() {
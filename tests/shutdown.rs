@@ -282,11 +282,32 @@ fn timely_shutdown_splunk_hec() {
 }
 
 #[test]
-fn timely_shutdown_statsd() {
+fn timely_shutdown_statsd_tcp() {
     test_timely_shutdown(source_vector(
         r#"
-    type = "statsd"
-    address = "${VECTOR_TEST_ADDRESS}""#,
+        type = "statsd"
+        address = "${VECTOR_TEST_ADDRESS}"
+        mode = "tcp""#,
+    ));
+}
+
+#[test]
+fn timely_shutdown_statsd_udp() {
+    test_timely_shutdown(source_vector(
+        r#"
+        type = "statsd"
+        address = "${VECTOR_TEST_ADDRESS}"
+        mode = "udp""#,
+    ));
+}
+
+#[test]
+fn timely_shutdown_statsd_unix() {
+    test_timely_shutdown(source_vector(
+        r#"
+        type = "statsd"
+        path = "${VECTOR_TEST_UNIX_PATH}"
+        mode = "unix""#,
     ));
 }
 
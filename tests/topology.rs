@@ -10,6 +10,7 @@ use futures01::{
     future, future::Future, sink::Sink, stream::iter_ok, stream::Stream, sync::mpsc::SendError,
     sync::oneshot,
 };
+use metrics_runtime::Measurement;
 use std::sync::{
     atomic::{AtomicBool, AtomicUsize, Ordering},
     Arc,
@@ -20,6 +21,7 @@ use vector::event::{self, Event};
 use vector::test_util::{runtime, shutdown_on_idle, trace_init};
 use vector::topology;
 use vector::topology::config::Config;
+use vector::transforms::json_parser::JsonParserConfig;
 
 fn basic_config() -> Config {
     let mut config = Config::empty();
@@ -212,6 +214,114 @@ fn topology_transform_chain() {
     assert_eq!(vec!["this first second"], res);
 }
 
+#[test]
+fn topology_transform_named_output() {
+    let mut rt = runtime();
+    let (in1, source1) = source();
+    let (out1, sink1) = sink(10);
+    let (errors1, sink2) = sink(10);
+
+    let mut config = Config::empty();
+    config.add_source("in1", source1);
+    config.add_transform(
+        "parse",
+        &["in1"],
+        JsonParserConfig {
+            drop_invalid: true,
+            ..Default::default()
+        },
+    );
+    config.add_sink("out1", &["parse"], sink1);
+    config.add_sink("errors1", &["parse.errors"], sink2);
+
+    let (topology, _crash) = rt.block_on_std(topology::start(config, false)).unwrap();
+
+    in1.send(Event::from(r#"{"message": "parsed"}"#))
+        .wait()
+        .unwrap()
+        .send(Event::from("not json"))
+        .wait()
+        .unwrap();
+
+    rt.block_on(topology.stop()).unwrap();
+
+    let res1 = out1.map(into_message).collect().wait().unwrap();
+    let res2 = errors1.map(into_message).collect().wait().unwrap();
+
+    shutdown_on_idle(rt);
+    assert_eq!(vec!["parsed"], res1);
+    assert_eq!(vec!["not json"], res2);
+}
+
+fn component_counter(component: &str, counter: &str) -> u64 {
+    vector::metrics::CONTROLLER
+        .get()
+        .expect("metrics are not initialized")
+        .snapshot()
+        .into_measurements()
+        .into_iter()
+        .filter(|(key, _)| {
+            &*key.name() == counter
+                && key
+                    .labels()
+                    .any(|label| label.key() == "component_name" && label.value() == component)
+        })
+        .map(|(_, measurement)| match measurement {
+            Measurement::Counter(value) => value,
+            _ => 0,
+        })
+        .sum()
+}
+
+#[test]
+fn topology_component_counters() {
+    // Other tests in this binary don't initialize metrics.
+    vector::metrics::init().unwrap();
+
+    let mut rt = runtime();
+    let (in1, source1) = source();
+    let (out1, sink1) = sink(10);
+    let (errors1, sink2) = sink(10);
+
+    let mut config = Config::empty();
+    config.add_source("counted_in", source1);
+    config.add_transform(
+        "counted_parse",
+        &["counted_in"],
+        JsonParserConfig {
+            drop_invalid: true,
+            ..Default::default()
+        },
+    );
+    config.add_sink("counted_out", &["counted_parse"], sink1);
+    config.add_sink("counted_errors", &["counted_parse.errors"], sink2);
+
+    let (topology, _crash) = rt.block_on_std(topology::start(config, false)).unwrap();
+
+    in1.send(Event::from(r#"{"message": "parsed"}"#))
+        .wait()
+        .unwrap()
+        .send(Event::from("not json"))
+        .wait()
+        .unwrap();
+
+    rt.block_on(topology.stop()).unwrap();
+    assert_eq!(out1.collect().wait().unwrap().len(), 1);
+    assert_eq!(errors1.collect().wait().unwrap().len(), 1);
+    shutdown_on_idle(rt);
+
+    assert_eq!(component_counter("counted_in", "events_received"), 0);
+    assert_eq!(component_counter("counted_in", "events_processed"), 2);
+    assert_eq!(component_counter("counted_parse", "events_received"), 2);
+    assert_eq!(component_counter("counted_parse", "events_processed"), 2);
+    assert_eq!(component_counter("counted_parse", "processing_errors"), 1);
+    assert_eq!(component_counter("counted_out", "events_received"), 1);
+    assert_eq!(component_counter("counted_out", "events_processed"), 1);
+    assert_eq!(component_counter("counted_out", "processing_errors"), 0);
+    assert_eq!(component_counter("counted_errors", "events_received"), 1);
+    assert!(component_counter("counted_out", "bytes_processed") > 0);
+}
+
 #[test]
 fn topology_remove_one_source() {
     let mut rt = runtime();
@@ -547,6 +657,33 @@ fn topology_optional_healthcheck_does_not_fail_start() {
     });
 }
 
+#[test]
+fn topology_failed_healthcheck_reports_unhealthy() {
+    let config = basic_config_with_sink_failing_healthcheck();
+    let mut rt = runtime();
+    rt.block_on_std(async move {
+        let (topology, _crash) = topology::start(config, false).await.unwrap();
+        let health = topology.health();
+
+        let mut attempts = 0;
+        while health.is_healthy("out1") {
+            assert!(attempts < 500, "healthcheck result was never reported");
+            attempts += 1;
+            tokio::time::delay_for(Duration::from_millis(10)).await;
+        }
+
+        let healthy = topology
+            .components()
+            .into_iter()
+            .map(|component| (component.name, component.healthy))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            healthy,
+            vec![("in1".to_string(), true), ("out1".to_string(), false)]
+        );
+    });
+}
+
 #[test]
 fn topology_optional_healthcheck_does_not_fail_reload() {
     let mut rt = runtime();